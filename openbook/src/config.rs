@@ -0,0 +1,1974 @@
+//! This module loads book configurations from `book.toml` files.
+//!
+//! A `book.toml` file looks like the following, where every key is optional:
+//!
+//! ```toml
+//! title = "OpenBook"
+//! description = "An open-source alternative to gitbook"
+//! author = "Sirui Mu"
+//! isbn = "978-3-16-148410-0"
+//! language = "en"
+//! direction = "ltr"
+//! edit-url-template = "https://github.com/Lancern/OpenBook/edit/master/{path}"
+//! title-from-content = false
+//! force-content-title = false
+//! frontmatter-format = "auto"
+//!
+//! [structure]
+//! readme = "README.md"
+//! summary = "SUMMARY.md"
+//! glossary = "GLOSSARY.md"
+//! languages = "LANGS.md"
+//!
+//! [outputs.html]
+//! directory = "book/html"
+//! template = "theme/page.html"
+//!
+//! [outputs.print]
+//! format = "pdf"
+//! ```
+//!
+//! Every table under `outputs` describes a build target. Its `format` defaults to the name of the
+//! table and its `directory` to the directory named after the table under [`DEFAULT_OUTPUT_DIR`],
+//! and all other entries are options for the renderer of the format.
+//!
+//! The same entries can be written in a `book.yaml` file instead. Projects migrated from GitBook
+//! may keep their `book.json` file, which is loaded as a GitBook configuration. When a book has
+//! several of these files, `book.toml` takes precedence over `book.yaml`, which takes precedence
+//! over `book.json`, see [`ConfigFormat::ALL`].
+//!
+//! Entries can be overridden by `OPENBOOK_*` environment variables without editing the file, e.g.
+//! `OPENBOOK_TITLE` overrides `title`. Such overrides take precedence over all configuration files.
+//!
+//! Entries that OpenBook does not recognize are not dropped but kept in `BookConfig::extra`, so
+//! that tools built on OpenBook can keep their own settings in the same file.
+//!
+//! The `schema` function describes the format of the file as a JSON Schema, for editors and CI to
+//! validate configuration files against.
+//!
+//! [`ConfigFormat::ALL`]: enum.ConfigFormat.html#associatedconstant.ALL
+//! [`DEFAULT_OUTPUT_DIR`]: constant.DEFAULT_OUTPUT_DIR.html
+//!
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use toml::value::Table;
+use toml::Value;
+
+use crate::error::{Error, Result};
+use crate::fs::{normalize, FileSystem};
+use crate::render::json_string;
+use crate::tree::frontmatter::FrontmatterFormat;
+use crate::tree::{BookConfig, BookStructureConfig, OutputConfig, TextDirection};
+
+/// Name of the configuration file under the root directory of a book.
+pub const CONFIG_FILE_NAME: &str = "book.toml";
+
+/// Name of the YAML configuration file under the root directory of a book, which is loaded when
+/// there is no [`CONFIG_FILE_NAME`] file.
+///
+/// [`CONFIG_FILE_NAME`]: constant.CONFIG_FILE_NAME.html
+pub const YAML_CONFIG_FILE_NAME: &str = "book.yaml";
+
+/// Name of the GitBook configuration file under the root directory of a book, which is loaded
+/// when there is neither a [`CONFIG_FILE_NAME`] nor a [`YAML_CONFIG_FILE_NAME`] file.
+///
+/// [`CONFIG_FILE_NAME`]: constant.CONFIG_FILE_NAME.html
+/// [`YAML_CONFIG_FILE_NAME`]: constant.YAML_CONFIG_FILE_NAME.html
+pub const GITBOOK_CONFIG_FILE_NAME: &str = "book.json";
+
+/// Prefix of the names of the environment variables that override configuration entries, see
+/// `BookConfig::apply_env_overrides`.
+pub const ENV_PREFIX: &str = "OPENBOOK_";
+
+/// Path to the directory, relative to the root directory of the project, under which build targets
+/// that do not set their `directory` are built.
+pub const DEFAULT_OUTPUT_DIR: &str = "book";
+
+/// URI of the JSON Schema dialect of the schema produced by [`schema`].
+///
+/// [`schema`]: fn.schema.html
+pub const SCHEMA_DIALECT: &str = "http://json-schema.org/draft-07/schema#";
+
+/// An entry of a configuration file that is loaded into a field of `BookConfig`.
+///
+/// The entries are listed in [`CONFIG_ENTRIES`], which determines both which entries are
+/// recognized when a configuration file is read and the [`schema`] of the file, so that the two
+/// cannot diverge. Entries added to `BookConfig` should be listed there.
+///
+/// [`CONFIG_ENTRIES`]: constant.CONFIG_ENTRIES.html
+/// [`schema`]: fn.schema.html
+struct Entry {
+    key: &'static str,
+    kind: EntryKind,
+    description: &'static str,
+}
+
+/// The kind of the value of a configuration [`Entry`].
+///
+/// [`Entry`]: struct.Entry.html
+enum EntryKind {
+    /// A string.
+    String,
+
+    /// A boolean.
+    Boolean,
+
+    /// A string that is one of the listed values.
+    Enum(&'static [&'static str]),
+
+    /// A table with the listed entries.
+    Table(&'static [Entry]),
+
+    /// A table whose entries are all tables with the listed entries.
+    TableOf(&'static [Entry]),
+}
+
+/// The top-level entries of a configuration file.
+const CONFIG_ENTRIES: &[Entry] = &[
+    Entry {
+        key: "title",
+        kind: EntryKind::String,
+        description: "Title of the book.",
+    },
+    Entry {
+        key: "description",
+        kind: EntryKind::String,
+        description: "Description of the book.",
+    },
+    Entry {
+        key: "author",
+        kind: EntryKind::String,
+        description: "Author of the book.",
+    },
+    Entry {
+        key: "isbn",
+        kind: EntryKind::String,
+        description: "ISBN-10 or ISBN-13 of the book.",
+    },
+    Entry {
+        key: "language",
+        kind: EntryKind::String,
+        description:
+            "Language of the book, as an ISO 639-1 language code like \"en\" or \"zh-CN\".",
+    },
+    Entry {
+        key: "direction",
+        kind: EntryKind::Enum(&["ltr", "rtl"]),
+        description: "Text direction of the book, inferred from the language if not set.",
+    },
+    Entry {
+        key: "edit-url-template",
+        kind: EntryKind::String,
+        description: "Template of the URL at which the source of a section can be edited, where \
+                      {path} is replaced with the path to the source file.",
+    },
+    Entry {
+        key: "title-from-content",
+        kind: EntryKind::Boolean,
+        description: "Whether sections are named after the first level 1 heading of their \
+                      content, which is then removed from the content.",
+    },
+    Entry {
+        key: "force-content-title",
+        kind: EntryKind::Boolean,
+        description: "Whether names taken from the content with title-from-content take \
+                      precedence over the names given in SUMMARY.md.",
+    },
+    Entry {
+        key: "frontmatter-format",
+        kind: EntryKind::Enum(&["auto", "yaml", "toml", "json"]),
+        description: "Format of the frontmatter of section content. Defaults to \"auto\", which \
+                      detects the format from the delimiter.",
+    },
+    Entry {
+        key: "structure",
+        kind: EntryKind::Table(STRUCTURE_ENTRIES),
+        description: "Paths to the special files of the book, relative to its root directory.",
+    },
+    Entry {
+        key: "outputs",
+        kind: EntryKind::TableOf(OUTPUT_ENTRIES),
+        description: "Build targets of the book, keyed by their names. Entries other than format \
+                      and directory are options for the renderer of the format.",
+    },
+];
+
+/// The entries of the `structure` table of a configuration file.
+const STRUCTURE_ENTRIES: &[Entry] = &[
+    Entry {
+        key: "readme",
+        kind: EntryKind::String,
+        description: "Path to the README.md file, which is the preface of the book.",
+    },
+    Entry {
+        key: "summary",
+        kind: EntryKind::String,
+        description: "Path to the SUMMARY.md file, which describes the structure of the book.",
+    },
+    Entry {
+        key: "glossary",
+        kind: EntryKind::String,
+        description: "Path to the GLOSSARY.md file, which defines the terms of the book.",
+    },
+    Entry {
+        key: "languages",
+        kind: EntryKind::String,
+        description: "Path to the LANGS.md file, which lists the languages of the project.",
+    },
+];
+
+/// The entries of a table under the `outputs` table of a configuration file.
+const OUTPUT_ENTRIES: &[Entry] = &[
+    Entry {
+        key: "format",
+        kind: EntryKind::String,
+        description: "Format of the output, like \"html\", \"epub\" or \"pdf\". Defaults to \
+                      the name of the target.",
+    },
+    Entry {
+        key: "directory",
+        kind: EntryKind::String,
+        description: "Directory that the target is built into, relative to the root directory of \
+                      the project. Defaults to the directory named after the target under \
+                      \"book\".",
+    },
+];
+
+/// Get the keys of the specified entries.
+fn keys(entries: &[Entry]) -> Vec<&'static str> {
+    entries.iter().map(|entry| entry.key).collect()
+}
+
+/// Format of a configuration file.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ConfigFormat {
+    /// TOML, in the [`CONFIG_FILE_NAME`] file.
+    ///
+    /// [`CONFIG_FILE_NAME`]: constant.CONFIG_FILE_NAME.html
+    Toml,
+
+    /// YAML, in the [`YAML_CONFIG_FILE_NAME`] file, with the same entries as the TOML file.
+    ///
+    /// [`YAML_CONFIG_FILE_NAME`]: constant.YAML_CONFIG_FILE_NAME.html
+    Yaml,
+
+    /// JSON, in the GitBook [`GITBOOK_CONFIG_FILE_NAME`] file, see [`load_gitbook_config`].
+    ///
+    /// The file has the same entries as the TOML file, except that a top-level `root` entry moves
+    /// the content of the book into a subdirectory as in GitBook.
+    ///
+    /// [`GITBOOK_CONFIG_FILE_NAME`]: constant.GITBOOK_CONFIG_FILE_NAME.html
+    /// [`load_gitbook_config`]: fn.load_gitbook_config.html
+    Json,
+}
+
+impl ConfigFormat {
+    /// All formats, in the order of precedence in which [`find_config_file`] looks for their files.
+    ///
+    /// [`find_config_file`]: fn.find_config_file.html
+    pub const ALL: &'static [ConfigFormat] =
+        &[ConfigFormat::Toml, ConfigFormat::Yaml, ConfigFormat::Json];
+
+    /// Get the name of the configuration file in this format under the root directory of a book.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ConfigFormat::Toml => CONFIG_FILE_NAME,
+            ConfigFormat::Yaml => YAML_CONFIG_FILE_NAME,
+            ConfigFormat::Json => GITBOOK_CONFIG_FILE_NAME,
+        }
+    }
+}
+
+/// Find the configuration file of the book whose root directory is at the specified path, and get
+/// its format and path.
+///
+/// The files of the formats in [`ConfigFormat::ALL`] are looked up through `FileSystem::has_file`
+/// in that order, and the first one that exists is returned. Returns `None` if there is no
+/// configuration file.
+///
+/// [`ConfigFormat::ALL`]: enum.ConfigFormat.html#associatedconstant.ALL
+pub fn find_config_file<F: FileSystem>(fs: &F, root: &Path) -> Option<(ConfigFormat, PathBuf)> {
+    ConfigFormat::ALL.iter().find_map(|&format| {
+        let path = root.join(format.file_name());
+        if fs.has_file(&path) {
+            Some((format, path))
+        } else {
+            None
+        }
+    })
+}
+
+/// Load the configuration of the book whose root directory is at the specified path.
+///
+/// The configuration is read from the configuration file that [`find_config_file`] finds under
+/// `root`, and `root` becomes the `root` of the returned configuration. A [`CONFIG_FILE_NAME`] file
+/// is parsed as TOML and a [`YAML_CONFIG_FILE_NAME`] file as YAML, with the same entries. If there
+/// is no configuration file, a default configuration is returned. Unrecognized entries are
+/// collected into `BookConfig::extra`. Returns an error of kind `ErrorKind::Config` if the file is
+/// not valid in its format or if an entry has an unexpected type or value.
+///
+/// YAML files are parsed with `serde_yaml`, so loading them requires the `serde` feature; without
+/// it, a `book.yaml` file is reported as an error rather than skipped. Entries whose value is
+/// `null` are ignored, since TOML cannot represent them.
+///
+/// A [`GITBOOK_CONFIG_FILE_NAME`] file is not a JSON spelling of `book.toml` but a GitBook
+/// configuration, and is loaded by [`load_gitbook_config`]. It accepts every entry of `book.toml`
+/// with the same meaning, with one exception: a top-level `root` entry is GitBook's subdirectory
+/// that contains the content of the book, rather than an unrecognized entry.
+///
+/// [`find_config_file`]: fn.find_config_file.html
+/// [`CONFIG_FILE_NAME`]: constant.CONFIG_FILE_NAME.html
+/// [`YAML_CONFIG_FILE_NAME`]: constant.YAML_CONFIG_FILE_NAME.html
+/// [`GITBOOK_CONFIG_FILE_NAME`]: constant.GITBOOK_CONFIG_FILE_NAME.html
+/// [`load_gitbook_config`]: fn.load_gitbook_config.html
+pub fn load_config<F: FileSystem>(fs: &F, root: &Path) -> Result<BookConfig> {
+    let (format, path) = match find_config_file(fs, root) {
+        Some((ConfigFormat::Json, _)) => return load_gitbook_config(fs, root),
+        Some(found) => found,
+        None => {
+            return Ok(BookConfig {
+                root: root.to_path_buf(),
+                ..BookConfig::default()
+            })
+        }
+    };
+
+    let content = fs.read_file_as_string(&path)?;
+    let parsed = match format {
+        ConfigFormat::Yaml => parse_yaml(&path, &content),
+        _ => content
+            .parse::<Value>()
+            .map_err(|e| format!("{}: {}", path.display(), e)),
+    };
+    let table = match parsed {
+        Ok(Value::Table(table)) => table,
+        Ok(_) => {
+            return Err(Error::config(format!(
+                "{}: expected a table at the top level",
+                path.display()
+            )))
+        }
+        Err(msg) => return Err(Error::config(msg)),
+    };
+
+    let mut config = BookConfig {
+        root: root.to_path_buf(),
+        ..BookConfig::default()
+    };
+    read_config_table(&path, &table, &keys(CONFIG_ENTRIES), &mut config)?;
+    Ok(config)
+}
+
+/// Load the configuration of the book whose root directory is at the specified path from a GitBook
+/// `book.json` file.
+///
+/// The configuration is read from the [`GITBOOK_CONFIG_FILE_NAME`] file under `root`. The `title`,
+/// `description`, `author`, `isbn`, `language`, `direction` and `structure` entries of GitBook have
+/// the same meaning as in `book.toml` and are loaded into the same fields. The `root` entry of
+/// GitBook, which moves the content of the book into a subdirectory, is joined with `root` to
+/// become the `root` of the returned configuration. All other entries, such as `plugins` and
+/// `pluginsConfig`, are collected into `BookConfig::extra` in their TOML representation. Entries
+/// whose value is `null` are ignored, and a `null` inside an array is an error, since TOML cannot
+/// represent them.
+/// The file is parsed with `serde_json`, so loading it requires the `serde` feature, just like
+/// YAML files.
+///
+/// If the file does not exist, a default configuration is returned. Returns an error of kind
+/// `ErrorKind::Config` if the file is not valid JSON or if an entry has an unexpected type or
+/// value.
+///
+/// [`GITBOOK_CONFIG_FILE_NAME`]: constant.GITBOOK_CONFIG_FILE_NAME.html
+pub fn load_gitbook_config<F: FileSystem>(fs: &F, root: &Path) -> Result<BookConfig> {
+    let mut config = BookConfig {
+        root: root.to_path_buf(),
+        ..BookConfig::default()
+    };
+
+    let path = root.join(GITBOOK_CONFIG_FILE_NAME);
+    if !fs.has_file(&path) {
+        return Ok(config);
+    }
+
+    let content = fs.read_file_as_string(&path)?;
+    let table = match parse_json(&path, &content).map_err(Error::config)? {
+        Some(Value::Table(table)) => table,
+        _ => {
+            return Err(Error::config(format!(
+                "{}: expected an object at the top level",
+                path.display()
+            )))
+        }
+    };
+
+    let mut known = keys(CONFIG_ENTRIES);
+    known.push("root");
+    read_config_table(&path, &table, &known, &mut config)?;
+    if let Some(subdirectory) = (ConfigContext { path: &path }).get_path(&table, "root")? {
+        config.root = normalize(root.join(subdirectory));
+    }
+    Ok(config)
+}
+
+/// Read the entries of the specified table parsed from the configuration file at the specified
+/// path into the specified configuration.
+///
+/// Entries whose keys are not among the specified known keys are collected into
+/// `BookConfig::extra`.
+fn read_config_table(
+    path: &Path,
+    table: &Table,
+    known: &[&str],
+    config: &mut BookConfig,
+) -> Result<()> {
+    let context = ConfigContext { path };
+
+    config.title = context.get_string(table, "title")?;
+    config.description = context.get_string(table, "description")?;
+    config.author = context.get_string(table, "author")?;
+    config.isbn = context.get_string(table, "isbn")?;
+    config.language = context.get_string(table, "language")?;
+    config.edit_url_template = context.get_string(table, "edit-url-template")?;
+    config.title_from_content = context.get_bool(table, "title-from-content")?;
+    config.force_content_title = context.get_bool(table, "force-content-title")?;
+    config.direction = match context.get_string(table, "direction")?.as_deref() {
+        Some("ltr") => Some(TextDirection::Ltr),
+        Some("rtl") => Some(TextDirection::Rtl),
+        Some(direction) => {
+            return Err(context.error(format!(
+                "invalid direction \"{}\", expected \"ltr\" or \"rtl\"",
+                direction
+            )))
+        }
+        None => None,
+    };
+    config.frontmatter_format = match context.get_string(table, "frontmatter-format")?.as_deref() {
+        Some("auto") => Some(FrontmatterFormat::Auto),
+        Some("yaml") => Some(FrontmatterFormat::Yaml),
+        Some("toml") => Some(FrontmatterFormat::Toml),
+        Some("json") => Some(FrontmatterFormat::Json),
+        Some(format) => {
+            return Err(context.error(format!(
+            "invalid frontmatter-format \"{}\", expected \"auto\", \"yaml\", \"toml\" or \"json\"",
+            format
+        )))
+        }
+        None => None,
+    };
+
+    match table.get("structure") {
+        Some(Value::Table(structure)) => {
+            config.structure = BookStructureConfig {
+                readme: context.get_path(structure, "readme")?,
+                summary: context.get_path(structure, "summary")?,
+                glossary: context.get_path(structure, "glossary")?,
+                languages: context.get_path(structure, "languages")?,
+            };
+            collect_extra(structure, &keys(STRUCTURE_ENTRIES), "structure.", config);
+        }
+        Some(_) => return Err(context.error("\"structure\" should be a table")),
+        None => {}
+    }
+
+    match table.get("outputs") {
+        Some(Value::Table(outputs)) => {
+            let known = keys(OUTPUT_ENTRIES);
+            for (name, output) in outputs {
+                let output = match output {
+                    Value::Table(output) => output,
+                    _ => {
+                        return Err(context.error(format!("\"outputs.{}\" should be a table", name)))
+                    }
+                };
+                let mut options = BTreeMap::new();
+                for (key, value) in output {
+                    if !known.contains(&key.as_str()) {
+                        options.insert(key.clone(), extra_string(value));
+                    }
+                }
+                config.outputs.push(OutputConfig {
+                    name: name.clone(),
+                    format: context.get_string(output, "format")?,
+                    directory: context.get_path(output, "directory")?,
+                    options,
+                });
+            }
+        }
+        Some(_) => return Err(context.error("\"outputs\" should be a table")),
+        None => {}
+    }
+    collect_extra(table, known, "", config);
+
+    Ok(())
+}
+
+/// Add the entries of the specified table whose keys are not among the known keys to the `extra`
+/// entries of the specified configuration, prefixing their keys with the specified prefix.
+fn collect_extra(table: &Table, known: &[&str], prefix: &str, config: &mut BookConfig) {
+    for (key, value) in table {
+        if known.contains(&key.as_str()) {
+            continue;
+        }
+        config
+            .extra
+            .insert(format!("{}{}", prefix, key), extra_string(value));
+    }
+}
+
+/// Get the string that represents the specified value of an unrecognized entry, which is the
+/// string itself for strings and the TOML representation for other values.
+fn extra_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+impl BookConfig {
+    /// Override the entries of this configuration with the specified environment variables.
+    ///
+    /// Only variables whose names start with [`ENV_PREFIX`] are considered. The rest of the name is
+    /// the key of the entry in `book.toml`, where `__` separates the keys of nested tables and `_`
+    /// stands for `-`, case-insensitively. For example, `OPENBOOK_TITLE` overrides `title`,
+    /// `OPENBOOK_EDIT_URL_TEMPLATE` overrides `edit-url-template`, `OPENBOOK_STRUCTURE__SUMMARY`
+    /// overrides `summary` in the `structure` table, `OPENBOOK_OUTPUTS__HTML__DIRECTORY` overrides
+    /// the `directory` of the `html` build target and `OPENBOOK_OUTPUT__HTML__THEME` overrides
+    /// `theme` in the `output.html` table of `extra`. A leading `BOOK__` is accepted as well, so
+    /// `OPENBOOK_BOOK__TITLE` overrides `title` too. Keys that are not recognized are added to
+    /// `extra`. The root directory cannot be overridden.
+    ///
+    /// Returns an error of kind `ErrorKind::Config` if a value is invalid for its entry, e.g. an
+    /// unknown text direction.
+    ///
+    /// [`ENV_PREFIX`]: constant.ENV_PREFIX.html
+    pub fn apply_env_overrides<I>(&mut self, vars: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut table = Table::new();
+        for (name, value) in vars {
+            let key = match name.strip_prefix(ENV_PREFIX) {
+                Some(key) if !key.is_empty() => key.to_ascii_lowercase().replace("__", "."),
+                _ => continue,
+            };
+            let key = key.strip_prefix("book.").unwrap_or(&key).replace('_', "-");
+            let mut entry = Value::String(value);
+            for name in key.rsplit('.') {
+                let mut nested = Table::new();
+                nested.insert(name.to_owned(), entry);
+                entry = Value::Table(nested);
+            }
+            if let Value::Table(entry) = entry {
+                merge_tables(&mut table, entry);
+            }
+        }
+        if table.is_empty() {
+            return Ok(());
+        }
+
+        let mut overrides = BookConfig::default();
+        read_config_table(
+            Path::new("environment variables"),
+            &table,
+            &keys(CONFIG_ENTRIES),
+            &mut overrides,
+        )?;
+        *self = overrides.merge_over(self);
+        Ok(())
+    }
+
+    /// Get the entry of `extra` at the specified dotted key as a TOML value.
+    ///
+    /// The key starts with the key of an entry in `extra`, which may be followed by the keys of
+    /// tables nested in the entry: `output.html` gets the `html` table of the `output` entry, and
+    /// `output.html.theme` gets the `theme` entry of that table. Entries that were not strings in
+    /// the configuration file are decoded from their TOML representation, and all other entries
+    /// are strings. Returns `None` if there is no such entry.
+    pub fn get_extra(&self, key: &str) -> Option<Value> {
+        let mut split = key.len();
+        loop {
+            if let Some(raw) = self.extra.get(&key[..split]) {
+                let mut value = decode_extra(raw);
+                for name in key[split..].split('.').filter(|name| !name.is_empty()) {
+                    value = match value {
+                        Value::Table(mut table) => table.remove(name)?,
+                        _ => return None,
+                    };
+                }
+                return Some(value);
+            }
+            split = key[..split].rfind('.')?;
+        }
+    }
+
+    /// Deserialize the entry of `extra` at the specified dotted key, which is looked up as by
+    /// `get_extra`.
+    ///
+    /// An entry that is kept as a string in `extra` but decodes into another TOML value, like
+    /// `"42"`, is deserialized from either form. Returns `Ok(None)` if there is no such entry, or
+    /// an error of kind `ErrorKind::Config` if the entry cannot be deserialized into `T`.
+    #[cfg(feature = "serde")]
+    pub fn get_deserialized<T: serde::de::DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        let value = match self.get_extra(key) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        let raw = match (&value, self.extra.get(key)) {
+            (Value::String(_), _) | (_, None) => None,
+            (_, Some(raw)) => Some(Value::String(raw.clone())),
+        };
+        match value.try_into() {
+            Ok(value) => Ok(Some(value)),
+            Err(e) => match raw.map(Value::try_into) {
+                Some(Ok(value)) => Ok(Some(value)),
+                _ => Err(Error::config(format!("extra entry \"{}\": {}", key, e))),
+            },
+        }
+    }
+}
+
+/// Decode the specified entry of `BookConfig::extra` into a TOML value.
+fn decode_extra(raw: &str) -> Value {
+    match raw.parse::<Value>() {
+        Ok(Value::Table(table)) if !table.is_empty() => return Value::Table(table),
+        _ => {}
+    }
+    match format!("value = {}", raw).parse::<Value>() {
+        Ok(Value::Table(mut table)) => table
+            .remove("value")
+            .unwrap_or_else(|| Value::String(raw.to_owned())),
+        _ => Value::String(raw.to_owned()),
+    }
+}
+
+/// Merge the specified local entry of `BookConfig::extra` into the specified global entry with the
+/// same key, as by `BookConfig::merge_over`.
+///
+/// If both entries are tables, the tables are merged recursively and the entries of the local
+/// table take precedence. Otherwise, the local entry replaces the global entry.
+pub(crate) fn merge_extra(global: &str, local: &str) -> String {
+    match (decode_extra(global), decode_extra(local)) {
+        (Value::Table(mut global), Value::Table(local)) => {
+            merge_tables(&mut global, local);
+            Value::Table(global).to_string()
+        }
+        _ => local.to_owned(),
+    }
+}
+
+/// Merge the specified local table into the specified global table recursively.
+fn merge_tables(global: &mut Table, local: Table) {
+    for (key, value) in local {
+        let value = match (global.get_mut(&key), value) {
+            (Some(Value::Table(global)), Value::Table(local)) => {
+                merge_tables(global, local);
+                continue;
+            }
+            (_, value) => value,
+        };
+        global.insert(key, value);
+    }
+}
+
+/// Load the configuration of the book whose root directory is at the specified path, and validate
+/// the format of its fields with `BookConfig::validate_format`.
+///
+/// For how the configuration is loaded, please refer to [`load_config`].
+///
+/// [`load_config`]: fn.load_config.html
+pub fn load_validated_config<F: FileSystem>(fs: &F, root: &Path) -> Result<BookConfig> {
+    let config = load_config(fs, root)?;
+    config.validate_format()?;
+    Ok(config)
+}
+
+/// The ISO 639-1 language codes, in ascending order.
+const LANGUAGE_CODES: &[&str] = &[
+    "aa", "ab", "ae", "af", "ak", "am", "an", "ar", "as", "av", "ay", "az", "ba", "be", "bg", "bh",
+    "bi", "bm", "bn", "bo", "br", "bs", "ca", "ce", "ch", "co", "cr", "cs", "cu", "cv", "cy", "da",
+    "de", "dv", "dz", "ee", "el", "en", "eo", "es", "et", "eu", "fa", "ff", "fi", "fj", "fo", "fr",
+    "fy", "ga", "gd", "gl", "gn", "gu", "gv", "ha", "he", "hi", "ho", "hr", "ht", "hu", "hy", "hz",
+    "ia", "id", "ie", "ig", "ii", "ik", "io", "is", "it", "iu", "ja", "jv", "ka", "kg", "ki", "kj",
+    "kk", "kl", "km", "kn", "ko", "kr", "ks", "ku", "kv", "kw", "ky", "la", "lb", "lg", "li", "ln",
+    "lo", "lt", "lu", "lv", "mg", "mh", "mi", "mk", "ml", "mn", "mr", "ms", "mt", "my", "na", "nb",
+    "nd", "ne", "ng", "nl", "nn", "no", "nr", "nv", "ny", "oc", "oj", "om", "or", "os", "pa", "pi",
+    "pl", "ps", "pt", "qu", "rm", "rn", "ro", "ru", "rw", "sa", "sc", "sd", "se", "sg", "si", "sk",
+    "sl", "sm", "sn", "so", "sq", "sr", "ss", "st", "su", "sv", "sw", "ta", "te", "tg", "th", "ti",
+    "tk", "tl", "tn", "to", "tr", "ts", "tt", "tw", "ty", "ug", "uk", "ur", "uz", "ve", "vi", "vo",
+    "wa", "wo", "xh", "yi", "yo", "za", "zh", "zu",
+];
+
+/// A problem found in a configuration by `BookConfig::validate`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConfigDiagnostic {
+    /// Name of the offending entry, as it is written in `book.toml`, e.g. `isbn` or
+    /// `structure.summary`.
+    pub field: String,
+
+    /// Description of the problem.
+    pub message: String,
+
+    /// Suggestion on how to fix the problem.
+    pub hint: String,
+}
+
+impl Display for ConfigDiagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} ({})", self.field, self.message, self.hint)
+    }
+}
+
+impl BookConfig {
+    /// Validate the fields of this configuration that have a well-defined format, without
+    /// accessing any file system.
+    ///
+    /// `isbn`, if present, must be an ISBN-10 or ISBN-13 with a correct check digit; hyphens and
+    /// spaces between the digits are ignored. `language`, if present, must start with an ISO
+    /// 639-1 language code, optionally followed by subtags such as a region, like `en` or
+    /// `zh-CN`. Returns an error of kind `ErrorKind::Config` naming the first invalid field.
+    pub fn validate_format(&self) -> Result<()> {
+        match self.diagnose_format().into_iter().next() {
+            Some(diagnostic) => Err(Error::config(diagnostic.message)),
+            None => Ok(()),
+        }
+    }
+
+    /// Validate this configuration against the specified file system and collect all problems
+    /// found.
+    ///
+    /// In addition to the checks of `validate_format`, the root directory must exist, and every
+    /// path in `structure` that is set must point at a file under the root directory. Unlike
+    /// `validate_format`, every problem is reported rather than just the first one, in the order of
+    /// the fields. Returns an empty list if the configuration is valid.
+    pub fn validate<F: FileSystem>(&self, fs: &F) -> Vec<ConfigDiagnostic> {
+        let mut diagnostics = Vec::new();
+
+        let root = if self.root.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            self.root.as_path()
+        };
+        if !fs.has_dir(root) {
+            diagnostics.push(ConfigDiagnostic {
+                field: "root".to_owned(),
+                message: format!("root directory {} does not exist", root.display()),
+                hint: "create the directory or correct the path to the book".to_owned(),
+            });
+        } else {
+            let structure = &self.structure;
+            let paths = [
+                ("structure.readme", &structure.readme),
+                ("structure.summary", &structure.summary),
+                ("structure.glossary", &structure.glossary),
+                ("structure.languages", &structure.languages),
+            ];
+            for (field, path) in paths.iter() {
+                let path = match path {
+                    Some(path) => root.join(path),
+                    None => continue,
+                };
+                if !fs.has_file(&path) {
+                    diagnostics.push(ConfigDiagnostic {
+                        field: (*field).to_owned(),
+                        message: format!("file {} does not exist", path.display()),
+                        hint: "create the file, correct its path relative to the root directory \
+                               or remove the entry to use the default path"
+                            .to_owned(),
+                    });
+                }
+            }
+        }
+
+        diagnostics.extend(self.diagnose_format());
+        diagnostics
+    }
+
+    /// Collect the problems of the fields of this configuration that have a well-defined format.
+    fn diagnose_format(&self) -> Vec<ConfigDiagnostic> {
+        let mut diagnostics = Vec::new();
+        if let Some(isbn) = &self.isbn {
+            if !is_valid_isbn(isbn) {
+                diagnostics.push(ConfigDiagnostic {
+                    field: "isbn".to_owned(),
+                    message: format!(
+                        "invalid isbn \"{}\": expected an ISBN-10 or ISBN-13 with a correct check \
+                         digit",
+                        isbn
+                    ),
+                    hint: "check the digits against the ISBN of the book, or remove the entry"
+                        .to_owned(),
+                });
+            }
+        }
+        if let Some(language) = &self.language {
+            if !is_valid_language(language) {
+                diagnostics.push(ConfigDiagnostic {
+                    field: "language".to_owned(),
+                    message: format!(
+                        "invalid language \"{}\": expected an ISO 639-1 language code",
+                        language
+                    ),
+                    hint: "use a two-letter code such as \"en\", optionally followed by a \
+                           region such as \"en-US\""
+                        .to_owned(),
+                });
+            }
+        }
+        diagnostics
+    }
+}
+
+/// Determine whether the specified text is an ISBN-10 or an ISBN-13 with a correct check digit.
+fn is_valid_isbn(isbn: &str) -> bool {
+    let chars: Vec<char> = isbn.chars().filter(|c| !matches!(c, '-' | ' ')).collect();
+    match chars.len() {
+        10 => {
+            let mut sum = 0;
+            for (i, c) in chars.iter().enumerate() {
+                let digit = match c.to_digit(10) {
+                    Some(digit) => digit,
+                    None if i == 9 && matches!(c, 'X' | 'x') => 10,
+                    None => return false,
+                };
+                sum += digit * (10 - i as u32);
+            }
+            sum % 11 == 0
+        }
+        13 => {
+            let mut sum = 0;
+            for (i, c) in chars.iter().enumerate() {
+                let digit = match c.to_digit(10) {
+                    Some(digit) => digit,
+                    None => return false,
+                };
+                sum += if i % 2 == 0 { digit } else { digit * 3 };
+            }
+            sum % 10 == 0
+        }
+        _ => false,
+    }
+}
+
+/// Determine whether the specified text is an ISO 639-1 language code, optionally followed by
+/// subtags.
+fn is_valid_language(language: &str) -> bool {
+    let mut subtags = language.split(&['-', '_'][..]);
+    let code = subtags.next().unwrap_or_default().to_ascii_lowercase();
+    LANGUAGE_CODES.binary_search(&code.as_str()).is_ok()
+        && subtags
+            .all(|s| !s.is_empty() && s.len() <= 8 && s.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// Get the JSON Schema of configuration files, which editors and CI can use to validate
+/// `book.toml` files.
+///
+/// The schema is written in the [`SCHEMA_DIALECT`] and describes the type of every entry that is
+/// loaded into `BookConfig`, including the entries of the `structure` and `outputs` tables.
+/// Unrecognized entries are allowed in every table, since they are kept in `BookConfig::extra` or
+/// in the options of build targets.
+///
+/// [`SCHEMA_DIALECT`]: constant.SCHEMA_DIALECT.html
+pub fn schema() -> String {
+    let mut root = vec![
+        ("$schema", Json::String(SCHEMA_DIALECT.to_owned())),
+        ("title", Json::String("OpenBook configuration".to_owned())),
+    ];
+    if let Json::Object(entries) = table_schema(
+        "Configuration of an OpenBook book, read from its book.toml file.",
+        CONFIG_ENTRIES,
+    ) {
+        root.extend(entries);
+    }
+
+    let mut output = String::new();
+    Json::Object(root).write(0, &mut output);
+    output.push('\n');
+    output
+}
+
+/// Get the schema of a table with the specified description and entries.
+fn table_schema(description: &str, entries: &[Entry]) -> Json {
+    let properties = entries
+        .iter()
+        .map(|entry| (entry.key, entry_schema(entry)))
+        .collect();
+    Json::Object(vec![
+        ("description", Json::String(description.to_owned())),
+        ("type", Json::String("object".to_owned())),
+        ("properties", Json::Object(properties)),
+        ("additionalProperties", Json::Bool(true)),
+    ])
+}
+
+/// Get the schema of the specified entry.
+fn entry_schema(entry: &Entry) -> Json {
+    let description = Json::String(entry.description.to_owned());
+    match &entry.kind {
+        EntryKind::String => Json::Object(vec![
+            ("description", description),
+            ("type", Json::String("string".to_owned())),
+        ]),
+        EntryKind::Boolean => Json::Object(vec![
+            ("description", description),
+            ("type", Json::String("boolean".to_owned())),
+        ]),
+        EntryKind::Enum(values) => Json::Object(vec![
+            ("description", description),
+            ("type", Json::String("string".to_owned())),
+            (
+                "enum",
+                Json::Array(
+                    values
+                        .iter()
+                        .map(|v| Json::String((*v).to_owned()))
+                        .collect(),
+                ),
+            ),
+        ]),
+        EntryKind::Table(entries) => table_schema(entry.description, entries),
+        EntryKind::TableOf(entries) => Json::Object(vec![
+            ("description", description),
+            ("type", Json::String("object".to_owned())),
+            (
+                "additionalProperties",
+                table_schema(entry.description, entries),
+            ),
+        ]),
+    }
+}
+
+/// A JSON value of a schema.
+enum Json {
+    Bool(bool),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    /// Write this value with the specified indentation level, indenting by two spaces per level.
+    fn write(&self, indent: usize, output: &mut String) {
+        match self {
+            Json::Bool(value) => output.push_str(if *value { "true" } else { "false" }),
+            Json::String(value) => output.push_str(&json_string(value)),
+            Json::Array(items) => {
+                output.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        output.push_str(", ");
+                    }
+                    item.write(indent, output);
+                }
+                output.push(']');
+            }
+            Json::Object(entries) => {
+                output.push('{');
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        output.push(',');
+                    }
+                    output.push('\n');
+                    output.push_str(&"  ".repeat(indent + 1));
+                    output.push_str(&json_string(key));
+                    output.push_str(": ");
+                    value.write(indent + 1, output);
+                }
+                if !entries.is_empty() {
+                    output.push('\n');
+                    output.push_str(&"  ".repeat(indent));
+                }
+                output.push('}');
+            }
+        }
+    }
+}
+
+/// Reads typed entries from a parsed configuration file.
+struct ConfigContext<'a> {
+    path: &'a Path,
+}
+
+impl<'a> ConfigContext<'a> {
+    fn error<M: AsRef<str>>(&self, msg: M) -> Error {
+        Error::config(format!("{}: {}", self.path.display(), msg.as_ref()))
+    }
+
+    fn get_string(&self, table: &Table, key: &str) -> Result<Option<String>> {
+        match table.get(key) {
+            Some(Value::String(value)) => Ok(Some(value.clone())),
+            Some(_) => Err(self.error(format!("\"{}\" should be a string", key))),
+            None => Ok(None),
+        }
+    }
+
+    fn get_path(&self, table: &Table, key: &str) -> Result<Option<PathBuf>> {
+        Ok(self.get_string(table, key)?.map(PathBuf::from))
+    }
+
+    /// Read a boolean entry, which may also be the string `"true"` or `"false"` as given by
+    /// environment variables.
+    fn get_bool(&self, table: &Table, key: &str) -> Result<Option<bool>> {
+        match table.get(key) {
+            Some(Value::Boolean(value)) => Ok(Some(*value)),
+            Some(Value::String(value)) if value == "true" => Ok(Some(true)),
+            Some(Value::String(value)) if value == "false" => Ok(Some(false)),
+            Some(_) => Err(self.error(format!("\"{}\" should be a boolean", key))),
+            None => Ok(None),
+        }
+    }
+}
+
+/// The line and column, both starting from 1, at which a JSON document is malformed, and the
+/// description of the problem.
+type JsonError = (usize, usize, String);
+
+/// Get the position and description of the specified JSON parse error.
+#[cfg(feature = "serde")]
+fn json_error(e: serde_json::Error) -> JsonError {
+    // The position is reported separately, so it is removed from the description.
+    let mut msg = e.to_string();
+    let suffix = format!(" at line {} column {}", e.line(), e.column());
+    if let Some(len) = msg.strip_suffix(&suffix).map(str::len) {
+        msg.truncate(len);
+    }
+    (e.line(), e.column(), msg)
+}
+
+/// Convert the specified JSON value into a TOML value, or `None` if it is `null`.
+///
+/// Integers become `Value::Integer`, other numbers become `Value::Float` and entries of objects
+/// whose value is `null` are dropped. TOML cannot represent a `null` inside an array, so an error
+/// message is returned for it instead.
+#[cfg(feature = "serde")]
+fn json_to_toml(value: serde_json::Value) -> std::result::Result<Option<Value>, String> {
+    use serde_json::Value as Json;
+
+    let value = match value {
+        Json::Null => return Ok(None),
+        Json::Bool(b) => Value::Boolean(b),
+        Json::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Json::String(s) => Value::String(s),
+        Json::Array(items) => {
+            let mut array = Vec::with_capacity(items.len());
+            for item in items {
+                match json_to_toml(item)? {
+                    Some(item) => array.push(item),
+                    None => return Err("null is not supported in arrays".to_owned()),
+                }
+            }
+            Value::Array(array)
+        }
+        Json::Object(object) => {
+            let mut table = Table::new();
+            for (key, value) in object {
+                if let Some(value) = json_to_toml(value)? {
+                    table.insert(key, value);
+                }
+            }
+            Value::Table(table)
+        }
+    };
+    Ok(Some(value))
+}
+
+/// Parse the specified JSON configuration file content into a TOML value, which is `None` for
+/// `null`.
+///
+/// Errors are reported as `path:line:column: description`, or `path: description` if the problem
+/// has no position.
+#[cfg(feature = "serde")]
+fn parse_json(path: &Path, content: &str) -> std::result::Result<Option<Value>, String> {
+    let value: serde_json::Value = serde_json::from_str(content).map_err(|e| {
+        let (line, column, msg) = json_error(e);
+        format!("{}:{}:{}: {}", path.display(), line, column, msg)
+    })?;
+    json_to_toml(value).map_err(|msg| format!("{}: {}", path.display(), msg))
+}
+
+/// Report that JSON configuration files cannot be parsed without the `serde` feature.
+#[cfg(not(feature = "serde"))]
+fn parse_json(path: &Path, _content: &str) -> std::result::Result<Option<Value>, String> {
+    Err(format!(
+        "{}: JSON configuration files require the \"serde\" feature of OpenBook",
+        path.display()
+    ))
+}
+
+/// Parse the JSON value at the beginning of the specified text, which is `None` for `null`, and get
+/// it with the length of the text that it spans.
+///
+/// The rest of the text is left unparsed. Values are converted like [`load_gitbook_config`] does.
+///
+/// [`load_gitbook_config`]: fn.load_gitbook_config.html
+#[cfg(feature = "serde")]
+pub(crate) fn parse_json_prefix(
+    text: &str,
+) -> std::result::Result<(Option<Value>, usize), JsonError> {
+    let mut values = serde_json::Deserializer::from_str(text).into_iter::<serde_json::Value>();
+    let value = match values.next() {
+        Some(value) => value.map_err(json_error)?,
+        None => return Err((1, 1, "expected a JSON value".to_owned())),
+    };
+    let end = values.byte_offset();
+    let value = json_to_toml(value).map_err(|msg| {
+        let line = 1 + text[..end].matches('\n').count();
+        (line, 1, msg)
+    })?;
+    Ok((value, end))
+}
+
+/// Report that JSON cannot be parsed without the `serde` feature.
+#[cfg(not(feature = "serde"))]
+pub(crate) fn parse_json_prefix(
+    _text: &str,
+) -> std::result::Result<(Option<Value>, usize), JsonError> {
+    Err((
+        1,
+        1,
+        "JSON frontmatters require the \"serde\" feature of OpenBook".to_owned(),
+    ))
+}
+
+/// Convert the specified YAML value into a TOML value, or `None` if it is `null`.
+///
+/// `null`s are dropped, since TOML cannot represent them, and tags are ignored. Returns an error
+/// message if a mapping has a key that is not a string, a boolean or a number.
+#[cfg(feature = "serde")]
+pub(crate) fn yaml_to_toml(value: serde_yaml::Value) -> std::result::Result<Option<Value>, String> {
+    use serde_yaml::Value as Yaml;
+
+    let value = match value {
+        Yaml::Null => return Ok(None),
+        Yaml::Bool(b) => Value::Boolean(b),
+        Yaml::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Yaml::String(s) => Value::String(s),
+        Yaml::Sequence(items) => {
+            let mut array = Vec::with_capacity(items.len());
+            for item in items {
+                array.extend(yaml_to_toml(item)?);
+            }
+            Value::Array(array)
+        }
+        Yaml::Mapping(mapping) => {
+            let mut table = Table::new();
+            for (key, value) in mapping {
+                let key = match key {
+                    Yaml::String(s) => s,
+                    Yaml::Bool(b) => b.to_string(),
+                    Yaml::Number(n) => n.to_string(),
+                    key => return Err(format!("unsupported mapping key {:?}", key)),
+                };
+                if let Some(value) = yaml_to_toml(value)? {
+                    table.insert(key, value);
+                }
+            }
+            Value::Table(table)
+        }
+        Yaml::Tagged(tagged) => return yaml_to_toml(tagged.value),
+    };
+    Ok(Some(value))
+}
+
+/// Parse the specified YAML configuration file content into a TOML value.
+#[cfg(feature = "serde")]
+fn parse_yaml(path: &Path, content: &str) -> std::result::Result<Value, String> {
+    let value: serde_yaml::Value =
+        serde_yaml::from_str(content).map_err(|e| format!("{}: {}", path.display(), e))?;
+    yaml_to_toml(value)
+        .map(|value| value.unwrap_or_else(|| Value::Table(Table::new())))
+        .map_err(|msg| format!("{}: {}", path.display(), msg))
+}
+
+/// Report that YAML configuration files cannot be parsed without the `serde` feature.
+#[cfg(not(feature = "serde"))]
+fn parse_yaml(path: &Path, _content: &str) -> std::result::Result<Value, String> {
+    Err(format!(
+        "{}: YAML configuration files require the \"serde\" feature of OpenBook",
+        path.display()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::fs::scratch::ScratchFileSystem;
+    use crate::tree::builder::BookConfigBuilder;
+
+    const ROOT: &str = "/openbook-test/config";
+
+    fn fields(diagnostics: &[ConfigDiagnostic]) -> Vec<&str> {
+        diagnostics.iter().map(|d| d.field.as_str()).collect()
+    }
+
+    #[test]
+    fn validate_accepts_a_valid_configuration() {
+        let fs = ScratchFileSystem::new();
+        fs.write_file(Path::new(ROOT).join("TOC.md"), b"# Summary\n")
+            .unwrap();
+        let config = BookConfigBuilder::new()
+            .set_root(ROOT)
+            .set_summary("TOC.md")
+            .set_isbn("978-0-306-40615-7")
+            .set_language("zh-CN")
+            .build();
+
+        assert_eq!(config.validate(&fs), Vec::new());
+        assert!(config.validate_format().is_ok());
+    }
+
+    #[test]
+    fn validate_reports_every_problem() {
+        let fs = ScratchFileSystem::new();
+        fs.create_dir_all(ROOT).unwrap();
+        let config = BookConfigBuilder::new()
+            .set_root(ROOT)
+            .set_summary("TOC.md")
+            .set_glossary("terms.md")
+            .set_isbn("0-306-40615-3")
+            .set_language("english")
+            .build();
+
+        let diagnostics = config.validate(&fs);
+        assert_eq!(
+            fields(&diagnostics),
+            vec![
+                "structure.summary",
+                "structure.glossary",
+                "isbn",
+                "language"
+            ]
+        );
+        assert!(diagnostics.iter().all(|d| !d.hint.is_empty()));
+    }
+
+    #[test]
+    fn isbns_are_checked_by_their_checksums() {
+        for isbn in &[
+            "0-306-40615-2",
+            "0306406152",
+            "0-8044-2957-X",
+            "0 8044 2957 x",
+            "978-0-306-40615-7",
+            "9780306406157",
+        ] {
+            assert!(is_valid_isbn(isbn), "{}", isbn);
+        }
+        for isbn in &[
+            "0-306-40615-3",
+            "978-0-306-40615-8",
+            "X-306-40615-2",
+            "978-0-306-4061X-7",
+            "030640615",
+            "",
+        ] {
+            assert!(!is_valid_isbn(isbn), "{}", isbn);
+        }
+    }
+
+    #[test]
+    fn languages_are_iso_639_1_codes_with_optional_subtags() {
+        for language in &["en", "EN", "zh-CN", "zh_Hant_TW", "pt-BR", "sr-Latn"] {
+            assert!(is_valid_language(language), "{}", language);
+        }
+        for language in &[
+            "english",
+            "xx",
+            "zh-",
+            "en--US",
+            "en-toolongsubtag",
+            "en-U$",
+            "",
+        ] {
+            assert!(!is_valid_language(language), "{}", language);
+        }
+    }
+
+    #[test]
+    fn validate_reports_a_missing_root_only_once() {
+        let fs = ScratchFileSystem::new();
+        let config = BookConfigBuilder::new()
+            .set_root(ROOT)
+            .set_summary("TOC.md")
+            .build();
+
+        assert_eq!(fields(&config.validate(&fs)), vec!["root"]);
+    }
+
+    #[test]
+    fn validate_format_fails_on_the_first_invalid_field() {
+        let config = BookConfigBuilder::new()
+            .set_isbn("0-306-40615-3")
+            .set_language("xx")
+            .build();
+        let error = config.validate_format().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Config);
+        assert!(error.to_string().contains("isbn"));
+        assert!(is_valid_isbn("0-306-40615-2"));
+        assert!(!is_valid_language("xx"));
+    }
+
+    #[test]
+    fn find_config_file_follows_the_precedence_order() {
+        let fs = ScratchFileSystem::new();
+        let root = Path::new(ROOT);
+        assert_eq!(find_config_file(&fs, root), None);
+
+        fs.write_file(root.join(GITBOOK_CONFIG_FILE_NAME), b"{}")
+            .unwrap();
+        assert_eq!(
+            find_config_file(&fs, root),
+            Some((ConfigFormat::Json, root.join(GITBOOK_CONFIG_FILE_NAME)))
+        );
+        fs.write_file(root.join(YAML_CONFIG_FILE_NAME), b"")
+            .unwrap();
+        assert_eq!(find_config_file(&fs, root).unwrap().0, ConfigFormat::Yaml);
+        fs.write_file(root.join(CONFIG_FILE_NAME), b"").unwrap();
+        assert_eq!(find_config_file(&fs, root).unwrap().0, ConfigFormat::Toml);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn yaml_and_toml_files_load_the_same_entries() {
+        let toml = "title = \"Book\"\ndirection = \"rtl\"\n\n[structure]\nsummary = \"TOC.md\"\n\n\
+                    [outputs.html]\ndirectory = \"out\"\nfold = true\n\n[plugins]\nsearch = [1, 2]\n";
+        let yaml = "# comment\ntitle: Book\ndirection: rtl\nisbn: ~\nstructure:\n  summary: TOC.md\n\
+                    outputs:\n  html:\n    directory: out\n    fold: true\nplugins:\n  search: [1, 2]\n";
+
+        let toml_fs = ScratchFileSystem::new();
+        toml_fs
+            .write_file(Path::new(ROOT).join(CONFIG_FILE_NAME), toml.as_bytes())
+            .unwrap();
+        let yaml_fs = ScratchFileSystem::new();
+        yaml_fs
+            .write_file(Path::new(ROOT).join(YAML_CONFIG_FILE_NAME), yaml.as_bytes())
+            .unwrap();
+
+        let config = load_config(&yaml_fs, Path::new(ROOT)).unwrap();
+        assert_eq!(config, load_config(&toml_fs, Path::new(ROOT)).unwrap());
+        assert_eq!(config.title.as_deref(), Some("Book"));
+        assert_eq!(config.isbn, None);
+        assert_eq!(
+            config.outputs[0].options.get("fold").map(String::as_str),
+            Some("true")
+        );
+        assert_eq!(
+            config.get_extra("plugins.search"),
+            Some(Value::Array(vec![Value::Integer(1), Value::Integer(2),]))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn invalid_yaml_files_are_config_errors() {
+        let fs = ScratchFileSystem::new();
+        let path = Path::new(ROOT).join(YAML_CONFIG_FILE_NAME);
+        fs.write_file(&path, b"title: [unclosed\n").unwrap();
+        let error = load_config(&fs, Path::new(ROOT)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Config);
+        assert!(error.to_string().contains(&path.display().to_string()));
+
+        fs.write_file(&path, b"- a\n- b\n").unwrap();
+        let error = load_config(&fs, Path::new(ROOT)).unwrap_err();
+        assert!(error.to_string().contains("expected a table"));
+
+        fs.write_file(&path, b"title: 1\n").unwrap();
+        assert_eq!(
+            load_config(&fs, Path::new(ROOT)).unwrap_err().kind(),
+            ErrorKind::Config
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "serde"))]
+    fn yaml_files_require_the_serde_feature() {
+        let fs = ScratchFileSystem::new();
+        fs.write_file(
+            Path::new(ROOT).join(YAML_CONFIG_FILE_NAME),
+            b"title: Book\n",
+        )
+        .unwrap();
+        let error = load_config(&fs, Path::new(ROOT)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Config);
+        assert!(error.to_string().contains("serde"));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn gitbook_files_accept_the_toml_entries() {
+        let fs = ScratchFileSystem::new();
+        let json = r#"{
+            "title": "Book",
+            "direction": "rtl",
+            "root": "./docs",
+            "structure": { "summary": "TOC.md" },
+            "outputs": { "html": { "directory": "out" } },
+            "plugins": ["search"],
+            "isbn": null
+        }"#;
+        fs.write_file(
+            Path::new(ROOT).join(GITBOOK_CONFIG_FILE_NAME),
+            json.as_bytes(),
+        )
+        .unwrap();
+
+        let config = load_config(&fs, Path::new(ROOT)).unwrap();
+        assert_eq!(config.root, Path::new(ROOT).join("docs"));
+        assert_eq!(config.title.as_deref(), Some("Book"));
+        assert_eq!(config.direction, Some(TextDirection::Rtl));
+        assert_eq!(config.structure.summary, Some(PathBuf::from("TOC.md")));
+        assert_eq!(config.outputs[0].directory, Some(PathBuf::from("out")));
+        assert_eq!(config.isbn, None);
+        assert!(config.get_extra("root").is_none());
+        assert!(config.get_extra("plugins").is_some());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn gitbook_files_map_onto_book_configs() {
+        let fs = ScratchFileSystem::new();
+        let json = r#"{
+            "title": "Migrated",
+            "description": "From GitBook",
+            "author": "Ana",
+            "language": "fr",
+            "structure": {
+                "readme": "INTRO.md",
+                "glossary": "TERMS.md",
+                "languages": "LANGUAGES.md"
+            },
+            "plugins": ["search", "-sharing"],
+            "pluginsConfig": { "search": { "maxIndexSize": 100000 } }
+        }"#;
+        fs.write_file(
+            Path::new(ROOT).join(GITBOOK_CONFIG_FILE_NAME),
+            json.as_bytes(),
+        )
+        .unwrap();
+
+        let config = load_gitbook_config(&fs, Path::new(ROOT)).unwrap();
+        assert_eq!(config, load_config(&fs, Path::new(ROOT)).unwrap());
+        assert_eq!(config.root, PathBuf::from(ROOT));
+        assert_eq!(config.description.as_deref(), Some("From GitBook"));
+        assert_eq!(config.author.as_deref(), Some("Ana"));
+        assert_eq!(config.language.as_deref(), Some("fr"));
+        assert_eq!(config.structure.readme, Some(PathBuf::from("INTRO.md")));
+        assert_eq!(config.structure.glossary, Some(PathBuf::from("TERMS.md")));
+        assert_eq!(
+            config.structure.languages,
+            Some(PathBuf::from("LANGUAGES.md"))
+        );
+        assert_eq!(
+            config.get_extra("plugins"),
+            Some(Value::Array(vec![
+                Value::String("search".to_owned()),
+                Value::String("-sharing".to_owned()),
+            ]))
+        );
+        assert_eq!(
+            config.get_extra("pluginsConfig.search.maxIndexSize"),
+            Some(Value::Integer(100000))
+        );
+    }
+
+    #[test]
+    fn gitbook_files_are_loaded_only_without_book_toml() {
+        let fs = ScratchFileSystem::new();
+        let root = Path::new(ROOT);
+        assert_eq!(
+            load_gitbook_config(&fs, root).unwrap(),
+            BookConfigBuilder::new().set_root(ROOT).build()
+        );
+
+        fs.write_file(root.join(GITBOOK_CONFIG_FILE_NAME), b"{ \"title\": ")
+            .unwrap();
+        let err = load_config(&fs, root).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Config);
+
+        fs.write_file(root.join(CONFIG_FILE_NAME), b"title = \"TOML\"\n")
+            .unwrap();
+        assert_eq!(
+            load_config(&fs, root).unwrap().title.as_deref(),
+            Some("TOML")
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn gitbook_files_report_invalid_json() {
+        let fs = ScratchFileSystem::new();
+        let root = Path::new(ROOT);
+        let path = root.join(GITBOOK_CONFIG_FILE_NAME);
+        let load = |json: &str| {
+            fs.write_file(&path, json.as_bytes()).unwrap();
+            load_gitbook_config(&fs, root).unwrap_err().to_string()
+        };
+
+        let error = load("{\n  \"title\": tru,\n}");
+        assert!(error.contains("book.json:2:"), "{}", error);
+        assert!(!error.contains(" at line "), "{}", error);
+        assert!(load("[]").contains("expected an object at the top level"));
+        assert!(load("{ \"plugins\": [\"search\", null] }").contains("null"));
+
+        // Deeply nested values are rejected instead of overflowing the stack.
+        let error = load(&"[".repeat(100_000));
+        assert!(error.contains("recursion limit"), "{}", error);
+    }
+
+    #[test]
+    #[cfg(not(feature = "serde"))]
+    fn gitbook_files_require_the_serde_feature() {
+        let fs = ScratchFileSystem::new();
+        fs.write_file(Path::new(ROOT).join(GITBOOK_CONFIG_FILE_NAME), b"{}")
+            .unwrap();
+        let error = load_config(&fs, Path::new(ROOT)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Config);
+        assert!(error.to_string().contains("serde"));
+    }
+
+    #[test]
+    fn reads_title_options() {
+        let fs = ScratchFileSystem::new();
+        fs.write_file(
+            Path::new(ROOT).join(CONFIG_FILE_NAME),
+            b"title-from-content = true\n",
+        )
+        .unwrap();
+        let mut config = load_config(&fs, Path::new(ROOT)).unwrap();
+        assert!(config.effective_title_from_content());
+        assert!(!config.effective_force_content_title());
+
+        let env = vec![("OPENBOOK_FORCE_CONTENT_TITLE".to_owned(), "true".to_owned())];
+        config.apply_env_overrides(env).unwrap();
+        assert_eq!(config.force_content_title, Some(true));
+
+        fs.write_file(
+            Path::new(ROOT).join(CONFIG_FILE_NAME),
+            b"title-from-content = \"yes\"\n",
+        )
+        .unwrap();
+        let err = load_config(&fs, Path::new(ROOT)).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("\"title-from-content\" should be a boolean"));
+    }
+
+    #[test]
+    fn reads_frontmatter_format() {
+        let fs = ScratchFileSystem::new();
+        fs.write_file(
+            Path::new(ROOT).join(CONFIG_FILE_NAME),
+            b"frontmatter-format = \"toml\"\n",
+        )
+        .unwrap();
+        let config = load_config(&fs, Path::new(ROOT)).unwrap();
+        assert_eq!(
+            config.effective_frontmatter_format(),
+            FrontmatterFormat::Toml
+        );
+        assert_eq!(
+            BookConfig::default().effective_frontmatter_format(),
+            FrontmatterFormat::Auto
+        );
+
+        fs.write_file(
+            Path::new(ROOT).join(CONFIG_FILE_NAME),
+            b"frontmatter-format = \"xml\"\n",
+        )
+        .unwrap();
+        let err = load_config(&fs, Path::new(ROOT)).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("invalid frontmatter-format \"xml\""));
+    }
+
+    #[test]
+    fn loads_book_toml_and_keeps_unrecognized_entries() {
+        let fs = ScratchFileSystem::new();
+        let toml = "title = \"OpenBook\"\nauthor = \"Sirui Mu\"\nisbn = \"978-3-16-148410-0\"\n\
+                    language = \"he\"\ndirection = \"rtl\"\ntheme = \"dark\"\n\n\
+                    [structure]\nsummary = \"TOC.md\"\nindex = \"INDEX.md\"\n\n\
+                    [plugins.search]\nlimit = 10\n";
+        fs.write_file(Path::new(ROOT).join(CONFIG_FILE_NAME), toml.as_bytes())
+            .unwrap();
+
+        let config = load_config(&fs, Path::new(ROOT)).unwrap();
+        assert_eq!(config.root, PathBuf::from(ROOT));
+        assert_eq!(config.title.as_deref(), Some("OpenBook"));
+        assert_eq!(config.author.as_deref(), Some("Sirui Mu"));
+        assert_eq!(config.isbn.as_deref(), Some("978-3-16-148410-0"));
+        assert_eq!(config.language.as_deref(), Some("he"));
+        assert_eq!(config.direction, Some(TextDirection::Rtl));
+        assert_eq!(config.structure.summary, Some(PathBuf::from("TOC.md")));
+
+        assert_eq!(config.extra["theme"], "dark");
+        assert_eq!(config.extra["structure.index"], "INDEX.md");
+        assert_eq!(
+            config.get_extra("plugins.search.limit"),
+            Some(Value::Integer(10))
+        );
+    }
+
+    #[test]
+    fn malformed_book_toml_files_are_config_errors() {
+        let fs = ScratchFileSystem::new();
+        let path = Path::new(ROOT).join(CONFIG_FILE_NAME);
+        for content in &[
+            "title = ",
+            "title = 1\n",
+            "direction = \"up\"\n",
+            "structure = 1\n",
+        ] {
+            fs.write_file(&path, content.as_bytes()).unwrap();
+            let err = load_config(&fs, Path::new(ROOT)).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Config, "{}: {}", content, err);
+        }
+        assert_eq!(
+            load_config(&ScratchFileSystem::new(), Path::new(ROOT)).unwrap(),
+            BookConfigBuilder::new().set_root(ROOT).build()
+        );
+    }
+
+    #[test]
+    fn book_toml_structure_paths_locate_the_special_files() {
+        let fs = ScratchFileSystem::new();
+        let root = Path::new(ROOT);
+        let toml = "[structure]\nreadme = \"docs/INTRO.md\"\nsummary = \"docs/TOC.md\"\n\
+                    glossary = \"docs/TERMS.md\"\nlanguages = \"LANGUAGES.md\"\n";
+        fs.write_file(root.join(CONFIG_FILE_NAME), toml.as_bytes())
+            .unwrap();
+        fs.write_file(root.join("docs/TOC.md"), b"* [Setup](setup.md)\n")
+            .unwrap();
+        fs.write_file(root.join("docs/setup.md"), b"Setup.\n")
+            .unwrap();
+        fs.write_file(root.join("docs/INTRO.md"), b"# Intro\n")
+            .unwrap();
+
+        let config = load_config(&fs, root).unwrap();
+        let structure = &config.structure;
+        assert_eq!(structure.readme, Some(PathBuf::from("docs/INTRO.md")));
+        assert_eq!(structure.summary, Some(PathBuf::from("docs/TOC.md")));
+        assert_eq!(structure.glossary, Some(PathBuf::from("docs/TERMS.md")));
+        assert_eq!(structure.languages, Some(PathBuf::from("LANGUAGES.md")));
+
+        let book = crate::parse::parse_summary(&fs, &config).unwrap();
+        assert_eq!(book.preface.name, "Intro");
+        assert_eq!(
+            book.chapters[0].sections[0].file,
+            PathBuf::from("docs/setup.md")
+        );
+    }
+
+    #[test]
+    fn book_toml_errors_name_the_file_and_the_entry() {
+        let fs = ScratchFileSystem::new();
+        let path = Path::new(ROOT).join(CONFIG_FILE_NAME);
+        fs.write_file(&path, b"[structure]\nsummary = 1\n").unwrap();
+        let err = load_config(&fs, Path::new(ROOT)).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains(&path.display().to_string()), "{}", message);
+        assert!(message.contains("\"summary\""), "{}", message);
+    }
+
+    #[test]
+    fn environment_variables_override_entries() {
+        let vars = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        let mut config = BookConfigBuilder::new()
+            .set_root(ROOT)
+            .set_title("File title")
+            .set_author("File author")
+            .build();
+        config
+            .apply_env_overrides(vars(&[
+                ("OPENBOOK_BOOK__TITLE", "CI title"),
+                ("OPENBOOK_EDIT_URL_TEMPLATE", "https://example.com/{path}"),
+                ("OPENBOOK_STRUCTURE__SUMMARY", "TOC.md"),
+                ("OPENBOOK_OUTPUTS__HTML__DIRECTORY", "public"),
+                ("OPENBOOK_OUTPUT__HTML__THEME", "dark"),
+                ("OPENBOOK_ROOT", "/elsewhere"),
+                ("HOME", "/root"),
+                ("OPENBOOK_", "ignored"),
+            ]))
+            .unwrap();
+
+        assert_eq!(config.title.as_deref(), Some("CI title"));
+        assert_eq!(config.author.as_deref(), Some("File author"));
+        assert_eq!(
+            config.edit_url_template.as_deref(),
+            Some("https://example.com/{path}")
+        );
+        assert_eq!(config.structure.summary, Some(PathBuf::from("TOC.md")));
+        assert_eq!(config.outputs[0].name, "html");
+        assert_eq!(config.outputs[0].directory, Some(PathBuf::from("public")));
+        assert_eq!(
+            config.get_extra("output.html.theme"),
+            Some(Value::String("dark".to_owned()))
+        );
+        assert_eq!(config.root, PathBuf::from(ROOT));
+
+        let err = config
+            .apply_env_overrides(vars(&[("OPENBOOK_DIRECTION", "up")]))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Config);
+        assert!(err.to_string().contains("environment variables"), "{}", err);
+    }
+
+    #[test]
+    fn extra_entries_are_read_as_typed_values() {
+        let fs = ScratchFileSystem::new();
+        let toml = "theme = \"dark\"\nport = \"42\"\n\n\
+                    [output.html]\ndirectory = \"public\"\nfold = true\ndepth = 2\n";
+        fs.write_file(Path::new(ROOT).join(CONFIG_FILE_NAME), toml.as_bytes())
+            .unwrap();
+        let config = load_config(&fs, Path::new(ROOT)).unwrap();
+
+        assert_eq!(
+            config.get_extra("theme"),
+            Some(Value::String("dark".to_owned()))
+        );
+        assert_eq!(
+            config.get_extra("output.html.fold"),
+            Some(Value::Boolean(true))
+        );
+        assert_eq!(
+            config.get_extra("output.html.depth"),
+            Some(Value::Integer(2))
+        );
+        assert!(matches!(
+            config.get_extra("output.html"),
+            Some(Value::Table(_))
+        ));
+        assert_eq!(config.get_extra("output.pdf"), None);
+        assert_eq!(config.get_extra("theme.name"), None);
+        assert_eq!(config.get_extra("missing"), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn extra_entries_are_deserialized() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Html {
+            directory: String,
+            fold: bool,
+        }
+
+        let fs = ScratchFileSystem::new();
+        let toml = "port = \"42\"\n\n[output.html]\ndirectory = \"public\"\nfold = true\n";
+        fs.write_file(Path::new(ROOT).join(CONFIG_FILE_NAME), toml.as_bytes())
+            .unwrap();
+        let config = load_config(&fs, Path::new(ROOT)).unwrap();
+
+        assert_eq!(
+            config.get_deserialized::<Html>("output.html").unwrap(),
+            Some(Html {
+                directory: "public".to_owned(),
+                fold: true,
+            })
+        );
+        assert_eq!(config.get_deserialized::<u32>("port").unwrap(), Some(42));
+        assert_eq!(
+            config
+                .get_deserialized::<String>("port")
+                .unwrap()
+                .as_deref(),
+            Some("42")
+        );
+        assert_eq!(config.get_deserialized::<u32>("missing").unwrap(), None);
+
+        let err = config.get_deserialized::<u32>("output.html").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Config);
+        assert!(err.to_string().contains("\"output.html\""), "{}", err);
+    }
+
+    #[test]
+    fn extra_tables_are_merged_recursively() {
+        let fs = ScratchFileSystem::new();
+        let root = Path::new(ROOT);
+        fs.write_file(
+            root.join(CONFIG_FILE_NAME),
+            b"[output.html]\ntheme = \"light\"\nfold = true\n",
+        )
+        .unwrap();
+        fs.write_file(
+            root.join("zh").join(CONFIG_FILE_NAME),
+            b"[output.html]\ntheme = \"dark\"\n\n[output.pdf]\npaper = \"a4\"\n",
+        )
+        .unwrap();
+        let global = load_config(&fs, root).unwrap();
+        let local = load_config(&fs, &root.join("zh")).unwrap();
+
+        let merged = local.merge_over(&global);
+        assert_eq!(
+            merged.get_extra("output.html.theme"),
+            Some(Value::String("dark".to_owned()))
+        );
+        assert_eq!(
+            merged.get_extra("output.html.fold"),
+            Some(Value::Boolean(true))
+        );
+        assert_eq!(
+            merged.get_extra("output.pdf.paper"),
+            Some(Value::String("a4".to_owned()))
+        );
+    }
+
+    #[test]
+    fn outputs_tables_become_build_targets() {
+        let fs = ScratchFileSystem::new();
+        let root = Path::new(ROOT);
+        let toml = "[outputs.html]\ndirectory = \"public\"\ntemplate = \"theme/page.html\"\n\n\
+                    [outputs.print]\nformat = \"pdf\"\n";
+        fs.write_file(root.join(CONFIG_FILE_NAME), toml.as_bytes())
+            .unwrap();
+        let global = load_config(&fs, root).unwrap();
+
+        let targets: Vec<(&str, &str, PathBuf)> = global
+            .outputs
+            .iter()
+            .map(|o| {
+                (
+                    o.name.as_str(),
+                    o.effective_format(),
+                    o.effective_directory(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            targets,
+            vec![
+                ("html", "html", PathBuf::from("public")),
+                ("print", "pdf", Path::new(DEFAULT_OUTPUT_DIR).join("print")),
+            ]
+        );
+        assert_eq!(global.outputs[0].options["template"], "theme/page.html");
+        assert!(!global.outputs[0].options.contains_key("directory"));
+
+        fs.write_file(
+            root.join("zh").join(CONFIG_FILE_NAME),
+            b"[outputs.html]\ntemplate = \"theme/zh.html\"\n\n[outputs.export]\nformat = \"mdbook\"\n",
+        )
+        .unwrap();
+        let merged = load_config(&fs, &root.join("zh"))
+            .unwrap()
+            .merge_over(&global);
+        let names: Vec<&str> = merged.outputs.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, vec!["export", "html", "print"]);
+        assert_eq!(merged.outputs[1].directory, Some(PathBuf::from("public")));
+        assert_eq!(merged.outputs[1].options["template"], "theme/zh.html");
+    }
+
+    #[test]
+    fn malformed_outputs_are_config_errors() {
+        let fs = ScratchFileSystem::new();
+        let path = Path::new(ROOT).join(CONFIG_FILE_NAME);
+        for content in &["outputs = 1\n", "[outputs]\nhtml = 1\n"] {
+            fs.write_file(&path, content.as_bytes()).unwrap();
+            let err = load_config(&fs, Path::new(ROOT)).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Config, "{}: {}", content, err);
+        }
+    }
+
+    #[test]
+    fn schema_describes_every_entry() {
+        let schema = schema();
+        assert!(
+            schema.starts_with(&format!("{{\n  \"$schema\": \"{}\",\n", SCHEMA_DIALECT)),
+            "{}",
+            schema
+        );
+        for entry in CONFIG_ENTRIES
+            .iter()
+            .chain(STRUCTURE_ENTRIES)
+            .chain(OUTPUT_ENTRIES)
+        {
+            assert!(
+                schema.contains(&format!("\"{}\": {{", entry.key)),
+                "{}",
+                entry.key
+            );
+        }
+        assert!(
+            schema.contains("\"enum\": [\"ltr\", \"rtl\"]"),
+            "{}",
+            schema
+        );
+    }
+
+    #[test]
+    fn loader_checks_the_types_that_the_schema_describes() {
+        let fs = ScratchFileSystem::new();
+        let path = Path::new(ROOT).join(CONFIG_FILE_NAME);
+        for entry in CONFIG_ENTRIES {
+            let (valid, invalid) = match entry.kind {
+                EntryKind::String => ("\"value\"".to_owned(), "1"),
+                EntryKind::Boolean => ("true".to_owned(), "\"yes\""),
+                EntryKind::Enum(values) => (format!("\"{}\"", values[0]), "\"none of them\""),
+                EntryKind::Table(_) | EntryKind::TableOf(_) => ("{}".to_owned(), "1"),
+            };
+            // The language is checked when the configuration is validated, not when it is read.
+            let valid = if entry.key == "language" {
+                "\"en\"".to_owned()
+            } else {
+                valid
+            };
+
+            let content = format!("{} = {}\n", entry.key, valid);
+            fs.write_file(&path, content.as_bytes()).unwrap();
+            let config = load_config(&fs, Path::new(ROOT));
+            assert!(config.is_ok(), "{}: {}", content, config.unwrap_err());
+            assert!(
+                !config.unwrap().extra.contains_key(entry.key),
+                "{}",
+                content
+            );
+
+            let content = format!("{} = {}\n", entry.key, invalid);
+            fs.write_file(&path, content.as_bytes()).unwrap();
+            let err = load_config(&fs, Path::new(ROOT)).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Config, "{}: {}", content, err);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn schema_is_well_formed() {
+        use serde_yaml::Value as Json;
+
+        // JSON is a subset of YAML, so the YAML parser checks that the schema is well-formed.
+        let schema: Json = serde_yaml::from_str(&schema()).unwrap();
+        assert_eq!(schema["$schema"], Json::from(SCHEMA_DIALECT));
+        assert_eq!(schema["type"], Json::from("object"));
+        assert_eq!(schema["additionalProperties"], Json::from(true));
+
+        let properties = &schema["properties"];
+        assert_eq!(properties["title"]["type"], Json::from("string"));
+        assert_eq!(
+            properties["title-from-content"]["type"],
+            Json::from("boolean")
+        );
+        assert_eq!(
+            properties["structure"]["properties"]["summary"]["type"],
+            Json::from("string")
+        );
+        let output = &properties["outputs"]["additionalProperties"];
+        assert_eq!(output["type"], Json::from("object"));
+        assert_eq!(output["properties"]["format"]["type"], Json::from("string"));
+        assert!(properties["edit-url-template"]["description"]
+            .as_str()
+            .unwrap()
+            .contains("{path}"));
+    }
+}
@@ -8,40 +8,250 @@ use std::fmt::{Display, Formatter};
 /// Errors in OpenBook come from two sources:
 ///
 /// * Errors that come from the dependency of OpenBook, which are represented by the `Inner`
-/// variant;
+///   variant;
 /// * Errors that come directly from OpenBook components, which are represented by the `Msg`
-/// variant.
+///   variant.
+///
+/// Every error is classified by an [`ErrorKind`], which can be queried through the `kind` method.
+/// Errors created from a message with a kind other than `ErrorKind::Other`, such as by
+/// `Error::parse`, are stored in the `Inner` variant as a private error type that only carries the
+/// message and the kind; use `kind` and `Display` rather than matching on the variants to inspect
+/// them.
+///
+/// [`ErrorKind`]: enum.ErrorKind.html
 #[derive(Debug)]
 pub enum Error {
     /// Errors that originally come from a dependency of OpenBook.
-    Inner(Box<dyn std::error::Error>),
+    Inner(Box<dyn std::error::Error + Send + Sync>),
 
     /// Errors that directly come from OpenBook.
     Msg(String),
 }
 
+/// An error message of a specific kind, which is stored in the `Inner` variant of `Error`.
+#[derive(Debug)]
+struct KindedMessage {
+    kind: ErrorKind,
+    msg: String,
+}
+
+impl Display for KindedMessage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl std::error::Error for KindedMessage {}
+
+/// Classification of OpenBook errors.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ErrorKind {
+    /// An I/O operation failed.
+    Io,
+
+    /// Some input, such as a markdown document, could not be parsed.
+    Parse,
+
+    /// The configuration is invalid.
+    Config,
+
+    /// A requested file or entity does not exist.
+    NotFound,
+
+    /// Any other error.
+    Other,
+}
+
 impl Error {
     /// Create `Error` value from the specified inner error.
-    pub fn from_inner<E: std::error::Error>(inner: E) -> Self {
+    ///
+    /// The kind of the error is inferred from the inner error: I/O errors are classified as
+    /// `ErrorKind::Io`, or `ErrorKind::NotFound` if the file does not exist, and UTF-8 decoding
+    /// errors are classified as `ErrorKind::Parse`. All other errors are classified as
+    /// `ErrorKind::Other`.
+    pub fn from_inner<E: std::error::Error + Send + Sync + 'static>(inner: E) -> Self {
         Self::Inner(Box::new(inner))
     }
 
     /// Create `Error` value from the specified error message.
+    ///
+    /// The created error is classified as `ErrorKind::Other`.
     pub fn from_message<M: Into<String>>(msg: M) -> Self {
-        Self::Msg(msg.into())
+        Self::with_kind(ErrorKind::Other, msg)
+    }
+
+    /// Create `Error` value of the specified kind from the specified error message.
+    ///
+    /// Errors of kind `ErrorKind::Other` are represented by the `Msg` variant, and errors of other
+    /// kinds are represented by the `Inner` variant.
+    pub fn with_kind<M: Into<String>>(kind: ErrorKind, msg: M) -> Self {
+        let msg = msg.into();
+        match kind {
+            ErrorKind::Other => Self::Msg(msg),
+            kind => Self::Inner(Box::new(KindedMessage { kind, msg })),
+        }
+    }
+
+    /// Create `Error` value that represents a parse failure from the specified error message.
+    pub fn parse<M: Into<String>>(msg: M) -> Self {
+        Self::with_kind(ErrorKind::Parse, msg)
+    }
+
+    /// Create `Error` value that represents an invalid configuration from the specified error
+    /// message.
+    pub fn config<M: Into<String>>(msg: M) -> Self {
+        Self::with_kind(ErrorKind::Config, msg)
+    }
+
+    /// Create `Error` value that represents a missing file or entity from the specified error
+    /// message.
+    pub fn not_found<M: Into<String>>(msg: M) -> Self {
+        Self::with_kind(ErrorKind::NotFound, msg)
+    }
+
+    /// Get the kind of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Inner(inner) => classify_inner(inner.as_ref()),
+            Self::Msg(_) => ErrorKind::Other,
+        }
     }
 }
 
+/// Infer the kind of an error that comes from a dependency of OpenBook.
+fn classify_inner(inner: &(dyn std::error::Error + 'static)) -> ErrorKind {
+    if let Some(e) = inner.downcast_ref::<KindedMessage>() {
+        return e.kind;
+    }
+    if let Some(e) = inner.downcast_ref::<std::io::Error>() {
+        return match e.kind() {
+            std::io::ErrorKind::NotFound => ErrorKind::NotFound,
+            _ => ErrorKind::Io,
+        };
+    }
+    if let Some(e) = inner.downcast_ref::<notify::Error>() {
+        return match e {
+            notify::Error::Io(e) if e.kind() == std::io::ErrorKind::NotFound => ErrorKind::NotFound,
+            notify::Error::Io(_) => ErrorKind::Io,
+            notify::Error::PathNotFound | notify::Error::WatchNotFound => ErrorKind::NotFound,
+            _ => ErrorKind::Other,
+        };
+    }
+    if inner.is::<std::string::FromUtf8Error>() || inner.is::<std::str::Utf8Error>() {
+        return ErrorKind::Parse;
+    }
+    ErrorKind::Other
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
+            Self::Inner(inner) if inner.is::<KindedMessage>() => {
+                f.write_fmt(format_args!("OpenBook error: {}", inner))
+            }
             Self::Inner(inner) => f.write_fmt(format_args!("OpenBook inner error: {}", inner)),
             Self::Msg(msg) => f.write_fmt(format_args!("OpenBook error: {}", msg)),
         }
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Inner(inner) if inner.is::<KindedMessage>() => None,
+            Self::Inner(inner) => Some(inner.as_ref()),
+            Self::Msg(_) => None,
+        }
+    }
+}
 
 /// Result type used in OpenBook.
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn constructors_classify_errors() {
+        assert_eq!(Error::parse("p").kind(), ErrorKind::Parse);
+        assert_eq!(Error::config("c").kind(), ErrorKind::Config);
+        assert_eq!(Error::not_found("n").kind(), ErrorKind::NotFound);
+        assert_eq!(Error::with_kind(ErrorKind::Io, "i").kind(), ErrorKind::Io);
+        assert_eq!(Error::from_message("o").kind(), ErrorKind::Other);
+    }
+
+    #[test]
+    fn inner_errors_are_classified_by_their_type() {
+        let missing = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+        assert_eq!(Error::from_inner(missing).kind(), ErrorKind::NotFound);
+        let denied = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied");
+        assert_eq!(Error::from_inner(denied).kind(), ErrorKind::Io);
+        let utf8 = String::from_utf8(vec![0xff]).unwrap_err();
+        assert_eq!(Error::from_inner(utf8).kind(), ErrorKind::Parse);
+        assert_eq!(Error::from_inner(std::fmt::Error).kind(), ErrorKind::Other);
+    }
+
+    #[test]
+    fn variants_keep_their_shapes() {
+        assert!(matches!(Error::from_message("m"), Error::Msg(msg) if msg == "m"));
+        let error = Error::from_inner(std::fmt::Error);
+        assert!(matches!(&error, Error::Inner(inner) if inner.is::<std::fmt::Error>()));
+        assert!(error.source().is_some());
+    }
+
+    #[test]
+    fn messages_of_every_kind_display_alike() {
+        assert_eq!(Error::from_message("m").to_string(), "OpenBook error: m");
+        let error = Error::parse("bad line");
+        assert_eq!(error.to_string(), "OpenBook error: bad line");
+        assert!(error.source().is_none());
+    }
+
+    #[test]
+    fn errors_convert_into_boxed_std_errors() {
+        fn fail() -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Err(Error::config("bad entry"))?;
+            Ok(())
+        }
+        let error = fail().unwrap_err();
+        assert_eq!(error.to_string(), "OpenBook error: bad entry");
+        assert_eq!(
+            error.downcast_ref::<Error>().map(Error::kind),
+            Some(ErrorKind::Config)
+        );
+    }
+
+    #[test]
+    fn sources_chain_to_the_inner_error() {
+        let io = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied");
+        let error = Error::from_inner(io);
+        let source = error.source().unwrap();
+        assert_eq!(source.to_string(), "denied");
+        assert_eq!(
+            source
+                .downcast_ref::<std::io::Error>()
+                .map(std::io::Error::kind),
+            Some(std::io::ErrorKind::PermissionDenied)
+        );
+        assert!(Error::not_found("n").source().is_none());
+    }
+
+    #[test]
+    fn errors_cross_threads() {
+        fn assert_send_sync<T: Send + Sync + 'static>() {}
+        assert_send_sync::<Error>();
+
+        let error = std::thread::spawn(|| Error::from_inner(std::fmt::Error))
+            .join()
+            .unwrap();
+        assert!(error.source().unwrap().is::<std::fmt::Error>());
+
+        let (send, recv) = std::sync::mpsc::channel();
+        std::thread::spawn(move || send.send(Error::parse("sent")).unwrap())
+            .join()
+            .unwrap();
+        assert_eq!(recv.recv().unwrap().kind(), ErrorKind::Parse);
+    }
+}
@@ -0,0 +1,399 @@
+//! This module provides a file system event sink that batches events.
+//!
+//! Some editors save a file by deleting it, creating it again and writing to it, which produces a
+//! burst of events for a single change. The [`BatchingSink`] collects the events that arrive
+//! within a time window, coalesces the events on the same path and forwards the result as a single
+//! batch to a [`BatchSink`].
+//!
+//! [`BatchingSink`]: struct.BatchingSink.html
+//! [`BatchSink`]: trait.BatchSink.html
+//!
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::error::{Error, Result};
+use crate::fs::{FileSystemEvent, FileSystemEventSink};
+
+/// Batching sinks emit batches of file system events into this sink.
+pub trait BatchSink: Send {
+    /// Send the specified batch of events into this sink.
+    fn send_batch(&self, events: Vec<FileSystemEvent>) -> Result<()>;
+}
+
+impl BatchSink for Sender<Vec<FileSystemEvent>> {
+    fn send_batch(&self, events: Vec<FileSystemEvent>) -> Result<()> {
+        self.send(events).map_err(Error::from_inner)
+    }
+}
+
+/// A [`FileSystemEventSink`] that collects events for a time window and forwards them as a
+/// coalesced batch to a [`BatchSink`].
+///
+/// A batch is started by the first event that arrives after the previous batch is flushed, and
+/// is flushed by a background thread once the window has passed since then, or explicitly by
+/// `flush`. Empty batches are never forwarded.
+///
+/// When a batch is flushed, the `Create`, `Delete` and `Write` events on the same path are
+/// coalesced into at most one event that describes the net change, placed where the first of them
+/// was:
+///
+/// * The file did not exist before the batch and exists after it: `Create`;
+/// * The file existed before the batch and exists after it: `Write`, e.g. for a
+///   `Delete`, `Create`, `Write` burst;
+/// * The file existed before the batch and does not exist after it: `Delete`;
+/// * The file neither existed before nor after the batch: no event.
+///
+/// `Rename`, `Rescan` and `Error` events are forwarded as-is in their original order. Events on
+/// the two paths of a `Rename` are not coalesced across it.
+///
+/// Errors returned by the inner sink during a background flush are reported by the next call to
+/// `send`. The events that are still pending when the sink is dropped are flushed by `drop`, where
+/// errors can no longer be reported. If the inner sink panics, `send` and `flush` return errors
+/// from then on.
+///
+/// [`FileSystemEventSink`]: ../trait.FileSystemEventSink.html
+/// [`BatchSink`]: trait.BatchSink.html
+pub struct BatchingSink {
+    shared: Arc<Shared>,
+}
+
+/// State shared between a `BatchingSink` and its flushing thread.
+struct Shared {
+    state: Mutex<BatchState>,
+    condvar: Condvar,
+}
+
+/// The batch under collection and the inner sink it is forwarded to.
+struct BatchState {
+    inner: Box<dyn BatchSink>,
+    pending: Vec<FileSystemEvent>,
+    started: Option<Instant>,
+    error: Option<Error>,
+    closed: bool,
+}
+
+impl Shared {
+    /// Lock the state, reporting a lock poisoned by a panic of the inner sink as an error.
+    fn lock(&self) -> Result<MutexGuard<'_, BatchState>> {
+        self.state.lock().map_err(poisoned)
+    }
+
+    /// Flush the batches of the specified window in the background until the sink is dropped.
+    fn run(&self, window: Duration) -> Result<()> {
+        let mut state = self.lock()?;
+        loop {
+            if state.closed {
+                return Ok(());
+            }
+            state = match state.started {
+                Some(started) if started.elapsed() >= window => {
+                    if let Err(e) = state.flush() {
+                        state.error.get_or_insert(e);
+                    }
+                    state
+                }
+                Some(started) => {
+                    let timeout = window - started.elapsed().min(window);
+                    self.condvar
+                        .wait_timeout(state, timeout)
+                        .map_err(poisoned)?
+                        .0
+                }
+                None => self.condvar.wait(state).map_err(poisoned)?,
+            };
+        }
+    }
+}
+
+/// Report that the state of a `BatchingSink` is poisoned, since the inner sink panicked while
+/// the state was locked.
+fn poisoned<T>(_: PoisonError<T>) -> Error {
+    Error::from_message("batching sink: the inner sink panicked")
+}
+
+impl BatchState {
+    /// Forward the pending events to the inner sink.
+    fn flush(&mut self) -> Result<()> {
+        self.started = None;
+        let events = coalesce(std::mem::take(&mut self.pending));
+        if events.is_empty() {
+            return Ok(());
+        }
+        self.inner.send_batch(events)
+    }
+}
+
+impl BatchingSink {
+    /// Create a new `BatchingSink` instance that forwards the events collected within the
+    /// specified window to the specified sink.
+    pub fn new(inner: Box<dyn BatchSink>, window: Duration) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(BatchState {
+                inner,
+                pending: Vec::new(),
+                started: None,
+                error: None,
+                closed: false,
+            }),
+            condvar: Condvar::new(),
+        });
+
+        let thread_shared = shared.clone();
+        std::thread::spawn(move || {
+            // A poisoned lock ends the thread, and is reported by `send` and `flush` instead.
+            let _ = thread_shared.run(window);
+        });
+
+        Self { shared }
+    }
+
+    /// Forward the events collected so far to the inner sink immediately.
+    pub fn flush(&self) -> Result<()> {
+        self.shared.lock()?.flush()
+    }
+}
+
+impl FileSystemEventSink for BatchingSink {
+    fn send(&self, event: FileSystemEvent) -> Result<()> {
+        let mut state = self.shared.lock()?;
+        if let Some(e) = state.error.take() {
+            return Err(e);
+        }
+
+        state.pending.push(event);
+        if state.started.is_none() {
+            state.started = Some(Instant::now());
+            self.shared.condvar.notify_one();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for BatchingSink {
+    fn drop(&mut self) {
+        let mut state = match self.shared.state.lock() {
+            Ok(mut state) => {
+                // Nothing can flush the pending events after the sink is dropped, and nothing can
+                // receive the error of flushing them.
+                let _ = state.flush();
+                state
+            }
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        state.closed = true;
+        self.shared.condvar.notify_one();
+    }
+}
+
+/// An entry in a coalesced batch.
+enum Coalesced {
+    /// The net change to the file at a path, given by whether it existed before and after.
+    Path {
+        path: PathBuf,
+        existed: bool,
+        exists: bool,
+    },
+
+    /// An event that is forwarded as-is.
+    Event(FileSystemEvent),
+}
+
+/// Coalesce the events on the same path in the specified batch.
+fn coalesce(events: Vec<FileSystemEvent>) -> Vec<FileSystemEvent> {
+    let mut entries = Vec::with_capacity(events.len());
+    // Indices of the entries of the paths that can still be coalesced.
+    let mut open: HashMap<PathBuf, usize> = HashMap::new();
+
+    for event in events {
+        let (path, existed, exists) = match event {
+            FileSystemEvent::Create(path) => (path, false, true),
+            FileSystemEvent::Delete(path) => (path, true, false),
+            FileSystemEvent::Write(path) => (path, true, true),
+            FileSystemEvent::Rename { from, to } => {
+                open.remove(&from);
+                open.remove(&to);
+                entries.push(Coalesced::Event(FileSystemEvent::Rename { from, to }));
+                continue;
+            }
+            event => {
+                entries.push(Coalesced::Event(event));
+                continue;
+            }
+        };
+
+        match open.get(&path) {
+            Some(&index) => {
+                if let Coalesced::Path { exists: e, .. } = &mut entries[index] {
+                    *e = exists;
+                }
+            }
+            None => {
+                open.insert(path.clone(), entries.len());
+                entries.push(Coalesced::Path {
+                    path,
+                    existed,
+                    exists,
+                });
+            }
+        }
+    }
+
+    entries
+        .into_iter()
+        .filter_map(|entry| match entry {
+            Coalesced::Path {
+                path,
+                existed,
+                exists,
+            } => match (existed, exists) {
+                (false, true) => Some(FileSystemEvent::Create(path)),
+                (true, true) => Some(FileSystemEvent::Write(path)),
+                (true, false) => Some(FileSystemEvent::Delete(path)),
+                (false, false) => None,
+            },
+            Coalesced::Event(event) => Some(event),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::tests::{EVENT_TIMEOUT, QUIET_TIMEOUT};
+
+    fn debug(events: &[FileSystemEvent]) -> Vec<String> {
+        events.iter().map(|e| format!("{:?}", e)).collect()
+    }
+
+    fn path(name: &str) -> PathBuf {
+        PathBuf::from(name)
+    }
+
+    #[test]
+    fn bursts_are_forwarded_as_a_single_coalesced_batch() {
+        let (send, batches) = std::sync::mpsc::channel();
+        let sink = BatchingSink::new(Box::new(send), Duration::from_millis(100));
+        sink.send(FileSystemEvent::Delete(path("a.md"))).unwrap();
+        sink.send(FileSystemEvent::Create(path("a.md"))).unwrap();
+        sink.send(FileSystemEvent::Write(path("a.md"))).unwrap();
+
+        let batch = batches.recv_timeout(EVENT_TIMEOUT).unwrap();
+        assert_eq!(
+            debug(&batch),
+            debug(&[FileSystemEvent::Write(path("a.md"))])
+        );
+        assert!(batches.recv_timeout(QUIET_TIMEOUT).is_err());
+    }
+
+    #[test]
+    fn flush_forwards_pending_events_immediately() {
+        let (send, batches) = std::sync::mpsc::channel();
+        let sink = BatchingSink::new(Box::new(send), Duration::from_secs(60));
+        sink.flush().unwrap();
+        sink.send(FileSystemEvent::Create(path("a.md"))).unwrap();
+        sink.flush().unwrap();
+
+        let batch = batches.try_recv().unwrap();
+        assert_eq!(
+            debug(&batch),
+            debug(&[FileSystemEvent::Create(path("a.md"))])
+        );
+        assert!(batches.try_recv().is_err());
+    }
+
+    #[test]
+    fn coalesces_events_by_net_change() {
+        let events = vec![
+            FileSystemEvent::Create(path("new.md")),
+            FileSystemEvent::Write(path("old.md")),
+            FileSystemEvent::Write(path("new.md")),
+            FileSystemEvent::Create(path("temp.md")),
+            FileSystemEvent::Delete(path("temp.md")),
+            FileSystemEvent::Write(path("gone.md")),
+            FileSystemEvent::Delete(path("gone.md")),
+            FileSystemEvent::Rescan,
+        ];
+        assert_eq!(
+            debug(&coalesce(events)),
+            debug(&[
+                FileSystemEvent::Create(path("new.md")),
+                FileSystemEvent::Write(path("old.md")),
+                FileSystemEvent::Delete(path("gone.md")),
+                FileSystemEvent::Rescan,
+            ])
+        );
+    }
+
+    #[test]
+    fn renames_are_not_coalesced_across() {
+        let events = vec![
+            FileSystemEvent::Write(path("a.md")),
+            FileSystemEvent::Rename {
+                from: path("a.md"),
+                to: path("b.md"),
+            },
+            FileSystemEvent::Write(path("a.md")),
+        ];
+        assert_eq!(debug(&coalesce(events)).len(), 3);
+    }
+
+    #[test]
+    fn background_flush_errors_are_reported_by_send() {
+        let (send, batches) = std::sync::mpsc::channel();
+        drop(batches);
+        let sink = BatchingSink::new(Box::new(send), Duration::from_millis(10));
+        sink.send(FileSystemEvent::Write(path("a.md"))).unwrap();
+
+        let start = Instant::now();
+        loop {
+            assert!(
+                start.elapsed() < EVENT_TIMEOUT,
+                "the error is never reported"
+            );
+            std::thread::sleep(Duration::from_millis(20));
+            if sink.send(FileSystemEvent::Rescan).is_err() {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn dropping_the_sink_flushes_pending_events() {
+        let (send, batches) = std::sync::mpsc::channel();
+        let sink = BatchingSink::new(Box::new(send), Duration::from_secs(60));
+        sink.send(FileSystemEvent::Create(path("a.md"))).unwrap();
+        sink.send(FileSystemEvent::Write(path("a.md"))).unwrap();
+        drop(sink);
+
+        let batch = batches.try_recv().unwrap();
+        assert_eq!(
+            debug(&batch),
+            debug(&[FileSystemEvent::Create(path("a.md"))])
+        );
+        assert!(batches.try_recv().is_err());
+    }
+
+    #[test]
+    fn panics_of_the_inner_sink_are_reported_as_errors() {
+        struct PanickingSink;
+
+        impl BatchSink for PanickingSink {
+            fn send_batch(&self, _events: Vec<FileSystemEvent>) -> Result<()> {
+                panic!("the inner sink panicked");
+            }
+        }
+
+        let sink = BatchingSink::new(Box::new(PanickingSink), Duration::from_secs(60));
+        sink.send(FileSystemEvent::Write(path("a.md"))).unwrap();
+        let flush = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| sink.flush()));
+        assert!(flush.is_err());
+
+        assert!(sink.send(FileSystemEvent::Rescan).is_err());
+        assert!(sink.flush().is_err());
+        drop(sink);
+    }
+}
@@ -0,0 +1,335 @@
+//! This module implements glob patterns and directory iteration filtered by them.
+//!
+//! A glob pattern consists of components separated by `/`, each of which is matched against a
+//! single component of a relative path. The following wildcards are supported in a component:
+//!
+//! * `?` matches any single character;
+//! * `*` matches any sequence of characters;
+//! * `[abc]` matches one of the listed characters, and `[a-z]` matches a character in the range.
+//!   `[!abc]` matches any character that is not listed.
+//!
+//! A component that is exactly `**` matches any number of path components, including none, so
+//! `**/*.md` matches `README.md` as well as `guide/intro.md`.
+//!
+
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::fs::{FileSystem, Walk};
+
+/// A compiled glob pattern.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Glob {
+    pattern: String,
+    components: Vec<Component>,
+}
+
+/// A compiled component of a glob pattern.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Component {
+    /// `**`, which matches any number of path components.
+    AnyComponents,
+
+    /// A pattern that matches a single path component.
+    Tokens(Vec<Token>),
+}
+
+/// A compiled token of a glob pattern component.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Char(char),
+    AnyChar,
+    AnySequence,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Glob {
+    /// Compile the specified glob pattern.
+    ///
+    /// Returns an error of kind `ErrorKind::Parse` if the pattern is malformed, e.g. if a `[` is
+    /// not closed or if `**` is not a whole component.
+    pub fn new(pattern: &str) -> Result<Self> {
+        let error =
+            |msg: &str| Error::parse(format!("invalid glob pattern \"{}\": {}", pattern, msg));
+
+        let mut components = Vec::new();
+        for component in pattern.split('/') {
+            if component == "**" {
+                components.push(Component::AnyComponents);
+                continue;
+            }
+            if component.contains("**") {
+                return Err(error("\"**\" must be a whole path component"));
+            }
+
+            let mut tokens = Vec::new();
+            let mut chars = component.chars();
+            while let Some(c) = chars.next() {
+                let token = match c {
+                    '?' => Token::AnyChar,
+                    '*' => Token::AnySequence,
+                    '[' => {
+                        let mut negated = false;
+                        let mut ranges = Vec::new();
+                        let mut closed = false;
+                        let mut first = true;
+                        while let Some(c) = chars.next() {
+                            match c {
+                                '!' if first && !negated => {
+                                    negated = true;
+                                    continue;
+                                }
+                                ']' if !first => {
+                                    closed = true;
+                                    break;
+                                }
+                                c => {
+                                    let mut lookahead = chars.clone();
+                                    match (lookahead.next(), lookahead.next()) {
+                                        (Some('-'), Some(end)) if end != ']' => {
+                                            if end < c {
+                                                return Err(error("invalid character range"));
+                                            }
+                                            ranges.push((c, end));
+                                            chars = lookahead;
+                                        }
+                                        _ => ranges.push((c, c)),
+                                    }
+                                }
+                            }
+                            first = false;
+                        }
+                        if !closed {
+                            return Err(error("unclosed character class"));
+                        }
+                        if ranges.is_empty() {
+                            return Err(error("empty character class"));
+                        }
+                        Token::Class { negated, ranges }
+                    }
+                    c => Token::Char(c),
+                };
+                tokens.push(token);
+            }
+            components.push(Component::Tokens(tokens));
+        }
+
+        Ok(Self {
+            pattern: pattern.to_owned(),
+            components,
+        })
+    }
+
+    /// Get the source of this pattern.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Determine whether this pattern has more than one component and thus needs to be matched
+    /// against the files under a directory recursively.
+    pub fn is_recursive(&self) -> bool {
+        self.components.len() > 1 || self.components.first() == Some(&Component::AnyComponents)
+    }
+
+    /// Determine whether the specified relative path matches this pattern.
+    pub fn matches<P: AsRef<Path>>(&self, path: P) -> bool {
+        let names: Vec<String> = path
+            .as_ref()
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        match_components(&self.components, &names)
+    }
+}
+
+/// Match the specified pattern components against the specified path components.
+fn match_components(components: &[Component], names: &[&str]) -> bool {
+    match components.split_first() {
+        None => names.is_empty(),
+        Some((Component::AnyComponents, rest)) => {
+            (0..=names.len()).any(|skip| match_components(rest, &names[skip..]))
+        }
+        Some((Component::Tokens(tokens), rest)) => match names.split_first() {
+            Some((name, names)) => {
+                let chars: Vec<char> = name.chars().collect();
+                match_tokens(tokens, &chars) && match_components(rest, names)
+            }
+            None => false,
+        },
+    }
+}
+
+/// Match the specified component tokens against the characters of a path component.
+fn match_tokens(tokens: &[Token], chars: &[char]) -> bool {
+    match tokens.split_first() {
+        None => chars.is_empty(),
+        Some((Token::AnySequence, rest)) => {
+            (0..=chars.len()).any(|skip| match_tokens(rest, &chars[skip..]))
+        }
+        Some((token, rest)) => match chars.split_first() {
+            Some((&c, chars)) => {
+                let matched = match token {
+                    Token::Char(expected) => c == *expected,
+                    Token::AnyChar => true,
+                    Token::Class { negated, ranges } => {
+                        ranges.iter().any(|&(start, end)| start <= c && c <= end) != *negated
+                    }
+                    Token::AnySequence => unreachable!(),
+                };
+                matched && match_tokens(rest, chars)
+            }
+            None => false,
+        },
+    }
+}
+
+/// Iterates over the entries under a directory in a [`FileSystem`] whose paths, relative to the
+/// directory, match a glob pattern.
+///
+/// If the pattern has a single component, only the direct entries of the directory, both files
+/// and subdirectories, are matched against it. Otherwise, all files under the directory are walked
+/// recursively with a [`Walk`] and matched against the pattern.
+///
+/// [`FileSystem`]: ../trait.FileSystem.html
+/// [`Walk`]: ../struct.Walk.html
+pub struct GlobDirIter<'a, F: FileSystem> {
+    base: PathBuf,
+    glob: Glob,
+    entries: GlobEntries<'a, F>,
+}
+
+/// The underlying iterator of a [`GlobDirIter`].
+///
+/// [`GlobDirIter`]: struct.GlobDirIter.html
+enum GlobEntries<'a, F: FileSystem> {
+    Direct(F::DirIter),
+    Recursive(Walk<'a, F>),
+}
+
+impl<'a, F: FileSystem> GlobDirIter<'a, F> {
+    /// Create a new `GlobDirIter` instance that iterates over the entries under the specified
+    /// directory in the specified file system that match the specified pattern.
+    ///
+    /// Returns an error of kind `ErrorKind::Parse` if the pattern is malformed.
+    pub fn new<P: AsRef<Path>>(fs: &'a F, path: P, pattern: &str) -> Result<Self> {
+        let glob = Glob::new(pattern)?;
+        let base = path.as_ref().to_path_buf();
+        let entries = if glob.is_recursive() {
+            GlobEntries::Recursive(fs.walk(&base))
+        } else {
+            GlobEntries::Direct(fs.read_directory(&base)?)
+        };
+        Ok(Self {
+            base,
+            glob,
+            entries,
+        })
+    }
+}
+
+impl<'a, F: FileSystem> Iterator for GlobDirIter<'a, F> {
+    type Item = Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let entry = match &mut self.entries {
+                GlobEntries::Direct(entries) => entries.next()?,
+                GlobEntries::Recursive(walk) => walk.next()?,
+            };
+            let path = match entry {
+                Ok(path) => path,
+                Err(e) => return Some(Err(e)),
+            };
+
+            let relative = path.strip_prefix(&self.base).unwrap_or(&path);
+            if self.glob.matches(relative) {
+                return Some(Ok(path));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::fs::local::LocalFileSystem;
+    use crate::fs::scratch::ScratchFileSystem;
+    use crate::fs::tests::temp_dir;
+
+    /// Write a mixed directory of markdown and other files under the specified root.
+    fn write_mixed<F: FileSystem>(fs: &F, root: &Path) {
+        for file in &["README.md", "logo.png", "guide/intro.md", "guide/img/a.svg"] {
+            fs.write_file(root.join(file), b"").unwrap();
+        }
+    }
+
+    fn matched<F: FileSystem>(fs: &F, root: &Path, pattern: &str) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = fs
+            .read_directory_glob(root, pattern)
+            .unwrap()
+            .map(|p| p.unwrap().strip_prefix(root).unwrap().to_path_buf())
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        let glob = Glob::new("src/**/[a-c]?.r[!x]").unwrap();
+        assert!(glob.matches("src/ab.rs"));
+        assert!(glob.matches("src/x/y/c1.rt"));
+        assert!(!glob.matches("src/d1.rs"));
+        assert!(!glob.matches("src/ab.rx"));
+        assert!(!glob.matches("lib/ab.rs"));
+
+        assert!(Glob::new("*.md").unwrap().matches("README.md"));
+        assert!(!Glob::new("*.md").unwrap().matches("guide/intro.md"));
+        assert!(Glob::new("**/*.md").unwrap().matches("README.md"));
+    }
+
+    #[test]
+    fn rejects_malformed_patterns() {
+        for pattern in &["[abc", "a**/b", "***"] {
+            assert_eq!(
+                Glob::new(pattern).err().map(|e| e.kind()),
+                Some(ErrorKind::Parse),
+                "{}",
+                pattern
+            );
+        }
+        let fs = ScratchFileSystem::new();
+        assert!(fs.read_directory_glob("/openbook-test", "[").is_err());
+    }
+
+    #[test]
+    fn filters_scratch_directories() {
+        let fs = ScratchFileSystem::new();
+        let root = Path::new("/openbook-test/glob");
+        write_mixed(&fs, root);
+        assert_eq!(matched(&fs, root, "*.md"), vec![PathBuf::from("README.md")]);
+        assert_eq!(
+            matched(&fs, root, "**/*.md"),
+            vec![PathBuf::from("README.md"), PathBuf::from("guide/intro.md")]
+        );
+    }
+
+    #[test]
+    fn filters_local_directories() {
+        let root = temp_dir("glob");
+        let fs = LocalFileSystem::new();
+        write_mixed(&fs, &root);
+        assert_eq!(
+            matched(&fs, &root, "*.md"),
+            vec![PathBuf::from("README.md")]
+        );
+        assert_eq!(
+            matched(&fs, &root, "guide/**/*.svg"),
+            vec![PathBuf::from("guide/img/a.svg")]
+        );
+    }
+}
@@ -5,8 +5,10 @@
 
 use std::fs::ReadDir;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvError;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
 
@@ -16,6 +18,7 @@ use crate::fs::{
 };
 
 /// An implementation of [`FileSystem`] that operates on the local file system.
+#[derive(Default)]
 pub struct LocalFileSystem;
 
 impl LocalFileSystem {
@@ -38,16 +41,65 @@ impl FileSystem for LocalFileSystem {
         path.as_ref().is_dir()
     }
 
-    fn read_file_as_string<P: AsRef<Path>>(&self, path: P) -> Result<String> {
-        std::fs::read_to_string(path).map_err(Error::from_inner)
+    fn read_file_as_bytes<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
+        std::fs::read(path).map_err(Error::from_inner)
+    }
+
+    fn write_file<P: AsRef<Path>>(&self, path: P, contents: &[u8]) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                self.create_dir_all(parent)?;
+            }
+        }
+        std::fs::write(path, contents).map_err(Error::from_inner)
+    }
+
+    fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::create_dir_all(path).map_err(Error::from_inner)
     }
 
     fn read_directory<P: AsRef<Path>>(&self, path: P) -> Result<Self::DirIter> {
         LocalFileSystemIter::new(path)
     }
 
-    fn create_watcher(&self, event_sink: Box<dyn FileSystemEventSink>) -> Result<Self::Watcher> {
-        LocalFileSystemWatcher::new(event_sink)
+    fn canonicalize<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        std::fs::canonicalize(path).map_err(Error::from_inner)
+    }
+
+    fn changed_since<P: AsRef<Path>>(&self, root: P, since: SystemTime) -> Result<Vec<PathBuf>> {
+        let mut changed = Vec::new();
+        let mut pending = vec![root.as_ref().to_path_buf()];
+
+        while let Some(dir) = pending.pop() {
+            for entry in std::fs::read_dir(dir).map_err(Error::from_inner)? {
+                let entry = entry.map_err(Error::from_inner)?;
+                let path = entry.path();
+                let file_type = entry.file_type().map_err(Error::from_inner)?;
+
+                // Symbolic links to directories are not followed to avoid walking into cycles.
+                if file_type.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+
+                let metadata = std::fs::metadata(&path).map_err(Error::from_inner)?;
+                if metadata.is_file() && metadata.modified().map_err(Error::from_inner)? > since {
+                    changed.push(path);
+                }
+            }
+        }
+
+        changed.sort();
+        Ok(changed)
+    }
+
+    fn create_watcher_with_debounce(
+        &self,
+        event_sink: Box<dyn FileSystemEventSink>,
+        debounce: Duration,
+    ) -> Result<Self::Watcher> {
+        LocalFileSystemWatcher::new(event_sink, debounce)
     }
 }
 
@@ -77,46 +129,173 @@ impl Iterator for LocalFileSystemIter {
 }
 
 /// Filter out un-interesting file system events produced by the underlying `notify` crate.
+///
+/// `NoticeWrite` and `NoticeRemove` are suppressed since they are always followed by a debounced
+/// `Write` or `Remove` event on the same path. `Chmod` is suppressed as well since changes to
+/// metadata do not change the content of a file.
 fn filter_raw_fs_event(raw_event: DebouncedEvent) -> Option<FileSystemEvent> {
-    match event {
+    match raw_event {
+        DebouncedEvent::NoticeWrite(_) | DebouncedEvent::NoticeRemove(_) => None,
         DebouncedEvent::Create(path) => Some(FileSystemEvent::Create(path)),
+        DebouncedEvent::Write(path) => Some(FileSystemEvent::Write(path)),
+        DebouncedEvent::Chmod(_) => None,
         DebouncedEvent::Remove(path) => Some(FileSystemEvent::Delete(path)),
         DebouncedEvent::Rename(from, to) => Some(FileSystemEvent::Rename { from, to }),
-        DebouncedEvent::Write(path) => Some(FileSystemEvent::Write(path)),
+        DebouncedEvent::Rescan => Some(FileSystemEvent::Rescan),
         DebouncedEvent::Error(e, path) => Some(FileSystemEvent::Error(Error::from_inner(e), path)),
-        _ => None,
+    }
+}
+
+/// Determine whether the specified event concerns any of the specified watches.
+///
+/// `notify` reports events on the direct children of a directory that is watched non-recursively,
+/// so such events are filtered out here to honor `FileSystemWatchMode::Normal`. Events without a
+/// path are always forwarded.
+fn is_watched_event(watches: &[Watch], event: &FileSystemEvent) -> bool {
+    let is_watched = |path: &Path| watches.iter().any(|w| w.covers(path));
+    match event {
+        FileSystemEvent::Create(path)
+        | FileSystemEvent::Delete(path)
+        | FileSystemEvent::Write(path) => is_watched(path),
+        FileSystemEvent::Rename { from, to } => is_watched(from) || is_watched(to),
+        FileSystemEvent::Rescan => true,
+        FileSystemEvent::Error(_, Some(path)) => is_watched(path),
+        FileSystemEvent::Error(_, None) => true,
+    }
+}
+
+/// A path watched by a `LocalFileSystemWatcher`.
+struct Watch {
+    path: PathBuf,
+
+    /// The canonical form of `path`, which `notify` may report events with instead.
+    canonical: Option<PathBuf>,
+
+    mode: FileSystemWatchMode,
+}
+
+impl Watch {
+    /// Determine whether events on the specified path should be reported for this watch.
+    fn covers(&self, path: &Path) -> bool {
+        let watched = std::iter::once(&self.path).chain(self.canonical.iter());
+        match self.mode {
+            FileSystemWatchMode::Normal => watched.into_iter().any(|p| p == path),
+            FileSystemWatchMode::Recursive => watched.into_iter().any(|p| path.starts_with(p)),
+        }
     }
 }
 
 /// Watches file system state changes in the local file system.
+///
+/// Only events on the watched paths themselves are reported for paths watched with
+/// `FileSystemWatchMode::Normal`, while events on everything under a directory are reported for
+/// directories watched with `FileSystemWatchMode::Recursive`.
 pub struct LocalFileSystemWatcher {
     raw_watcher: Mutex<RecommendedWatcher>,
+    heartbeat: Arc<Heartbeat>,
+    watches: Arc<Mutex<Vec<Watch>>>,
+}
+
+/// Liveness information shared between a `LocalFileSystemWatcher` and its event forwarding
+/// thread.
+struct Heartbeat {
+    /// Whether the event forwarding thread is still running.
+    alive: AtomicBool,
+
+    /// Whether the channel from the underlying `notify` watcher has been disconnected, which means
+    /// that `notify` has stopped and no more events arrive.
+    disconnected: AtomicBool,
+
+    last_event_time: Mutex<Option<SystemTime>>,
+}
+
+impl Heartbeat {
+    fn beat(&self) {
+        *self.last_event_time.lock().expect("mutex lock failed") = Some(SystemTime::now());
+    }
+}
+
+/// Marks the heartbeat as dead when the event forwarding thread exits, even if it panics.
+struct HeartbeatGuard(Arc<Heartbeat>);
+
+impl Drop for HeartbeatGuard {
+    fn drop(&mut self) {
+        self.0.alive.store(false, Ordering::SeqCst);
+    }
 }
 
 impl LocalFileSystemWatcher {
     /// Create a new `LocalFileSystemWatcher` instance that emits file system events into the
-    /// specified event sink.
-    pub fn new(event_sink: Box<dyn FileSystemEventSink>) -> Result<Self> {
+    /// specified event sink, debouncing events with the specified duration.
+    pub fn new(event_sink: Box<dyn FileSystemEventSink>, debounce: Duration) -> Result<Self> {
         let (raw_events_send, raw_events_recv) = std::sync::mpsc::channel();
 
+        let heartbeat = Arc::new(Heartbeat {
+            alive: AtomicBool::new(true),
+            disconnected: AtomicBool::new(false),
+            last_event_time: Mutex::new(None),
+        });
+        let heartbeat_guard = HeartbeatGuard(heartbeat.clone());
+        let watches = Arc::new(Mutex::new(Vec::new()));
+        let thread_watches = watches.clone();
+
         std::thread::spawn(move || {
+            let heartbeat_guard = heartbeat_guard;
             loop {
                 let event = match raw_events_recv.recv() {
                     Ok(e) => e,
-                    Err(_) => return,
+                    Err(RecvError) => {
+                        heartbeat_guard.0.disconnected.store(true, Ordering::SeqCst);
+                        return;
+                    }
                 };
+                heartbeat_guard.0.beat();
 
-                let user_event = filter_raw_fs_event(event);
+                let user_event = filter_raw_fs_event(event).filter(|e| {
+                    let watches = thread_watches.lock().expect("mutex lock failed");
+                    is_watched_event(&watches, e)
+                });
                 if let Some(e) = user_event {
                     event_sink.send(e).ok(); // Ignore all errors during event_sink.send
                 }
             }
         });
 
-        let raw_watcher = Mutex::new(
-            notify::watcher(raw_events_send, Duration::new(0, 0)).map_err(Error::from_inner)?,
-        );
-        Ok(Self { raw_watcher })
+        let raw_watcher =
+            Mutex::new(notify::watcher(raw_events_send, debounce).map_err(Error::from_inner)?);
+        Ok(Self {
+            raw_watcher,
+            heartbeat,
+            watches,
+        })
+    }
+
+    /// Determine whether this watcher is still able to deliver events.
+    ///
+    /// A watcher is considered dead when the channel from the underlying `notify` watcher has
+    /// been disconnected, when the background thread that forwards events from that channel has
+    /// stopped for any other reason, or when any of the watched paths no longer exists, since the
+    /// watch on a deleted path never reports events again.
+    pub fn is_alive(&self) -> bool {
+        if self.heartbeat.disconnected.load(Ordering::SeqCst)
+            || !self.heartbeat.alive.load(Ordering::SeqCst)
+        {
+            return false;
+        }
+
+        let watches = self.watches.lock().expect("mutex lock failed");
+        watches.iter().all(|w| w.path.exists())
+    }
+
+    /// Get the time at which the last event was received from the underlying `notify` watcher.
+    ///
+    /// Returns `None` if no event has been received yet.
+    pub fn last_event_time(&self) -> Option<SystemTime> {
+        *self
+            .heartbeat
+            .last_event_time
+            .lock()
+            .expect("mutex lock failed")
     }
 }
 
@@ -128,7 +307,301 @@ impl FileSystemWatcher for LocalFileSystemWatcher {
         };
 
         // TODO: refactor mutex lock code as a function to provide a single `expect` message.
+        let path = path.as_ref();
         let mut lock = self.raw_watcher.lock().expect("mutex lock failed");
-        lock.watch(path, notify_mode).map_err(Error::from_inner)
+
+        // Record the watch before `notify` starts reporting events on it, and roll it back if
+        // `notify` fails, so that the recorded watches always match the registered ones.
+        self.watches.lock().expect("mutex lock failed").push(Watch {
+            path: path.to_path_buf(),
+            canonical: std::fs::canonicalize(path).ok(),
+            mode,
+        });
+        if let Err(e) = lock.watch(path, notify_mode) {
+            let mut watches = self.watches.lock().expect("mutex lock failed");
+            watches.pop();
+            return Err(Error::from_inner(e));
+        }
+        Ok(())
+    }
+
+    fn unwatch<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        // Do not panic on poisoned locks since this is called when dropping `WatchGuard`s.
+        let path = path.as_ref();
+        let mut lock = self
+            .raw_watcher
+            .lock()
+            .map_err(|_| Error::from_message("watcher mutex is poisoned"))?;
+        lock.unwatch(path).map_err(Error::from_inner)?;
+
+        self.watches
+            .lock()
+            .map_err(|_| Error::from_message("watcher mutex is poisoned"))?
+            .retain(|w| w.path != path);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::tests::{local_watcher, temp_dir, EVENT_TIMEOUT, QUIET_TIMEOUT};
+
+    /// Wait until the specified condition holds, or fail after `EVENT_TIMEOUT`.
+    fn wait_until<C: Fn() -> bool>(condition: C) {
+        let start = std::time::Instant::now();
+        while !condition() {
+            assert!(start.elapsed() < EVENT_TIMEOUT, "condition never held");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn new_watcher_is_alive() {
+        let (watcher, _events) = local_watcher();
+        assert!(watcher.is_alive());
+        assert_eq!(watcher.last_event_time(), None);
+    }
+
+    #[test]
+    fn events_update_last_event_time() {
+        let dir = temp_dir("local-heartbeat");
+        let (watcher, events) = local_watcher();
+        watcher.watch(&dir, FileSystemWatchMode::Recursive).unwrap();
+
+        std::fs::write(dir.join("a.md"), "a").unwrap();
+        events.recv_timeout(EVENT_TIMEOUT).unwrap();
+        assert!(watcher.last_event_time().is_some());
+        assert!(watcher.is_alive());
+    }
+
+    #[test]
+    fn dropping_the_inner_watcher_kills_the_watcher() {
+        let (watcher, _events) = local_watcher();
+        // Replacing the inner watcher drops it together with its end of the event channel.
+        let (send, _recv) = std::sync::mpsc::channel();
+        let replacement = notify::watcher(send, Duration::from_millis(50)).unwrap();
+        drop(std::mem::replace(
+            &mut *watcher.raw_watcher.lock().unwrap(),
+            replacement,
+        ));
+
+        wait_until(|| !watcher.is_alive());
+        assert!(watcher.heartbeat.disconnected.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn deleting_a_watched_path_kills_the_watcher() {
+        let dir = temp_dir("local-deleted");
+        let (watcher, _events) = local_watcher();
+        watcher.watch(&dir, FileSystemWatchMode::Recursive).unwrap();
+        assert!(watcher.is_alive());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(!watcher.is_alive());
+    }
+
+    #[test]
+    fn failed_watches_are_not_recorded() {
+        let dir = temp_dir("local-failed");
+        let (watcher, _events) = local_watcher();
+        assert!(watcher
+            .watch(dir.join("missing"), FileSystemWatchMode::Normal)
+            .is_err());
+        assert!(watcher.watches.lock().unwrap().is_empty());
+        assert!(watcher.is_alive());
+
+        watcher.watch(&dir, FileSystemWatchMode::Normal).unwrap();
+        watcher.unwatch(&dir).unwrap();
+        assert!(watcher.watches.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn changed_since_lists_files_modified_after_the_time() {
+        let dir = temp_dir("local-changed-since");
+        let since = SystemTime::now() - Duration::from_secs(60);
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        for name in &["old.md", "new.md", "sub/new.md"] {
+            std::fs::write(dir.join(name), name).unwrap();
+        }
+        std::fs::File::options()
+            .write(true)
+            .open(dir.join("old.md"))
+            .unwrap()
+            .set_modified(since - Duration::from_secs(60))
+            .unwrap();
+
+        let changed = LocalFileSystem::new().changed_since(&dir, since).unwrap();
+        assert_eq!(changed, vec![dir.join("new.md"), dir.join("sub/new.md")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn has_file_and_has_dir_follow_symbolic_links() {
+        use std::os::unix::fs::symlink;
+
+        let dir = temp_dir("local-has-entry");
+        std::fs::write(dir.join("a.md"), "a").unwrap();
+        std::fs::create_dir(dir.join("sub")).unwrap();
+        symlink(dir.join("a.md"), dir.join("file-link")).unwrap();
+        symlink(dir.join("sub"), dir.join("dir-link")).unwrap();
+        symlink(dir.join("missing"), dir.join("broken-link")).unwrap();
+
+        let fs = LocalFileSystem::new();
+        assert!(fs.has_file(dir.join("file-link")) && !fs.has_dir(dir.join("file-link")));
+        assert!(fs.has_dir(dir.join("dir-link")) && !fs.has_file(dir.join("dir-link")));
+        assert!(!fs.has_entry(dir.join("broken-link")));
+        assert!(!fs.has_entry(dir.join("missing")));
+    }
+
+    #[test]
+    fn reads_raw_bytes_and_rejects_invalid_utf8_strings() {
+        let dir = temp_dir("local-read-bytes");
+        let path = dir.join("image.bin");
+        std::fs::write(&path, [0xff, 0x00, 0xfe]).unwrap();
+
+        let fs = LocalFileSystem::new();
+        assert_eq!(
+            fs.read_file_as_bytes(&path).unwrap(),
+            vec![0xff, 0x00, 0xfe]
+        );
+        let err = fs.read_file_as_string(&path).unwrap_err();
+        assert_eq!(err.kind(), crate::error::ErrorKind::Parse);
+        assert!(err.to_string().contains("image.bin"), "{}", err);
+    }
+
+    #[test]
+    fn write_file_creates_missing_parent_directories() {
+        let dir = temp_dir("local-write");
+        let fs = LocalFileSystem::new();
+        fs.write_file(dir.join("site/zh/a.html"), b"first").unwrap();
+        fs.write_file(dir.join("site/zh/a.html"), b"second")
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("site/zh/a.html")).unwrap(),
+            "second"
+        );
+
+        fs.create_dir_all(dir.join("site/en/assets")).unwrap();
+        fs.create_dir_all(dir.join("site/en/assets")).unwrap();
+        assert!(fs.has_dir(dir.join("site/en/assets")));
+    }
+
+    #[test]
+    fn writes_within_the_debounce_duration_are_coalesced() {
+        let dir = temp_dir("local-debounce");
+        std::fs::write(dir.join("a.md"), "a").unwrap();
+        let (send, events) = std::sync::mpsc::channel();
+        let watcher = LocalFileSystem::new()
+            .create_watcher_with_debounce(Box::new(send), Duration::from_millis(300))
+            .unwrap();
+        watcher.watch(&dir, FileSystemWatchMode::Recursive).unwrap();
+
+        std::fs::write(dir.join("a.md"), "b").unwrap();
+        std::fs::write(dir.join("a.md"), "c").unwrap();
+        match events.recv_timeout(EVENT_TIMEOUT).unwrap() {
+            FileSystemEvent::Write(path) => assert_eq!(path, dir.join("a.md")),
+            event => panic!("unexpected event {:?}", event),
+        }
+        assert!(events.recv_timeout(QUIET_TIMEOUT).is_err());
+    }
+
+    #[test]
+    fn filter_raw_fs_event_maps_every_debounced_event() {
+        let (a, b) = (PathBuf::from("a.md"), PathBuf::from("b.md"));
+        assert!(filter_raw_fs_event(DebouncedEvent::NoticeWrite(a.clone())).is_none());
+        assert!(filter_raw_fs_event(DebouncedEvent::NoticeRemove(a.clone())).is_none());
+        assert!(filter_raw_fs_event(DebouncedEvent::Chmod(a.clone())).is_none());
+
+        let mapped = |raw| filter_raw_fs_event(raw).map(|event| format!("{:?}", event));
+        assert_eq!(
+            mapped(DebouncedEvent::Create(a.clone())),
+            Some(format!("{:?}", FileSystemEvent::Create(a.clone())))
+        );
+        assert_eq!(
+            mapped(DebouncedEvent::Write(a.clone())),
+            Some(format!("{:?}", FileSystemEvent::Write(a.clone())))
+        );
+        assert_eq!(
+            mapped(DebouncedEvent::Remove(a.clone())),
+            Some(format!("{:?}", FileSystemEvent::Delete(a.clone())))
+        );
+        assert_eq!(
+            mapped(DebouncedEvent::Rename(a.clone(), b.clone())),
+            Some(format!(
+                "{:?}",
+                FileSystemEvent::Rename {
+                    from: a.clone(),
+                    to: b
+                }
+            ))
+        );
+        assert_eq!(
+            mapped(DebouncedEvent::Rescan),
+            Some(format!("{:?}", FileSystemEvent::Rescan))
+        );
+
+        let raw = DebouncedEvent::Error(notify::Error::Generic("lost".to_owned()), Some(a.clone()));
+        match filter_raw_fs_event(raw) {
+            Some(FileSystemEvent::Error(err, path)) => {
+                assert!(err.to_string().contains("lost"), "{}", err);
+                assert_eq!(path, Some(a));
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn normal_watches_only_report_the_watched_path() {
+        let watch = |path: &str, mode| Watch {
+            path: PathBuf::from(path),
+            canonical: Some(PathBuf::from(format!("/canonical{}", path))),
+            mode,
+        };
+        let watches = [
+            watch("/book", FileSystemWatchMode::Normal),
+            watch("/assets", FileSystemWatchMode::Recursive),
+        ];
+        let write = |path: &str| FileSystemEvent::Write(PathBuf::from(path));
+        assert!(is_watched_event(&watches, &write("/book")));
+        assert!(is_watched_event(&watches, &write("/canonical/book")));
+        assert!(!is_watched_event(&watches, &write("/book/a.md")));
+        assert!(is_watched_event(&watches, &write("/assets/img/a.png")));
+        assert!(is_watched_event(
+            &watches,
+            &write("/canonical/assets/a.png")
+        ));
+        assert!(!is_watched_event(&watches, &write("/other")));
+
+        let rename = FileSystemEvent::Rename {
+            from: PathBuf::from("/book"),
+            to: PathBuf::from("/moved"),
+        };
+        assert!(is_watched_event(&watches, &rename));
+        assert!(is_watched_event(&watches, &FileSystemEvent::Rescan));
+    }
+
+    #[test]
+    fn normal_watches_suppress_events_on_children() {
+        let dir = temp_dir("local-normal");
+        std::fs::create_dir_all(dir.join("book")).unwrap();
+        std::fs::write(dir.join("book/a.md"), "a").unwrap();
+        let (watcher, events) = local_watcher();
+        watcher
+            .watch(dir.join("book"), FileSystemWatchMode::Normal)
+            .unwrap();
+
+        std::fs::write(dir.join("book/a.md"), "b").unwrap();
+        std::fs::write(dir.join("book/b.md"), "b").unwrap();
+        assert!(events.recv_timeout(QUIET_TIMEOUT).is_err());
+
+        std::fs::rename(dir.join("book"), dir.join("moved")).unwrap();
+        // `notify` reports a watched directory that is moved away as deleted.
+        match events.recv_timeout(EVENT_TIMEOUT).unwrap() {
+            FileSystemEvent::Delete(path) | FileSystemEvent::Rename { from: path, .. } => {
+                assert_eq!(path, dir.join("book"))
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
     }
 }
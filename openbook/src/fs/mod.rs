@@ -3,8 +3,11 @@
 //!
 //! OpenBook requires the following operations to be supported by the file system:
 //! * Determine whether a file or a directory exists at a specified path;
-//! * Read a file;
-//! * List all files under a directory;
+//! * Read a file, either as raw bytes or as a string;
+//! * Write a file and create directories;
+//! * List all files under a directory, either directly or recursively, optionally filtered by a
+//!   glob pattern;
+//! * Enumerate files modified after a point in time;
 //! * Watch for file changes. The following file system events will be watched:
 //!   * A new file is created;
 //!   * An existing file is deleted;
@@ -13,17 +16,29 @@
 //!
 //! OpenBook relies on the [`FileSystem`] trait that provides the aforementioned file system
 //! operations. The `local` submodule provides a [`FileSystem`] implementation that operates on the
-//! local file system.
+//! local file system. The `scratch` submodule provides a [`FileSystem`] implementation that
+//! overlays in-memory files on top of the local file system. The `glob` submodule implements
+//! directory iteration filtered by glob patterns, and the `batch` submodule provides an event sink
+//! that coalesces bursts of file system events into batches.
 //!
 //! [`FileSystem`]: trait.FileSystem.html
 //!
 
+pub mod batch;
+pub mod glob;
 pub mod local;
+pub mod scratch;
 
-use std::path::{Path, PathBuf};
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
 use std::sync::mpsc::Sender;
+use std::time::{Duration, SystemTime};
 
 use crate::error::{Error, Result};
+use crate::fs::glob::GlobDirIter;
+
+/// The debounce duration used by file system watchers created by `FileSystem::create_watcher`.
+pub const DEFAULT_WATCHER_DEBOUNCE: Duration = Duration::from_millis(250);
 
 /// A platform independent definition of a file system that supports the operations needed by
 /// OpenBook.
@@ -35,19 +50,57 @@ pub trait FileSystem: Sync {
     type Watcher: FileSystemWatcher;
 
     /// Determine whether a normal file exists at the specified path in the file system.
+    ///
+    /// Symbolic links are followed, so a link that points to a normal file counts as a normal file.
+    /// This function returns `false` if nothing exists at the path, if the entry at the path is not
+    /// a normal file, if the path is a broken symbolic link, or if the type of the entry cannot be
+    /// determined (e.g. due to insufficient permissions).
     fn has_file<P: AsRef<Path>>(&self, path: P) -> bool;
 
     /// Determine whether a directory exists at the specified path in the file system.
+    ///
+    /// Symbolic links are followed, so a link that points to a directory counts as a directory.
+    /// This function returns `false` if nothing exists at the path, if the entry at the path is not
+    /// a directory, if the path is a broken symbolic link, or if the type of the entry cannot be
+    /// determined (e.g. due to insufficient permissions).
     fn has_dir<P: AsRef<Path>>(&self, path: P) -> bool;
 
     /// Determine whether a file or a directory exists at the specified path in the file system.
+    ///
+    /// As with `has_file` and `has_dir`, a broken symbolic link is not considered an entry.
     fn has_entry<P: AsRef<Path>>(&self, path: P) -> bool {
         let path = path.as_ref();
         self.has_file(path) || self.has_dir(path)
     }
 
+    /// Read the whole content of the specified file as raw bytes.
+    fn read_file_as_bytes<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>>;
+
     /// Read the whole content of the specified file as a string.
-    fn read_file_as_string<P: AsRef<Path>>(&self, path: P) -> Result<String>;
+    ///
+    /// The default implementation reads the file with `read_file_as_bytes` and fails with an error
+    /// naming the file if its content is not valid UTF-8.
+    fn read_file_as_string<P: AsRef<Path>>(&self, path: P) -> Result<String> {
+        let path = path.as_ref();
+        let bytes = self.read_file_as_bytes(path)?;
+        String::from_utf8(bytes).map_err(|e| {
+            Error::parse(format!(
+                "file \"{}\" is not valid UTF-8: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Write the specified content to the specified file, replacing the file if it already exists.
+    ///
+    /// Missing parent directories of the file are created as needed.
+    fn write_file<P: AsRef<Path>>(&self, path: P, contents: &[u8]) -> Result<()>;
+
+    /// Create the specified directory and all of its missing parent directories.
+    ///
+    /// It is not an error if the directory already exists.
+    fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> Result<()>;
 
     /// Create a `DirIter` that iterates over all files and subdirectories under the specified
     /// directory.
@@ -55,8 +108,236 @@ pub trait FileSystem: Sync {
     /// Note that the returned iterator will not iterate the specified directory **recursively**.
     fn read_directory<P: AsRef<Path>>(&self, path: P) -> Result<Self::DirIter>;
 
+    /// Get the canonical form of the specified path.
+    ///
+    /// Paths that refer to the same entry in the file system should have the same canonical form.
+    /// The default implementation returns the path as-is, which is only correct for file systems
+    /// that do not support links.
+    fn canonicalize<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        Ok(path.as_ref().to_path_buf())
+    }
+
+    /// Create a [`Walk`] that recursively iterates over all files under the specified directory.
+    ///
+    /// For details about the walk, please refer to [`Walk`].
+    ///
+    /// [`Walk`]: struct.Walk.html
+    fn walk<P: AsRef<Path>>(&self, path: P) -> Walk<'_, Self>
+    where
+        Self: Sized,
+    {
+        Walk::new(self, path)
+    }
+
+    /// Create a [`GlobDirIter`] that iterates over the entries under the specified directory whose
+    /// paths, relative to the directory, match the specified glob pattern.
+    ///
+    /// A pattern with a single component, like `*.md`, only matches the direct entries of the
+    /// directory, while a pattern with multiple components, like `**/*.md`, matches the files under
+    /// the directory recursively. For the syntax of glob patterns, please refer to the `glob`
+    /// module. Returns an error of kind `ErrorKind::Parse` if the pattern is malformed.
+    ///
+    /// [`GlobDirIter`]: glob/struct.GlobDirIter.html
+    fn read_directory_glob<P: AsRef<Path>>(
+        &self,
+        path: P,
+        pattern: &str,
+    ) -> Result<GlobDirIter<'_, Self>>
+    where
+        Self: Sized,
+    {
+        GlobDirIter::new(self, path, pattern)
+    }
+
+    /// Recursively walk the directory at the specified path and get all files that are modified
+    /// after the specified point in time.
+    ///
+    /// Only files are returned; directories are walked but never included in the result.
+    fn changed_since<P: AsRef<Path>>(&self, root: P, since: SystemTime) -> Result<Vec<PathBuf>>;
+
     /// Create a file system watcher that emits events into the specified event sink.
-    fn create_watcher(&self, event_sink: Box<dyn FileSystemEventSink>) -> Result<Self::Watcher>;
+    ///
+    /// The created watcher debounces events with [`DEFAULT_WATCHER_DEBOUNCE`].
+    ///
+    /// [`DEFAULT_WATCHER_DEBOUNCE`]: constant.DEFAULT_WATCHER_DEBOUNCE.html
+    fn create_watcher(&self, event_sink: Box<dyn FileSystemEventSink>) -> Result<Self::Watcher> {
+        self.create_watcher_with_debounce(event_sink, DEFAULT_WATCHER_DEBOUNCE)
+    }
+
+    /// Create a file system watcher that emits events into the specified event sink, debouncing
+    /// events with the specified duration.
+    ///
+    /// Events on the same path that occur within the debounce duration are coalesced into a single
+    /// event, so that e.g. an editor that writes a file several times while saving produces a
+    /// single `Write` event.
+    fn create_watcher_with_debounce(
+        &self,
+        event_sink: Box<dyn FileSystemEventSink>,
+        debounce: Duration,
+    ) -> Result<Self::Watcher>;
+}
+
+/// Lexically normalize the specified path by removing `.` components and resolving `..`
+/// components, without accessing any file system.
+///
+/// `..` components that would go above the beginning of a relative path are kept, and `..`
+/// components directly under the root are removed. Note that the result may refer to a different
+/// entry than the original path if the path goes through symbolic links; use
+/// `FileSystem::canonicalize` to resolve them.
+pub fn normalize<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut components: Vec<Component> = Vec::new();
+    for c in path.as_ref().components() {
+        match c {
+            Component::CurDir => {}
+            Component::ParentDir => match components.last() {
+                Some(Component::Normal(_)) => {
+                    components.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => components.push(c),
+            },
+            _ => components.push(c),
+        }
+    }
+    components.iter().collect()
+}
+
+/// Get the path that leads from the directory `base` to `path`.
+///
+/// Both paths are normalized lexically with [`normalize`] first, so callers that need symbolic
+/// links to be resolved should canonicalize them with `FileSystem::canonicalize` beforehand. If
+/// `path` is outside of `base`, the result starts with `..` components, e.g. `../b/c` for the base
+/// `/a/x` and the path `/a/b/c`.
+///
+/// Returns an error if one path is absolute and the other is relative, if the paths have different
+/// prefixes, or if `base` has `..` components that cannot be resolved lexically.
+///
+/// [`normalize`]: fn.normalize.html
+pub fn relative_to<B: AsRef<Path>, P: AsRef<Path>>(base: B, path: P) -> Result<PathBuf> {
+    let base = normalize(base.as_ref());
+    let path = normalize(path.as_ref());
+    let error = || {
+        Error::from_message(format!(
+            "cannot get the path of {} relative to {}",
+            path.display(),
+            base.display()
+        ))
+    };
+
+    if base.has_root() != path.has_root() {
+        return Err(error());
+    }
+
+    let base_components: Vec<Component> = base.components().collect();
+    let path_components: Vec<Component> = path.components().collect();
+    let common = base_components
+        .iter()
+        .zip(&path_components)
+        .take_while(|(b, p)| b == p)
+        .count();
+    let is_prefix = |c: Option<&Component>| matches!(c, Some(Component::Prefix(_)));
+    if (is_prefix(base_components.first()) || is_prefix(path_components.first())) && common == 0 {
+        return Err(error());
+    }
+
+    let mut relative = PathBuf::new();
+    for c in &base_components[common..] {
+        match c {
+            Component::Normal(_) => relative.push(".."),
+            _ => return Err(error()),
+        }
+    }
+    for c in &path_components[common..] {
+        relative.push(c.as_os_str());
+    }
+    Ok(relative)
+}
+
+/// Get the link from a file in the directory `base` to the file `path`, such as the target of a
+/// markdown link.
+///
+/// The link is the path returned by [`relative_to`] with its components separated by forward
+/// slashes on every platform, e.g. `../b/c.md` for the base `a/x` and the path `a/b/c.md`. If the
+/// relative path cannot be computed, the normalized `path` itself is used.
+///
+/// [`relative_to`]: fn.relative_to.html
+pub fn relative_link<B: AsRef<Path>, P: AsRef<Path>>(base: B, path: P) -> String {
+    let relative = relative_to(base, path.as_ref()).unwrap_or_else(|_| normalize(path));
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Recursively iterates over all files under a directory in a [`FileSystem`].
+///
+/// The directory is walked in depth-first pre-order using `read_directory`. Subdirectories are
+/// descended into but never yielded. Each directory is read at most once as determined by its
+/// canonical path, so symbolic link cycles do not make the walk run forever.
+///
+/// Errors that occur when reading a single entry or directory are yielded as `Err` items, after
+/// which the walk continues with the remaining entries.
+///
+/// [`FileSystem`]: trait.FileSystem.html
+pub struct Walk<'a, F: FileSystem> {
+    fs: &'a F,
+    root: Option<PathBuf>,
+    stack: Vec<F::DirIter>,
+    visited: HashSet<PathBuf>,
+}
+
+impl<'a, F: FileSystem> Walk<'a, F> {
+    /// Create a new `Walk` instance that walks the specified directory in the specified file
+    /// system.
+    pub fn new<P: AsRef<Path>>(fs: &'a F, path: P) -> Self {
+        Self {
+            fs,
+            root: Some(path.as_ref().to_path_buf()),
+            stack: Vec::new(),
+            visited: HashSet::new(),
+        }
+    }
+
+    /// Start reading the specified directory, unless it has been read before.
+    fn enter(&mut self, dir: &Path) -> Result<()> {
+        let canonical = self.fs.canonicalize(dir)?;
+        if self.visited.insert(canonical) {
+            self.stack.push(self.fs.read_directory(dir)?);
+        }
+        Ok(())
+    }
+}
+
+impl<'a, F: FileSystem> Iterator for Walk<'a, F> {
+    type Item = Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(root) = self.root.take() {
+            if let Err(e) = self.enter(&root) {
+                return Some(Err(e));
+            }
+        }
+
+        loop {
+            let path = match self.stack.last_mut()?.next() {
+                Some(Ok(path)) => path,
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+
+            if self.fs.has_dir(&path) {
+                if let Err(e) = self.enter(&path) {
+                    return Some(Err(e));
+                }
+            } else if self.fs.has_file(&path) {
+                return Some(Ok(path));
+            }
+        }
+    }
 }
 
 /// Watches state changes in the file system and emits corresponding events.
@@ -67,6 +348,70 @@ pub trait FileSystemWatcher {
     ///
     /// [`FileSystemWatchMode`]: enum.FileSystemWatchMode.html
     fn watch<P: AsRef<Path>>(&self, path: P, mode: FileSystemWatchMode) -> Result<()>;
+
+    /// Stop watching the specified file system path, which must have been watched by `watch`.
+    fn unwatch<P: AsRef<Path>>(&self, path: P) -> Result<()>;
+
+    /// Watch the specified file system path for changes with the specified mode until the
+    /// returned guard is dropped.
+    ///
+    /// The guard calls `unwatch` on the path when it is dropped. Errors from `unwatch` at that
+    /// point are ignored rather than causing a panic; call `WatchGuard::unwatch` instead to stop
+    /// watching and get the error.
+    fn watch_scoped<P: AsRef<Path>>(
+        &self,
+        path: P,
+        mode: FileSystemWatchMode,
+    ) -> Result<WatchGuard<'_, Self>>
+    where
+        Self: Sized,
+    {
+        let path = path.as_ref();
+        self.watch(path, mode)?;
+        Ok(WatchGuard {
+            watcher: self,
+            path: path.to_path_buf(),
+            active: true,
+        })
+    }
+}
+
+/// Stops watching a path in a [`FileSystemWatcher`] when dropped.
+///
+/// `WatchGuard` instances are created by `FileSystemWatcher::watch_scoped`.
+///
+/// [`FileSystemWatcher`]: trait.FileSystemWatcher.html
+pub struct WatchGuard<'a, W: FileSystemWatcher> {
+    watcher: &'a W,
+    path: PathBuf,
+
+    /// Whether the path still needs to be unwatched when the guard is dropped.
+    active: bool,
+}
+
+impl<'a, W: FileSystemWatcher> WatchGuard<'a, W> {
+    /// Get the watched path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Stop watching the path now and consume the guard.
+    ///
+    /// Unlike dropping the guard, this reports the error returned by `FileSystemWatcher::unwatch`.
+    /// The path is not unwatched again when the guard is dropped, even if an error is returned.
+    pub fn unwatch(mut self) -> Result<()> {
+        self.active = false;
+        self.watcher.unwatch(&self.path)
+    }
+}
+
+impl<'a, W: FileSystemWatcher> Drop for WatchGuard<'a, W> {
+    fn drop(&mut self) {
+        if self.active {
+            // There is no way to report the error from `drop`; callers that care use `unwatch`.
+            self.watcher.unwatch(&self.path).ok();
+        }
+    }
 }
 
 /// Specifies whether the file system watcher should watch the specified path recursively.
@@ -102,12 +447,16 @@ pub enum FileSystemEvent {
     /// The file at the specified path is been written to.
     Write(PathBuf),
 
+    /// The file system watcher may have missed some events, and the whole watched tree should be
+    /// reloaded.
+    Rescan,
+
     /// An error occurred in the file system watcher.
     Error(Error, Option<PathBuf>),
 }
 
 /// File system watchers emit file system events into this sink.
-pub trait FileSystemEventSink {
+pub trait FileSystemEventSink: Send {
     /// Send the specified event into this sink.
     fn send(&self, event: FileSystemEvent) -> Result<()>;
 }
@@ -117,3 +466,227 @@ impl FileSystemEventSink for Sender<FileSystemEvent> {
         self.send(event).map_err(Error::from_inner)
     }
 }
+
+/// A [`FileSystemEventSink`] that passes every event to the wrapped closure.
+///
+/// ```ignore
+/// let watcher = fs.create_watcher(Box::new(FnSink(|e| {
+///     println!("{:?}", e);
+///     Ok(())
+/// })))?;
+/// ```
+///
+/// [`FileSystemEventSink`]: trait.FileSystemEventSink.html
+pub struct FnSink<F>(pub F);
+
+impl<F> FileSystemEventSink for FnSink<F>
+where
+    F: Fn(FileSystemEvent) -> Result<()> + Send + Sync,
+{
+    fn send(&self, event: FileSystemEvent) -> Result<()> {
+        (self.0)(event)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use std::sync::mpsc::{channel, Receiver};
+
+    use crate::fs::local::{LocalFileSystem, LocalFileSystemWatcher};
+
+    /// Create an empty directory for the test with the specified name on the local file system.
+    pub(crate) fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("openbook-{}-{}", std::process::id(), name));
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    /// Create a watcher on the local file system with a short debounce duration.
+    pub(crate) fn local_watcher() -> (LocalFileSystemWatcher, Receiver<FileSystemEvent>) {
+        let (send, recv) = channel();
+        let watcher = LocalFileSystem::new()
+            .create_watcher_with_debounce(Box::new(send), Duration::from_millis(50))
+            .unwrap();
+        (watcher, recv)
+    }
+
+    /// How long to wait for an expected event.
+    pub(crate) const EVENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+    /// How long to wait before concluding that no event is emitted.
+    pub(crate) const QUIET_TIMEOUT: Duration = Duration::from_millis(500);
+
+    #[test]
+    fn watch_guard_reports_events_while_alive() {
+        let dir = temp_dir("watch-guard-alive");
+        let (watcher, events) = local_watcher();
+        let guard = watcher
+            .watch_scoped(&dir, FileSystemWatchMode::Recursive)
+            .unwrap();
+        assert_eq!(guard.path(), dir.as_path());
+
+        std::fs::write(dir.join("a.md"), "a").unwrap();
+        assert!(events.recv_timeout(EVENT_TIMEOUT).is_ok());
+    }
+
+    #[test]
+    fn dropped_watch_guard_stops_watching() {
+        let dir = temp_dir("watch-guard-dropped");
+        let (watcher, events) = local_watcher();
+        let guard = watcher
+            .watch_scoped(&dir, FileSystemWatchMode::Recursive)
+            .unwrap();
+        drop(guard);
+
+        std::fs::write(dir.join("a.md"), "a").unwrap();
+        assert!(events.recv_timeout(QUIET_TIMEOUT).is_err());
+    }
+
+    #[test]
+    fn watch_guard_unwatch_reports_errors() {
+        let dir = temp_dir("watch-guard-unwatch");
+        let (watcher, events) = local_watcher();
+
+        let guard = watcher
+            .watch_scoped(&dir, FileSystemWatchMode::Recursive)
+            .unwrap();
+        guard.unwatch().unwrap();
+        std::fs::write(dir.join("a.md"), "a").unwrap();
+        assert!(events.recv_timeout(QUIET_TIMEOUT).is_err());
+
+        let guard = watcher
+            .watch_scoped(&dir, FileSystemWatchMode::Recursive)
+            .unwrap();
+        watcher.unwatch(&dir).unwrap();
+        assert!(guard.unwatch().is_err());
+    }
+
+    #[test]
+    fn fn_sinks_pass_events_to_the_closure() {
+        let sink = FnSink(|event| match event {
+            FileSystemEvent::Write(_) => Ok(()),
+            _ => Err(Error::not_found("unexpected event")),
+        });
+        assert!(sink
+            .send(FileSystemEvent::Write(PathBuf::from("a.md")))
+            .is_ok());
+        let err = sink.send(FileSystemEvent::Rescan).unwrap_err();
+        assert_eq!(err.kind(), crate::error::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn watchers_report_events_to_fn_sinks() {
+        let dir = temp_dir("fn-sink");
+        let (send, events) = std::sync::mpsc::sync_channel(16);
+        let watcher = LocalFileSystem::new()
+            .create_watcher_with_debounce(
+                Box::new(FnSink(move |event| {
+                    send.send(event).map_err(Error::from_inner)
+                })),
+                Duration::from_millis(50),
+            )
+            .unwrap();
+        watcher.watch(&dir, FileSystemWatchMode::Recursive).unwrap();
+
+        std::fs::write(dir.join("a.md"), "a").unwrap();
+        match events.recv_timeout(EVENT_TIMEOUT).unwrap() {
+            FileSystemEvent::Create(path) | FileSystemEvent::Write(path) => {
+                assert_eq!(path, dir.join("a.md"))
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn relative_link_walks_up_only_to_the_common_directory() {
+        assert_eq!(relative_link("", "a/b.md"), "a/b.md");
+        assert_eq!(relative_link("a", "a/b/c.md"), "b/c.md");
+        assert_eq!(relative_link("a/x", "a/b/c.md"), "../b/c.md");
+        assert_eq!(relative_link("a/b", "c.md"), "../../c.md");
+        assert_eq!(relative_link("./a/../b", "b/./c.md"), "c.md");
+    }
+
+    #[test]
+    fn relative_link_falls_back_to_the_path() {
+        assert_eq!(relative_link("/root", "a/./b.md"), "a/b.md");
+        assert_eq!(relative_link("../up", "a.md"), "a.md");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_yields_files_once_despite_link_cycles() {
+        let dir = temp_dir("walk-cycles");
+        std::fs::create_dir_all(dir.join("a/b")).unwrap();
+        std::fs::write(dir.join("top.md"), "").unwrap();
+        std::fs::write(dir.join("a/b/deep.md"), "").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("a/b/loop")).unwrap();
+
+        let mut files: Vec<PathBuf> = LocalFileSystem::new()
+            .walk(&dir)
+            .map(|f| f.unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(files, vec![dir.join("a/b/deep.md"), dir.join("top.md")]);
+    }
+
+    #[test]
+    fn walk_reports_missing_directories() {
+        let dir = temp_dir("walk-missing");
+        let fs = LocalFileSystem::new();
+        let mut walk = fs.walk(dir.join("missing"));
+        assert!(walk.next().unwrap().is_err());
+        assert!(walk.next().is_none());
+    }
+
+    #[test]
+    fn normalize_collapses_dots_lexically() {
+        assert_eq!(normalize("./a/b/../c/./d.md"), PathBuf::from("a/c/d.md"));
+        assert_eq!(normalize("../a/../../b"), PathBuf::from("../../b"));
+        assert_eq!(normalize("/../a/./b"), PathBuf::from("/a/b"));
+        assert_eq!(normalize("/a/b/c"), PathBuf::from("/a/b/c"));
+    }
+
+    #[test]
+    fn relative_to_walks_up_and_down() {
+        assert_eq!(
+            relative_to("/a/x", "/a/b/c").unwrap(),
+            PathBuf::from("../b/c")
+        );
+        assert_eq!(
+            relative_to("/a", "/a/./b/../c").unwrap(),
+            PathBuf::from("c")
+        );
+        assert_eq!(relative_to("a/b", "a/b").unwrap(), PathBuf::new());
+        assert_eq!(
+            relative_to("a/b/c", "d").unwrap(),
+            PathBuf::from("../../../d")
+        );
+        assert!(relative_to("/a", "b").is_err());
+        assert!(relative_to("a", "/b").is_err());
+        assert!(relative_to("../a", "b").is_err());
+    }
+
+    #[test]
+    fn scratch_paths_are_canonicalized_lexically() {
+        let fs = crate::fs::scratch::ScratchFileSystem::new();
+        fs.write_file("/openbook-test/canonical/a/b.md", b"")
+            .unwrap();
+        assert_eq!(
+            fs.canonicalize("/openbook-test/canonical/x/../a/./b.md")
+                .unwrap(),
+            PathBuf::from("/openbook-test/canonical/a/b.md")
+        );
+
+        let dir = temp_dir("canonicalize");
+        std::fs::create_dir_all(dir.join("a")).unwrap();
+        assert_eq!(fs.canonicalize(dir.join("a/../a")).unwrap(), dir.join("a"));
+        assert!(LocalFileSystem::new()
+            .canonicalize(dir.join("missing"))
+            .is_err());
+    }
+}
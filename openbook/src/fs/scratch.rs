@@ -0,0 +1,273 @@
+//! Provide an implementation of [`FileSystem`] that overlays in-memory file contents on top of the
+//! local file system.
+//!
+//! This is useful for testing and for preview servers that want to render unsaved editor buffers
+//! without writing them to disk.
+//!
+//! [`FileSystem`]: ../trait.FileSystem.html
+//!
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, SystemTime};
+
+use crate::error::Result;
+use crate::fs::local::{LocalFileSystem, LocalFileSystemWatcher};
+use crate::fs::{normalize, FileSystem, FileSystemEventSink};
+
+/// An in-memory file that overrides the file at the same path in the base file system.
+struct ScratchFile {
+    content: Vec<u8>,
+    modified: SystemTime,
+}
+
+/// An implementation of [`FileSystem`] that combines a [`LocalFileSystem`] base with a set of
+/// in-memory files.
+///
+/// In-memory files take precedence over the files at the same path in the base file system when
+/// reading, and directory listings reflect both layers. Writes through `write_file` and
+/// `create_dir_all` only go to the in-memory layer and never touch the disk.
+///
+/// Note that the watchers created by this file system only watch the base file system; writes to
+/// the in-memory layer do not produce any file system event.
+///
+/// [`FileSystem`]: ../trait.FileSystem.html
+/// [`LocalFileSystem`]: ../local/struct.LocalFileSystem.html
+#[derive(Default)]
+pub struct ScratchFileSystem {
+    base: LocalFileSystem,
+    overrides: RwLock<HashMap<PathBuf, ScratchFile>>,
+    dirs: RwLock<HashSet<PathBuf>>,
+}
+
+impl ScratchFileSystem {
+    /// Create a new `ScratchFileSystem` instance with no in-memory files.
+    pub fn new() -> Self {
+        Self {
+            base: LocalFileSystem::new(),
+            overrides: RwLock::new(HashMap::new()),
+            dirs: RwLock::new(HashSet::new()),
+        }
+    }
+
+    /// Remove the in-memory file at the specified path so that the file in the base file system
+    /// becomes visible again.
+    ///
+    /// Returns whether an in-memory file existed at the path.
+    pub fn discard_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.write_overrides().remove(path.as_ref()).is_some()
+    }
+
+    fn read_overrides(&self) -> RwLockReadGuard<'_, HashMap<PathBuf, ScratchFile>> {
+        self.overrides.read().expect("rwlock lock failed")
+    }
+
+    fn write_overrides(&self) -> RwLockWriteGuard<'_, HashMap<PathBuf, ScratchFile>> {
+        self.overrides.write().expect("rwlock lock failed")
+    }
+
+    fn read_dirs(&self) -> RwLockReadGuard<'_, HashSet<PathBuf>> {
+        self.dirs.read().expect("rwlock lock failed")
+    }
+
+    /// Get the paths of all in-memory files and directories.
+    fn scratch_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self.read_overrides().keys().cloned().collect();
+        paths.extend(self.read_dirs().iter().cloned());
+        paths
+    }
+}
+
+impl FileSystem for ScratchFileSystem {
+    type DirIter = ScratchFileSystemIter;
+
+    type Watcher = LocalFileSystemWatcher;
+
+    fn has_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = path.as_ref();
+        self.read_overrides().contains_key(path) || self.base.has_file(path)
+    }
+
+    fn has_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = path.as_ref();
+        self.base.has_dir(path)
+            || self.read_dirs().contains(path)
+            || self
+                .scratch_paths()
+                .iter()
+                .any(|p| p != path && p.starts_with(path))
+    }
+
+    fn read_file_as_bytes<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
+        let path = path.as_ref();
+        match self.read_overrides().get(path) {
+            Some(file) => Ok(file.content.clone()),
+            None => self.base.read_file_as_bytes(path),
+        }
+    }
+
+    fn read_directory<P: AsRef<Path>>(&self, path: P) -> Result<Self::DirIter> {
+        let path = path.as_ref();
+
+        let mut scratch_entries = BTreeSet::new();
+        for p in self.scratch_paths() {
+            if let Ok(relative) = p.strip_prefix(path) {
+                if let Some(child) = relative.components().next() {
+                    scratch_entries.insert(path.join(child));
+                }
+            }
+        }
+
+        let mut entries = Vec::new();
+        match self.base.read_directory(path) {
+            Ok(iter) => {
+                for entry in iter {
+                    if let Ok(p) = &entry {
+                        if scratch_entries.contains(p) {
+                            continue;
+                        }
+                    }
+                    entries.push(entry);
+                }
+            }
+            Err(e) if scratch_entries.is_empty() => return Err(e),
+            Err(_) => {}
+        }
+        entries.extend(scratch_entries.into_iter().map(Ok));
+
+        Ok(ScratchFileSystemIter {
+            inner: entries.into_iter(),
+        })
+    }
+
+    fn write_file<P: AsRef<Path>>(&self, path: P, contents: &[u8]) -> Result<()> {
+        let file = ScratchFile {
+            content: contents.to_vec(),
+            modified: SystemTime::now(),
+        };
+        self.write_overrides()
+            .insert(path.as_ref().to_path_buf(), file);
+        Ok(())
+    }
+
+    fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.dirs
+            .write()
+            .expect("rwlock lock failed")
+            .insert(path.as_ref().to_path_buf());
+        Ok(())
+    }
+
+    fn canonicalize<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        let path = path.as_ref();
+        if self.base.has_entry(path) {
+            self.base.canonicalize(path)
+        } else {
+            // The path only exists in the in-memory layer, which does not support links, so the
+            // path is canonicalized lexically.
+            Ok(normalize(path))
+        }
+    }
+
+    fn changed_since<P: AsRef<Path>>(&self, root: P, since: SystemTime) -> Result<Vec<PathBuf>> {
+        let root = root.as_ref();
+
+        let overrides = self.read_overrides();
+
+        let mut changed = BTreeSet::new();
+        if self.base.has_dir(root) {
+            for p in self.base.changed_since(root, since)? {
+                // The modification time of an overridden file is the time it was written to the
+                // in-memory layer.
+                if !overrides.contains_key(&p) {
+                    changed.insert(p);
+                }
+            }
+        }
+        for (p, file) in overrides.iter() {
+            if p.starts_with(root) && file.modified > since {
+                changed.insert(p.clone());
+            }
+        }
+
+        Ok(changed.into_iter().collect())
+    }
+
+    fn create_watcher_with_debounce(
+        &self,
+        event_sink: Box<dyn FileSystemEventSink>,
+        debounce: Duration,
+    ) -> Result<Self::Watcher> {
+        self.base.create_watcher_with_debounce(event_sink, debounce)
+    }
+}
+
+/// Iterates over all files and subdirectories under a directory in a [`ScratchFileSystem`].
+///
+/// [`ScratchFileSystem`]: struct.ScratchFileSystem.html
+pub struct ScratchFileSystemIter {
+    inner: std::vec::IntoIter<Result<PathBuf>>,
+}
+
+impl Iterator for ScratchFileSystemIter {
+    type Item = Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::tests::temp_dir;
+
+    #[test]
+    fn in_memory_files_override_the_base() {
+        let dir = temp_dir("scratch-overlay");
+        std::fs::write(dir.join("a.md"), "disk a").unwrap();
+        std::fs::write(dir.join("b.md"), "disk b").unwrap();
+
+        let fs = ScratchFileSystem::new();
+        fs.write_file(dir.join("a.md"), b"memory a").unwrap();
+        fs.write_file(dir.join("sub/c.md"), b"memory c").unwrap();
+        assert_eq!(
+            fs.read_file_as_string(dir.join("a.md")).unwrap(),
+            "memory a"
+        );
+        assert_eq!(fs.read_file_as_string(dir.join("b.md")).unwrap(), "disk b");
+        assert!(!dir.join("sub").exists());
+
+        let mut entries: Vec<PathBuf> = fs
+            .read_directory(&dir)
+            .unwrap()
+            .map(|e| e.unwrap())
+            .collect();
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![dir.join("a.md"), dir.join("b.md"), dir.join("sub")]
+        );
+
+        assert!(fs.discard_file(dir.join("a.md")));
+        assert!(!fs.discard_file(dir.join("a.md")));
+        assert_eq!(fs.read_file_as_string(dir.join("a.md")).unwrap(), "disk a");
+    }
+
+    #[test]
+    fn changed_since_includes_in_memory_writes() {
+        let dir = temp_dir("scratch-changed-since");
+        std::fs::write(dir.join("a.md"), "disk a").unwrap();
+        let since = SystemTime::now() + Duration::from_secs(60);
+
+        let fs = ScratchFileSystem::new();
+        assert!(fs.changed_since(&dir, since).unwrap().is_empty());
+        fs.write_file(dir.join("b.md"), b"memory b").unwrap();
+        let earlier = SystemTime::now() - Duration::from_secs(60);
+        assert_eq!(
+            fs.changed_since(&dir, earlier).unwrap(),
+            vec![dir.join("a.md"), dir.join("b.md")]
+        );
+    }
+}
@@ -1,5 +1,9 @@
 extern crate notify;
 
+pub mod config;
 pub mod error;
 pub mod fs;
+pub mod parse;
+pub mod render;
 pub mod tree;
+pub mod watch;
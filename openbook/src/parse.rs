@@ -0,0 +1,1821 @@
+//! This module builds the OpenBook document tree from the markdown files of a book.
+//!
+//! The structure of a book is described by its `SUMMARY.md` file, which contains a nested list of
+//! links to the content files of the sections:
+//!
+//! ```markdown
+//! # Summary
+//!
+//! * [Introduction](README.md)
+//!
+//! ## User Manual
+//!
+//! * [What is OpenBook](user/what.md)
+//! * [How to use OpenBook](user/usage.md)
+//!   * [How to build](user/build.md)
+//!
+//! * Developer Manual
+//! * [How to contribute](dev/contribute.md)
+//! ```
+//!
+//! Each link becomes a [`Section`], and links nested under another link become its subsections. A
+//! link with an empty target, like `[Coming soon]()`, becomes a draft section without a content
+//! file. A new [`Chapter`] is started by a top-level list entry without a link, or by a heading of
+//! level 2 or deeper. A level 1 heading at the beginning of the file is the title of the summary
+//! itself and is ignored, while later level 1 headings start a part that groups the following
+//! chapters, as in mdBook. A `---` line separates the chapter before it from the one after it. A
+//! top-level link to the book's `README.md` becomes the preface of the book. If `SUMMARY.md` does
+//! not link to it, the `README.md` still becomes the preface, named after its first heading.
+//!
+//! Sections are numbered like `1.2.3`, see the `numbering` module of the tree. As in mdBook, a link
+//! on its own line instead of in a list entry, like `[Foreword](foreword.md)`, is an unnumbered
+//! section; consecutive unnumbered top-level sections form an unnumbered chapter, which mdBook
+//! calls a prefix or suffix chapter.
+//!
+//! The inverse, `serialize_summary`, writes the structure of a [`Book`] back in the same format, so
+//! that trees restructured in memory can be saved again.
+//!
+//! A book without a `SUMMARY.md` is built from the layout of its root directory instead by
+//! `parse_directory`, where subdirectories become chapters and nested sections.
+//!
+//! A project that contains the same book in multiple languages has a `LANGS.md` file at its root,
+//! which lists the subdirectory of each language:
+//!
+//! ```markdown
+//! * [English](en/)
+//! * [中文](zh/)
+//! ```
+//!
+//! The `load_project` function loads the book in every listed language into a
+//! [`GlobalizedBooks`].
+//!
+//! [`Book`]: ../tree/struct.Book.html
+//! [`Section`]: ../tree/struct.Section.html
+//! [`Chapter`]: ../tree/struct.Chapter.html
+//! [`GlobalizedBooks`]: ../tree/struct.GlobalizedBooks.html
+//!
+
+use std::path::{Path, PathBuf};
+
+use crate::config::{load_config, ENV_PREFIX};
+use crate::error::{Error, ErrorKind, Result};
+use crate::fs::{relative_link, FileSystem};
+use crate::tree::markup::Markup;
+use crate::tree::{Book, BookConfig, Chapter, GlobalizedBooks, Glossary, GlossarySpan, Section};
+
+/// Path to the `SUMMARY.md` file, relative to the root directory of the book, used when the book
+/// configuration does not specify one.
+pub const DEFAULT_SUMMARY_PATH: &str = "SUMMARY.md";
+
+/// Path to the `README.md` file, relative to the root directory of the book, used when the book
+/// configuration does not specify one.
+pub const DEFAULT_README_PATH: &str = "README.md";
+
+/// Path to the `GLOSSARY.md` file, relative to the root directory of the book, used when the book
+/// configuration does not specify one.
+pub const DEFAULT_GLOSSARY_PATH: &str = "GLOSSARY.md";
+
+/// Path to the `LANGS.md` file, relative to the root directory of the project, used when the
+/// project configuration does not specify one.
+pub const DEFAULT_LANGUAGES_PATH: &str = "LANGS.md";
+
+/// A list entry in the `SUMMARY.md` file.
+struct SummaryEntry {
+    indent: usize,
+    name: String,
+    link: Option<String>,
+
+    /// Whether the entry is a list entry, rather than a link on its own line.
+    numbered: bool,
+}
+
+/// A line in the `SUMMARY.md` file that contributes to the structure of the book.
+enum SummaryLine {
+    /// A level 1 heading, which is the title of the summary or starts a new part.
+    Part(String),
+
+    /// A heading that starts a new chapter.
+    Chapter(String),
+
+    /// A `---` line that separates chapters.
+    Separator,
+
+    /// A list entry.
+    Entry(SummaryEntry),
+}
+
+/// Build a [`Book`] from the `SUMMARY.md` file of the book with the specified configuration.
+///
+/// The path to `SUMMARY.md` is taken from `config.structure.summary` and defaults to
+/// [`DEFAULT_SUMMARY_PATH`]; a relative path is resolved against `config.root`. Link targets are
+/// resolved against the directory that contains `SUMMARY.md` and stored in `Section::file` relative
+/// to `config.root`. The content of every section is read from its file; a section that starts at
+/// an anchor receives the whole content of the file. The frontmatter of the content, if any, is
+/// moved into `Section::metadata`, and content that is not written in markdown is converted into
+/// markdown according to `Section::markup`. If `config.title_from_content` is set, sections without
+/// a name in `SUMMARY.md`, or every section if `config.force_content_title` is set as well, are
+/// named after the first level 1 heading of their content, which is removed from the content by
+/// `Section::take_title_heading`; entries like `[](intro.md)` are only allowed in that case, and
+/// are named after their file if the content has no such heading. The preface of the book is its
+/// `README.md`, whose path is taken from `config.structure.readme` and defaults to
+/// [`DEFAULT_README_PATH`]; it is empty if the file does not exist. The glossary of the book is
+/// parsed by [`parse_glossary`] into `Book::glossary`, and is empty if the book does not have a
+/// glossary file. Finally, the sections are numbered by `Book::number_sections`.
+///
+/// Returns an error if `SUMMARY.md` or any linked file cannot be read, or an error of kind
+/// `ErrorKind::Parse` naming the offending line if `SUMMARY.md` or the glossary file contains a
+/// malformed entry.
+///
+/// [`Book`]: ../tree/struct.Book.html
+/// [`DEFAULT_SUMMARY_PATH`]: constant.DEFAULT_SUMMARY_PATH.html
+/// [`DEFAULT_README_PATH`]: constant.DEFAULT_README_PATH.html
+/// [`parse_glossary`]: fn.parse_glossary.html
+pub fn parse_summary<F: FileSystem>(fs: &F, config: &BookConfig) -> Result<Book> {
+    let summary_path = config
+        .structure
+        .summary
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_SUMMARY_PATH));
+    let summary_file = config.root.join(&summary_path);
+    if !fs.has_file(&summary_file) {
+        return Err(Error::not_found(format!(
+            "summary file {} does not exist",
+            summary_file.display()
+        )));
+    }
+    let summary = fs.read_file_as_string(&summary_file)?;
+
+    let base = summary_path.parent().unwrap_or_else(|| Path::new(""));
+    let readme = config
+        .structure
+        .readme
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_README_PATH));
+
+    let mut builder = TreeBuilder::default();
+    for (index, line) in summary.lines().enumerate() {
+        let line_number = index + 1;
+        let parsed = parse_summary_line(line).map_err(|msg| {
+            Error::parse(format!(
+                "{}:{}: {}",
+                summary_file.display(),
+                line_number,
+                msg
+            ))
+        })?;
+        match parsed {
+            // A level 1 heading before anything else is the title of the summary.
+            Some(SummaryLine::Part(_)) if builder.is_empty() && !builder.titled => {
+                builder.titled = true
+            }
+            Some(SummaryLine::Part(name)) => builder.start_part(name),
+            Some(SummaryLine::Chapter(name)) => builder.start_chapter(name),
+            Some(SummaryLine::Separator) => builder.separate(),
+            Some(SummaryLine::Entry(entry)) => {
+                let section = match &entry.link {
+                    Some(link) if link.is_empty() => Section {
+                        name: entry.name,
+                        ..Section::default()
+                    },
+                    Some(_) if entry.name.is_empty() && !config.effective_title_from_content() => {
+                        return Err(Error::parse(format!(
+                            "{}:{}: list entry has no name",
+                            summary_file.display(),
+                            line_number
+                        )));
+                    }
+                    Some(link) => create_section(fs, config, base, &entry.name, link)?,
+                    None => {
+                        if builder.in_list(entry.indent) {
+                            return Err(Error::parse(format!(
+                                "{}:{}: nested entry \"{}\" does not link to a file",
+                                summary_file.display(),
+                                line_number,
+                                entry.name
+                            )));
+                        }
+                        builder.start_chapter(entry.name);
+                        continue;
+                    }
+                };
+
+                if builder.in_list(entry.indent) {
+                    builder.push_section(entry.indent, section);
+                } else if builder.preface.is_none() && section.file == readme {
+                    builder.preface = Some(section);
+                } else {
+                    builder.set_unnumbered(!entry.numbered);
+                    builder.push_section(entry.indent, section);
+                }
+            }
+            None => {}
+        }
+    }
+
+    let mut preface = builder.preface.take();
+    let chapters = builder.finish();
+    if preface.is_none() && fs.has_file(config.root.join(&readme)) {
+        let mut section = Section {
+            content: fs.read_file_as_string(config.root.join(&readme))?,
+            file: readme,
+            ..Section::default()
+        };
+        section.extract_frontmatter_with(config.effective_frontmatter_format())?;
+        section.convert_markup();
+        if let Some(heading) = section.headings().into_iter().next() {
+            section.name = heading.text;
+        }
+        apply_content_title(config, &mut section);
+        preface = Some(section);
+    }
+
+    let glossary = match parse_glossary(fs, config) {
+        Ok(glossary) => glossary,
+        Err(e) if e.kind() == ErrorKind::NotFound => Glossary::default(),
+        Err(e) => return Err(e),
+    };
+
+    let mut book = Book {
+        config: config.clone(),
+        preface: preface.unwrap_or_default(),
+        chapters,
+        glossary,
+    };
+    book.number_sections();
+    Ok(book)
+}
+
+/// Serialize the structure of the specified book into a canonical `SUMMARY.md`, which
+/// [`parse_summary`] parses back into the same structure.
+///
+/// The summary starts with a `# Summary` title, followed by a top-level entry that links to the
+/// preface if it has a content file and a name. Every chapter is introduced by a `## name`
+/// heading, preceded by a `---` separator if it is separated from the previous chapter and by a
+/// `# part` heading if its part differs from that of the previous chapter. Sections become
+/// `* [name](link)` entries indented by two spaces per level of nesting, where drafts have an empty
+/// link and sections that start at an anchor link to `file#anchor`. The top-level sections of
+/// unnumbered chapters are written as links on their own lines instead. Links are relative to the
+/// directory that contains `SUMMARY.md`, whose path is taken from `config.structure.summary` and
+/// defaults to [`DEFAULT_SUMMARY_PATH`].
+///
+/// Some structures cannot be expressed in `SUMMARY.md`: chapters without a part that follow a
+/// chapter in a part are written in the preceding part, and chapters without a name are merged
+/// into the previous chapter unless they start a part or are separated from it. Sections without a
+/// name are named after their content file.
+///
+/// [`parse_summary`]: fn.parse_summary.html
+/// [`DEFAULT_SUMMARY_PATH`]: constant.DEFAULT_SUMMARY_PATH.html
+pub fn serialize_summary(book: &Book) -> String {
+    let summary_path = book
+        .config
+        .structure
+        .summary
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_SUMMARY_PATH));
+    let base = summary_path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut output = String::from("# Summary\n");
+    if book.preface.has_file() && !book.preface.name.is_empty() {
+        output.push('\n');
+        serialize_summary_entry(&book.preface, base, 0, true, &mut output);
+    }
+
+    let mut part = None;
+    for chapter in &book.chapters {
+        if chapter.separated {
+            output.push_str("\n---\n");
+        }
+        if let Some(name) = &chapter.part {
+            if part != Some(name) {
+                part = Some(name);
+                output.push_str(&format!("\n# {}\n", name));
+            }
+        }
+        if !chapter.name.is_empty() {
+            output.push_str(&format!("\n## {}\n", chapter.name));
+        }
+        if !chapter.sections.is_empty() {
+            output.push('\n');
+            for s in &chapter.sections {
+                serialize_summary_entry(s, base, 0, !chapter.unnumbered, &mut output);
+            }
+        }
+    }
+    output
+}
+
+/// Write the `SUMMARY.md` of the specified book, as serialized by [`serialize_summary`], to the
+/// summary file in its configuration, replacing the file if it already exists.
+///
+/// [`serialize_summary`]: fn.serialize_summary.html
+pub fn write_summary<F: FileSystem>(fs: &F, book: &Book) -> Result<()> {
+    let summary_file = book
+        .config
+        .resolve_structure_path(&book.config.structure.summary, DEFAULT_SUMMARY_PATH);
+    fs.write_file(summary_file, serialize_summary(book).as_bytes())
+}
+
+/// Serialize the `SUMMARY.md` entry of the specified section and its subsections at the specified
+/// nesting depth, with links relative to `base`.
+///
+/// A top-level section that is not `numbered` is written as a link on its own line.
+fn serialize_summary_entry(
+    section: &Section,
+    base: &Path,
+    depth: usize,
+    numbered: bool,
+    output: &mut String,
+) {
+    // An explicit stack keeps deeply nested sections from overflowing the call stack.
+    let mut stack = vec![(section, depth)];
+    while let Some((section, depth)) = stack.pop() {
+        let name = if section.name.is_empty() {
+            name_from_path(&section.file)
+        } else {
+            section.name.clone()
+        };
+        let mut link = if section.has_file() {
+            relative_link(base, &section.file)
+        } else {
+            String::new()
+        };
+        if let Some(anchor) = &section.anchor {
+            link.push('#');
+            link.push_str(anchor);
+        }
+
+        let marker = if numbered || depth > 0 { "* " } else { "" };
+        output.push_str(&format!(
+            "{}{}[{}]({})\n",
+            "  ".repeat(depth),
+            marker,
+            name,
+            link
+        ));
+        stack.extend(section.subsections.iter().rev().map(|s| (s, depth + 1)));
+    }
+}
+
+/// Parse the `LANGS.md` file of the project with the specified configuration.
+///
+/// The path to `LANGS.md` is taken from `config.structure.languages` and defaults to
+/// [`DEFAULT_LANGUAGES_PATH`]; a relative path is resolved against `config.root`. Every list entry
+/// of the file must be a link whose text is the display name of a language and whose target is the
+/// subdirectory that contains the book in that language. Returns the display name and the
+/// subdirectory, relative to `config.root`, of each entry in the order they are listed.
+///
+/// Returns an error of kind `ErrorKind::NotFound` if `LANGS.md` does not exist, or an error of kind
+/// `ErrorKind::Parse` naming the offending line if it contains a malformed entry.
+///
+/// [`DEFAULT_LANGUAGES_PATH`]: constant.DEFAULT_LANGUAGES_PATH.html
+pub fn parse_languages<F: FileSystem>(
+    fs: &F,
+    config: &BookConfig,
+) -> Result<Vec<(String, PathBuf)>> {
+    let languages_path = config
+        .structure
+        .languages
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_LANGUAGES_PATH));
+    let languages_file = config.root.join(&languages_path);
+    if !fs.has_file(&languages_file) {
+        return Err(Error::not_found(format!(
+            "languages file {} does not exist",
+            languages_file.display()
+        )));
+    }
+    let content = fs.read_file_as_string(&languages_file)?;
+
+    let base = languages_path.parent().unwrap_or_else(|| Path::new(""));
+    let mut languages = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |msg: String| {
+            Error::parse(format!(
+                "{}:{}: {}",
+                languages_file.display(),
+                line_number,
+                msg
+            ))
+        };
+        match parse_summary_line(line).map_err(error)? {
+            Some(SummaryLine::Entry(SummaryEntry {
+                name,
+                link: Some(link),
+                ..
+            })) if !link.is_empty() => {
+                languages.push((name, base.join(link.trim_end_matches('/'))))
+            }
+            Some(SummaryLine::Entry(entry)) => {
+                return Err(error(format!(
+                    "language \"{}\" does not link to a directory",
+                    entry.name
+                )))
+            }
+            Some(SummaryLine::Part(_))
+            | Some(SummaryLine::Chapter(_))
+            | Some(SummaryLine::Separator)
+            | None => {}
+        }
+    }
+
+    Ok(languages)
+}
+
+/// Parse the `GLOSSARY.md` file of the book with the specified configuration.
+///
+/// The path to `GLOSSARY.md` is taken from `config.structure.glossary` and defaults to
+/// [`DEFAULT_GLOSSARY_PATH`]; a relative path is resolved against `config.root`. The file is a
+/// markdown definition list, where each term is on a line of its own and is followed by its
+/// definition on lines that start with `:`:
+///
+/// ```markdown
+/// OpenBook
+/// : An open-source alternative to gitbook.
+///
+/// Section
+/// : A page of a book.
+/// ```
+///
+/// Indented lines continue the definition above them, and multiple definitions of the same term
+/// are joined with a line break. Headings are ignored.
+///
+/// Returns an error of kind `ErrorKind::NotFound` if `GLOSSARY.md` does not exist, or an error of
+/// kind `ErrorKind::Parse` naming the offending line if a definition does not follow a term or a
+/// term does not have a definition.
+///
+/// [`DEFAULT_GLOSSARY_PATH`]: constant.DEFAULT_GLOSSARY_PATH.html
+pub fn parse_glossary<F: FileSystem>(fs: &F, config: &BookConfig) -> Result<Glossary> {
+    let glossary_file =
+        config.resolve_structure_path(&config.structure.glossary, DEFAULT_GLOSSARY_PATH);
+    if !fs.has_file(&glossary_file) {
+        return Err(Error::not_found(format!(
+            "glossary file {} does not exist",
+            glossary_file.display()
+        )));
+    }
+    let content = fs.read_file_as_string(&glossary_file)?;
+
+    let mut glossary = Glossary::default();
+    // The term being defined, with the line it is on and its definition so far.
+    let mut term: Option<(usize, String, String)> = None;
+    // The last line of the definition of the term being defined.
+    let mut end_line = 0;
+    let mut finish = |term: Option<(usize, String, String)>, end_line: usize| match term {
+        Some((line_number, term, definition)) if definition.is_empty() => {
+            Err(Error::parse(format!(
+                "{}:{}: term \"{}\" does not have a definition",
+                glossary_file.display(),
+                line_number,
+                term
+            )))
+        }
+        Some((start_line, term, definition)) => {
+            glossary.spans.insert(
+                term.clone(),
+                GlossarySpan {
+                    start_line,
+                    end_line,
+                },
+            );
+            glossary.entries.insert(term, definition);
+            Ok(())
+        }
+        None => Ok(()),
+    };
+
+    for (index, line) in content.lines().enumerate() {
+        let line_number = index + 1;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if let Some(definition) = trimmed.strip_prefix(':') {
+            match &mut term {
+                Some((_, _, existing)) => {
+                    if !existing.is_empty() {
+                        existing.push('\n');
+                    }
+                    existing.push_str(definition.trim());
+                    end_line = line_number;
+                }
+                None => {
+                    return Err(Error::parse(format!(
+                        "{}:{}: definition does not follow a term",
+                        glossary_file.display(),
+                        line_number
+                    )))
+                }
+            }
+        } else if line.starts_with(char::is_whitespace) {
+            match &mut term {
+                Some((_, _, existing)) if !existing.is_empty() => {
+                    existing.push(' ');
+                    existing.push_str(trimmed);
+                    end_line = line_number;
+                }
+                _ => {
+                    return Err(Error::parse(format!(
+                        "{}:{}: definition does not follow a term",
+                        glossary_file.display(),
+                        line_number
+                    )))
+                }
+            }
+        } else {
+            finish(term.take(), end_line)?;
+            term = Some((line_number, trimmed.to_owned(), String::new()));
+        }
+    }
+    finish(term, end_line)?;
+
+    Ok(glossary)
+}
+
+/// Build the document tree of the book with the specified configuration from the layout of its
+/// root directory, for books that do not have a `SUMMARY.md`.
+///
+/// The directory is scanned through `FileSystem::read_directory`, and its entries are visited in
+/// the order of their names, skipping entries whose names start with `.` or `_`. Content files
+/// are the files in a markup that `Markup::from_path` recognizes. The `README.md` of the book
+/// becomes the preface, every other content file becomes a chapter with a single section, and
+/// every subdirectory that contains content files becomes a chapter. Within a chapter directory,
+/// the `README.md` comes first and every content file becomes a section, while every
+/// subdirectory becomes a section whose subsections are its own content; such a section starts at
+/// the `README.md` of the subdirectory and has no file if there is none. The `SUMMARY.md`,
+/// glossary and `LANGS.md` files of the book are not part of the tree.
+///
+/// Sections are named after the first heading of their content, or after the file name with `-`
+/// and `_` replaced by spaces if there is no heading. Chapters and sections of subdirectories are
+/// named after the `README.md` of the directory in the same way, or after the directory name.
+/// If `config.title_from_content` is set, the first level 1 heading of every content file is
+/// removed from the content, and the section is named after it.
+/// The glossary of the book is parsed and the sections are numbered as in [`parse_summary`].
+///
+/// Returns an error if a directory or a content file cannot be read.
+///
+/// [`parse_summary`]: fn.parse_summary.html
+pub fn parse_directory<F: FileSystem>(fs: &F, config: &BookConfig) -> Result<Book> {
+    let scanner = DirectoryScanner::new(fs, config);
+    let mut preface = None;
+    let mut chapters = Vec::new();
+    for (file, is_dir) in scanner.read_entries(Path::new(""))? {
+        if is_dir {
+            let sections = scanner.scan(&file)?;
+            if sections.is_empty() {
+                continue;
+            }
+            let name = if scanner.starts_with_readme(&file, &sections) {
+                sections[0].name.clone()
+            } else {
+                name_from_path(&file)
+            };
+            chapters.push(Chapter {
+                name,
+                sections,
+                ..Chapter::default()
+            });
+        } else if scanner.is_readme(&file) {
+            preface = Some(scanner.load_section(file)?);
+        } else {
+            let section = scanner.load_section(file)?;
+            chapters.push(Chapter {
+                name: section.name.clone(),
+                sections: vec![section],
+                ..Chapter::default()
+            });
+        }
+    }
+
+    let glossary = match parse_glossary(fs, config) {
+        Ok(glossary) => glossary,
+        Err(e) if e.kind() == ErrorKind::NotFound => Glossary::default(),
+        Err(e) => return Err(e),
+    };
+
+    let mut book = Book {
+        config: config.clone(),
+        preface: preface.unwrap_or_default(),
+        chapters,
+        glossary,
+    };
+    book.number_sections();
+    Ok(book)
+}
+
+/// Build the document tree of the book with the specified configuration from its `SUMMARY.md` by
+/// [`parse_summary`], or from the layout of its root directory by [`parse_directory`] if it does
+/// not have one.
+///
+/// [`parse_summary`]: fn.parse_summary.html
+/// [`parse_directory`]: fn.parse_directory.html
+pub fn parse_book<F: FileSystem>(fs: &F, config: &BookConfig) -> Result<Book> {
+    let summary_file =
+        config.resolve_structure_path(&config.structure.summary, DEFAULT_SUMMARY_PATH);
+    if fs.has_file(&summary_file) {
+        parse_summary(fs, config)
+    } else {
+        parse_directory(fs, config)
+    }
+}
+
+/// Load the whole project whose root directory is at the specified path.
+///
+/// The project configuration is loaded from the `book.toml` under `root`. If the project has a
+/// `LANGS.md` file, the book in each listed language is loaded from its subdirectory of the root
+/// directory of the project configuration and keyed by the language's display name; a `book.toml`
+/// in the subdirectory is layered over the project configuration by `BookConfig::merge_over`,
+/// and the language code defaults to the name of the subdirectory. Otherwise, the project
+/// contains a single book that is not associated with any language. The `OPENBOOK_*` environment
+/// variables override the configuration of the project and of every book, see
+/// `BookConfig::apply_env_overrides`.
+pub fn load_project<F: FileSystem>(fs: &F, root: &Path) -> Result<GlobalizedBooks> {
+    let env: Vec<(String, String)> = std::env::vars()
+        .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+        .collect();
+    let mut config = load_config(fs, root)?;
+    config.apply_env_overrides(env.clone())?;
+
+    let languages = match parse_languages(fs, &config) {
+        Ok(languages) => languages,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            let book = parse_book(fs, &config)?;
+            return Ok(GlobalizedBooks {
+                config,
+                books: vec![(String::new(), book)],
+            });
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut books = Vec::with_capacity(languages.len());
+    for (name, dir) in languages {
+        let mut local = load_config(fs, &config.root.join(&dir))?;
+        if local.language.is_none() {
+            local.language = dir
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned());
+        }
+        let mut merged = local.merge_over(&config);
+        merged.apply_env_overrides(env.clone())?;
+        books.push((name, parse_book(fs, &merged)?));
+    }
+
+    Ok(GlobalizedBooks { config, books })
+}
+
+/// Read the content of the specified sections and all of their subsections from their files in
+/// parallel.
+///
+/// `root` is the root directory of the book, which `Section::file` is relative to. Sections
+/// without a file are left untouched, and the sections themselves are never reordered. If some
+/// files cannot be read, the error of the first such section in depth-first pre-order is returned
+/// regardless of the order in which the files are read; the content of the other sections is
+/// loaded nevertheless. The frontmatter of the content in the specified format, if any, replaces
+/// `Section::metadata`.
+#[cfg(feature = "rayon")]
+pub fn load_sections_parallel<F: FileSystem>(
+    fs: &F,
+    root: &Path,
+    sections: &mut [Section],
+    format: crate::tree::frontmatter::FrontmatterFormat,
+) -> Result<()> {
+    use rayon::prelude::*;
+
+    use crate::tree::frontmatter::split_frontmatter;
+    use crate::tree::markup::convert_content;
+
+    let mut all = Vec::new();
+    let mut stack: Vec<&mut Section> = sections.iter_mut().rev().collect();
+    while let Some(s) = stack.pop() {
+        let Section {
+            file,
+            content,
+            metadata,
+            subsections,
+            ..
+        } = s;
+        if !file.as_os_str().is_empty() {
+            all.push((&*file, content, metadata));
+        }
+        stack.extend(subsections.iter_mut().rev());
+    }
+
+    let results: Vec<Result<()>> = all
+        .into_par_iter()
+        .map(|(file, content, metadata)| {
+            let raw = fs.read_file_as_string(root.join(file))?;
+            let (frontmatter, body) = split_frontmatter(file, &raw, format)?;
+            let body = body.unwrap_or(raw);
+            *metadata = frontmatter;
+            *content = convert_content(file, &body, metadata).unwrap_or(body);
+            Ok(())
+        })
+        .collect();
+    results.into_iter().collect()
+}
+
+/// Parse a single line of `SUMMARY.md`.
+///
+/// Returns `Ok(None)` for lines that do not contribute to the structure of the book, and the
+/// description of the problem if the line is malformed.
+fn parse_summary_line(line: &str) -> std::result::Result<Option<SummaryLine>, String> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+
+    if trimmed.len() >= 3 && trimmed.chars().all(|c| c == '-') {
+        return Ok(Some(SummaryLine::Separator));
+    }
+
+    if trimmed.starts_with('#') {
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        let name = trimmed[level..].trim();
+        if level == 1 {
+            return Ok(if name.is_empty() {
+                None
+            } else {
+                Some(SummaryLine::Part(name.to_owned()))
+            });
+        }
+        if name.is_empty() {
+            return Err("heading has no title".to_owned());
+        }
+        return Ok(Some(SummaryLine::Chapter(name.to_owned())));
+    }
+
+    let indent = line
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum();
+    let (item, numbered) = match trimmed
+        .strip_prefix("* ")
+        .or_else(|| trimmed.strip_prefix("- "))
+        .or_else(|| trimmed.strip_prefix("+ "))
+    {
+        Some(item) => (item.trim(), true),
+        // A link on its own line at the top level denotes an unnumbered section.
+        None if indent == 0 && trimmed.starts_with('[') => (trimmed, false),
+        None => return Err(format!("expected a list entry, found \"{}\"", trimmed)),
+    };
+
+    let (name, link) = if item.starts_with('[') {
+        let name_end = item
+            .find("](")
+            .ok_or_else(|| format!("malformed link \"{}\"", item))?;
+        if !item.ends_with(')') {
+            return Err(format!("malformed link \"{}\"", item));
+        }
+        // An empty link target denotes a draft section.
+        let link = item[name_end + 2..item.len() - 1].trim();
+        (&item[1..name_end], Some(link.to_owned()))
+    } else {
+        (item, None)
+    };
+    // Entries that link to a file may leave their name to the content, see `title_from_content`.
+    let linked = link.as_ref().is_some_and(|link| !link.is_empty());
+    if name.trim().is_empty() && !linked {
+        return Err("list entry has no name".to_owned());
+    }
+
+    Ok(Some(SummaryLine::Entry(SummaryEntry {
+        indent,
+        name: name.trim().to_owned(),
+        link,
+        numbered,
+    })))
+}
+
+/// Create the section that is linked from `SUMMARY.md` and load its content.
+fn create_section<F: FileSystem>(
+    fs: &F,
+    config: &BookConfig,
+    base: &Path,
+    name: &str,
+    link: &str,
+) -> Result<Section> {
+    let (target, anchor) = match link.find('#') {
+        Some(i) => (&link[..i], Some(link[i + 1..].to_owned())),
+        None => (link, None),
+    };
+    let file = base.join(target);
+    let content = fs.read_file_as_string(config.root.join(&file))?;
+
+    let mut section = Section {
+        file,
+        anchor,
+        name: name.to_owned(),
+        content,
+        ..Section::default()
+    };
+    section.extract_frontmatter_with(config.effective_frontmatter_format())?;
+    section.convert_markup();
+    if name.is_empty() || config.effective_force_content_title() {
+        apply_content_title(config, &mut section);
+    }
+    if section.name.is_empty() {
+        section.name = name_from_path(&section.file);
+    }
+    Ok(section)
+}
+
+/// Name the specified section after the first level 1 heading of its content and remove the
+/// heading, if the specified configuration sets `title_from_content`.
+fn apply_content_title(config: &BookConfig, section: &mut Section) {
+    if !config.effective_title_from_content() {
+        return;
+    }
+    if let Some(title) = section.take_title_heading() {
+        section.name = title;
+    }
+}
+
+/// Scans the root directory of a book for [`parse_directory`].
+///
+/// [`parse_directory`]: fn.parse_directory.html
+struct DirectoryScanner<'a, F: FileSystem> {
+    fs: &'a F,
+    config: &'a BookConfig,
+    readme: PathBuf,
+
+    /// Files under the root directory that are not content files.
+    excluded: Vec<PathBuf>,
+}
+
+impl<'a, F: FileSystem> DirectoryScanner<'a, F> {
+    fn new(fs: &'a F, config: &'a BookConfig) -> Self {
+        let structure = &config.structure;
+        let path = |path: &Option<PathBuf>, default: &str| {
+            path.clone().unwrap_or_else(|| PathBuf::from(default))
+        };
+        Self {
+            fs,
+            config,
+            readme: path(&structure.readme, DEFAULT_README_PATH),
+            excluded: vec![
+                path(&structure.summary, DEFAULT_SUMMARY_PATH),
+                path(&structure.glossary, DEFAULT_GLOSSARY_PATH),
+                path(&structure.languages, DEFAULT_LANGUAGES_PATH),
+            ],
+        }
+    }
+
+    /// Determine whether the specified file is the `README.md` of its directory.
+    fn is_readme(&self, file: &Path) -> bool {
+        file.file_name() == self.readme.file_name()
+    }
+
+    /// Determine whether the specified sections of the content of the specified directory start
+    /// with the `README.md` of the directory.
+    fn starts_with_readme(&self, dir: &Path, sections: &[Section]) -> bool {
+        match sections.first() {
+            Some(first) => first.file.parent() == Some(dir) && self.is_readme(&first.file),
+            None => false,
+        }
+    }
+
+    /// Get the content files and the subdirectories of the specified directory, relative to the
+    /// root directory of the book, sorted by their names with the `README.md` first. The second
+    /// field of an element tells whether it is a directory.
+    fn read_entries(&self, dir: &Path) -> Result<Vec<(PathBuf, bool)>> {
+        let mut entries = Vec::new();
+        for path in self.fs.read_directory(self.config.root.join(dir))? {
+            let path = path?;
+            let name = match path.file_name() {
+                Some(name) => name.to_string_lossy().into_owned(),
+                None => continue,
+            };
+            if name.starts_with(&['.', '_'][..]) {
+                continue;
+            }
+
+            let file = dir.join(&name);
+            if self.fs.has_dir(&path) {
+                entries.push((file, true));
+            } else if Markup::from_path(&file).is_some() && !self.excluded.contains(&file) {
+                entries.push((file, false));
+            }
+        }
+
+        entries.sort_by(|(a, _), (b, _)| {
+            (!self.is_readme(a), a.file_name()).cmp(&(!self.is_readme(b), b.file_name()))
+        });
+        Ok(entries)
+    }
+
+    /// Get the sections of the content of the specified directory, with the `README.md` of the
+    /// directory first.
+    fn scan(&self, dir: &Path) -> Result<Vec<Section>> {
+        let mut sections = Vec::new();
+        for (file, is_dir) in self.read_entries(dir)? {
+            if !is_dir {
+                sections.push(self.load_section(file)?);
+                continue;
+            }
+
+            let mut subsections = self.scan(&file)?;
+            if subsections.is_empty() {
+                continue;
+            }
+            let mut section = if self.starts_with_readme(&file, &subsections) {
+                subsections.remove(0)
+            } else {
+                Section {
+                    name: name_from_path(&file),
+                    ..Section::default()
+                }
+            };
+            section.subsections = subsections;
+            sections.push(section);
+        }
+        Ok(sections)
+    }
+
+    /// Load the section of the specified content file, named after its first heading or its file
+    /// name.
+    fn load_section(&self, file: PathBuf) -> Result<Section> {
+        let mut section = Section {
+            content: self.fs.read_file_as_string(self.config.root.join(&file))?,
+            name: name_from_path(&file),
+            file,
+            ..Section::default()
+        };
+        section.extract_frontmatter_with(self.config.effective_frontmatter_format())?;
+        section.convert_markup();
+        if let Some(heading) = section.headings().into_iter().next() {
+            section.name = heading.text;
+        }
+        apply_content_title(self.config, &mut section);
+        Ok(section)
+    }
+}
+
+/// Derive a name from the file name of the specified path, without its extension and with `-` and
+/// `_` replaced by spaces.
+fn name_from_path(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().replace(&['-', '_'][..], " "))
+        .unwrap_or_default()
+}
+
+/// Assembles chapters and nested sections from the list entries of `SUMMARY.md`.
+#[derive(Default)]
+struct TreeBuilder {
+    preface: Option<Section>,
+    chapters: Vec<Chapter>,
+    current: Option<Chapter>,
+
+    /// Sections whose subsections are still being collected, with the indentation of their list
+    /// entries.
+    open: Vec<(usize, Section)>,
+
+    /// The part that new chapters belong to.
+    part: Option<String>,
+
+    /// Whether a separator precedes the next chapter.
+    separated: bool,
+
+    /// Whether the title of the summary has been seen.
+    titled: bool,
+
+    /// Whether the top-level sections being added are unnumbered.
+    unnumbered: bool,
+}
+
+impl TreeBuilder {
+    /// Determine whether nothing has been added to this builder yet.
+    fn is_empty(&self) -> bool {
+        self.preface.is_none()
+            && self.chapters.is_empty()
+            && self.current.is_none()
+            && self.open.is_empty()
+            && self.part.is_none()
+            && !self.separated
+    }
+
+    /// Determine whether an entry with the specified indentation is nested under an open entry.
+    fn in_list(&self, indent: usize) -> bool {
+        self.open.iter().any(|(i, _)| *i < indent)
+    }
+
+    /// Finish the current chapter and start a new one with the specified name.
+    fn start_chapter(&mut self, name: String) {
+        self.finish_chapter();
+        self.current = Some(self.new_chapter(name));
+    }
+
+    /// Finish the current chapter and start a new part with the specified name, which the
+    /// following chapters belong to.
+    fn start_part(&mut self, name: String) {
+        self.finish_chapter();
+        self.part = Some(name);
+    }
+
+    /// Finish the current chapter and separate it from the next one.
+    fn separate(&mut self) {
+        self.finish_chapter();
+        self.separated = true;
+    }
+
+    /// Switch between numbered and unnumbered top-level sections for the next top-level section.
+    ///
+    /// The current chapter takes the setting if it does not have any section yet, and is finished
+    /// if it has sections with the other setting.
+    fn set_unnumbered(&mut self, unnumbered: bool) {
+        self.close_until(0);
+        if let Some(chapter) = &mut self.current {
+            if chapter.sections.is_empty() {
+                chapter.unnumbered = unnumbered;
+            } else if chapter.unnumbered != unnumbered {
+                self.finish_chapter();
+            }
+        }
+        self.unnumbered = unnumbered;
+    }
+
+    /// Close all open sections and finish the current chapter, if any.
+    fn finish_chapter(&mut self) {
+        self.close_until(0);
+        if let Some(chapter) = self.current.take() {
+            self.chapters.push(chapter);
+        }
+    }
+
+    /// Create a chapter with the specified name in the current part.
+    fn new_chapter(&mut self, name: String) -> Chapter {
+        Chapter {
+            name,
+            sections: Vec::new(),
+            part: self.part.clone(),
+            separated: std::mem::take(&mut self.separated),
+            unnumbered: self.unnumbered,
+        }
+    }
+
+    /// Add a section whose list entry has the specified indentation.
+    fn push_section(&mut self, indent: usize, section: Section) {
+        self.close_until(indent);
+        self.open.push((indent, section));
+    }
+
+    /// Close all open sections whose indentation is not less than `indent`, attaching each of them
+    /// to its parent.
+    fn close_until(&mut self, indent: usize) {
+        while let Some((i, _)) = self.open.last() {
+            if *i < indent {
+                break;
+            }
+            let (_, section) = self.open.pop().unwrap();
+            match self.open.last_mut() {
+                Some((_, parent)) => parent.subsections.push(section),
+                None => {
+                    if self.current.is_none() {
+                        self.current = Some(self.new_chapter(String::new()));
+                    }
+                    self.current.as_mut().unwrap().sections.push(section);
+                }
+            }
+        }
+    }
+
+    /// Close all open sections and chapters and get the chapters.
+    fn finish(mut self) -> Vec<Chapter> {
+        self.finish_chapter();
+        self.chapters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::scratch::ScratchFileSystem;
+    use crate::tree::builder::{BookBuilder, BookConfigBuilder, ChapterBuilder, SectionBuilder};
+    use crate::tree::visitor::tests::{chain_with, drop_book_iteratively, with_small_stack};
+
+    const ROOT: &str = "/openbook-test/parse";
+
+    fn project(summary: &str) -> ScratchFileSystem {
+        let fs = ScratchFileSystem::new();
+        let root = Path::new(ROOT);
+        fs.write_file(root.join("SUMMARY.md"), summary.as_bytes())
+            .unwrap();
+        fs.write_file(root.join("intro.md"), b"# Introduction\n\nHello.\n")
+            .unwrap();
+        fs.write_file(root.join("plain.md"), b"Plain text.\n")
+            .unwrap();
+        fs
+    }
+
+    fn names(book: &Book) -> Vec<&str> {
+        book.chapters
+            .iter()
+            .flat_map(|c| c.sections.iter().map(|s| s.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn summary_names_win_over_content_titles() {
+        let fs = project("* [Intro](intro.md)\n* [](plain.md)\n");
+        let config = BookConfigBuilder::new()
+            .set_root(ROOT)
+            .set_title_from_content(true)
+            .build();
+        let book = parse_summary(&fs, &config).unwrap();
+        assert_eq!(names(&book), vec!["Intro", "plain"]);
+        assert_eq!(
+            book.chapters[0].sections[0].content,
+            "# Introduction\n\nHello.\n"
+        );
+    }
+
+    #[test]
+    fn content_titles_are_taken_and_stripped() {
+        let forced = BookConfigBuilder::new()
+            .set_root(ROOT)
+            .set_title_from_content(true)
+            .set_force_content_title(true)
+            .build();
+        let fs = project("* [Intro](intro.md)\n");
+        let book = parse_summary(&fs, &forced).unwrap();
+        assert_eq!(names(&book), vec!["Introduction"]);
+        assert_eq!(book.chapters[0].sections[0].content, "Hello.\n");
+
+        let unforced = BookConfigBuilder::new()
+            .set_root(ROOT)
+            .set_title_from_content(true)
+            .build();
+        let book = parse_summary(&project(" * [](intro.md)\n"), &unforced).unwrap();
+        assert_eq!(names(&book), vec!["Introduction"]);
+        assert_eq!(book.chapters[0].sections[0].content, "Hello.\n");
+    }
+
+    #[test]
+    fn unnamed_entries_need_title_from_content() {
+        let config = BookConfigBuilder::new().set_root(ROOT).build();
+        let err = parse_summary(&project("* [](intro.md)\n"), &config).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Parse);
+        assert!(err
+            .to_string()
+            .contains("SUMMARY.md:1: list entry has no name"));
+    }
+
+    #[test]
+    fn directory_sections_strip_content_titles() {
+        let fs = ScratchFileSystem::new();
+        fs.write_file(Path::new(ROOT).join("guide.md"), b"# Guide\n\n## Setup\n")
+            .unwrap();
+        let config = BookConfigBuilder::new()
+            .set_root(ROOT)
+            .set_title_from_content(true)
+            .build();
+        let book = parse_directory(&fs, &config).unwrap();
+        let section = &book.chapters[0].sections[0];
+        assert_eq!(section.name, "Guide");
+        assert_eq!(section.content, "## Setup\n");
+    }
+
+    #[test]
+    fn directory_layouts_build_chapters_and_nested_sections() {
+        let fs = ScratchFileSystem::new();
+        let root = Path::new(ROOT);
+        for (file, content) in &[
+            ("README.md", "# Welcome\n"),
+            ("GLOSSARY.md", "Term\n: Definition\n"),
+            ("LANGS.md", ""),
+            ("getting_started.md", "No heading.\n"),
+            ("guide/README.md", "# The Guide\n"),
+            ("guide/b-setup.md", "# Setup\n"),
+            ("guide/a-intro.rst", "Intro\n=====\n"),
+            ("guide/advanced/tuning.md", "# Tuning\n"),
+            ("guide/notes.png", ""),
+            ("_drafts/next.md", "# Next\n"),
+            (".git/HEAD.md", "# Head\n"),
+            ("empty/image.png", ""),
+        ] {
+            fs.write_file(root.join(file), content.as_bytes()).unwrap();
+        }
+        let config = BookConfigBuilder::new().set_root(ROOT).build();
+        let book = parse_book(&fs, &config).unwrap();
+        assert_eq!(book, parse_directory(&fs, &config).unwrap());
+
+        assert_eq!(book.preface.name, "Welcome");
+        let chapters: Vec<&str> = book.chapters.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(chapters, vec!["getting started", "The Guide"]);
+
+        let guide = &book.chapters[1];
+        let sections: Vec<(&str, &Path)> = guide
+            .sections
+            .iter()
+            .map(|s| (s.name.as_str(), s.file.as_path()))
+            .collect();
+        assert_eq!(
+            sections,
+            vec![
+                ("The Guide", Path::new("guide/README.md")),
+                ("Intro", Path::new("guide/a-intro.rst")),
+                ("advanced", Path::new("")),
+                ("Setup", Path::new("guide/b-setup.md")),
+            ]
+        );
+        assert_eq!(guide.sections[1].content, "# Intro\n");
+        let tuning = &guide.sections[2].subsections[0];
+        assert_eq!(tuning.name, "Tuning");
+        assert!(tuning.number.is_some());
+    }
+
+    #[test]
+    fn summary_lists_build_chapters_and_nested_sections() {
+        let fs = ScratchFileSystem::new();
+        let root = Path::new(ROOT);
+        let summary = "# Summary\n\n\
+                       ## User Manual\n\n\
+                       * [What](user/what.md)\n\
+                       * [Usage](user/usage.md)\n  \
+                         * [Build](user/build.md)\n    \
+                           * [Flags](user/flags.md#flags)\n\n\
+                       * Developer Manual\n\
+                       * [Contribute](dev/contribute.md)\n";
+        fs.write_file(root.join("docs/SUMMARY.md"), summary.as_bytes())
+            .unwrap();
+        for file in &[
+            "user/what.md",
+            "user/usage.md",
+            "user/build.md",
+            "user/flags.md",
+        ] {
+            fs.write_file(root.join("docs").join(file), file.as_bytes())
+                .unwrap();
+        }
+        fs.write_file(root.join("docs/dev/contribute.md"), b"contribute")
+            .unwrap();
+        let config = BookConfigBuilder::new()
+            .set_root(ROOT)
+            .set_summary("docs/SUMMARY.md")
+            .build();
+
+        let book = parse_summary(&fs, &config).unwrap();
+        let chapters: Vec<&str> = book.chapters.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(chapters, vec!["User Manual", "Developer Manual"]);
+        assert_eq!(names(&book), vec!["What", "Usage", "Contribute"]);
+
+        let usage = &book.chapters[0].sections[1];
+        assert_eq!(usage.file, PathBuf::from("docs/user/usage.md"));
+        assert_eq!(usage.content, "user/usage.md");
+        let build = &usage.subsections[0];
+        assert_eq!(build.name, "Build");
+        let flags = &build.subsections[0];
+        assert_eq!(flags.file, PathBuf::from("docs/user/flags.md"));
+        assert_eq!(flags.anchor.as_deref(), Some("flags"));
+    }
+
+    #[test]
+    fn malformed_summary_entries_name_their_line() {
+        let config = BookConfigBuilder::new().set_root(ROOT).build();
+        let err =
+            parse_summary(&project("* [Intro](intro.md)\nnot an entry\n"), &config).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Parse);
+        assert!(
+            err.to_string()
+                .contains("SUMMARY.md:2: expected a list entry"),
+            "{}",
+            err
+        );
+
+        let err = parse_summary(&project("* [Intro](intro.md\n"), &config).unwrap_err();
+        assert!(
+            err.to_string().contains("SUMMARY.md:1: malformed link"),
+            "{}",
+            err
+        );
+
+        let err =
+            parse_summary(&project("* [Intro](intro.md)\n  * Nested\n"), &config).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("SUMMARY.md:2: nested entry \"Nested\""),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn parses_language_lists() {
+        let fs = ScratchFileSystem::new();
+        let root = Path::new(ROOT);
+        fs.write_file(
+            root.join("LANGS.md"),
+            "* [English](en/)\n* [中文](zh)\n".as_bytes(),
+        )
+        .unwrap();
+        let config = BookConfigBuilder::new().set_root(ROOT).build();
+        assert_eq!(
+            parse_languages(&fs, &config).unwrap(),
+            vec![
+                ("English".to_owned(), PathBuf::from("en")),
+                ("中文".to_owned(), PathBuf::from("zh")),
+            ]
+        );
+
+        fs.write_file(root.join("LANGS.md"), b"* [English](en/)\n* Deutsch\n")
+            .unwrap();
+        let err = parse_languages(&fs, &config).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Parse);
+        assert!(err.to_string().contains("LANGS.md:2"), "{}", err);
+    }
+
+    #[test]
+    fn projects_without_languages_have_a_default_book() {
+        let fs = project("* [Intro](intro.md)\n");
+        let books = load_project(&fs, Path::new(ROOT)).unwrap();
+        assert_eq!(books.books.len(), 1);
+        assert_eq!(books.books[0].0, "");
+        assert_eq!(names(&books.books[0].1), vec!["Intro"]);
+    }
+
+    #[test]
+    fn projects_with_languages_load_every_book() {
+        let fs = ScratchFileSystem::new();
+        let root = Path::new(ROOT);
+        fs.write_file(
+            root.join("LANGS.md"),
+            "* [English](en/)\n* [中文](zh/)\n".as_bytes(),
+        )
+        .unwrap();
+        fs.write_file(root.join("en/SUMMARY.md"), b"* [Intro](intro.md)\n")
+            .unwrap();
+        fs.write_file(root.join("en/intro.md"), b"Hello.\n")
+            .unwrap();
+        fs.write_file(
+            root.join("zh/SUMMARY.md"),
+            "* [介绍](intro.md)\n".as_bytes(),
+        )
+        .unwrap();
+        fs.write_file(root.join("zh/intro.md"), "你好。\n".as_bytes())
+            .unwrap();
+
+        let books = load_project(&fs, root).unwrap();
+        let languages: Vec<&str> = books.books.iter().map(|(l, _)| l.as_str()).collect();
+        assert_eq!(languages, vec!["English", "中文"]);
+        let (_, zh) = &books.books[1];
+        assert_eq!(names(zh), vec!["介绍"]);
+        assert_eq!(zh.config.root, root.join("zh"));
+        assert_eq!(zh.config.language.as_deref(), Some("zh"));
+        assert_eq!(zh.chapters[0].sections[0].content, "你好。\n");
+    }
+
+    #[test]
+    fn language_books_layer_their_configs_over_the_project_config() {
+        use crate::config::CONFIG_FILE_NAME;
+
+        let fs = ScratchFileSystem::new();
+        let root = Path::new(ROOT);
+        fs.write_file(
+            root.join(CONFIG_FILE_NAME),
+            b"title = \"Project\"\nauthor = \"Ana\"\n\n[structure]\nsummary = \"TOC.md\"\n",
+        )
+        .unwrap();
+        fs.write_file(
+            root.join("LANGS.md"),
+            b"* [English](en/)\n* [Francais](fr/)\n",
+        )
+        .unwrap();
+        fs.write_file(
+            root.join("fr").join(CONFIG_FILE_NAME),
+            b"title = \"Projet\"\n",
+        )
+        .unwrap();
+        for language in &["en", "fr"] {
+            fs.write_file(root.join(language).join("TOC.md"), b"* [Intro](intro.md)\n")
+                .unwrap();
+            fs.write_file(root.join(language).join("intro.md"), b"Hello.\n")
+                .unwrap();
+        }
+
+        let books = load_project(&fs, root).unwrap();
+        assert_eq!(books.config.title.as_deref(), Some("Project"));
+        let (_, en) = &books.books[0];
+        let (_, fr) = &books.books[1];
+        assert_eq!(en.config.title.as_deref(), Some("Project"));
+        assert_eq!(fr.config.title.as_deref(), Some("Projet"));
+        assert_eq!(fr.config.author.as_deref(), Some("Ana"));
+        assert_eq!(
+            fr.config
+                .resolve_structure_path(&fr.config.structure.summary, DEFAULT_SUMMARY_PATH),
+            root.join("fr/TOC.md")
+        );
+        assert_eq!(names(fr), vec!["Intro"]);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn loads_many_sections_in_parallel() {
+        use crate::tree::frontmatter::FrontmatterFormat;
+
+        let fs = ScratchFileSystem::new();
+        let root = Path::new(ROOT);
+        let mut sections: Vec<Section> = (0..200)
+            .map(|i| {
+                let file = PathBuf::from(format!("s{}.md", i));
+                fs.write_file(
+                    root.join(&file),
+                    format!("---\nindex: {}\n---\nSection {}\n", i, i).as_bytes(),
+                )
+                .unwrap();
+                Section {
+                    subsections: vec![Section::default()],
+                    file,
+                    ..Section::default()
+                }
+            })
+            .collect();
+
+        load_sections_parallel(&fs, root, &mut sections, FrontmatterFormat::Auto).unwrap();
+        for (i, section) in sections.iter().enumerate() {
+            assert_eq!(section.file, PathBuf::from(format!("s{}.md", i)));
+            assert_eq!(section.content, format!("Section {}\n", i));
+            assert_eq!(section.metadata["index"], i.to_string());
+            assert_eq!(section.subsections[0], Section::default());
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_loading_reports_the_first_error_in_tree_order() {
+        use crate::tree::frontmatter::FrontmatterFormat;
+
+        let fs = ScratchFileSystem::new();
+        let root = Path::new(ROOT);
+        fs.write_file(root.join("a.md"), b"a").unwrap();
+        fs.write_file(root.join("bad.md"), b"+++\ntitle = \n+++\n")
+            .unwrap();
+        let section = |file: &str, subsections| Section {
+            file: PathBuf::from(file),
+            subsections,
+            ..Section::default()
+        };
+
+        let mut sections = vec![
+            section("a.md", vec![section("bad.md", Vec::new())]),
+            section("missing.md", Vec::new()),
+        ];
+        let mut reversed = vec![
+            section("a.md", vec![section("missing.md", Vec::new())]),
+            section("bad.md", Vec::new()),
+        ];
+        for _ in 0..20 {
+            let err = load_sections_parallel(&fs, root, &mut sections, FrontmatterFormat::Auto)
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Parse, "{}", err);
+            let err = load_sections_parallel(&fs, root, &mut reversed, FrontmatterFormat::Auto)
+                .unwrap_err();
+            assert_ne!(err.kind(), ErrorKind::Parse, "{}", err);
+        }
+        assert_eq!(sections[0].content, "a");
+    }
+
+    #[test]
+    fn parses_glossary_definition_lists() {
+        let fs = ScratchFileSystem::new();
+        let root = Path::new(ROOT);
+        let glossary = "# Glossary\n\n\
+                        OpenBook\n\
+                        : An open-source alternative\n  \
+                          to gitbook.\n\n\
+                        Section\n\
+                        : A page of a book.\n\
+                        : A part of a chapter.\n";
+        fs.write_file(root.join("GLOSSARY.md"), glossary.as_bytes())
+            .unwrap();
+        let config = BookConfigBuilder::new().set_root(ROOT).build();
+
+        let glossary = parse_glossary(&fs, &config).unwrap();
+        assert_eq!(glossary.entries.len(), 2);
+        assert_eq!(
+            glossary.entries["OpenBook"],
+            "An open-source alternative to gitbook."
+        );
+        assert_eq!(
+            glossary.entries["Section"],
+            "A page of a book.\nA part of a chapter."
+        );
+
+        fs.write_file(root.join("GLOSSARY.md"), b": orphan\n")
+            .unwrap();
+        let err = parse_glossary(&fs, &config).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("GLOSSARY.md:1: definition does not follow a term"),
+            "{}",
+            err
+        );
+
+        fs.write_file(root.join("GLOSSARY.md"), b"Term\n\nOther\n: defined\n")
+            .unwrap();
+        let err = parse_glossary(&fs, &config).unwrap_err();
+        assert!(
+            err.to_string().contains("GLOSSARY.md:1: term \"Term\""),
+            "{}",
+            err
+        );
+
+        let empty = BookConfigBuilder::new().set_root("/elsewhere").build();
+        assert_eq!(
+            parse_glossary(&fs, &empty).unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn books_carry_their_glossaries_with_spans() {
+        let fs = project("* [Intro](intro.md)\n");
+        let glossary = "# Glossary\n\nBook\n: A book.\n  Continued.\n\nPage\n: A page.\n";
+        fs.write_file(Path::new(ROOT).join("TERMS.md"), glossary.as_bytes())
+            .unwrap();
+        let config = BookConfigBuilder::new()
+            .set_root(ROOT)
+            .set_glossary("TERMS.md")
+            .build();
+
+        let book = parse_summary(&fs, &config).unwrap();
+        let span = |term: &str| book.glossary.spans[term];
+        assert_eq!(book.glossary.entries["Book"], "A book. Continued.");
+        assert_eq!(
+            span("Book"),
+            GlossarySpan {
+                start_line: 3,
+                end_line: 5
+            }
+        );
+        assert_eq!(
+            span("Page"),
+            GlossarySpan {
+                start_line: 7,
+                end_line: 8
+            }
+        );
+
+        let book = parse_summary(&fs, &BookConfigBuilder::new().set_root(ROOT).build()).unwrap();
+        assert_eq!(book.glossary, Glossary::default());
+    }
+
+    #[test]
+    fn readme_becomes_the_preface() {
+        let config = BookConfigBuilder::new().set_root(ROOT).build();
+        let fs = project("* [Intro](intro.md)\n");
+        let book = parse_summary(&fs, &config).unwrap();
+        assert_eq!(book.preface, Section::default());
+
+        fs.write_file(Path::new(ROOT).join("README.md"), b"# Welcome\n\nHi.\n")
+            .unwrap();
+        let book = parse_summary(&fs, &config).unwrap();
+        assert_eq!(book.preface.name, "Welcome");
+        assert_eq!(book.preface.file, PathBuf::from("README.md"));
+        assert_eq!(book.preface.content, "# Welcome\n\nHi.\n");
+        assert_eq!(names(&book), vec!["Intro"]);
+
+        let fs = project("* [Start here](README.md)\n* [Intro](intro.md)\n");
+        fs.write_file(Path::new(ROOT).join("README.md"), b"# Welcome\n")
+            .unwrap();
+        let book = parse_summary(&fs, &config).unwrap();
+        assert_eq!(book.preface.name, "Start here");
+        assert_eq!(names(&book), vec!["Intro"]);
+    }
+
+    #[test]
+    fn the_configured_readme_becomes_the_preface() {
+        let fs = project("* [Intro](intro.md)\n");
+        fs.write_file(Path::new(ROOT).join("README.md"), b"# Ignored\n")
+            .unwrap();
+        fs.write_file(Path::new(ROOT).join("docs/INDEX.md"), b"# Index\n")
+            .unwrap();
+        let config = BookConfigBuilder::new()
+            .set_root(ROOT)
+            .set_readme("docs/INDEX.md")
+            .build();
+        let book = parse_summary(&fs, &config).unwrap();
+        assert_eq!(book.preface.name, "Index");
+        assert_eq!(book.preface.file, PathBuf::from("docs/INDEX.md"));
+    }
+
+    #[test]
+    fn entries_without_a_target_are_drafts() {
+        let config = BookConfigBuilder::new().set_root(ROOT).build();
+        let fs = project("* [Intro](intro.md)\n  * [Coming soon]()\n* [Later]( )\n");
+        let book = parse_summary(&fs, &config).unwrap();
+        assert_eq!(names(&book), vec!["Intro", "Later"]);
+
+        let intro = &book.chapters[0].sections[0];
+        assert!(intro.has_file());
+        let draft = &intro.subsections[0];
+        assert_eq!(draft.name, "Coming soon");
+        assert!(!draft.has_file());
+        assert_eq!(draft.content, "");
+        assert!(!book.chapters[0].sections[1].has_file());
+
+        let err = parse_summary(&project("* []()\n"), &config).unwrap_err();
+        assert!(
+            err.to_string().contains("list entry has no name"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn parts_and_separators_group_chapters() {
+        let summary = "# Summary\n\n\
+                       [Foreword](intro.md)\n\n\
+                       # Part One\n\n\
+                       ## Basics\n\
+                       * [A](plain.md)\n\n\
+                       ---\n\n\
+                       ## More\n\
+                       * [B](plain.md)\n\n\
+                       # Part Two\n\n\
+                       * [C](plain.md)\n";
+        let config = BookConfigBuilder::new().set_root(ROOT).build();
+        let book = parse_summary(&project(summary), &config).unwrap();
+
+        let chapters: Vec<(&str, Option<&str>, bool, bool)> = book
+            .chapters
+            .iter()
+            .map(|c| {
+                (
+                    c.name.as_str(),
+                    c.part.as_deref(),
+                    c.separated,
+                    c.unnumbered,
+                )
+            })
+            .collect();
+        assert_eq!(
+            chapters,
+            vec![
+                ("", None, false, true),
+                ("Basics", Some("Part One"), false, false),
+                ("More", Some("Part One"), true, false),
+                ("", Some("Part Two"), false, false),
+            ]
+        );
+        assert_eq!(names(&book), vec!["Foreword", "A", "B", "C"]);
+
+        let parts: Vec<(Option<&str>, usize)> = book
+            .parts()
+            .into_iter()
+            .map(|(p, c)| (p, c.len()))
+            .collect();
+        assert_eq!(
+            parts,
+            vec![(None, 1), (Some("Part One"), 2), (Some("Part Two"), 1)]
+        );
+    }
+
+    #[test]
+    fn sections_in_other_markups_are_converted_when_loaded() {
+        let config = BookConfigBuilder::new()
+            .set_root(ROOT)
+            .set_title_from_content(true)
+            .build();
+        let fs = project("* [](guide.rst)\n* [Notes](notes.txt)\n");
+        fs.write_file(
+            Path::new(ROOT).join("guide.rst"),
+            b"Guide\n=====\n\n* step\n",
+        )
+        .unwrap();
+        fs.write_file(Path::new(ROOT).join("notes.txt"), b"a\nb\n")
+            .unwrap();
+        let book = parse_summary(&fs, &config).unwrap();
+        assert_eq!(names(&book), vec!["Guide", "Notes"]);
+
+        let sections = &book.chapters[0].sections;
+        assert_eq!(sections[0].file, PathBuf::from("guide.rst"));
+        assert_eq!(sections[0].content, "* step\n");
+        assert_eq!(sections[1].content, "a\\\nb\n");
+    }
+
+    #[test]
+    fn serialized_summaries_parse_back_into_the_same_book() {
+        let summary = "# Summary\n\n\
+                       [Foreword](intro.md)\n\n\
+                       # Part One\n\n\
+                       ## Basics\n\
+                       * [A](plain.md)\n\
+                       \x20 * [A.1](guide/setup.md#install)\n\
+                       \x20   * [Soon]()\n\
+                       * [B](plain.md)\n\n\
+                       ---\n\n\
+                       ## More\n\
+                       * [C](guide/setup.md)\n\n\
+                       # Part Two\n\n\
+                       ## Appendix\n\
+                       * [D](plain.md)\n";
+        let fs = project(summary);
+        fs.write_file(
+            Path::new(ROOT).join("guide/setup.md"),
+            b"# Setup\n\n## Install\n",
+        )
+        .unwrap();
+        let config = BookConfigBuilder::new().set_root(ROOT).build();
+        let book = parse_summary(&fs, &config).unwrap();
+
+        let serialized = serialize_summary(&book);
+        assert!(serialized.starts_with("# Summary\n"), "{}", serialized);
+        assert!(serialized.contains("    * [Soon]()\n"), "{}", serialized);
+        fs.write_file(Path::new(ROOT).join("SUMMARY.md"), serialized.as_bytes())
+            .unwrap();
+        assert_eq!(parse_summary(&fs, &config).unwrap(), book);
+        assert_eq!(serialize_summary(&book), serialized);
+    }
+
+    #[test]
+    fn serializes_deeply_nested_sections() {
+        with_small_stack(serialize_deep_chain);
+    }
+
+    fn serialize_deep_chain() {
+        // The indentation of the summary grows quadratically with the depth.
+        const DEPTH: usize = 2_000;
+        let chain = chain_with(DEPTH, |d| {
+            SectionBuilder::new()
+                .set_name(d.to_string())
+                .set_file(format!("{}.md", d))
+                .build()
+        });
+        let book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("Deep")
+                    .add_section(chain)
+                    .build(),
+            )
+            .build();
+        let serialized = serialize_summary(&book);
+        drop_book_iteratively(book);
+
+        assert!(serialized.starts_with("# Summary\n\n## Deep\n\n* [0](0.md)\n  * [1](1.md)\n"));
+        let deepest = format!("{}* [{}]({}.md)\n", "  ".repeat(DEPTH), DEPTH, DEPTH);
+        assert!(serialized.ends_with(&deepest));
+        assert_eq!(serialized.matches("* [").count(), DEPTH + 1);
+    }
+
+    #[test]
+    fn serialized_links_are_relative_to_the_summary() {
+        let fs = ScratchFileSystem::new();
+        let root = Path::new(ROOT);
+        fs.write_file(root.join("src/SUMMARY.md"), b"* [Setup](guide/setup.md)\n")
+            .unwrap();
+        fs.write_file(root.join("src/guide/setup.md"), b"Setup.\n")
+            .unwrap();
+        let config = BookConfigBuilder::new()
+            .set_root(ROOT)
+            .set_summary("src/SUMMARY.md")
+            .build();
+        let book = parse_summary(&fs, &config).unwrap();
+        assert_eq!(
+            book.chapters[0].sections[0].file,
+            PathBuf::from("src/guide/setup.md")
+        );
+
+        let serialized = serialize_summary(&book);
+        assert!(
+            serialized.contains("* [Setup](guide/setup.md)\n"),
+            "{}",
+            serialized
+        );
+    }
+
+    #[test]
+    fn frontmatters_are_extracted_while_building_the_tree() {
+        let config = BookConfigBuilder::new().set_root(ROOT).build();
+        let fs = project("* [Intro](intro.md)\n* [Setup](setup.md)\n");
+        fs.write_file(
+            Path::new(ROOT).join("setup.md"),
+            b"---\ntitle: Setting up\ndescription: First steps\ntags: [guide]\ndraft: true\n---\n\nBody.\n",
+        )
+        .unwrap();
+        fs.write_file(
+            Path::new(ROOT).join("README.md"),
+            b"---\nslug: home\n---\n# Welcome\n",
+        )
+        .unwrap();
+        let book = parse_summary(&fs, &config).unwrap();
+
+        let setup = &book.chapters[0].sections[1];
+        assert_eq!(setup.name, "Setup");
+        assert_eq!(setup.title(), "Setting up");
+        assert_eq!(setup.description(), Some("First steps"));
+        assert_eq!(setup.tags(), vec!["guide"]);
+        assert!(setup.is_draft());
+        assert_eq!(setup.content, "Body.\n");
+        assert_eq!(book.preface.metadata["slug"], "home");
+        assert_eq!(book.preface.name, "Welcome");
+
+        fs.write_file(Path::new(ROOT).join("setup.md"), b"+++\ndraft = \n+++\n")
+            .unwrap();
+        let err = parse_summary(&fs, &config).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Parse);
+        assert!(err.to_string().contains("setup.md"), "{}", err);
+    }
+}
@@ -0,0 +1,113 @@
+//! This module copies the assets of a book, such as images, stylesheets and fonts, into the output
+//! directory.
+//!
+
+use std::path::Path;
+
+use crate::error::Result;
+use crate::fs::glob::Glob;
+use crate::fs::FileSystem;
+
+/// Copy all files under `src_root` to the same relative paths under `dst_root`, except for the
+/// files that match any of the `skip` glob patterns, and get the number of copied files.
+///
+/// A pattern without `/`, like `*.md`, is matched against the file name of every file, so it
+/// skips matching files at any depth. Other patterns, like `drafts/**`, are matched against the
+/// path of the file relative to `src_root`. Subdirectories are created in the destination as
+/// needed. Returns an error of kind `ErrorKind::Parse` if a pattern is malformed, or the first
+/// error that occurs while walking, reading or writing.
+pub fn copy_tree<F: FileSystem>(
+    fs: &F,
+    src_root: &Path,
+    dst_root: &Path,
+    skip: &[&str],
+) -> Result<usize> {
+    let skip = skip
+        .iter()
+        .map(|pattern| Ok((Glob::new(pattern)?, pattern.contains('/'))))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut copied = 0;
+    for file in fs.walk(src_root) {
+        let file = file?;
+        let relative = file.strip_prefix(src_root).unwrap_or(&file);
+        let skipped = skip
+            .iter()
+            .any(|(glob, whole_path)| match relative.file_name() {
+                Some(name) if !*whole_path => glob.matches(name),
+                _ => glob.matches(relative),
+            });
+        if skipped {
+            continue;
+        }
+
+        let target = dst_root.join(relative);
+        if let Some(parent) = target.parent() {
+            fs.create_dir_all(parent)?;
+        }
+        fs.write_file(&target, &fs.read_file_as_bytes(&file)?)?;
+        copied += 1;
+    }
+
+    Ok(copied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::fs::scratch::ScratchFileSystem;
+
+    const SRC: &str = "/openbook-test/assets/src";
+    const DST: &str = "/openbook-test/assets/site";
+
+    fn book() -> ScratchFileSystem {
+        let fs = ScratchFileSystem::new();
+        let src = Path::new(SRC);
+        fs.write_file(src.join("README.md"), b"# Book").unwrap();
+        fs.write_file(src.join("guide/intro.md"), b"# Intro")
+            .unwrap();
+        fs.write_file(src.join("guide/img/logo.png"), &[0x89, b'P', 0x00, 0xff])
+            .unwrap();
+        fs.write_file(src.join("theme/style.css"), b"body {}")
+            .unwrap();
+        fs.write_file(src.join("drafts/sketch.png"), b"").unwrap();
+        fs
+    }
+
+    #[test]
+    fn copies_assets_and_skips_markdown() {
+        let fs = book();
+        let (src, dst) = (Path::new(SRC), Path::new(DST));
+        assert_eq!(copy_tree(&fs, src, dst, &["*.md", "drafts/**"]).unwrap(), 2);
+
+        assert_eq!(
+            fs.read_file_as_bytes(dst.join("guide/img/logo.png"))
+                .unwrap(),
+            vec![0x89, b'P', 0x00, 0xff]
+        );
+        assert!(fs.has_dir(dst.join("guide/img")));
+        assert!(fs.has_file(dst.join("theme/style.css")));
+        assert!(!fs.has_file(dst.join("README.md")));
+        assert!(!fs.has_file(dst.join("guide/intro.md")));
+        assert!(!fs.has_file(dst.join("drafts/sketch.png")));
+    }
+
+    #[test]
+    fn copies_everything_without_skip_patterns() {
+        let fs = book();
+        assert_eq!(
+            copy_tree(&fs, Path::new(SRC), Path::new(DST), &[]).unwrap(),
+            5
+        );
+        assert!(fs.has_file(Path::new(DST).join("guide/intro.md")));
+    }
+
+    #[test]
+    fn rejects_malformed_skip_patterns() {
+        let fs = book();
+        let err = copy_tree(&fs, Path::new(SRC), Path::new(DST), &["[md"]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Parse);
+        assert!(!fs.has_dir(DST));
+    }
+}
@@ -0,0 +1,199 @@
+//! This module implements an output sink that writes identical pages only once.
+//!
+//! Localized books often contain identical untranslated pages in several languages. The
+//! [`DeduplicatingSink`] writes the content of such pages once and records every other copy as a
+//! reference to the written page in a redirect manifest, which content-addressed hosts can serve
+//! as redirects. Pages of the `HtmlRenderer` are only identical across books if it links to the
+//! translations of pages with absolute URLs, see `HtmlRenderer::set_site_url`.
+//!
+//! [`DeduplicatingSink`]: struct.DeduplicatingSink.html
+//!
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+use crate::render::sink::OutputSink;
+use crate::render::{json_string, path_to_url};
+
+/// The path, relative to the root of the output, at which [`DeduplicatingSink`] writes its
+/// redirect manifest by default.
+///
+/// [`DeduplicatingSink`]: struct.DeduplicatingSink.html
+pub const DEFAULT_DEDUP_MANIFEST: &str = "dedup.json";
+
+/// An [`OutputSink`] that writes pages with identical content only once.
+///
+/// The first page written with some content is written to the inner sink and becomes the blob of
+/// that content. Every later page with the same content is not written; instead, its path is
+/// recorded as a reference to the path of the blob. `finish` writes the references into the
+/// redirect manifest, a JSON document of the form
+///
+/// ```json
+/// {"references": {"zh/a.html": "en/a.html"}}
+/// ```
+///
+/// and finishes the inner sink. Pages are compared by their full content, so the sink keeps the
+/// content of every blob in memory until it is dropped.
+///
+/// [`OutputSink`]: ../sink/trait.OutputSink.html
+pub struct DeduplicatingSink<S: OutputSink> {
+    sink: S,
+    manifest: PathBuf,
+    state: RefCell<DedupState>,
+}
+
+#[derive(Default)]
+struct DedupState {
+    /// Path of the blob of every distinct content.
+    blobs: HashMap<Vec<u8>, PathBuf>,
+
+    /// Content of the blob at every path.
+    contents: HashMap<PathBuf, Vec<u8>>,
+
+    /// Path of the blob that every reference refers to.
+    references: BTreeMap<PathBuf, PathBuf>,
+}
+
+impl<S: OutputSink> DeduplicatingSink<S> {
+    /// Create a new `DeduplicatingSink` instance that writes into the specified sink, and writes
+    /// its redirect manifest to [`DEFAULT_DEDUP_MANIFEST`].
+    ///
+    /// [`DEFAULT_DEDUP_MANIFEST`]: constant.DEFAULT_DEDUP_MANIFEST.html
+    pub fn new(sink: S) -> Self {
+        Self {
+            sink,
+            manifest: PathBuf::from(DEFAULT_DEDUP_MANIFEST),
+            state: RefCell::new(DedupState::default()),
+        }
+    }
+
+    /// Set the path, relative to the root of the output, of the redirect manifest.
+    pub fn set_manifest<P: Into<PathBuf>>(mut self, manifest: P) -> Self {
+        self.manifest = manifest.into();
+        self
+    }
+
+    /// Get the path of the blob that every deduplicated page refers to, keyed by the path of the
+    /// page.
+    pub fn references(&self) -> BTreeMap<PathBuf, PathBuf> {
+        self.state.borrow().references.clone()
+    }
+
+    /// Get the redirect manifest of the pages written so far.
+    pub fn manifest_json(&self) -> String {
+        let state = self.state.borrow();
+        let references: Vec<_> = state
+            .references
+            .iter()
+            .map(|(page, blob)| {
+                format!(
+                    "{}: {}",
+                    json_string(&path_to_url(page)),
+                    json_string(&path_to_url(blob))
+                )
+            })
+            .collect();
+        format!("{{\"references\": {{{}}}}}\n", references.join(", "))
+    }
+}
+
+impl<S: OutputSink> OutputSink for DeduplicatingSink<S> {
+    fn write_page(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        state.references.remove(path);
+
+        if let Some(blob) = state.blobs.get(bytes) {
+            if blob != path {
+                let blob = blob.clone();
+                state.references.insert(path.to_path_buf(), blob);
+            }
+            return Ok(());
+        }
+
+        // The page replaces a blob with other content. Move the old blob to one of its references
+        // so that the others keep resolving.
+        if let Some(old) = state.contents.remove(path) {
+            state.blobs.remove(&old);
+            let referrers: Vec<PathBuf> = state
+                .references
+                .iter()
+                .filter(|(_, blob)| blob.as_path() == path)
+                .map(|(page, _)| page.clone())
+                .collect();
+            if let Some((moved, rest)) = referrers.split_first() {
+                self.sink.write_page(moved, &old)?;
+                state.references.remove(moved);
+                for page in rest {
+                    state.references.insert(page.clone(), moved.clone());
+                }
+                state.blobs.insert(old.clone(), moved.clone());
+                state.contents.insert(moved.clone(), old);
+            }
+        }
+
+        self.sink.write_page(path, bytes)?;
+        state.blobs.insert(bytes.to_vec(), path.to_path_buf());
+        state.contents.insert(path.to_path_buf(), bytes.to_vec());
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<()> {
+        let manifest = self.manifest_json();
+        self.sink.write_page(&self.manifest, manifest.as_bytes())?;
+        self.sink.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::sink::tests::MemorySink;
+
+    #[test]
+    fn writes_identical_pages_once() {
+        let inner = MemorySink::default();
+        let sink = DeduplicatingSink::new(&inner);
+        sink.write_page(Path::new("en/a.html"), b"same").unwrap();
+        sink.write_page(Path::new("zh/a.html"), b"same").unwrap();
+        sink.write_page(Path::new("en/b.html"), b"one").unwrap();
+        sink.write_page(Path::new("zh/b.html"), b"two").unwrap();
+        sink.finish().unwrap();
+
+        let pages: Vec<_> = inner.pages.borrow().keys().cloned().collect();
+        let expected: Vec<PathBuf> = ["dedup.json", "en/a.html", "en/b.html", "zh/b.html"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(pages, expected);
+        assert_eq!(
+            sink.references(),
+            std::iter::once((PathBuf::from("zh/a.html"), PathBuf::from("en/a.html"))).collect()
+        );
+        assert_eq!(
+            inner.page("dedup.json"),
+            "{\"references\": {\"zh/a.html\": \"en/a.html\"}}\n"
+        );
+        assert!(*inner.finished.borrow());
+    }
+
+    #[test]
+    fn moves_replaced_blobs_to_a_reference() {
+        let inner = MemorySink::default();
+        let sink = DeduplicatingSink::new(&inner).set_manifest("meta/redirects.json");
+        for page in &["a.html", "b.html", "c.html"] {
+            sink.write_page(Path::new(page), b"old").unwrap();
+        }
+        sink.write_page(Path::new("a.html"), b"new").unwrap();
+        sink.finish().unwrap();
+
+        assert_eq!(inner.page("a.html"), "new");
+        assert_eq!(inner.page("b.html"), "old");
+        assert!(!inner.pages.borrow().contains_key(Path::new("c.html")));
+        assert_eq!(
+            inner.page("meta/redirects.json"),
+            "{\"references\": {\"c.html\": \"b.html\"}}\n"
+        );
+    }
+}
@@ -0,0 +1,1373 @@
+//! This module implements a renderer that produces a static HTML site from the document tree.
+//!
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+
+use crate::error::{Error, Result};
+use crate::fs::{normalize, relative_link, FileSystem};
+use crate::render::math::MathProtectProcessor;
+use crate::render::paginate::{paginate, sub_page_url};
+#[cfg(feature = "sanitize")]
+use crate::render::sanitize::SanitizeProcessor;
+use crate::render::sink::{FileSystemOutputSink, OutputSink};
+use crate::render::{escape_html, page_url};
+use crate::tree::markup::Markup;
+use crate::tree::visitor::{VisitContext, Visitor};
+use crate::tree::{Book, BookConfig, Chapter, GlobalizedBooks, Section, TextDirection};
+
+/// The template used by [`HtmlRenderer`] when no template is set.
+///
+/// [`HtmlRenderer`]: struct.HtmlRenderer.html
+pub const DEFAULT_TEMPLATE: &str = "<!DOCTYPE html>
+<html lang=\"{{lang}}\"{{dir}}>
+<head>
+<meta charset=\"utf-8\">
+<title>{{title}}</title>{{alternates}}
+</head>
+<body>
+{{content}}{{edit}}
+</body>
+</html>
+";
+
+/// Specifies the letter case of the anchors generated by [`HtmlRenderer`].
+///
+/// [`HtmlRenderer`]: struct.HtmlRenderer.html
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum AnchorCase {
+    /// Anchors keep the case of their source.
+    #[default]
+    Preserve,
+
+    /// Anchors are lowercased.
+    Lower,
+
+    /// Anchors are uppercased.
+    Upper,
+}
+
+/// Normalizes the anchors generated by [`HtmlRenderer`] to a case policy and a character set, for
+/// hosting platforms that are strict about anchors.
+///
+/// The same normalization is applied to the `id`s of headings, to the fragments of links to
+/// headings within the book and to the anchors of sections in `index.html`, so that links keep
+/// resolving. Links to other sites are left untouched. The default normalizer leaves anchors
+/// unchanged.
+///
+/// [`HtmlRenderer`]: struct.HtmlRenderer.html
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AnchorNormalizer {
+    case: AnchorCase,
+    ascii_only: bool,
+}
+
+impl AnchorNormalizer {
+    /// Create a new `AnchorNormalizer` instance that converts anchors to the specified case.
+    pub fn new(case: AnchorCase) -> Self {
+        Self {
+            case,
+            ascii_only: false,
+        }
+    }
+
+    /// Set whether anchors are restricted to ASCII alphanumeric characters, `-` and `_`, in which
+    /// case all other characters are removed.
+    pub fn set_ascii_only(mut self, ascii_only: bool) -> Self {
+        self.ascii_only = ascii_only;
+        self
+    }
+
+    /// Normalize the specified anchor.
+    ///
+    /// Note that distinct anchors may become equal after normalization, e.g. `Intro` and `intro`
+    /// when lowercasing.
+    pub fn normalize(&self, anchor: &str) -> String {
+        let anchor = match self.case {
+            AnchorCase::Preserve => anchor.to_owned(),
+            AnchorCase::Lower => anchor.to_lowercase(),
+            AnchorCase::Upper => anchor.to_uppercase(),
+        };
+        if self.ascii_only {
+            anchor
+                .chars()
+                .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+                .collect()
+        } else {
+            anchor
+        }
+    }
+}
+
+/// A visitor that renders the visited books into HTML files.
+///
+/// Pages are written to an [`OutputSink`], which is a [`FileSystemOutputSink`] for renderers
+/// created by `new`; use `with_sink` to write them elsewhere. All paths below are relative to the
+/// root of the output.
+///
+/// For every visited section, the markdown content is converted to HTML and written to the file
+/// at `Section::file` with the extension replaced by `.html` and characters that are not safe in
+/// URLs replaced by `-`. Links to other content files are rewritten
+/// to point to the corresponding HTML files. Sections that share a content file are rendered once.
+/// For every visited book, an `index.html` that lists the chapters and sections of the book is
+/// written as well. Books associated with a language are rendered into a subdirectory of the
+/// output directory named after the language.
+///
+/// Every page is produced by filling a template, which contains the following placeholders:
+///
+/// * `{{content}}`: The rendered content of the page;
+/// * `{{title}}`: The title of the page;
+/// * `{{lang}}`: The language code of the book;
+/// * `{{dir}}`: Expands to ` dir="rtl"` for right-to-left books and to nothing otherwise. Custom
+///   templates should put it inside the root element, like `<html{{dir}}>`;
+/// * `{{alternates}}`: The `<link rel="alternate" hreflang="...">` tags of the translations of the
+///   page, each on a line of its own, which belong in the `<head>` element;
+/// * `{{edit}}`: The `<a class="edit-page">` link to edit the content file of the page at the URL
+///   composed by `BookConfig::edit_url`, on a line of its own. It expands to nothing for
+///   `index.html`, for books without an `edit-url-template` and for sections whose metadata
+///   contains `edit = false`.
+///
+/// When a project with several books is visited, every page links to the pages of the same
+/// content file in every book that has a section of that file, with the language code of the
+/// book, and to the page in the default book, i.e. the book that is not associated with a language
+/// or the first book if every book is, with `x-default`. All pages of a split section link to the
+/// first page of its translations. The `index.html` of every book links to those of all books.
+/// The links are relative to the page unless the URL of the site is set with `set_site_url`, which
+/// search engines prefer and which keeps the copies of untranslated pages identical.
+///
+/// Templates split into partial templates with `{{> name}}` tags are expanded by a
+/// [`TemplateLoader`] before they are set with `set_template`.
+///
+/// To help print stylesheets produce clean PDFs, the content of the first page of every chapter
+/// is wrapped in `<div class="chapter-start">`, and the navigation list of `index.html` is wrapped
+/// in `<nav class="no-print">`. With `set_print_page_breaks`, a `<div class="page-break"></div>`
+/// is inserted before the start of every chapter as well.
+///
+/// The anchors of headings and the fragments of internal links can be normalized with
+/// `set_anchor_normalizer`, see [`AnchorNormalizer`]. TeX math is kept intact for MathJax and
+/// KaTeX with `set_math_processor`, see [`MathProtectProcessor`]. With the `sanitize` feature,
+/// the HTML rendered from the content of sections can be sanitized with `set_sanitizer` before it
+/// is put into the template, which books from untrusted sources should use.
+///
+/// With `set_max_page_headings`, sections with more level 2 headings than the limit are split into
+/// several pages at their level 2 headings, each with at most that many of them. The first page
+/// keeps the path of the section and later pages append `-2`, `-3` and so on to its file stem.
+/// Every such page ends with a `<nav class="page-nav">` that links to the previous and the next
+/// page of the section. Links to the headings of a split section, including those in `index.html`,
+/// point at the page that contains the heading. Since every page is parsed on its own, reference
+/// link definitions only apply within the page that contains them.
+///
+/// Errors are collected during the traversal and reported by `finish`.
+///
+/// [`OutputSink`]: ../sink/trait.OutputSink.html
+/// [`FileSystemOutputSink`]: ../sink/struct.FileSystemOutputSink.html
+/// [`TemplateLoader`]: ../template/struct.TemplateLoader.html
+/// [`AnchorNormalizer`]: struct.AnchorNormalizer.html
+/// [`MathProtectProcessor`]: ../math/struct.MathProtectProcessor.html
+pub struct HtmlRenderer<S: OutputSink> {
+    sink: S,
+    template: String,
+    print_page_breaks: bool,
+    max_page_headings: Option<usize>,
+    anchors: AnchorNormalizer,
+    math: Option<MathProtectProcessor>,
+    #[cfg(feature = "sanitize")]
+    sanitizer: Option<SanitizeProcessor>,
+    site_url: Option<String>,
+    translations: Vec<Translation>,
+    book: Option<BookState>,
+    chapter_start: bool,
+    rendered: HashSet<PathBuf>,
+    error: Option<Error>,
+}
+
+/// Rendering state of the book under visit.
+struct BookState {
+    /// Directory of the output of the book, relative to the root of the output.
+    output: PathBuf,
+    language: String,
+    direction: TextDirection,
+
+    /// Configuration of the book, which composes the edit URLs of its pages.
+    config: BookConfig,
+
+    /// Split sections of the book, keyed by the normalized paths of their content files.
+    paginations: HashMap<PathBuf, Pagination>,
+}
+
+/// A book of the visited project, whose pages are the translations of the pages of the other
+/// books.
+struct Translation {
+    /// Directory of the output of the book, relative to the root of the output.
+    output: PathBuf,
+
+    /// Language code of the book, if it has one.
+    language: Option<String>,
+
+    /// Normalized paths of the content files of the sections of the book.
+    files: HashSet<PathBuf>,
+}
+
+/// The pages of a section split at its level 2 headings.
+struct Pagination {
+    /// Index of the page that contains every heading, keyed by the normalized slug.
+    headings: HashMap<String, usize>,
+}
+
+/// The page that links are rewritten for.
+struct LinkContext<'a> {
+    anchors: &'a AnchorNormalizer,
+    paginations: Option<&'a HashMap<PathBuf, Pagination>>,
+
+    /// Content file of the page, relative to the root directory of the book.
+    file: &'a Path,
+
+    /// Index of the page in the pages of its section.
+    page: usize,
+}
+
+impl<'a> LinkContext<'a> {
+    /// Get the index of the page of the specified content file that contains the heading with the
+    /// specified normalized anchor, if the file is split.
+    fn page_of(&self, file: &Path, anchor: &str) -> Option<usize> {
+        let pagination = self.paginations?.get(&normalize(file))?;
+        pagination.headings.get(anchor).copied()
+    }
+}
+
+impl<'a, F: FileSystem> HtmlRenderer<FileSystemOutputSink<'a, F>> {
+    /// Create a new `HtmlRenderer` instance that writes into the specified output directory in the
+    /// specified file system, using the [`DEFAULT_TEMPLATE`].
+    ///
+    /// [`DEFAULT_TEMPLATE`]: constant.DEFAULT_TEMPLATE.html
+    pub fn new<P: Into<PathBuf>>(fs: &'a F, output: P) -> Self {
+        Self::with_sink(FileSystemOutputSink::new(fs, output))
+    }
+}
+
+impl<S: OutputSink> HtmlRenderer<S> {
+    /// Create a new `HtmlRenderer` instance that writes into the specified sink, using the
+    /// [`DEFAULT_TEMPLATE`].
+    ///
+    /// [`DEFAULT_TEMPLATE`]: constant.DEFAULT_TEMPLATE.html
+    pub fn with_sink(sink: S) -> Self {
+        Self {
+            sink,
+            template: DEFAULT_TEMPLATE.to_owned(),
+            print_page_breaks: false,
+            max_page_headings: None,
+            anchors: AnchorNormalizer::default(),
+            math: None,
+            #[cfg(feature = "sanitize")]
+            sanitizer: None,
+            site_url: None,
+            translations: Vec::new(),
+            book: None,
+            chapter_start: false,
+            rendered: HashSet::new(),
+            error: None,
+        }
+    }
+
+    /// Set the template used to produce every page.
+    pub fn set_template<T: Into<String>>(mut self, template: T) -> Self {
+        self.template = template.into();
+        self
+    }
+
+    /// Set whether a `<div class="page-break"></div>` is inserted before the start of every
+    /// chapter, which print stylesheets can turn into page breaks.
+    pub fn set_print_page_breaks(mut self, print_page_breaks: bool) -> Self {
+        self.print_page_breaks = print_page_breaks;
+        self
+    }
+
+    /// Set the maximum number of level 2 headings of a page, beyond which sections are split into
+    /// several pages.
+    pub fn set_max_page_headings(mut self, max_page_headings: usize) -> Self {
+        self.max_page_headings = Some(max_page_headings);
+        self
+    }
+
+    /// Set the normalizer of the anchors of headings and the fragments of internal links.
+    pub fn set_anchor_normalizer(mut self, anchors: AnchorNormalizer) -> Self {
+        self.anchors = anchors;
+        self
+    }
+
+    /// Set the processor that protects TeX math in the content of sections from the markdown
+    /// parser, for books typeset with MathJax or KaTeX.
+    pub fn set_math_processor(mut self, math: MathProtectProcessor) -> Self {
+        self.math = Some(math);
+        self
+    }
+
+    /// Set the sanitizer of the HTML rendered from the content of sections.
+    ///
+    /// This function is only available with the `sanitize` feature.
+    #[cfg(feature = "sanitize")]
+    pub fn set_sanitizer(mut self, sanitizer: SanitizeProcessor) -> Self {
+        self.sanitizer = Some(sanitizer);
+        self
+    }
+
+    /// Set the URL of the root of the output in the deployed site, like `https://example.com/book`,
+    /// which makes the links to the translations of pages absolute.
+    pub fn set_site_url<U: Into<String>>(mut self, site_url: U) -> Self {
+        self.site_url = Some(site_url.into());
+        self
+    }
+
+    /// Consume this renderer and report the first error that occurred during rendering, if any.
+    ///
+    /// If no error occurred, the sink is finished by `OutputSink::finish`.
+    pub fn finish(self) -> Result<()> {
+        match self.error {
+            Some(e) => Err(e),
+            None => self.sink.finish(),
+        }
+    }
+
+    /// Fill the template with the specified page.
+    fn fill_template(&self, title: &str, alternates: &str, edit: &str, content: &str) -> String {
+        let (language, direction) = match &self.book {
+            Some(book) => (book.language.as_str(), book.direction),
+            None => ("", TextDirection::Ltr),
+        };
+        let dir = match direction {
+            TextDirection::Ltr => "",
+            TextDirection::Rtl => " dir=\"rtl\"",
+        };
+
+        self.template
+            .replace("{{title}}", &escape_html(title))
+            .replace("{{lang}}", &escape_html(language))
+            .replace("{{dir}}", dir)
+            .replace("{{alternates}}", alternates)
+            .replace("{{edit}}", edit)
+            .replace("{{content}}", content)
+    }
+
+    /// Render the links to the translations of the page at the specified path within the output of
+    /// the current book, which is a page of the section with the specified content file, or
+    /// `index.html` if there is no such file.
+    fn render_alternates(&self, path: &Path, file: Option<&Path>) -> String {
+        if self.translations.is_empty() {
+            return String::new();
+        }
+
+        let output = self.output_path(path);
+        let base = output.parent().unwrap_or_else(|| Path::new(""));
+        let page = match file {
+            Some(file) => PathBuf::from(page_url(file)),
+            None => PathBuf::from("index.html"),
+        };
+        let file = file.map(normalize);
+        let translated = |t: &Translation| file.as_ref().is_none_or(|f| t.files.contains(f));
+        let link = |t: &Translation, language: &str| {
+            let target = t.output.join(&page);
+            let href = match &self.site_url {
+                Some(url) => format!(
+                    "{}/{}",
+                    url.trim_end_matches('/'),
+                    relative_link("", target)
+                ),
+                None => relative_link(base, target),
+            };
+            format!(
+                "\n<link rel=\"alternate\" hreflang=\"{}\" href=\"{}\">",
+                escape_html(language),
+                escape_html(&href)
+            )
+        };
+
+        let mut alternates = String::new();
+        for t in self.translations.iter().filter(|t| translated(t)) {
+            if let Some(language) = &t.language {
+                alternates.push_str(&link(t, language));
+            }
+        }
+        let default = self
+            .translations
+            .iter()
+            .find(|t| t.output.as_os_str().is_empty())
+            .or_else(|| self.translations.first());
+        if let Some(default) = default.filter(|t| translated(t)) {
+            alternates.push_str(&link(default, "x-default"));
+        }
+        alternates
+    }
+
+    /// Render the link to edit the content file of the specified section, which is empty if the
+    /// current book has no `edit_url_template` or if the section opts out with `edit = false`.
+    fn render_edit_link(&self, section: &Section) -> String {
+        let url = match &self.book {
+            Some(book) if section.is_editable() => book.config.edit_url(&section.file),
+            _ => None,
+        };
+        match url {
+            Some(url) => format!(
+                "\n<a class=\"edit-page\" href=\"{}\">Edit this page</a>",
+                escape_html(&url)
+            ),
+            None => String::new(),
+        }
+    }
+
+    /// Get the path of the page at the specified path within the output of the current book.
+    fn output_path(&self, path: &Path) -> PathBuf {
+        match &self.book {
+            Some(book) => book.output.join(path),
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// Write the specified page to the specified path within the output of the current book.
+    ///
+    /// `file` is the content file of the section of the page, or `None` for `index.html`, and
+    /// `edit` is the rendered link to edit it.
+    fn write_page(
+        &mut self,
+        path: &Path,
+        file: Option<&Path>,
+        title: &str,
+        edit: &str,
+        content: &str,
+    ) {
+        let output = self.output_path(path);
+        let alternates = self.render_alternates(path, file);
+        let page = self.fill_template(title, &alternates, edit, content);
+        if let Err(e) = self.sink.write_page(&output, page.as_bytes()) {
+            self.error.get_or_insert(e);
+        }
+    }
+}
+
+impl<S: OutputSink> Visitor for HtmlRenderer<S> {
+    fn visit_globalized_books(&mut self, globalized_books: &GlobalizedBooks) {
+        self.translations.clear();
+        if globalized_books.books.len() <= 1 {
+            return;
+        }
+
+        for (language, book) in &globalized_books.books {
+            self.translations.push(Translation {
+                output: PathBuf::from(language),
+                language: book
+                    .config
+                    .language
+                    .clone()
+                    .or_else(|| Some(language.clone()).filter(|l| !l.is_empty())),
+                files: content_files(book),
+            });
+        }
+    }
+
+    fn visit_book_in(&mut self, book: &Book, context: &VisitContext) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let language = context.language().unwrap_or_default();
+        self.book = Some(BookState {
+            output: PathBuf::from(language),
+            language: book
+                .config
+                .language
+                .clone()
+                .unwrap_or_else(|| language.to_owned()),
+            direction: book.config.effective_direction(),
+            config: book.config.clone(),
+            paginations: match self.max_page_headings {
+                Some(max) => paginate_book(book, max, &self.anchors),
+                None => HashMap::new(),
+            },
+        });
+
+        self.chapter_start = false;
+
+        let title = book.config.title.clone().unwrap_or_default();
+        let links = LinkContext {
+            anchors: &self.anchors,
+            paginations: self.book.as_ref().map(|b| &b.paginations),
+            file: Path::new(""),
+            page: 0,
+        };
+        let index = render_index(book, &title, &links);
+        self.write_page(Path::new("index.html"), None, &title, "", &index);
+    }
+
+    fn visit_chapter(&mut self, _chapter: &Chapter) {
+        self.chapter_start = true;
+    }
+
+    fn visit_section(&mut self, section: &Section) {
+        if self.error.is_some() || section.file.as_os_str().is_empty() {
+            return;
+        }
+
+        let path = PathBuf::from(page_url(&section.file));
+        if !self.rendered.insert(self.output_path(&path)) {
+            return;
+        }
+
+        let pages = match self.max_page_headings {
+            Some(max) => paginate(&section.content, max),
+            None => Vec::new(),
+        };
+        if pages.len() <= 1 {
+            let slugs = section.headings().into_iter().map(|h| h.slug);
+            let content = self.render_content(section, &section.content, slugs, 0);
+            self.write_section_page(&path, section, section.title(), content);
+            return;
+        }
+
+        let headings = section.headings();
+        for (index, page) in pages.iter().enumerate() {
+            let path = PathBuf::from(sub_page_url(&section.file, index));
+            self.rendered.insert(self.output_path(&path));
+            let slugs = headings[page.headings.clone()]
+                .iter()
+                .map(|h| h.slug.clone());
+            let source = &section.content[page.content.clone()];
+            let mut content = self.render_content(section, source, slugs, index);
+            content.push_str(&render_page_nav(&section.file, index, pages.len()));
+            let title = if index == 0 {
+                section.title().to_owned()
+            } else {
+                format!("{} ({}/{})", section.title(), index + 1, pages.len())
+            };
+            self.write_section_page(&path, section, &title, content);
+        }
+    }
+}
+
+impl<S: OutputSink> HtmlRenderer<S> {
+    /// Render the specified markdown source of the page with the specified index of the specified
+    /// section into HTML, whose headings receive the specified slugs.
+    fn render_content<I: Iterator<Item = String>>(
+        &self,
+        section: &Section,
+        source: &str,
+        slugs: I,
+        page: usize,
+    ) -> String {
+        let links = LinkContext {
+            anchors: &self.anchors,
+            paginations: self.book.as_ref().map(|b| &b.paginations),
+            file: &section.file,
+            page,
+        };
+        let content = render_markdown(source, slugs, &links, self.math.as_ref());
+        #[cfg(feature = "sanitize")]
+        let content = match &self.sanitizer {
+            Some(sanitizer) => sanitizer.sanitize(&content),
+            None => content,
+        };
+        content
+    }
+
+    /// Write a page of the specified section with the specified rendered content, marking the
+    /// start of a chapter if it is the first page of the chapter.
+    fn write_section_page(
+        &mut self,
+        path: &Path,
+        section: &Section,
+        title: &str,
+        mut content: String,
+    ) {
+        if std::mem::take(&mut self.chapter_start) {
+            let page_break = if self.print_page_breaks {
+                "<div class=\"page-break\"></div>\n"
+            } else {
+                ""
+            };
+            content = format!(
+                "{}<div class=\"chapter-start\">\n{}</div>\n",
+                page_break, content
+            );
+        }
+        let edit = self.render_edit_link(section);
+        self.write_page(path, Some(&section.file), title, &edit, &content);
+    }
+}
+
+/// Get the normalized paths of the content files of the sections of the specified book.
+fn content_files(book: &Book) -> HashSet<PathBuf> {
+    let mut files = HashSet::new();
+    let mut stack: Vec<&Section> = vec![&book.preface];
+    for chapter in &book.chapters {
+        stack.extend(&chapter.sections);
+    }
+    while let Some(section) = stack.pop() {
+        stack.extend(&section.subsections);
+        if section.has_file() {
+            files.insert(normalize(&section.file));
+        }
+    }
+    files
+}
+
+/// Split the sections of the specified book that have more than the specified number of level 2
+/// headings, keyed by the normalized paths of their content files.
+fn paginate_book(
+    book: &Book,
+    max_headings: usize,
+    anchors: &AnchorNormalizer,
+) -> HashMap<PathBuf, Pagination> {
+    let mut paginations = HashMap::new();
+    let mut stack: Vec<&Section> = vec![&book.preface];
+    for chapter in book.chapters.iter().rev() {
+        stack.extend(chapter.sections.iter().rev());
+    }
+    while let Some(section) = stack.pop() {
+        stack.extend(section.subsections.iter().rev());
+        let file = normalize(&section.file);
+        if section.file.as_os_str().is_empty() || paginations.contains_key(&file) {
+            continue;
+        }
+
+        let pages = paginate(&section.content, max_headings);
+        if pages.len() <= 1 {
+            continue;
+        }
+        let slugs: Vec<String> = section.headings().into_iter().map(|h| h.slug).collect();
+        let mut headings = HashMap::new();
+        for (index, page) in pages.iter().enumerate() {
+            for slug in &slugs[page.headings.clone()] {
+                headings.entry(anchors.normalize(slug)).or_insert(index);
+            }
+        }
+        paginations.insert(file, Pagination { headings });
+    }
+    paginations
+}
+
+/// Render the navigation between the page with the specified index and its neighbors among the
+/// specified number of pages of the section with the specified content file.
+fn render_page_nav(file: &Path, index: usize, pages: usize) -> String {
+    let link = |index: usize, rel: &str, text: &str| {
+        format!(
+            "<a href=\"{}\" rel=\"{}\">{}</a>\n",
+            escape_html(file_name(&sub_page_url(file, index))),
+            rel,
+            text
+        )
+    };
+    let mut nav = String::from("<nav class=\"page-nav\">\n");
+    if index > 0 {
+        nav.push_str(&link(index - 1, "prev", "Previous"));
+    }
+    if index + 1 < pages {
+        nav.push_str(&link(index + 1, "next", "Next"));
+    }
+    nav.push_str("</nav>\n");
+    nav
+}
+
+/// Get the last segment of the specified URL path.
+fn file_name(url: &str) -> &str {
+    url.rsplit('/').next().unwrap_or(url)
+}
+
+/// Convert the markdown content of the specified section to HTML.
+///
+/// Every heading receives the `id` of its slug, so that it can be linked to. Heading ids and the
+/// fragments of internal links are normalized by the specified normalizer. If a math processor is
+/// specified, math spans are protected from the markdown parser by it.
+pub(crate) fn markdown_to_html(
+    section: &Section,
+    anchors: &AnchorNormalizer,
+    math: Option<&MathProtectProcessor>,
+) -> String {
+    let links = LinkContext {
+        anchors,
+        paginations: None,
+        file: &section.file,
+        page: 0,
+    };
+    let slugs = section.headings().into_iter().map(|h| h.slug);
+    render_markdown(&section.content, slugs, &links, math)
+}
+
+/// Convert the specified markdown source of a page to HTML, where headings receive the specified
+/// slugs in order and links are rewritten for the specified page.
+fn render_markdown<I: Iterator<Item = String>>(
+    source: &str,
+    mut slugs: I,
+    links: &LinkContext,
+    math: Option<&MathProtectProcessor>,
+) -> String {
+    let protected = math.map(|math| math.protect(source));
+    let source = match &protected {
+        Some(protected) => protected.content(),
+        None => source,
+    };
+
+    let anchors = links.anchors;
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES | Options::ENABLE_STRIKETHROUGH;
+    let parser = Parser::new_ext(source, options).map(|event| match event {
+        Event::Start(Tag::Link(link_type, dest, title)) => {
+            Event::Start(Tag::Link(link_type, rewrite_link(dest, links), title))
+        }
+        Event::Start(Tag::Heading(level)) => match slugs.next() {
+            Some(slug) => Event::Html(CowStr::from(format!(
+                "<h{} id=\"{}\">",
+                level,
+                escape_html(&anchors.normalize(&slug))
+            ))),
+            None => Event::Start(Tag::Heading(level)),
+        },
+        event => event,
+    });
+
+    let mut output = String::new();
+    html::push_html(&mut output, parser);
+    match protected {
+        Some(protected) => protected.restore(&output),
+        None => output,
+    }
+}
+
+/// Rewrite a relative link to a content file so that it points to the rendered HTML file.
+///
+/// The fragments of links within the book, i.e. links that consist of a fragment only and links
+/// to content files, are normalized by the normalizer of the specified context. Links to the
+/// headings of split sections point at the page that contains the heading. Links to content files
+/// use `page_url`, so that they match the paths at which the pages are written.
+fn rewrite_link<'a>(dest: CowStr<'a>, links: &LinkContext) -> CowStr<'a> {
+    if dest.contains("://") || dest.starts_with("mailto:") {
+        return dest;
+    }
+
+    let (path, anchor) = match dest.find('#') {
+        Some(i) => (&dest[..i], Some(links.anchors.normalize(&dest[i + 1..]))),
+        None => (&dest[..], None),
+    };
+    let fragment = anchor
+        .as_ref()
+        .map(|anchor| format!("#{}", anchor))
+        .unwrap_or_default();
+    if path.is_empty() {
+        let page = anchor
+            .as_ref()
+            .and_then(|anchor| links.page_of(links.file, anchor));
+        return match page {
+            Some(page) if page != links.page => CowStr::from(format!(
+                "{}{}",
+                file_name(&sub_page_url(links.file, page)),
+                fragment
+            )),
+            _ => CowStr::from(fragment),
+        };
+    }
+    if Markup::from_path(path).is_none() {
+        return dest;
+    }
+    let path = Path::new(path);
+    let target = links
+        .file
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(path);
+    let page = anchor
+        .and_then(|anchor| links.page_of(&target, &anchor))
+        .unwrap_or(0);
+    CowStr::from(format!("{}{}", sub_page_url(path, page), fragment))
+}
+
+/// Render the index page of the specified book, which lists its chapters and sections.
+fn render_index(book: &Book, title: &str, links: &LinkContext) -> String {
+    let mut output = String::new();
+    if !title.is_empty() {
+        output.push_str(&format!("<h1>{}</h1>\n", escape_html(title)));
+    }
+
+    output.push_str("<nav class=\"no-print\">\n<ul>\n");
+    if !book.preface.file.as_os_str().is_empty() {
+        render_index_entry(&book.preface, links, &mut output);
+    }
+    for chapter in &book.chapters {
+        output.push_str(&format!("<li>{}", escape_html(&chapter.name)));
+        if !chapter.sections.is_empty() {
+            output.push_str("\n<ul>\n");
+            for s in &chapter.sections {
+                render_index_entry(s, links, &mut output);
+            }
+            output.push_str("</ul>\n");
+        }
+        output.push_str("</li>\n");
+    }
+    output.push_str("</ul>\n</nav>\n");
+    output
+}
+
+/// Render the index entry of the specified section and its subsections.
+///
+/// Sections without a content file are listed without a link.
+fn render_index_entry(section: &Section, links: &LinkContext, output: &mut String) {
+    // The stack holds the remaining sections of every list under rendering, from the outermost
+    // one, so that deeply nested sections cannot overflow the call stack.
+    let mut stack = vec![std::slice::from_ref(section).iter()];
+    while let Some(list) = stack.last_mut() {
+        let section = match list.next() {
+            Some(section) => section,
+            None => {
+                stack.pop();
+                if !stack.is_empty() {
+                    // Close the list of subsections and the entry of their parent.
+                    output.push_str("</ul>\n</li>\n");
+                }
+                continue;
+            }
+        };
+
+        if section.has_file() {
+            let anchor = section
+                .anchor
+                .as_ref()
+                .map(|anchor| links.anchors.normalize(anchor));
+            let page = anchor
+                .as_ref()
+                .and_then(|anchor| links.page_of(&section.file, anchor))
+                .unwrap_or(0);
+            let mut href = sub_page_url(&section.file, page);
+            if let Some(anchor) = anchor {
+                href.push('#');
+                href.push_str(&anchor);
+            }
+            output.push_str(&format!(
+                "<li><a href=\"{}\">{}</a>",
+                escape_html(&href),
+                escape_html(&section.name)
+            ));
+        } else {
+            output.push_str(&format!("<li>{}", escape_html(&section.name)));
+        }
+
+        if section.subsections.is_empty() {
+            output.push_str("</li>\n");
+        } else {
+            output.push_str("\n<ul>\n");
+            stack.push(section.subsections.iter());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::scratch::ScratchFileSystem;
+    use crate::render::sink::tests::MemorySink;
+    use crate::tree::builder::{
+        BookBuilder, BookConfigBuilder, ChapterBuilder, GlobalizedBooksBuilder, SectionBuilder,
+    };
+    use crate::tree::visitor::tests::{chain_with, drop_book_iteratively, with_small_stack};
+    use crate::tree::visitor::visit;
+    use crate::tree::GlobalizedBooks;
+
+    const OUTPUT: &str = "/openbook-test/html";
+
+    fn section(file: &str, content: &str) -> Section {
+        SectionBuilder::new()
+            .set_name(file)
+            .set_file(file)
+            .set_content(content)
+            .build()
+    }
+
+    fn books() -> GlobalizedBooks {
+        let book = BookBuilder::new()
+            .set_preface(section("README.md", "Welcome"))
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("First")
+                    .add_section(section("a.md", "# A\n\nSee [B](b.md#b)."))
+                    .add_section(section("b.md", "# B"))
+                    .build(),
+            )
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("Second")
+                    .add_section(section("c.md", "# C"))
+                    .build(),
+            )
+            .build();
+        GlobalizedBooksBuilder::new().add_default_book(book).build()
+    }
+
+    fn render(
+        renderer: impl FnOnce(HtmlRenderer<&MemorySink>) -> HtmlRenderer<&MemorySink>,
+    ) -> MemorySink {
+        let sink = MemorySink::default();
+        let mut html = renderer(HtmlRenderer::with_sink(&sink));
+        visit(&books(), &mut html);
+        html.finish().unwrap();
+        sink
+    }
+
+    fn page(sink: &MemorySink, path: &str) -> String {
+        sink.page(path)
+    }
+
+    #[test]
+    fn writes_pages_to_the_sink() {
+        let sink = render(|r| r);
+        let pages: Vec<_> = sink.pages.borrow().keys().cloned().collect();
+        let expected: Vec<PathBuf> = ["README.html", "a.html", "b.html", "c.html", "index.html"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(pages, expected);
+        assert!(*sink.finished.borrow());
+    }
+
+    fn bilingual_books() -> GlobalizedBooks {
+        let mut books = books();
+        let mut zh = books.books[0].1.clone();
+        zh.config.language = Some(String::from("zh-CN"));
+        zh.chapters[1].sections.clear();
+        books.books[0].1.config.language = Some(String::from("en"));
+        books.books.push((String::from("zh"), zh));
+        books
+    }
+
+    fn render_books(books: &GlobalizedBooks, site_url: Option<&str>) -> MemorySink {
+        let sink = MemorySink::default();
+        let mut html = HtmlRenderer::with_sink(&sink);
+        if let Some(url) = site_url {
+            html = html.set_site_url(url);
+        }
+        visit(books, &mut html);
+        html.finish().unwrap();
+        sink
+    }
+
+    #[test]
+    fn links_pages_to_their_translations() {
+        let sink = render_books(&bilingual_books(), None);
+        let alternates = "<title>a.md</title>\n\
+            <link rel=\"alternate\" hreflang=\"en\" href=\"../a.html\">\n\
+            <link rel=\"alternate\" hreflang=\"zh-CN\" href=\"a.html\">\n\
+            <link rel=\"alternate\" hreflang=\"x-default\" href=\"../a.html\">\n</head>";
+        assert!(page(&sink, "zh/a.html").contains(alternates));
+        assert!(page(&sink, "a.html").contains("hreflang=\"zh-CN\" href=\"zh/a.html\""));
+        assert!(
+            page(&sink, "zh/index.html").contains("hreflang=\"x-default\" href=\"../index.html\"")
+        );
+
+        // Untranslated sections only link to the books that have them.
+        let c = page(&sink, "c.html");
+        assert!(c.contains("hreflang=\"en\" href=\"c.html\""));
+        assert!(!c.contains("zh-CN"));
+
+        let sink = render_books(&bilingual_books(), Some("https://example.com/book/"));
+        assert!(page(&sink, "zh/a.html")
+            .contains("hreflang=\"en\" href=\"https://example.com/book/a.html\""));
+
+        let sink = render(|r| r);
+        assert!(!page(&sink, "a.html").contains("hreflang"));
+    }
+
+    #[test]
+    fn reports_the_first_failed_write() {
+        struct FailingSink;
+
+        impl OutputSink for FailingSink {
+            fn write_page(&self, path: &Path, _bytes: &[u8]) -> Result<()> {
+                if path == Path::new("index.html") {
+                    return Ok(());
+                }
+                Err(Error::from_message(format!(
+                    "cannot write {}",
+                    path.display()
+                )))
+            }
+
+            fn finish(&self) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        // Every page of the split section is written before the next visit checks the error.
+        let book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("Guide")
+                    .add_section(section("a.md", "## A\n\n## B\n\n## C\n"))
+                    .build(),
+            )
+            .build();
+        let books = GlobalizedBooksBuilder::new().add_default_book(book).build();
+        let mut html = HtmlRenderer::with_sink(FailingSink).set_max_page_headings(1);
+        visit(&books, &mut html);
+        let error = html.finish().unwrap_err();
+        assert!(
+            error.to_string().ends_with("cannot write a.html"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn new_writes_into_the_output_directory() {
+        let fs = ScratchFileSystem::new();
+        let mut html = HtmlRenderer::new(&fs, OUTPUT);
+        visit(&books(), &mut html);
+        html.finish().unwrap();
+        assert!(fs
+            .read_file_as_string(Path::new(OUTPUT).join("a.html"))
+            .unwrap()
+            .contains("<h1 id=\"a\">A</h1>"));
+    }
+
+    #[test]
+    fn renders_pages_and_rewrites_links() {
+        let sink = render(|r| r);
+        let a = page(&sink, "a.html");
+        assert!(a.contains("<title>a.md</title>"));
+        assert!(a.contains("<h1 id=\"a\">A</h1>"));
+        assert!(a.contains("<a href=\"b.html#b\">B</a>"));
+        assert!(page(&sink, "index.html").contains("<a href=\"c.html\">c.md</a>"));
+    }
+
+    #[test]
+    fn rewrites_links_to_the_urls_of_pages() {
+        let book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("Notes")
+                    .add_section(section(
+                        "guide/intro.md",
+                        "See [notes](<../my notes.md#top>) and [more](<../my notes.md#more>).",
+                    ))
+                    .add_section(section("my notes.md", "# Top\n\n## Usage\n\n## More\n"))
+                    .build(),
+            )
+            .build();
+        let books = GlobalizedBooksBuilder::new().add_default_book(book).build();
+        let sink = MemorySink::default();
+        let mut html = HtmlRenderer::with_sink(&sink).set_max_page_headings(1);
+        visit(&books, &mut html);
+        html.finish().unwrap();
+
+        let intro = page(&sink, "guide/intro.html");
+        assert!(
+            intro.contains("<a href=\"../my-notes.html#top\">notes</a>"),
+            "{}",
+            intro
+        );
+        assert!(
+            intro.contains("<a href=\"../my-notes-2.html#more\">more</a>"),
+            "{}",
+            intro
+        );
+        assert!(page(&sink, "my-notes.html").contains("<h1 id=\"top\">"));
+        assert!(page(&sink, "my-notes-2.html").contains("<h2 id=\"more\">"));
+    }
+
+    #[test]
+    fn links_pages_to_their_edit_urls() {
+        let config = BookConfigBuilder::new()
+            .set_root("/project/docs")
+            .set_edit_url_template("https://example.com/edit/main/docs/{path}")
+            .build();
+        let book = BookBuilder::new()
+            .set_config(config)
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("Guide")
+                    .add_section(section("guide/setup/usage.md", "# Usage"))
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_name("Generated")
+                            .set_file("guide/generated.md")
+                            .set_metadata("edit", "false")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let books = GlobalizedBooksBuilder::new().add_default_book(book).build();
+        let sink = MemorySink::default();
+        let mut html = HtmlRenderer::with_sink(&sink);
+        visit(&books, &mut html);
+        html.finish().unwrap();
+
+        assert!(page(&sink, "guide/setup/usage.html").contains(
+            "\n<a class=\"edit-page\" \
+             href=\"https://example.com/edit/main/docs/guide/setup/usage.md\">Edit this page</a>\n"
+        ));
+        assert!(!page(&sink, "guide/generated.html").contains("edit-page"));
+        assert!(!page(&sink, "index.html").contains("edit-page"));
+        assert!(!page(&render(|r| r), "a.html").contains("edit-page"));
+    }
+
+    #[test]
+    fn renders_nested_sections_into_their_own_pages() {
+        let book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_name("Guide")
+                            .set_file("guide/index.md")
+                            .set_content(
+                                "| a | b |\n| - | - |\n| 1 | 2 |\n\n```rust\nfn main() {}\n```",
+                            )
+                            .add_subsection(section("guide/setup.md", "~~old~~ *new*"))
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let books = GlobalizedBooksBuilder::new().add_default_book(book).build();
+        let sink = MemorySink::default();
+        let mut html = HtmlRenderer::with_sink(&sink)
+            .set_template("<html lang=\"{{lang}}\"><title>{{title}}</title>{{content}}</html>");
+        visit(&books, &mut html);
+        html.finish().unwrap();
+
+        let guide = page(&sink, "guide/index.html");
+        assert!(
+            guide.starts_with("<html lang=\"\"><title>Guide</title>"),
+            "{}",
+            guide
+        );
+        assert!(guide.contains("<td>1</td>"), "{}", guide);
+        assert!(
+            guide.contains("<code class=\"language-rust\">fn main() {}"),
+            "{}",
+            guide
+        );
+        let setup = page(&sink, "guide/setup.html");
+        assert!(setup.contains("<del>old</del> <em>new</em>"), "{}", setup);
+    }
+
+    #[test]
+    fn marks_print_regions() {
+        let sink = render(|r| r);
+        assert!(page(&sink, "a.html").contains("<div class=\"chapter-start\">\n<h1"));
+        assert!(page(&sink, "c.html").contains("<div class=\"chapter-start\">"));
+        assert!(!page(&sink, "b.html").contains("chapter-start"));
+        assert!(!page(&sink, "README.html").contains("chapter-start"));
+        assert!(!page(&sink, "a.html").contains("page-break"));
+        assert!(page(&sink, "index.html").contains("<nav class=\"no-print\">"));
+    }
+
+    #[test]
+    fn inserts_page_breaks_before_chapters() {
+        let sink = render(|r| r.set_print_page_breaks(true));
+        for file in &["a.html", "c.html"] {
+            assert!(page(&sink, file)
+                .contains("<div class=\"page-break\"></div>\n<div class=\"chapter-start\">"));
+        }
+        assert!(!page(&sink, "b.html").contains("page-break"));
+    }
+
+    #[test]
+    fn normalizes_heading_ids_and_link_fragments() {
+        let book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .add_section(section(
+                        "a.md",
+                        "# Überblick Intro\n\n[self](#Überblick-Intro) [other](b.md#Setup) \
+                         [site](https://example.com/#Keep)",
+                    ))
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_name("Setup")
+                            .set_file("b.md")
+                            .set_anchor("Setup")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let books = GlobalizedBooksBuilder::new().add_default_book(book).build();
+
+        let sink = MemorySink::default();
+        let mut html = HtmlRenderer::with_sink(&sink)
+            .set_anchor_normalizer(AnchorNormalizer::new(AnchorCase::Lower).set_ascii_only(true));
+        visit(&books, &mut html);
+        html.finish().unwrap();
+
+        let a = page(&sink, "a.html");
+        assert!(a.contains("<h1 id=\"berblick-intro\">"));
+        assert!(a.contains("<a href=\"#berblick-intro\">self</a>"));
+        assert!(a.contains("<a href=\"b.html#setup\">other</a>"));
+        assert!(a.contains("<a href=\"https://example.com/#Keep\">site</a>"));
+        assert!(page(&sink, "index.html").contains("<a href=\"b.html#setup\">Setup</a>"));
+    }
+
+    #[test]
+    fn protects_math_when_enabled() {
+        let book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .add_section(section("a.md", "Let $a_1 + b_1$ be _x_."))
+                    .build(),
+            )
+            .build();
+        let books = GlobalizedBooksBuilder::new().add_default_book(book).build();
+
+        let sink = MemorySink::default();
+        let mut html = HtmlRenderer::with_sink(&sink).set_math_processor(MathProtectProcessor);
+        visit(&books, &mut html);
+        html.finish().unwrap();
+        assert!(page(&sink, "a.html").contains("<p>Let $a_1 + b_1$ be <em>x</em>.</p>"));
+    }
+
+    #[test]
+    fn splits_long_sections_into_pages() {
+        let reference =
+            "# Reference\n\nSee [C](#c) and [A](#a).\n\n## A\n\n## B\n\n## C\n\n## D\n\n## E\n";
+        let book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("First")
+                    .add_section(section("ref/api.md", reference))
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_name("E")
+                            .set_file("ref/api.md")
+                            .set_anchor("e")
+                            .build(),
+                    )
+                    .add_section(section("b.md", "[D](ref/api.md#d) [API](ref/api.md)"))
+                    .build(),
+            )
+            .build();
+        let books = GlobalizedBooksBuilder::new().add_default_book(book).build();
+
+        let sink = MemorySink::default();
+        let mut html = HtmlRenderer::with_sink(&sink).set_max_page_headings(2);
+        visit(&books, &mut html);
+        html.finish().unwrap();
+
+        let pages: Vec<_> = sink.pages.borrow().keys().cloned().collect();
+        let expected: Vec<PathBuf> = [
+            "b.html",
+            "index.html",
+            "ref/api-2.html",
+            "ref/api-3.html",
+            "ref/api.html",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        assert_eq!(pages, expected);
+
+        let first = page(&sink, "ref/api.html");
+        assert!(first.contains("<title>ref/api.md</title>"));
+        assert!(first.contains("<div class=\"chapter-start\">"));
+        assert!(first.contains("<a href=\"api-2.html#c\">C</a> and <a href=\"#a\">A</a>"));
+        assert!(first.contains("<h2 id=\"b\">B</h2>"));
+        assert!(!first.contains("<h2 id=\"c\">"));
+        assert!(first.contains("<nav class=\"page-nav\">\n<a href=\"api-2.html\" rel=\"next\">"));
+        assert!(!first.contains("rel=\"prev\""));
+
+        let second = page(&sink, "ref/api-2.html");
+        assert!(second.contains("<title>ref/api.md (2/3)</title>"));
+        assert!(!second.contains("chapter-start"));
+        assert!(second.contains("<h2 id=\"c\">C</h2>"));
+        assert!(second.contains("<a href=\"api.html\" rel=\"prev\">Previous</a>"));
+        assert!(second.contains("<a href=\"api-3.html\" rel=\"next\">Next</a>"));
+
+        let third = page(&sink, "ref/api-3.html");
+        assert!(third.contains("<h2 id=\"e\">E</h2>"));
+        assert!(!third.contains("rel=\"next\""));
+
+        assert!(page(&sink, "b.html")
+            .contains("<a href=\"ref/api-2.html#d\">D</a> <a href=\"ref/api.html\">API</a>"));
+        let index = page(&sink, "index.html");
+        assert!(index.contains("<a href=\"ref/api.html\">ref/api.md</a>"));
+        assert!(index.contains("<a href=\"ref/api-3.html#e\">E</a>"));
+    }
+
+    #[cfg(feature = "sanitize")]
+    #[test]
+    fn sanitizes_section_content() {
+        let book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("First")
+                    .add_section(section(
+                        "a.md",
+                        "# A\n\n<script>alert(1)</script>\n\n[x](javascript:alert(1)) [B](b.md)",
+                    ))
+                    .build(),
+            )
+            .build();
+        let books = GlobalizedBooksBuilder::new().add_default_book(book).build();
+
+        let sink = MemorySink::default();
+        let mut html = HtmlRenderer::with_sink(&sink).set_sanitizer(SanitizeProcessor::new());
+        visit(&books, &mut html);
+        html.finish().unwrap();
+
+        let a = page(&sink, "a.html");
+        assert!(!a.contains("<script>"));
+        assert!(!a.contains("javascript:"));
+        assert!(a.contains("<div class=\"chapter-start\">\n<h1 id=\"a\">A</h1>"));
+        assert!(a.contains("<a href=\"b.html\">B</a>"));
+        assert!(render(|r| r)
+            .page("a.html")
+            .contains("<a href=\"b.html#b\">B</a>"));
+    }
+
+    #[test]
+    fn anchor_normalizer_applies_case_and_charset() {
+        assert_eq!(AnchorNormalizer::default().normalize("Mixed-Ü"), "Mixed-Ü");
+        assert_eq!(
+            AnchorNormalizer::new(AnchorCase::Lower).normalize("Mixed-Ü"),
+            "mixed-ü"
+        );
+        assert_eq!(
+            AnchorNormalizer::new(AnchorCase::Upper)
+                .set_ascii_only(true)
+                .normalize("a.b c_ü"),
+            "ABC_"
+        );
+    }
+
+    #[test]
+    fn renders_deeply_nested_sections() {
+        with_small_stack(render_deep_chain);
+    }
+
+    fn render_deep_chain() {
+        const DEPTH: usize = 10_000;
+        let chain = chain_with(DEPTH, |d| section(&format!("{}.md", d), "Text"));
+        let book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("Deep")
+                    .add_section(chain)
+                    .build(),
+            )
+            .build();
+        let mut books = GlobalizedBooksBuilder::new().add_default_book(book).build();
+        let sink = MemorySink::default();
+        let mut html = HtmlRenderer::with_sink(&sink);
+        visit(&books, &mut html);
+        html.finish().unwrap();
+        drop_book_iteratively(books.books.pop().unwrap().1);
+
+        assert_eq!(sink.pages.borrow().len(), DEPTH + 2);
+        assert!(page(&sink, "10000.html").contains("<p>Text</p>"));
+        let index = page(&sink, "index.html");
+        assert!(index.contains(
+            "<li>Deep\n<ul>\n<li><a href=\"0.html\">0.md</a>\n<ul>\n<li><a href=\"1.html\">"
+        ));
+        let closing = format!(
+            "</li>\n{}</ul>\n</li>\n</ul>\n</nav>",
+            "</ul>\n</li>\n".repeat(DEPTH)
+        );
+        assert!(index.contains(&format!("10000.md</a>{}", closing)));
+    }
+}
@@ -0,0 +1,301 @@
+//! This module implements a visitor that exports every page as a separate JSON document, for
+//! headless frontends that render the pages themselves.
+//!
+
+use std::collections::BTreeMap;
+
+use crate::render::html::{markdown_to_html, AnchorNormalizer};
+use crate::render::{json_string, page_url};
+use crate::tree::pages::PageRef;
+use crate::tree::visitor::{VisitContext, Visitor};
+use crate::tree::{GlobalizedBooks, Section};
+
+/// Specifies the format of `PageDoc::content`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum PageContentFormat {
+    /// The content is rendered into HTML the same way as by `HtmlRenderer`.
+    #[default]
+    Html,
+
+    /// The content is the markdown source of the section.
+    Markdown,
+}
+
+/// A link to a page in a [`PageDoc`].
+///
+/// [`PageDoc`]: struct.PageDoc.html
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PageLink {
+    /// Title of the page.
+    pub title: String,
+
+    /// Output path of the page.
+    pub path: String,
+}
+
+/// A page exported as a JSON document by [`JsonPageVisitor`].
+///
+/// [`JsonPageVisitor`]: struct.JsonPageVisitor.html
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PageDoc {
+    /// Title of the page, see `Section::title`.
+    pub title: String,
+
+    /// Output path of the page, produced the same way as by `HtmlRenderer` and prefixed with the
+    /// language directory for books associated with a language.
+    pub path: String,
+
+    /// Format of `content`.
+    pub format: PageContentFormat,
+
+    /// Content of the page.
+    pub content: String,
+
+    /// Names of the chapter and the sections that enclose the page, from the outermost to the
+    /// innermost.
+    pub breadcrumbs: Vec<String>,
+
+    /// The page that precedes this page in reading order.
+    pub prev: Option<PageLink>,
+
+    /// The page that follows this page in reading order.
+    pub next: Option<PageLink>,
+
+    /// Metadata of the section of the page.
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl PageDoc {
+    /// Serialize this document into a JSON object with the fields `title`, `path`, `format`
+    /// (`"html"` or `"markdown"`), `content`, `breadcrumbs`, `neighbors` (an object with the
+    /// fields `prev` and `next`, each a `{"title", "path"}` object or `null`) and `metadata`.
+    pub fn to_json(&self) -> String {
+        let link = |link: &Option<PageLink>| match link {
+            Some(link) => format!(
+                "{{\"title\":{},\"path\":{}}}",
+                json_string(&link.title),
+                json_string(&link.path)
+            ),
+            None => String::from("null"),
+        };
+        let breadcrumbs: Vec<_> = self.breadcrumbs.iter().map(|b| json_string(b)).collect();
+        let metadata: Vec<_> = self
+            .metadata
+            .iter()
+            .map(|(key, value)| format!("{}:{}", json_string(key), json_string(value)))
+            .collect();
+        let format = match self.format {
+            PageContentFormat::Html => "html",
+            PageContentFormat::Markdown => "markdown",
+        };
+        format!(
+            "{{\"title\":{},\"path\":{},\"format\":{},\"content\":{},\"breadcrumbs\":[{}],\
+             \"neighbors\":{{\"prev\":{},\"next\":{}}},\"metadata\":{{{}}}}}",
+            json_string(&self.title),
+            json_string(&self.path),
+            json_string(format),
+            json_string(&self.content),
+            breadcrumbs.join(","),
+            link(&self.prev),
+            link(&self.next),
+            metadata.join(",")
+        )
+    }
+}
+
+/// A visitor that exports every visited section with a content file as a [`PageDoc`].
+///
+/// The neighbors of a page are the previous and next pages of its book in the reading order of
+/// `GlobalizedBooks::flatten`, which the visitor computes when it visits the [`GlobalizedBooks`]
+/// node; sections that are not pages of that order, e.g. because they have subsections, have no
+/// neighbors. Like `HtmlRenderer`, only the first section of every output path is exported.
+///
+/// [`PageDoc`]: struct.PageDoc.html
+/// [`GlobalizedBooks`]: ../../tree/struct.GlobalizedBooks.html
+#[derive(Clone, Debug, Default)]
+pub struct JsonPageVisitor {
+    format: PageContentFormat,
+    flattened: Vec<PageRef>,
+    pages: BTreeMap<String, PageDoc>,
+}
+
+impl JsonPageVisitor {
+    /// Create a new `JsonPageVisitor` instance that renders the content of pages into HTML.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the format of the content of the exported pages.
+    pub fn set_format(mut self, format: PageContentFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Consume this visitor and get the exported pages, keyed by their output paths.
+    pub fn into_pages(self) -> BTreeMap<String, PageDoc> {
+        self.pages
+    }
+
+    /// Get the link to the page at the specified index in the flattened pages, if it belongs to
+    /// the specified language.
+    fn link(&self, index: Option<usize>, language: &str) -> Option<PageLink> {
+        let page = self.flattened.get(index?)?;
+        if page.language != language {
+            return None;
+        }
+        Some(PageLink {
+            title: page.name.clone(),
+            path: output_path(language, &page_url(&page.file)),
+        })
+    }
+}
+
+impl Visitor for JsonPageVisitor {
+    fn visit_globalized_books(&mut self, globalized_books: &GlobalizedBooks) {
+        self.flattened = globalized_books.flatten();
+    }
+
+    fn visit_section_in(&mut self, section: &Section, context: &VisitContext) {
+        if section.file.as_os_str().is_empty() {
+            return;
+        }
+
+        let language = context.language().unwrap_or_default();
+        let path = output_path(language, &page_url(&section.file));
+        if self.pages.contains_key(&path) {
+            return;
+        }
+
+        let index = self
+            .flattened
+            .iter()
+            .position(|p| p.language == language && p.file == section.file);
+        let (prev, next) = match index {
+            Some(index) => (
+                self.link(index.checked_sub(1), language),
+                self.link(Some(index + 1), language),
+            ),
+            None => (None, None),
+        };
+        let content = match self.format {
+            PageContentFormat::Html => {
+                markdown_to_html(section, &AnchorNormalizer::default(), None)
+            }
+            PageContentFormat::Markdown => section.content.clone(),
+        };
+
+        self.pages.insert(
+            path.clone(),
+            PageDoc {
+                title: section.title().to_owned(),
+                path,
+                format: self.format,
+                content,
+                breadcrumbs: context.ancestors().to_vec(),
+                prev,
+                next,
+                metadata: section.metadata.clone(),
+            },
+        );
+    }
+}
+
+/// Prefix the specified page URL with the directory of the specified language.
+fn output_path(language: &str, url: &str) -> String {
+    if language.is_empty() {
+        url.to_owned()
+    } else {
+        format!("{}/{}", language, url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::builder::{
+        BookBuilder, ChapterBuilder, GlobalizedBooksBuilder, SectionBuilder,
+    };
+    use crate::tree::visitor::visit;
+
+    fn section(name: &str, file: &str) -> SectionBuilder {
+        SectionBuilder::new()
+            .set_name(name)
+            .set_file(file)
+            .set_content(format!("# {}", name))
+    }
+
+    fn pages(format: PageContentFormat) -> BTreeMap<String, PageDoc> {
+        let book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("Guide")
+                    .add_section(
+                        section("Basics", "basics.md")
+                            .add_subsection(section("Install", "install.md").build())
+                            .add_subsection(
+                                section("Usage", "usage.md")
+                                    .set_metadata("tags", "cli")
+                                    .build(),
+                            )
+                            .build(),
+                    )
+                    .build(),
+            )
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("Reference")
+                    .add_section(section("Options", "options.md").build())
+                    .build(),
+            )
+            .build();
+        let books = GlobalizedBooksBuilder::new()
+            .add_localized_book(String::from("en"), book)
+            .build();
+        let mut visitor = JsonPageVisitor::new().set_format(format);
+        visit(&books, &mut visitor);
+        visitor.into_pages()
+    }
+
+    #[test]
+    fn exports_breadcrumbs_and_neighbors() {
+        let pages = pages(PageContentFormat::Html);
+        let keys: Vec<_> = pages.keys().map(String::as_str).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "en/basics.html",
+                "en/install.html",
+                "en/options.html",
+                "en/usage.html"
+            ]
+        );
+
+        let usage = &pages["en/usage.html"];
+        assert_eq!(usage.breadcrumbs, vec!["Guide", "Basics"]);
+        assert_eq!(usage.content, "<h1 id=\"usage\">Usage</h1>\n");
+        assert_eq!(
+            usage.to_json(),
+            "{\"title\":\"Usage\",\"path\":\"en/usage.html\",\"format\":\"html\",\
+             \"content\":\"<h1 id=\\\"usage\\\">Usage</h1>\\n\",\"breadcrumbs\":[\"Guide\",\"Basics\"],\
+             \"neighbors\":{\"prev\":{\"title\":\"Install\",\"path\":\"en/install.html\"},\
+             \"next\":{\"title\":\"Options\",\"path\":\"en/options.html\"}},\
+             \"metadata\":{\"tags\":\"cli\"}}"
+        );
+
+        let install = &pages["en/install.html"];
+        assert_eq!(install.prev, None);
+        assert_eq!(install.next.as_ref().unwrap().path, "en/usage.html");
+        assert_eq!(pages["en/options.html"].next, None);
+        assert_eq!(pages["en/basics.html"].prev, None);
+        assert_eq!(pages["en/basics.html"].breadcrumbs, vec!["Guide"]);
+    }
+
+    #[test]
+    fn exports_markdown() {
+        let pages = pages(PageContentFormat::Markdown);
+        assert_eq!(pages["en/options.html"].content, "# Options");
+        assert!(pages["en/options.html"]
+            .to_json()
+            .contains("\"format\":\"markdown\""));
+    }
+}
@@ -0,0 +1,278 @@
+//! This module protects TeX math in markdown content from the markdown parser.
+//!
+//! Books that are typeset with MathJax or KaTeX write math as `$...$` and `$$...$$`, whose
+//! content the markdown parser would otherwise mangle, e.g. by turning `a_1 + b_1` into emphasis.
+//! The [`MathProtectProcessor`] replaces every math span with a placeholder before the content is
+//! parsed, and puts the spans back into the rendered HTML afterwards.
+//!
+//! [`MathProtectProcessor`]: struct.MathProtectProcessor.html
+//!
+
+use crate::render::escape_html;
+
+/// The character that starts a placeholder, from the Unicode private use area.
+const PLACEHOLDER_START: char = '\u{E000}';
+
+/// The character that ends a placeholder, from the Unicode private use area.
+const PLACEHOLDER_END: char = '\u{E001}';
+
+/// Extracts the TeX math spans of markdown content before markdown parsing.
+///
+/// Display math is delimited by `$$` and may span several lines. Inline math is delimited by `$`
+/// within a line, where the opening `$` must not be followed by whitespace and the closing `$`
+/// must neither be preceded by whitespace nor followed by a digit, so that prices like `$5 and
+/// $10` are not math. An escaped `\$` never delimits math. Math in code spans and fenced code
+/// blocks is left alone.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct MathProtectProcessor;
+
+/// Markdown content whose math spans have been replaced by placeholders by
+/// [`MathProtectProcessor`].
+///
+/// [`MathProtectProcessor`]: struct.MathProtectProcessor.html
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProtectedMath {
+    content: String,
+    spans: Vec<String>,
+}
+
+impl MathProtectProcessor {
+    /// Create a new `MathProtectProcessor` instance.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Replace the math spans of the specified markdown content with placeholders.
+    pub fn protect(&self, content: &str) -> ProtectedMath {
+        let mut protected = ProtectedMath::default();
+        let mut fence: Option<String> = None;
+        let mut display: Option<String> = None;
+        for line in content.split_inclusive('\n') {
+            if display.is_none() {
+                let trimmed = line.trim_start();
+                if let Some(open) = &fence {
+                    if trimmed.starts_with(open.as_str()) {
+                        fence = None;
+                    }
+                    protected.content.push_str(line);
+                    continue;
+                }
+                if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                    let marker = trimmed.chars().next().unwrap_or('`');
+                    let len = trimmed.chars().take_while(|c| *c == marker).count();
+                    fence = Some(marker.to_string().repeat(len));
+                    protected.content.push_str(line);
+                    continue;
+                }
+            }
+            protected.protect_line(line, &mut display);
+        }
+        if let Some(open) = display {
+            // An unterminated display math block is not math.
+            protected.content.push_str(&open);
+        }
+        protected
+    }
+}
+
+impl ProtectedMath {
+    /// Get the content with placeholders, which is passed to the markdown parser.
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// Get the extracted math spans with their delimiters, in order.
+    pub fn spans(&self) -> &[String] {
+        &self.spans
+    }
+
+    /// Put the math spans back into the specified HTML rendered from `content`.
+    ///
+    /// The spans are escaped for HTML and keep their delimiters, so that MathJax and KaTeX find
+    /// them in the text of the page.
+    pub fn restore(&self, html: &str) -> String {
+        let mut output = String::with_capacity(html.len());
+        let mut rest = html;
+        while let Some(start) = rest.find(PLACEHOLDER_START) {
+            output.push_str(&rest[..start]);
+            let after = &rest[start + PLACEHOLDER_START.len_utf8()..];
+            let span = after.find(PLACEHOLDER_END).and_then(|end| {
+                let index: usize = after[..end].parse().ok()?;
+                Some((self.spans.get(index)?, end))
+            });
+            match span {
+                Some((span, end)) => {
+                    output.push_str(&escape_html(span));
+                    rest = &after[end + PLACEHOLDER_END.len_utf8()..];
+                }
+                None => {
+                    output.push(PLACEHOLDER_START);
+                    rest = after;
+                }
+            }
+        }
+        output.push_str(rest);
+        output
+    }
+
+    /// Replace the math spans in the specified line, where `display` holds the text of a display
+    /// math block that started on a previous line.
+    fn protect_line(&mut self, line: &str, display: &mut Option<String>) {
+        let chars: Vec<(usize, char)> = line.char_indices().collect();
+        let mut i = 0;
+        let mut copied = 0;
+        let mut code: Option<usize> = None;
+        while i < chars.len() {
+            let (offset, c) = chars[i];
+            if let Some(open) = display.as_mut() {
+                if c == '$' && next_char(&chars, i) == Some('$') {
+                    open.push_str(&line[copied..offset + 2]);
+                    let span = display.take().unwrap_or_default();
+                    self.push_placeholder(span);
+                    i += 2;
+                    copied = offset + 2;
+                } else {
+                    i += 1;
+                }
+                continue;
+            }
+
+            match c {
+                '\\' => i += 2,
+                '`' => {
+                    let run = chars[i..].iter().take_while(|(_, c)| *c == '`').count();
+                    match code {
+                        Some(open) if open == run => code = None,
+                        None => code = Some(run),
+                        _ => {}
+                    }
+                    i += run;
+                }
+                '$' if code.is_none() && next_char(&chars, i) == Some('$') => {
+                    self.content.push_str(&line[copied..offset]);
+                    copied = offset;
+                    let end = line[offset + 2..]
+                        .find("$$")
+                        .map(|end| offset + 2 + end + 2);
+                    match end {
+                        Some(end) => {
+                            self.push_placeholder(line[offset..end].to_owned());
+                            copied = end;
+                            i = chars
+                                .iter()
+                                .position(|(o, _)| *o >= end)
+                                .unwrap_or(chars.len());
+                        }
+                        None => {
+                            *display = Some(String::new());
+                            i += 2;
+                        }
+                    }
+                }
+                '$' if code.is_none() => match inline_math_end(&chars, i) {
+                    Some(end) => {
+                        let end_offset = chars[end].0 + 1;
+                        self.content.push_str(&line[copied..offset]);
+                        self.push_placeholder(line[offset..end_offset].to_owned());
+                        copied = end_offset;
+                        i = end + 1;
+                    }
+                    None => i += 1,
+                },
+                _ => i += 1,
+            }
+        }
+
+        match display {
+            Some(open) => open.push_str(&line[copied..]),
+            None => self.content.push_str(&line[copied..]),
+        }
+    }
+
+    /// Append a placeholder for the specified math span to the content.
+    fn push_placeholder(&mut self, span: String) {
+        self.content.push(PLACEHOLDER_START);
+        self.content.push_str(&self.spans.len().to_string());
+        self.content.push(PLACEHOLDER_END);
+        self.spans.push(span);
+    }
+}
+
+/// Get the character after the character at the specified index.
+fn next_char(chars: &[(usize, char)], index: usize) -> Option<char> {
+    chars.get(index + 1).map(|(_, c)| *c)
+}
+
+/// Find the index of the `$` that closes the inline math opened by the `$` at the specified
+/// index, if any.
+fn inline_math_end(chars: &[(usize, char)], start: usize) -> Option<usize> {
+    let first = next_char(chars, start)?;
+    if first.is_whitespace() {
+        return None;
+    }
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i].1 {
+            '\\' => i += 2,
+            '\n' => return None,
+            '$' => {
+                let closes = !chars[i - 1].1.is_whitespace()
+                    && !next_char(chars, i).is_some_and(|c| c.is_ascii_digit());
+                return if closes { Some(i) } else { None };
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pulldown_cmark::{html, Parser};
+
+    fn render(content: &str) -> String {
+        let protected = MathProtectProcessor::new().protect(content);
+        let mut output = String::new();
+        html::push_html(&mut output, Parser::new(protected.content()));
+        protected.restore(&output)
+    }
+
+    #[test]
+    fn keeps_underscores_in_inline_math() {
+        assert_eq!(
+            render("Let $a_1 + b_1$ and $x_*$ be *given*."),
+            "<p>Let $a_1 + b_1$ and $x_*$ be <em>given</em>.</p>\n"
+        );
+    }
+
+    #[test]
+    fn protects_display_math() {
+        let protected = MathProtectProcessor::new().protect("$$\na_1 < b_1\n$$\n\n$$x_i$$ _y_\n");
+        assert_eq!(protected.spans(), &["$$\na_1 < b_1\n$$", "$$x_i$$"]);
+        assert_eq!(
+            protected.restore("<p>\u{E000}0\u{E001}</p>"),
+            "<p>$$\na_1 &lt; b_1\n$$</p>"
+        );
+        assert_eq!(render("$$x_i$$ _y_"), "<p>$$x_i$$ <em>y</em></p>\n");
+    }
+
+    #[test]
+    fn ignores_escaped_dollars_prices_and_code() {
+        let processor = MathProtectProcessor::new();
+        for content in &[
+            "\\$a_1\\$ costs",
+            "from $5 to $10",
+            "`$a_1$` code",
+            "```\n$a_1$\n```\n",
+            "$ a$ spaced",
+        ] {
+            assert!(processor.protect(content).spans().is_empty(), "{}", content);
+        }
+        assert_eq!(
+            render("`$x$` and $y_2$"),
+            "<p><code>$x$</code> and $y_2$</p>\n"
+        );
+    }
+}
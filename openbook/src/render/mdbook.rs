@@ -0,0 +1,347 @@
+//! This module implements an exporter that writes the document tree in the on-disk layout of
+//! [mdBook](https://rust-lang.github.io/mdBook/).
+//!
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use toml::value::Table;
+use toml::Value;
+
+use crate::error::{Error, Result};
+use crate::fs::FileSystem;
+use crate::render::path_to_url;
+use crate::tree::visitor::{VisitContext, Visitor};
+use crate::tree::{Book, Section};
+
+/// Name of the directory, under the output directory of a book, that holds the markdown sources.
+pub const MDBOOK_SOURCE_DIR: &str = "src";
+
+/// A visitor that exports the visited books into directories that mdBook can build directly.
+///
+/// For every visited book, a `book.toml` with the title, description, author and language of the
+/// book is written to the output directory, and a `SUMMARY.md` reconstructed from the chapters and
+/// sections of the book is written to the [`MDBOOK_SOURCE_DIR`] directory under it. In
+/// `SUMMARY.md`, the preface becomes a prefix chapter, every chapter becomes a `# Heading` part
+/// title, chapters separated from the previous one are preceded by a `---` separator, and sections
+/// become nested `- [name](path)` list entries. Since mdBook does not support anchors in
+/// `SUMMARY.md`, sections that start at an anchor link to their whole content file.
+///
+/// For every visited section, its content is written to `Section::markdown_file` under the source
+/// directory, since the content of sections in other markups has been converted into markdown;
+/// sections that share a content file are written once. Books associated with a
+/// language are exported into a subdirectory of the output directory named after the language.
+///
+/// Errors are collected during the traversal and reported by `finish`.
+///
+/// [`MDBOOK_SOURCE_DIR`]: constant.MDBOOK_SOURCE_DIR.html
+pub struct MdBookExporter<'a, F: FileSystem> {
+    fs: &'a F,
+    output: PathBuf,
+    source: Option<PathBuf>,
+    written: HashSet<PathBuf>,
+    error: Option<Error>,
+}
+
+impl<'a, F: FileSystem> MdBookExporter<'a, F> {
+    /// Create a new `MdBookExporter` instance that writes into the specified output directory in
+    /// the specified file system.
+    pub fn new<P: Into<PathBuf>>(fs: &'a F, output: P) -> Self {
+        Self {
+            fs,
+            output: output.into(),
+            source: None,
+            written: HashSet::new(),
+            error: None,
+        }
+    }
+
+    /// Consume this exporter and report the first error that occurred during exporting, if any.
+    pub fn finish(self) -> Result<()> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Write the specified content to the specified path, recording the error if it is the first
+    /// one.
+    fn write(&mut self, path: &Path, content: &str) {
+        if let Err(e) = self.fs.write_file(path, content.as_bytes()) {
+            self.error.get_or_insert(e);
+        }
+    }
+}
+
+impl<'a, F: FileSystem> Visitor for MdBookExporter<'a, F> {
+    fn visit_book_in(&mut self, book: &Book, context: &VisitContext) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let output = self.output.join(context.language().unwrap_or_default());
+        let source = output.join(MDBOOK_SOURCE_DIR);
+        self.source = Some(source.clone());
+
+        self.write(&output.join("book.toml"), &render_config(book));
+        self.write(&source.join("SUMMARY.md"), &render_summary(book));
+    }
+
+    fn visit_section(&mut self, section: &Section) {
+        if self.error.is_some() || section.file.as_os_str().is_empty() {
+            return;
+        }
+
+        let path = match &self.source {
+            Some(source) => source.join(section.markdown_file()),
+            None => self
+                .output
+                .join(MDBOOK_SOURCE_DIR)
+                .join(section.markdown_file()),
+        };
+        if !self.written.insert(path.clone()) {
+            return;
+        }
+        self.write(&path, &section.content);
+    }
+}
+
+/// Render the `book.toml` of the specified book.
+fn render_config(book: &Book) -> String {
+    let config = &book.config;
+
+    let mut table = Table::new();
+    if let Some(title) = &config.title {
+        table.insert("title".to_owned(), Value::String(title.clone()));
+    }
+    if let Some(description) = &config.description {
+        table.insert("description".to_owned(), Value::String(description.clone()));
+    }
+    if let Some(author) = &config.author {
+        table.insert(
+            "authors".to_owned(),
+            Value::Array(vec![Value::String(author.clone())]),
+        );
+    }
+    if let Some(language) = &config.language {
+        table.insert("language".to_owned(), Value::String(language.clone()));
+    }
+    table.insert(
+        "src".to_owned(),
+        Value::String(MDBOOK_SOURCE_DIR.to_owned()),
+    );
+
+    let mut root = Table::new();
+    root.insert("book".to_owned(), Value::Table(table));
+    Value::Table(root).to_string()
+}
+
+/// Render the `SUMMARY.md` of the specified book.
+fn render_summary(book: &Book) -> String {
+    let mut output = String::from("# Summary\n\n");
+
+    if !book.preface.file.as_os_str().is_empty() {
+        let name = if book.preface.name.is_empty() {
+            "Introduction"
+        } else {
+            &book.preface.name
+        };
+        output.push_str(&format!(
+            "[{}]({})\n",
+            name,
+            path_to_url(&book.preface.markdown_file())
+        ));
+    }
+
+    for chapter in &book.chapters {
+        if chapter.separated {
+            output.push_str("\n---\n");
+        }
+        output.push_str(&format!("\n# {}\n\n", chapter.name));
+        for s in &chapter.sections {
+            render_summary_entry(s, 0, &mut output);
+        }
+    }
+    output
+}
+
+/// Render the `SUMMARY.md` entry of the specified section and its subsections at the specified
+/// nesting depth.
+fn render_summary_entry(section: &Section, depth: usize, output: &mut String) {
+    // An explicit stack keeps deeply nested sections from overflowing the call stack.
+    let mut stack = vec![(section, depth)];
+    while let Some((s, depth)) = stack.pop() {
+        output.push_str(&format!(
+            "{}- [{}]({})\n",
+            "  ".repeat(depth),
+            s.name,
+            path_to_url(&s.markdown_file())
+        ));
+        stack.extend(s.subsections.iter().rev().map(|s| (s, depth + 1)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::scratch::ScratchFileSystem;
+    use crate::tree::builder::{
+        BookBuilder, BookConfigBuilder, ChapterBuilder, GlobalizedBooksBuilder, SectionBuilder,
+    };
+    use crate::tree::visitor::tests::{chain_with, drop_book_iteratively, with_small_stack};
+    use crate::tree::visitor::visit;
+
+    const OUTPUT: &str = "/openbook-test/mdbook";
+
+    fn guide(title: &str) -> Book {
+        let section = |name: &str, file: &str| {
+            SectionBuilder::new()
+                .set_name(name)
+                .set_file(file)
+                .set_content(format!("{} content", name))
+        };
+        BookBuilder::new()
+            .set_config(
+                BookConfigBuilder::new()
+                    .set_title(title)
+                    .set_author("Ana")
+                    .build(),
+            )
+            .set_preface(section("Welcome", "README.md").build())
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("Basics")
+                    .add_section(
+                        section("Usage", "guide/usage.md")
+                            .add_subsection(
+                                section("Flags", "guide/usage.md")
+                                    .set_anchor("flags")
+                                    .build(),
+                            )
+                            .add_subsection(section("Build", "guide/build.md").build())
+                            .build(),
+                    )
+                    .build(),
+            )
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("Advanced")
+                    .set_separated(true)
+                    .add_section(section("Plugins", "advanced/plugins.md").build())
+                    .build(),
+            )
+            .build()
+    }
+
+    fn files(fs: &ScratchFileSystem) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = fs.walk(OUTPUT).map(|f| f.unwrap()).collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn exports_summary_config_and_sources() {
+        let fs = ScratchFileSystem::new();
+        let mut exporter = MdBookExporter::new(&fs, OUTPUT);
+        visit(&guide("Guide"), &mut exporter);
+        exporter.finish().unwrap();
+
+        let output = Path::new(OUTPUT);
+        assert_eq!(
+            files(&fs),
+            vec![
+                output.join("book.toml"),
+                output.join("src/README.md"),
+                output.join("src/SUMMARY.md"),
+                output.join("src/advanced/plugins.md"),
+                output.join("src/guide/build.md"),
+                output.join("src/guide/usage.md"),
+            ]
+        );
+        assert_eq!(
+            fs.read_file_as_string(output.join("src/SUMMARY.md"))
+                .unwrap(),
+            "# Summary\n\n\
+             [Welcome](README.md)\n\
+             \n# Basics\n\n\
+             - [Usage](guide/usage.md)\n  \
+               - [Flags](guide/usage.md)\n  \
+               - [Build](guide/build.md)\n\
+             \n---\n\
+             \n# Advanced\n\n\
+             - [Plugins](advanced/plugins.md)\n"
+        );
+        assert_eq!(
+            fs.read_file_as_string(output.join("src/guide/usage.md"))
+                .unwrap(),
+            "Usage content"
+        );
+
+        let config: Value = fs
+            .read_file_as_string(output.join("book.toml"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(config["book"]["title"].as_str(), Some("Guide"));
+        assert_eq!(config["book"]["authors"][0].as_str(), Some("Ana"));
+        assert_eq!(config["book"]["src"].as_str(), Some(MDBOOK_SOURCE_DIR));
+    }
+
+    #[test]
+    fn exports_every_language_into_its_own_directory() {
+        let fs = ScratchFileSystem::new();
+        let books = GlobalizedBooksBuilder::new()
+            .add_localized_book("en".to_owned(), guide("Guide"))
+            .add_localized_book("zh".to_owned(), guide("指南"))
+            .build();
+        let mut exporter = MdBookExporter::new(&fs, OUTPUT);
+        visit(&books, &mut exporter);
+        exporter.finish().unwrap();
+
+        let output = Path::new(OUTPUT);
+        for language in &["en", "zh"] {
+            assert!(fs.has_file(output.join(language).join("book.toml")));
+            assert!(fs.has_file(output.join(language).join("src/SUMMARY.md")));
+            assert!(fs.has_file(output.join(language).join("src/guide/build.md")));
+        }
+        assert!(fs
+            .read_file_as_string(output.join("zh/book.toml"))
+            .unwrap()
+            .contains("指南"));
+        assert!(!fs.has_file(output.join("book.toml")));
+    }
+
+    #[test]
+    fn exports_deeply_nested_sections() {
+        with_small_stack(export_deep_chain);
+    }
+
+    fn export_deep_chain() {
+        // The indentation of the summary grows quadratically with the depth.
+        const DEPTH: usize = 2_000;
+        let chain = chain_with(DEPTH, |d| {
+            SectionBuilder::new()
+                .set_name(d.to_string())
+                .set_file(format!("{}.md", d))
+                .build()
+        });
+        let book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("Deep")
+                    .add_section(chain)
+                    .build(),
+            )
+            .build();
+        let summary = render_summary(&book);
+        drop_book_iteratively(book);
+
+        assert!(summary.starts_with("# Summary\n\n\n# Deep\n\n- [0](0.md)\n  - [1](1.md)\n"));
+        let deepest = format!("{}- [{}]({}.md)\n", "  ".repeat(DEPTH), DEPTH, DEPTH);
+        assert!(summary.ends_with(&deepest));
+        assert_eq!(
+            summary.lines().filter(|l| l.contains("- [")).count(),
+            DEPTH + 1
+        );
+    }
+}
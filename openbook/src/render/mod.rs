@@ -0,0 +1,474 @@
+//! This module provides renderers that produce output documents from the OpenBook document tree.
+//!
+//! Renderers are [`Visitor`]s that write their output through the [`FileSystem`] abstraction:
+//!
+//! * `assets` copies the assets of a book into the output directory;
+//! * `dedup` provides an output sink that writes identical pages only once;
+//! * `html` provides a renderer that produces a static HTML site;
+//! * `json` provides a visitor that exports every page as a JSON document for headless frontends;
+//! * `math` protects TeX math in markdown content from the markdown parser;
+//! * `mdbook` provides an exporter that writes the books in the on-disk layout of mdBook;
+//! * `sanitize` provides a sanitizer for the HTML of untrusted books, with the `sanitize` feature;
+//! * `search` provides a visitor that generates a search index for client-side search;
+//! * `sink` defines the destinations that renderers write their pages to;
+//! * `template` loads page templates that include partial templates;
+//! * `toc` provides a visitor that generates the table of contents of a book.
+//!
+//! The `build_outputs` function runs the renderers of all build targets configured for a project.
+//!
+//! [`Visitor`]: ../tree/visitor/trait.Visitor.html
+//! [`FileSystem`]: ../fs/trait.FileSystem.html
+//!
+
+pub mod assets;
+pub mod dedup;
+pub mod html;
+pub mod json;
+pub mod math;
+pub mod mdbook;
+mod paginate;
+#[cfg(feature = "sanitize")]
+pub mod sanitize;
+pub mod search;
+pub mod sink;
+pub mod template;
+pub mod toc;
+
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::fs::FileSystem;
+use crate::render::dedup::DeduplicatingSink;
+use crate::render::html::{AnchorCase, AnchorNormalizer, HtmlRenderer};
+use crate::render::math::MathProtectProcessor;
+use crate::render::mdbook::MdBookExporter;
+#[cfg(feature = "sanitize")]
+use crate::render::sanitize::SanitizeProcessor;
+use crate::render::sink::{FileSystemOutputSink, OutputSink};
+use crate::render::template::TemplateLoader;
+use crate::tree::visitor::visit;
+use crate::tree::{GlobalizedBooks, OutputConfig};
+
+/// The output formats that [`build_outputs`] can build.
+///
+/// [`build_outputs`]: fn.build_outputs.html
+pub const SUPPORTED_OUTPUT_FORMATS: &[&str] = &["html", "mdbook"];
+
+/// Build every build target in the configuration of the specified project.
+///
+/// The targets are taken from `BookConfig::outputs` of the project configuration, and a project
+/// without any target is built as if it had a single `html` target with the default entries. Every
+/// target is rendered into its `OutputConfig::effective_directory` under the root directory of the
+/// project, by the renderer of its `OutputConfig::effective_format`:
+///
+/// * `html`: [`HtmlRenderer`]; the `template` option is the path, relative to the root directory
+///   of the project, to the template of the pages, which may include partial templates in its
+///   directory with `{{> name}}`, see [`TemplateLoader`], `print_page_breaks = true` inserts page
+///   breaks before chapters, `anchor_case` is one of `"preserve"`, `"lower"` and `"upper"`, and
+///   `anchor_ascii_only = true` restricts anchors to ASCII, see [`AnchorNormalizer`],
+///   `math = true` keeps TeX math intact, see [`MathProtectProcessor`], `max_page_headings` is
+///   the number of level 2 headings beyond which sections are split into several pages, and
+///   `sanitize = true` sanitizes the rendered content with a `SanitizeProcessor`, which requires
+///   the `sanitize` feature; `sanitize_tags` and `sanitize_attributes` are comma-separated lists
+///   of tags and attributes to allow in addition to its default allowlist, and
+///   `sanitize_url_schemes` is a comma-separated list that replaces its allowed URL schemes;
+///   `deduplicate = true` writes pages with identical content once and records the copies in a
+///   redirect manifest, see [`DeduplicatingSink`], and `site_url` is the URL of the target in the
+///   deployed site, which the links to the translations of pages are relative to;
+/// * `mdbook`: [`MdBookExporter`].
+///
+/// Returns an error of kind `ErrorKind::Config` naming the target if the format of any target is
+/// not one of the [`SUPPORTED_OUTPUT_FORMATS`], if the `anchor_case` or the `max_page_headings`
+/// of an `html` target is invalid or if an `html` target sets `sanitize = true` without the
+/// `sanitize` feature, in which case nothing is built. Otherwise the targets are built in order,
+/// and the first error that occurs while rendering is returned.
+///
+/// [`HtmlRenderer`]: html/struct.HtmlRenderer.html
+/// [`AnchorNormalizer`]: html/struct.AnchorNormalizer.html
+/// [`DeduplicatingSink`]: dedup/struct.DeduplicatingSink.html
+/// [`TemplateLoader`]: template/struct.TemplateLoader.html
+/// [`MathProtectProcessor`]: math/struct.MathProtectProcessor.html
+/// [`MdBookExporter`]: mdbook/struct.MdBookExporter.html
+/// [`SUPPORTED_OUTPUT_FORMATS`]: constant.SUPPORTED_OUTPUT_FORMATS.html
+pub fn build_outputs<F: FileSystem>(fs: &F, books: &GlobalizedBooks) -> Result<()> {
+    let default = [OutputConfig::new("html")];
+    let outputs = if books.config.outputs.is_empty() {
+        &default[..]
+    } else {
+        &books.config.outputs[..]
+    };
+
+    for output in outputs {
+        let format = output.effective_format();
+        if !SUPPORTED_OUTPUT_FORMATS.contains(&format) {
+            return Err(Error::config(format!(
+                "output \"{}\": unsupported format \"{}\", expected one of {}",
+                output.name,
+                format,
+                SUPPORTED_OUTPUT_FORMATS.join(", ")
+            )));
+        }
+        if format == "html" {
+            anchor_normalizer(output)?;
+            max_page_headings(output)?;
+            sanitize_enabled(output)?;
+        }
+    }
+
+    let root = &books.config.root;
+    for output in outputs {
+        let directory = root.join(output.effective_directory());
+        match output.effective_format() {
+            "html" => {
+                let sink = FileSystemOutputSink::new(fs, directory);
+                if output.options.get("deduplicate").map(String::as_str) == Some("true") {
+                    render_html(fs, books, output, DeduplicatingSink::new(sink))?;
+                } else {
+                    render_html(fs, books, output, sink)?;
+                }
+            }
+            _ => {
+                let mut exporter = MdBookExporter::new(fs, directory);
+                visit(books, &mut exporter);
+                exporter.finish()?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Render the specified books into the specified sink with an [`HtmlRenderer`] configured by the
+/// options of the specified `html` target.
+///
+/// [`HtmlRenderer`]: html/struct.HtmlRenderer.html
+fn render_html<F, S>(fs: &F, books: &GlobalizedBooks, output: &OutputConfig, sink: S) -> Result<()>
+where
+    F: FileSystem,
+    S: OutputSink,
+{
+    let mut renderer = HtmlRenderer::with_sink(sink);
+    if let Some(template) = output.options.get("template") {
+        let path = books.config.root.join(template);
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        renderer = renderer.set_template(TemplateLoader::new(fs, directory).load(&name)?);
+    }
+    if output.options.get("print_page_breaks").map(String::as_str) == Some("true") {
+        renderer = renderer.set_print_page_breaks(true);
+    }
+    renderer = renderer.set_anchor_normalizer(anchor_normalizer(output)?);
+    if let Some(max) = max_page_headings(output)? {
+        renderer = renderer.set_max_page_headings(max);
+    }
+    if output.options.get("math").map(String::as_str) == Some("true") {
+        renderer = renderer.set_math_processor(MathProtectProcessor::new());
+    }
+    if let Some(site_url) = output.options.get("site_url") {
+        renderer = renderer.set_site_url(site_url.as_str());
+    }
+    #[cfg(feature = "sanitize")]
+    if sanitize_enabled(output)? {
+        renderer = renderer.set_sanitizer(sanitizer(output));
+    }
+    visit(books, &mut renderer);
+    renderer.finish()
+}
+
+/// Get the anchor normalizer configured by the options of the specified `html` target.
+fn anchor_normalizer(output: &OutputConfig) -> Result<AnchorNormalizer> {
+    let case = match output.options.get("anchor_case").map(String::as_str) {
+        None | Some("preserve") => AnchorCase::Preserve,
+        Some("lower") => AnchorCase::Lower,
+        Some("upper") => AnchorCase::Upper,
+        Some(case) => {
+            return Err(Error::config(format!(
+                "output \"{}\": invalid anchor_case \"{}\", expected \"preserve\", \"lower\" or \
+                 \"upper\"",
+                output.name, case
+            )))
+        }
+    };
+    let ascii_only = output.options.get("anchor_ascii_only").map(String::as_str) == Some("true");
+    Ok(AnchorNormalizer::new(case).set_ascii_only(ascii_only))
+}
+
+/// Get the `max_page_headings` option of the specified `html` target.
+///
+/// Returns an error of kind `ErrorKind::Config` if it is not a positive integer.
+fn max_page_headings(output: &OutputConfig) -> Result<Option<usize>> {
+    match output.options.get("max_page_headings") {
+        Some(value) => match value.parse::<usize>() {
+            Ok(max) if max > 0 => Ok(Some(max)),
+            _ => Err(Error::config(format!(
+                "output \"{}\": invalid max_page_headings \"{}\", expected a positive integer",
+                output.name, value
+            ))),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Determine whether the specified `html` target sets `sanitize = true`.
+///
+/// Returns an error of kind `ErrorKind::Config` if it does without the `sanitize` feature.
+fn sanitize_enabled(output: &OutputConfig) -> Result<bool> {
+    let enabled = output.options.get("sanitize").map(String::as_str) == Some("true");
+    if enabled && cfg!(not(feature = "sanitize")) {
+        return Err(Error::config(format!(
+            "output \"{}\": sanitize requires the \"sanitize\" feature",
+            output.name
+        )));
+    }
+    Ok(enabled)
+}
+
+/// Get the sanitizer configured by the options of the specified `html` target.
+#[cfg(feature = "sanitize")]
+fn sanitizer(output: &OutputConfig) -> SanitizeProcessor {
+    let list = |key: &str| -> Vec<String> {
+        output
+            .options
+            .get(key)
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let mut sanitizer = SanitizeProcessor::new();
+    for tag in list("sanitize_tags") {
+        sanitizer = sanitizer.add_tag(tag);
+    }
+    for attribute in list("sanitize_attributes") {
+        sanitizer = sanitizer.add_attribute(attribute);
+    }
+    if output.options.contains_key("sanitize_url_schemes") {
+        sanitizer = sanitizer.set_url_schemes(list("sanitize_url_schemes"));
+    }
+    sanitizer
+}
+
+/// Convert the specified relative path to a URL path with forward slash separators.
+pub(crate) fn path_to_url(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Get the URL, relative to the output directory of a book, of the page rendered from the specified
+/// content file.
+///
+/// The extension of the file is replaced by `.html` and characters that are not safe in URL paths
+/// are replaced by `-`.
+pub(crate) fn page_url(file: &Path) -> String {
+    path_to_url(&file.with_extension("html"))
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '/' | '.' | '-' | '_' | '~') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Encode the specified text as a JSON string literal.
+pub(crate) fn json_string(text: &str) -> String {
+    let mut output = String::with_capacity(text.len() + 2);
+    output.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
+
+/// Escape the characters in the specified text that are special in HTML.
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    use crate::fs::scratch::ScratchFileSystem;
+    use crate::tree::builder::{
+        BookBuilder, BookConfigBuilder, ChapterBuilder, GlobalizedBooksBuilder, SectionBuilder,
+    };
+
+    fn books(output: OutputConfig) -> GlobalizedBooks {
+        let book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_file("a.md")
+                            .set_content("<script>x</script>\n\n<u>u</u> [f](ftp://host/f)")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        GlobalizedBooksBuilder::new()
+            .set_config(
+                BookConfigBuilder::new()
+                    .set_root("/openbook-test/render")
+                    .add_output(output)
+                    .build(),
+            )
+            .add_default_book(book)
+            .build()
+    }
+
+    fn html_output(options: &[(&str, &str)]) -> OutputConfig {
+        let mut output = OutputConfig::new("html");
+        output.directory = Some(PathBuf::from("site"));
+        for (key, value) in options {
+            output
+                .options
+                .insert(String::from(*key), String::from(*value));
+        }
+        output
+    }
+
+    #[test]
+    fn deduplicates_identical_localized_pages() {
+        let fs = ScratchFileSystem::new();
+        // Links to translations are absolute, so that they are the same in every copy.
+        let mut books = books(html_output(&[
+            ("deduplicate", "true"),
+            ("site_url", "https://example.com/"),
+        ]));
+        // Untranslated pages are identical only if both books declare the same language.
+        books.books[0].1.config.language = Some(String::from("en"));
+        let book = books.books[0].1.clone();
+        books.books.push((String::from("zh"), book));
+        build_outputs(&fs, &books).unwrap();
+
+        let site = Path::new("/openbook-test/render/site");
+        assert!(fs.has_file(site.join("a.html")));
+        assert!(!fs.has_file(site.join("zh/a.html")));
+        let manifest = fs.read_file_as_string(site.join("dedup.json")).unwrap();
+        assert!(manifest.contains("\"zh/a.html\": \"a.html\""));
+    }
+
+    #[test]
+    fn expands_partials_of_the_template() {
+        let fs = ScratchFileSystem::new();
+        let theme = Path::new("/openbook-test/render/theme");
+        fs.write_file(
+            theme.join("page.html"),
+            b"{{> header}}<main>{{content}}</main>",
+        )
+        .unwrap();
+        fs.write_file(theme.join("header.html"), b"<header>{{title}}</header>")
+            .unwrap();
+        let mut books = books(html_output(&[("template", "theme/page.html")]));
+        books.books[0].1.chapters[0].sections[0].name = String::from("A");
+        build_outputs(&fs, &books).unwrap();
+
+        let page = fs
+            .read_file_as_string("/openbook-test/render/site/a.html")
+            .unwrap();
+        assert!(page.starts_with("<header>A</header><main>"), "{}", page);
+    }
+
+    #[test]
+    fn rejects_invalid_html_options() {
+        let fs = ScratchFileSystem::new();
+        let books = books(html_output(&[("anchor_case", "title")]));
+        let err = build_outputs(&fs, &books).unwrap_err();
+        assert_eq!(err.kind(), crate::error::ErrorKind::Config);
+        assert!(!fs.has_dir("/openbook-test/render/site"));
+    }
+
+    #[cfg(feature = "sanitize")]
+    #[test]
+    fn sanitizes_with_configured_allowlist() {
+        let fs = ScratchFileSystem::new();
+        let books = books(html_output(&[
+            ("sanitize", "true"),
+            ("sanitize_tags", "u"),
+            ("sanitize_url_schemes", "https, ftp"),
+        ]));
+        build_outputs(&fs, &books).unwrap();
+        let page = fs
+            .read_file_as_string("/openbook-test/render/site/a.html")
+            .unwrap();
+        assert!(!page.contains("<script>"));
+        assert!(page.contains("<u>u</u>"));
+        assert!(page.contains("<a href=\"ftp://host/f\">f</a>"));
+    }
+
+    #[test]
+    fn builds_every_target_into_its_directory() {
+        let fs = ScratchFileSystem::new();
+        let mut mdbook = OutputConfig::new("export");
+        mdbook.format = Some(String::from("mdbook"));
+        let mut books = books(html_output(&[]));
+        books.config.outputs.push(mdbook);
+        build_outputs(&fs, &books).unwrap();
+
+        let root = Path::new("/openbook-test/render");
+        assert!(fs.has_file(root.join("site/a.html")));
+        assert!(fs.has_file(root.join("book/export/book.toml")));
+        assert!(fs.has_file(root.join("book/export/src/SUMMARY.md")));
+        assert!(fs.has_file(root.join("book/export/src/a.md")));
+    }
+
+    #[test]
+    fn builds_a_default_html_target() {
+        let fs = ScratchFileSystem::new();
+        let mut books = books(html_output(&[]));
+        books.config.outputs.clear();
+        build_outputs(&fs, &books).unwrap();
+        assert!(fs.has_file("/openbook-test/render/book/html/a.html"));
+    }
+
+    #[test]
+    fn rejects_unsupported_formats_before_building() {
+        let fs = ScratchFileSystem::new();
+        let mut print = OutputConfig::new("print");
+        print.format = Some(String::from("pdf"));
+        let mut books = books(html_output(&[]));
+        books.config.outputs.push(print);
+
+        let err = build_outputs(&fs, &books).unwrap_err();
+        assert_eq!(err.kind(), crate::error::ErrorKind::Config);
+        assert!(
+            err.to_string()
+                .contains("output \"print\": unsupported format \"pdf\""),
+            "{}",
+            err
+        );
+        assert!(!fs.has_dir("/openbook-test/render/site"));
+    }
+}
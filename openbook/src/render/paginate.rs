@@ -0,0 +1,110 @@
+//! This module splits the content of long sections into several pages at their level 2 headings.
+//!
+
+use std::ops::Range;
+use std::path::Path;
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+use crate::render::page_url;
+
+/// A page of the content of a section split by [`paginate`].
+///
+/// [`paginate`]: fn.paginate.html
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct SubPage {
+    /// Byte range of the page in the content.
+    pub(crate) content: Range<usize>,
+
+    /// Range of the indices of the headings of the page in `Section::headings`.
+    pub(crate) headings: Range<usize>,
+}
+
+/// Split the specified markdown content into pages that have at most the specified number of
+/// level 2 headings each.
+///
+/// Every page but the first starts at a level 2 heading, and the first page also contains the
+/// content before the first level 2 heading. Content with at most `max_headings` level 2 headings
+/// is a single page. A limit of 0 is treated as 1.
+pub(crate) fn paginate(content: &str, max_headings: usize) -> Vec<SubPage> {
+    let max_headings = max_headings.max(1);
+
+    // The byte offset and the heading index of every level 2 heading.
+    let mut sections = Vec::new();
+    let mut headings = 0;
+    for (event, span) in Parser::new(content).into_offset_iter() {
+        if let Event::Start(Tag::Heading(level)) = event {
+            if level == 2 {
+                sections.push((span.start, headings));
+            }
+            headings += 1;
+        }
+    }
+
+    let mut starts: Vec<(usize, usize)> = vec![(0, 0)];
+    starts.extend(sections.iter().skip(max_headings).step_by(max_headings));
+    let mut pages = Vec::with_capacity(starts.len());
+    for (i, (offset, heading)) in starts.iter().enumerate() {
+        let (end, end_heading) = starts
+            .get(i + 1)
+            .copied()
+            .unwrap_or((content.len(), headings));
+        pages.push(SubPage {
+            content: *offset..end,
+            headings: *heading..end_heading,
+        });
+    }
+    pages
+}
+
+/// Get the URL of the page with the specified index of the content file at the specified path.
+///
+/// The first page has the URL of the content file as given by `page_url`, and the page at index
+/// `i` has `-{i + 1}` appended to the file stem, like `reference-2.html`.
+pub(crate) fn sub_page_url(file: &Path, index: usize) -> String {
+    if index == 0 {
+        return page_url(file);
+    }
+    let stem = file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    page_url(&file.with_file_name(format!("{}-{}.md", stem, index + 1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_at_level_2_headings() {
+        let content = "# Top\n\nIntro\n\n## A\n\n### A1\n\n## B\n\n## C\n\n## D\n\n## E\n";
+        let pages = paginate(content, 2);
+        let texts: Vec<_> = pages.iter().map(|p| &content[p.content.clone()]).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "# Top\n\nIntro\n\n## A\n\n### A1\n\n## B\n\n",
+                "## C\n\n## D\n\n",
+                "## E\n"
+            ]
+        );
+        let headings: Vec<_> = pages.iter().map(|p| p.headings.clone()).collect();
+        assert_eq!(headings, vec![0..4, 4..6, 6..7]);
+
+        assert_eq!(paginate(content, 5).len(), 1);
+        assert_eq!(
+            paginate("", 1),
+            vec![SubPage {
+                content: 0..0,
+                headings: 0..0
+            }]
+        );
+    }
+
+    #[test]
+    fn numbers_sub_page_urls() {
+        assert_eq!(sub_page_url(Path::new("ref/api.md"), 0), "ref/api.html");
+        assert_eq!(sub_page_url(Path::new("ref/api.md"), 2), "ref/api-3.html");
+    }
+}
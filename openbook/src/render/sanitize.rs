@@ -0,0 +1,120 @@
+//! This module implements a sanitizer for the HTML rendered from untrusted book sources.
+//!
+//! This module is only available with the `sanitize` feature.
+//!
+
+use std::collections::BTreeSet;
+
+/// The URL schemes that [`SanitizeProcessor`] allows by default.
+///
+/// [`SanitizeProcessor`]: struct.SanitizeProcessor.html
+pub const DEFAULT_URL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Removes disallowed markup from rendered HTML.
+///
+/// The sanitizer keeps the tags and attributes of the default allowlist of `ammonia`, which covers
+/// the markup produced from markdown, together with the `id` attribute that `HtmlRenderer` gives
+/// headings. Every other tag is removed; the content of `<script>` and `<style>` elements is
+/// dropped with them, and the content of other removed elements is kept as text. Links and images
+/// keep their URLs only if the URL is relative or uses one of the allowed schemes, which are the
+/// [`DEFAULT_URL_SCHEMES`] unless set otherwise; `javascript:` URLs are therefore removed. The
+/// allowlist is extended with `add_tag` and `add_attribute`.
+///
+/// Set the sanitizer of an `HtmlRenderer` with `HtmlRenderer::set_sanitizer`; renderers without a
+/// sanitizer write the rendered HTML unchanged.
+///
+/// [`DEFAULT_URL_SCHEMES`]: constant.DEFAULT_URL_SCHEMES.html
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SanitizeProcessor {
+    tags: BTreeSet<String>,
+    attributes: BTreeSet<String>,
+    url_schemes: BTreeSet<String>,
+}
+
+impl SanitizeProcessor {
+    /// Create a new `SanitizeProcessor` instance with the default allowlist.
+    pub fn new() -> Self {
+        Self {
+            tags: BTreeSet::new(),
+            attributes: std::iter::once(String::from("id")).collect(),
+            url_schemes: DEFAULT_URL_SCHEMES
+                .iter()
+                .map(|s| String::from(*s))
+                .collect(),
+        }
+    }
+
+    /// Allow the specified tag in addition to the default allowlist.
+    pub fn add_tag<T: Into<String>>(mut self, tag: T) -> Self {
+        self.tags.insert(tag.into());
+        self
+    }
+
+    /// Allow the specified attribute on every allowed tag.
+    pub fn add_attribute<T: Into<String>>(mut self, attribute: T) -> Self {
+        self.attributes.insert(attribute.into());
+        self
+    }
+
+    /// Set the URL schemes that links and images may use, replacing the allowed schemes.
+    pub fn set_url_schemes<I, T>(mut self, schemes: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.url_schemes = schemes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sanitize the specified HTML fragment.
+    pub fn sanitize(&self, html: &str) -> String {
+        let mut builder = ammonia::Builder::default();
+        builder
+            .add_tags(self.tags.iter().map(String::as_str))
+            .add_generic_attributes(self.attributes.iter().map(String::as_str))
+            .url_schemes(self.url_schemes.iter().map(String::as_str).collect())
+            .link_rel(None);
+        builder.clean(html).to_string()
+    }
+}
+
+impl Default for SanitizeProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_scripts_and_dangerous_links() {
+        let html = SanitizeProcessor::new().sanitize(
+            "<h1 id=\"a\">A</h1>\n<script>alert(1)</script>\
+             <p><a href=\"javascript:alert(1)\">x</a> <a href=\"b.html#b\">B</a> \
+             <a href=\"https://example.com/\">site</a> <em>kept</em></p>",
+        );
+        assert_eq!(
+            html,
+            "<h1 id=\"a\">A</h1>\n<p><a>x</a> <a href=\"b.html#b\">B</a> \
+             <a href=\"https://example.com/\">site</a> <em>kept</em></p>"
+        );
+    }
+
+    #[test]
+    fn extends_the_allowlist() {
+        let sanitizer = SanitizeProcessor::new()
+            .add_tag("custom")
+            .add_attribute("data-x")
+            .set_url_schemes(vec!["https", "ftp"]);
+        assert_eq!(
+            sanitizer.sanitize("<custom data-x=\"1\">c</custom><blink>b</blink>"),
+            "<custom data-x=\"1\">c</custom>b"
+        );
+        assert_eq!(
+            sanitizer.sanitize("<a href=\"ftp://host/f\">f</a><a href=\"mailto:a@b\">m</a>"),
+            "<a href=\"ftp://host/f\">f</a><a>m</a>"
+        );
+    }
+}
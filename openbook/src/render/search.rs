@@ -0,0 +1,376 @@
+//! This module implements a visitor that generates a search index for client-side search.
+//!
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+use crate::render::{json_string, page_url};
+use crate::tree::visitor::{VisitContext, Visitor};
+use crate::tree::Section;
+
+/// The maximum length of `SearchDocument::body`, in characters, used when no limit is set.
+pub const DEFAULT_MAX_BODY_LENGTH: usize = 4096;
+
+/// A searchable document in a [`SearchIndex`], produced from a single section.
+///
+/// [`SearchIndex`]: struct.SearchIndex.html
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SearchDocument {
+    /// Identifier of the document, which is its position in the index.
+    pub id: usize,
+
+    /// Title of the document, i.e. the name of the section.
+    pub title: String,
+
+    /// Link to the page of the section.
+    pub href: String,
+
+    /// Plain text of the section's content.
+    pub body: String,
+}
+
+/// A search index, which is a list of searchable documents.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SearchIndex {
+    /// Documents in the index.
+    pub documents: Vec<SearchDocument>,
+}
+
+impl SearchIndex {
+    /// Serialize this index into a JSON array of objects with the fields `id`, `title`, `href` and
+    /// `body`, which can be fed to client-side search libraries such as lunr.js.
+    pub fn to_json(&self) -> String {
+        let mut output = String::from("[");
+        for (i, doc) in self.documents.iter().enumerate() {
+            if i > 0 {
+                output.push(',');
+            }
+            output.push_str(&format!(
+                "{{\"id\":{},\"title\":{},\"href\":{},\"body\":{}}}",
+                doc.id,
+                json_string(&doc.title),
+                json_string(&doc.href),
+                json_string(&doc.body)
+            ));
+        }
+        output.push(']');
+        output
+    }
+}
+
+/// Specifies how [`SearchIndexVisitor`] divides the content of a section into documents.
+///
+/// [`SearchIndexVisitor`]: struct.SearchIndexVisitor.html
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum SplitGranularity {
+    /// Every section is a single document, titled after the section.
+    #[default]
+    Section,
+
+    /// Every heading starts a new document, titled after the heading and linked to its anchor.
+    /// The content before the first heading is a document titled after the section.
+    Heading,
+
+    /// Every top-level block of the content other than headings and code blocks, like a
+    /// paragraph, a list or a table, is a document. Documents are titled after and linked to the
+    /// nearest preceding heading, or to the section if there is none.
+    Paragraph,
+}
+
+/// A visitor that builds a [`SearchIndex`] from the visited sections.
+///
+/// Every visited section with a content file is divided into documents according to the split
+/// granularity, see [`SplitGranularity`]; by default, the section becomes a single document
+/// titled after `Section::name`. The body of a document is the plain text of its content, without
+/// markup and code blocks, truncated to the maximum body length. Documents whose body is empty
+/// are skipped. The link of a document is produced the same way as by `HtmlRenderer`, followed by
+/// the anchor of the section or of the heading of the document, and is prefixed with the language
+/// directory for books associated with a language.
+///
+/// [`SearchIndex`]: struct.SearchIndex.html
+/// [`SplitGranularity`]: enum.SplitGranularity.html
+#[derive(Clone, Debug)]
+pub struct SearchIndexVisitor {
+    max_body_length: usize,
+    granularity: SplitGranularity,
+    index: SearchIndex,
+}
+
+impl SearchIndexVisitor {
+    /// Create a new `SearchIndexVisitor` instance that truncates bodies to
+    /// [`DEFAULT_MAX_BODY_LENGTH`].
+    ///
+    /// [`DEFAULT_MAX_BODY_LENGTH`]: constant.DEFAULT_MAX_BODY_LENGTH.html
+    pub fn new() -> Self {
+        Self {
+            max_body_length: DEFAULT_MAX_BODY_LENGTH,
+            granularity: SplitGranularity::default(),
+            index: SearchIndex::default(),
+        }
+    }
+
+    /// Set the maximum length of document bodies, in characters.
+    pub fn set_max_body_length(mut self, max_body_length: usize) -> Self {
+        self.max_body_length = max_body_length;
+        self
+    }
+
+    /// Set how the content of every section is divided into documents.
+    pub fn set_granularity(mut self, granularity: SplitGranularity) -> Self {
+        self.granularity = granularity;
+        self
+    }
+
+    /// Consume this visitor and get the generated search index.
+    pub fn into_index(self) -> SearchIndex {
+        self.index
+    }
+}
+
+impl Default for SearchIndexVisitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Visitor for SearchIndexVisitor {
+    fn visit_section_in(&mut self, section: &Section, context: &VisitContext) {
+        if section.file.as_os_str().is_empty() {
+            return;
+        }
+
+        let mut page = page_url(&section.file);
+        match context.language() {
+            Some(language) if !language.is_empty() => page = format!("{}/{}", language, page),
+            _ => {}
+        }
+
+        let headings = section.headings();
+        for unit in split_content(&section.content, self.granularity) {
+            let body = match unit.text.char_indices().nth(self.max_body_length) {
+                Some((end, _)) => unit.text[..end].trim_end().to_owned(),
+                None => unit.text,
+            };
+            if body.is_empty() {
+                continue;
+            }
+
+            let heading = unit.heading.and_then(|i| headings.get(i));
+            let (title, anchor) = match heading {
+                Some(heading) => (heading.text.clone(), Some(&heading.slug)),
+                None => (section.name.clone(), section.anchor.as_ref()),
+            };
+            let href = match anchor {
+                Some(anchor) => format!("{}#{}", page, anchor),
+                None => page.clone(),
+            };
+            self.index.documents.push(SearchDocument {
+                id: self.index.documents.len(),
+                title,
+                href,
+                body,
+            });
+        }
+    }
+}
+
+/// A part of the content of a section that becomes a search document.
+struct ContentUnit {
+    /// Index of the heading that the unit belongs to in `Section::headings`.
+    heading: Option<usize>,
+
+    /// Plain text of the unit, with runs of whitespace collapsed into a single space.
+    text: String,
+}
+
+/// Divide the specified markdown content into units of the specified granularity and get their
+/// plain text, without markup and code blocks.
+fn split_content(content: &str, granularity: SplitGranularity) -> Vec<ContentUnit> {
+    let mut units = Vec::new();
+    let mut text = String::new();
+    let mut heading = None;
+    let mut headings = 0;
+    let mut depth = 0;
+    let mut in_code_block = false;
+    let mut in_heading = false;
+
+    let mut flush = |text: &mut String, heading: Option<usize>| {
+        let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        text.clear();
+        units.push(ContentUnit {
+            heading,
+            text: collapsed,
+        });
+    };
+
+    for event in Parser::new(content) {
+        match event {
+            Event::Start(Tag::Heading(_)) => {
+                if granularity == SplitGranularity::Heading {
+                    flush(&mut text, heading);
+                }
+                heading = Some(headings);
+                headings += 1;
+                in_heading = true;
+            }
+            Event::End(Tag::Heading(_)) => {
+                in_heading = false;
+                if granularity == SplitGranularity::Section {
+                    text.push(' ');
+                }
+            }
+            _ if in_heading && granularity != SplitGranularity::Section => {}
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+            Event::Text(s) | Event::Code(s) if !in_code_block => text.push_str(&s),
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            // Inline markup does not separate words, but the end of a block does.
+            Event::Start(Tag::Emphasis)
+            | Event::Start(Tag::Strong)
+            | Event::Start(Tag::Strikethrough)
+            | Event::Start(Tag::Link(..))
+            | Event::Start(Tag::Image(..)) => {}
+            Event::End(Tag::Emphasis)
+            | Event::End(Tag::Strong)
+            | Event::End(Tag::Strikethrough)
+            | Event::End(Tag::Link(..))
+            | Event::End(Tag::Image(..)) => {}
+            Event::Start(_) => depth += 1,
+            Event::End(_) => {
+                text.push(' ');
+                depth -= 1;
+                if depth == 0 && granularity == SplitGranularity::Paragraph {
+                    flush(&mut text, heading);
+                }
+            }
+            _ => {}
+        }
+    }
+    flush(&mut text, heading);
+    if granularity == SplitGranularity::Section {
+        units.iter_mut().for_each(|unit| unit.heading = None);
+    }
+    units
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::builder::{
+        BookBuilder, ChapterBuilder, GlobalizedBooksBuilder, SectionBuilder,
+    };
+    use crate::tree::visitor::visit;
+
+    const CONTENT: &str = "Intro text.\n\n# Setup\n\nFirst *step*.\n\n```\ncode\n```\n\n\
+                           - one\n- two\n\n## Usage Notes\n\nRun it.\n\nDone.\n";
+
+    fn index(granularity: SplitGranularity) -> SearchIndex {
+        let book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_name("Guide")
+                            .set_file("guide.md")
+                            .set_content(CONTENT)
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let books = GlobalizedBooksBuilder::new()
+            .add_localized_book(String::from("en"), book)
+            .build();
+        let mut visitor = SearchIndexVisitor::new().set_granularity(granularity);
+        visit(&books, &mut visitor);
+        visitor.into_index()
+    }
+
+    fn records(index: &SearchIndex) -> Vec<(&str, &str, &str)> {
+        index
+            .documents
+            .iter()
+            .map(|d| (d.title.as_str(), d.href.as_str(), d.body.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn section_granularity_indexes_whole_sections() {
+        let index = index(SplitGranularity::Section);
+        assert_eq!(
+            records(&index),
+            vec![(
+                "Guide",
+                "en/guide.html",
+                "Intro text. Setup First step. one two Usage Notes Run it. Done."
+            )]
+        );
+    }
+
+    #[test]
+    fn heading_granularity_splits_at_headings() {
+        let index = index(SplitGranularity::Heading);
+        assert_eq!(
+            records(&index),
+            vec![
+                ("Guide", "en/guide.html", "Intro text."),
+                ("Setup", "en/guide.html#setup", "First step. one two"),
+                ("Usage Notes", "en/guide.html#usage-notes", "Run it. Done."),
+            ]
+        );
+    }
+
+    #[test]
+    fn paragraph_granularity_keeps_the_nearest_heading() {
+        let index = index(SplitGranularity::Paragraph);
+        assert_eq!(
+            records(&index),
+            vec![
+                ("Guide", "en/guide.html", "Intro text."),
+                ("Setup", "en/guide.html#setup", "First step."),
+                ("Setup", "en/guide.html#setup", "one two"),
+                ("Usage Notes", "en/guide.html#usage-notes", "Run it."),
+                ("Usage Notes", "en/guide.html#usage-notes", "Done."),
+            ]
+        );
+        let ids: Vec<_> = index.documents.iter().map(|d| d.id).collect();
+        assert_eq!(ids, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn indexes_every_section_with_a_file() {
+        let section = |name: &str, file: &str, content: &str| {
+            SectionBuilder::new()
+                .set_name(name)
+                .set_file(file)
+                .set_content(content)
+                .build()
+        };
+        let book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .add_section(section("Draft", "", "Not written yet."))
+                    .add_section(section("Ünïcödé", "a.md", "Ünïcödé **bödy** text"))
+                    .add_section(section("B", "guide/b.md", "Say \"hi\"\n\n<b>bold</b>"))
+                    .build(),
+            )
+            .build();
+        let books = GlobalizedBooksBuilder::new().add_default_book(book).build();
+        let mut visitor = SearchIndexVisitor::new().set_max_body_length(5);
+        visit(&books, &mut visitor);
+        let index = visitor.into_index();
+
+        assert_eq!(
+            records(&index),
+            vec![
+                ("Ünïcödé", "a.html", "Ünïcö"),
+                ("B", "guide/b.html", "Say \""),
+            ]
+        );
+        assert_eq!(
+            index.to_json(),
+            "[{\"id\":0,\"title\":\"Ünïcödé\",\"href\":\"a.html\",\"body\":\"Ünïcö\"},\
+             {\"id\":1,\"title\":\"B\",\"href\":\"guide/b.html\",\"body\":\"Say \\\"\"}]"
+        );
+        assert_eq!(SearchIndex::default().to_json(), "[]");
+    }
+}
@@ -0,0 +1,129 @@
+//! This module defines where renderers write their output.
+//!
+//! Renderers write the pages they produce to an [`OutputSink`] rather than to a [`FileSystem`]
+//! directly, which separates where the output goes from where the sources come from. Pages are
+//! addressed by paths relative to the root of the output, so that a sink can store them in places
+//! without directory semantics, such as an object store. The [`FileSystemOutputSink`] writes pages
+//! into a directory of any [`FileSystem`].
+//!
+//! [`OutputSink`]: trait.OutputSink.html
+//! [`FileSystem`]: ../../fs/trait.FileSystem.html
+//! [`FileSystemOutputSink`]: struct.FileSystemOutputSink.html
+//!
+
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+use crate::fs::FileSystem;
+
+/// A destination of rendered pages.
+pub trait OutputSink {
+    /// Write the specified page to the specified path, relative to the root of the output,
+    /// replacing the page if it has been written before.
+    fn write_page(&self, path: &Path, bytes: &[u8]) -> Result<()>;
+
+    /// Complete the output after all pages have been written.
+    ///
+    /// Sinks that buffer or upload pages should flush them here. Renderers call this function once
+    /// from their own `finish` function.
+    fn finish(&self) -> Result<()>;
+}
+
+impl<S: OutputSink + ?Sized> OutputSink for &S {
+    fn write_page(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        (**self).write_page(path, bytes)
+    }
+
+    fn finish(&self) -> Result<()> {
+        (**self).finish()
+    }
+}
+
+/// An [`OutputSink`] that writes pages into a directory of a [`FileSystem`].
+///
+/// Every page is written to its path joined with the output directory, and missing parent
+/// directories are created as needed. `finish` does nothing.
+///
+/// [`OutputSink`]: trait.OutputSink.html
+/// [`FileSystem`]: ../../fs/trait.FileSystem.html
+pub struct FileSystemOutputSink<'a, F: FileSystem> {
+    fs: &'a F,
+    output: PathBuf,
+}
+
+impl<'a, F: FileSystem> FileSystemOutputSink<'a, F> {
+    /// Create a new `FileSystemOutputSink` instance that writes into the specified output
+    /// directory in the specified file system.
+    pub fn new<P: Into<PathBuf>>(fs: &'a F, output: P) -> Self {
+        Self {
+            fs,
+            output: output.into(),
+        }
+    }
+
+    /// Get the output directory.
+    pub fn output(&self) -> &Path {
+        &self.output
+    }
+}
+
+impl<'a, F: FileSystem> OutputSink for FileSystemOutputSink<'a, F> {
+    fn write_page(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        self.fs.write_file(self.output.join(path), bytes)
+    }
+
+    fn finish(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+
+    use crate::fs::scratch::ScratchFileSystem;
+
+    /// An [`OutputSink`] that keeps the written pages in memory.
+    #[derive(Default)]
+    pub(crate) struct MemorySink {
+        pub(crate) pages: RefCell<BTreeMap<PathBuf, Vec<u8>>>,
+        pub(crate) finished: RefCell<bool>,
+    }
+
+    impl MemorySink {
+        /// Get the content of the page at the specified path.
+        pub(crate) fn page(&self, path: &str) -> String {
+            String::from_utf8(self.pages.borrow()[Path::new(path)].clone()).unwrap()
+        }
+    }
+
+    impl OutputSink for MemorySink {
+        fn write_page(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+            self.pages
+                .borrow_mut()
+                .insert(path.to_path_buf(), bytes.to_vec());
+            Ok(())
+        }
+
+        fn finish(&self) -> Result<()> {
+            *self.finished.borrow_mut() = true;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn file_system_sink_writes_under_the_output_directory() {
+        let fs = ScratchFileSystem::new();
+        let sink = FileSystemOutputSink::new(&fs, "/openbook-test/sink");
+        sink.write_page(Path::new("en/a/b.html"), b"page").unwrap();
+        sink.finish().unwrap();
+        assert_eq!(
+            fs.read_file_as_string("/openbook-test/sink/en/a/b.html")
+                .unwrap(),
+            "page"
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//! This module loads page templates that include partial templates through the `FileSystem`
+//! abstraction.
+//!
+//! A template includes a partial template with a `{{> name}}` tag, which is replaced with the
+//! content of the partial, itself expanded in the same way:
+//!
+//! ```html
+//! <!DOCTYPE html>
+//! <html lang="{{lang}}"{{dir}}>
+//! {{> head}}
+//! <body>
+//! {{> header}}
+//! {{content}}
+//! </body>
+//! </html>
+//! ```
+//!
+//! Other `{{...}}` tags are left to the renderer that fills the template.
+//!
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::error::{Error, Result};
+use crate::fs::FileSystem;
+
+/// The opening delimiter of a partial tag.
+const PARTIAL_START: &str = "{{>";
+
+/// The closing delimiter of a partial tag.
+const PARTIAL_END: &str = "}}";
+
+/// The extension of the file of a template whose name does not have one.
+pub const DEFAULT_TEMPLATE_EXTENSION: &str = "html";
+
+/// A segment of a parsed template.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Segment {
+    /// Text that is copied as-is.
+    Text(String),
+
+    /// A `{{> name}}` tag, with the name of the partial.
+    Partial(String),
+}
+
+/// Loads templates and the partial templates they include from a directory in a [`FileSystem`].
+///  The template named `name` is read from `name` under the directory of the loader, with
+/// [`DEFAULT_TEMPLATE_EXTENSION`] appended if `name` does not have an extension, so `{{> header}}`
+/// includes `header.html` and `{{> partials/nav.html}}` includes `partials/nav.html`. Every
+/// template is read and parsed at most once per loader; later inclusions reuse the parsed template.
+///
+/// Loading a template returns an error of kind `ErrorKind::NotFound` naming the including template
+/// if a partial does not exist, and an error of kind `ErrorKind::Parse` if a partial tag is not
+/// closed or if templates include one another in a cycle, which the error lists.
+///
+/// [`FileSystem`]: ../../fs/trait.FileSystem.html
+/// [`DEFAULT_TEMPLATE_EXTENSION`]: constant.DEFAULT_TEMPLATE_EXTENSION.html
+pub struct TemplateLoader<'a, F: FileSystem> {
+    fs: &'a F,
+    directory: PathBuf,
+    cache: HashMap<String, Rc<[Segment]>>,
+}
+
+impl<'a, F: FileSystem> TemplateLoader<'a, F> {
+    /// Create a new `TemplateLoader` instance that loads templates from the specified directory in
+    /// the specified file system.
+    pub fn new<P: Into<PathBuf>>(fs: &'a F, directory: P) -> Self {
+        Self {
+            fs,
+            directory: directory.into(),
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Get the directory that templates are loaded from.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Load the template with the specified name and expand the partials it includes.
+    pub fn load(&mut self, name: &str) -> Result<String> {
+        let template = self.parsed(name, None)?;
+        self.expand_parsed(name, template)
+    }
+
+    /// Expand the partials included by the specified template source, which is not loaded from a
+    /// file.
+    pub fn expand(&mut self, source: &str) -> Result<String> {
+        let template = parse_template("template", source)?;
+        self.expand_parsed("", template.into())
+    }
+
+    /// Expand the specified parsed template with the specified name.
+    fn expand_parsed(&mut self, name: &str, template: Rc<[Segment]>) -> Result<String> {
+        let mut output = String::new();
+
+        // The templates being expanded with the index of their next segment, from the outermost.
+        let mut stack = vec![(name.to_owned(), template, 0)];
+        while let Some((_, template, index)) = stack.last_mut() {
+            let segment = match template.get(*index) {
+                Some(segment) => segment.clone(),
+                None => {
+                    stack.pop();
+                    continue;
+                }
+            };
+            *index += 1;
+
+            match segment {
+                Segment::Text(text) => output.push_str(&text),
+                Segment::Partial(partial) => {
+                    if let Some(start) = stack.iter().position(|(n, _, _)| *n == partial) {
+                        let mut cycle: Vec<&str> =
+                            stack[start..].iter().map(|(n, _, _)| n.as_str()).collect();
+                        cycle.push(&partial);
+                        return Err(Error::parse(format!(
+                            "templates include one another in a cycle: {}",
+                            cycle.join(" -> ")
+                        )));
+                    }
+                    let includer = stack.last().map(|(n, _, _)| n.clone()).unwrap_or_default();
+                    let template = self.parsed(&partial, Some(&includer))?;
+                    stack.push((partial, template, 0));
+                }
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Get the parsed template with the specified name, reading it if it is not cached.
+    ///
+    /// `includer` is the name of the template that includes it, for error messages.
+    fn parsed(&mut self, name: &str, includer: Option<&str>) -> Result<Rc<[Segment]>> {
+        if let Some(template) = self.cache.get(name) {
+            return Ok(template.clone());
+        }
+
+        let path = self.path(name);
+        if !self.fs.has_file(&path) {
+            return Err(Error::not_found(match includer {
+                Some(includer) if !includer.is_empty() => format!(
+                    "template \"{}\" includes \"{}\", which does not exist at {}",
+                    includer,
+                    name,
+                    path.display()
+                ),
+                _ => format!("template \"{}\" does not exist at {}", name, path.display()),
+            }));
+        }
+        let source = self.fs.read_file_as_string(&path)?;
+        let template: Rc<[Segment]> = parse_template(name, &source)?.into();
+        self.cache.insert(name.to_owned(), template.clone());
+        Ok(template)
+    }
+
+    /// Get the path to the file of the template with the specified name.
+    fn path(&self, name: &str) -> PathBuf {
+        let path = self.directory.join(name);
+        if path.extension().is_some() {
+            path
+        } else {
+            path.with_extension(DEFAULT_TEMPLATE_EXTENSION)
+        }
+    }
+}
+
+/// Parse the specified source of the template with the specified name into segments.
+fn parse_template(name: &str, source: &str) -> Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut rest = source;
+    while let Some(start) = rest.find(PARTIAL_START) {
+        if start > 0 {
+            segments.push(Segment::Text(rest[..start].to_owned()));
+        }
+        let tag = &rest[start + PARTIAL_START.len()..];
+        let end = match tag.find(PARTIAL_END) {
+            Some(end) => end,
+            None => {
+                return Err(Error::parse(format!(
+                    "template \"{}\":{}: partial tag is not closed",
+                    name,
+                    line_of(source, rest, start)
+                )))
+            }
+        };
+        let partial = tag[..end].trim();
+        if partial.is_empty() {
+            return Err(Error::parse(format!(
+                "template \"{}\":{}: partial tag has no name",
+                name,
+                line_of(source, rest, start)
+            )));
+        }
+        segments.push(Segment::Partial(partial.to_owned()));
+        rest = &tag[end + PARTIAL_END.len()..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest.to_owned()));
+    }
+    Ok(segments)
+}
+
+/// Get the line number, starting from 1, of the specified offset into the specified rest of the
+/// specified source.
+fn line_of(source: &str, rest: &str, offset: usize) -> usize {
+    source[..source.len() - rest.len() + offset]
+        .matches('\n')
+        .count()
+        + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::fs::scratch::ScratchFileSystem;
+
+    const ROOT: &str = "/openbook-test/template";
+
+    fn theme(files: &[(&str, &str)]) -> ScratchFileSystem {
+        let fs = ScratchFileSystem::new();
+        for (name, content) in files {
+            fs.write_file(Path::new(ROOT).join(name), content.as_bytes())
+                .unwrap();
+        }
+        fs
+    }
+
+    #[test]
+    fn expands_nested_partials() {
+        let fs = theme(&[
+            (
+                "page.html",
+                "{{> header}}{{content}}{{> partials/footer.txt }}",
+            ),
+            ("header.html", "<header>{{> nav}}</header>"),
+            ("nav.html", "<nav>{{title}}</nav>"),
+            ("partials/footer.txt", "<footer></footer>"),
+        ]);
+        let mut loader = TemplateLoader::new(&fs, ROOT);
+        assert_eq!(
+            loader.load("page.html").unwrap(),
+            "<header><nav>{{title}}</nav></header>{{content}}<footer></footer>"
+        );
+        assert_eq!(
+            loader.expand("<body>{{> nav}}</body>").unwrap(),
+            "<body><nav>{{title}}</nav></body>"
+        );
+    }
+
+    #[test]
+    fn caches_parsed_templates() {
+        let fs = theme(&[("page.html", "{{> nav}}{{> nav}}"), ("nav.html", "a")]);
+        let mut loader = TemplateLoader::new(&fs, ROOT);
+        assert_eq!(loader.load("page").unwrap(), "aa");
+
+        fs.write_file(Path::new(ROOT).join("nav.html"), b"b")
+            .unwrap();
+        assert_eq!(loader.load("page").unwrap(), "aa");
+        assert_eq!(TemplateLoader::new(&fs, ROOT).load("page").unwrap(), "bb");
+    }
+
+    #[test]
+    fn reports_cycles_and_missing_partials() {
+        let fs = theme(&[
+            ("page.html", "{{> header}}"),
+            ("header.html", "{{> nav}}"),
+            ("nav.html", "{{> header}}"),
+            ("broken.html", "{{> footer}}"),
+            ("open.html", "\n{{> footer"),
+        ]);
+        let mut loader = TemplateLoader::new(&fs, ROOT);
+
+        let err = loader.load("page").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Parse);
+        assert!(
+            err.to_string().contains("header -> nav -> header"),
+            "{}",
+            err
+        );
+
+        let err = loader.load("broken").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(
+            err.to_string().contains("\"broken\" includes \"footer\""),
+            "{}",
+            err
+        );
+
+        let err = loader.load("open").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("\"open\":2: partial tag is not closed"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,278 @@
+//! This module implements a visitor that generates the table of contents of a book.
+//!
+
+use crate::render::{escape_html, page_url};
+use crate::tree::visitor::{VisitContext, Visitor};
+use crate::tree::{Chapter, Section};
+
+/// An entry in a table of contents.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TocNode {
+    /// Title of the entry.
+    pub title: String,
+
+    /// Link to the page of the entry, or `None` for chapters, which do not have a page.
+    pub href: Option<String>,
+
+    /// Nested entries.
+    pub children: Vec<TocNode>,
+}
+
+/// A visitor that generates the table of contents of the visited book.
+///
+/// Every chapter becomes a top-level entry without a link, and every section becomes an entry
+/// nested under its chapter or parent section, titled after `Section::name`. The preface becomes
+/// a top-level entry as well. The link of a section is its content file with the extension
+/// replaced by `.html`, where characters that are not safe in URLs are replaced by `-`, followed by
+/// its anchor if there is one.
+///
+/// The visitor must be driven in depth-first pre-order, e.g. by `visit`. Visiting multiple books
+/// with the same visitor concatenates their tables of contents.
+#[derive(Clone, Debug, Default)]
+pub struct TocVisitor {
+    nodes: Vec<TocNode>,
+}
+
+impl TocVisitor {
+    /// Create a new `TocVisitor` instance.
+    pub fn new() -> Self {
+        Self { nodes: Vec::new() }
+    }
+
+    /// Get the entries generated so far.
+    pub fn toc(&self) -> &[TocNode] {
+        &self.nodes
+    }
+
+    /// Consume this visitor and get the top-level entries of the table of contents.
+    pub fn into_toc(self) -> Vec<TocNode> {
+        self.nodes
+    }
+
+    /// Render the table of contents as nested HTML `<ul>` lists.
+    pub fn render_html(&self) -> String {
+        let mut output = String::new();
+        render_list(&self.nodes, &mut output);
+        output
+    }
+
+    /// Get the list to which an entry at the specified depth should be added.
+    fn list_at(&mut self, depth: usize) -> &mut Vec<TocNode> {
+        let mut list = &mut self.nodes;
+        for _ in 0..depth {
+            if list.is_empty() {
+                break;
+            }
+            list = &mut list.last_mut().unwrap().children;
+        }
+        list
+    }
+}
+
+impl Visitor for TocVisitor {
+    fn visit_chapter_in(&mut self, chapter: &Chapter, context: &VisitContext) {
+        self.list_at(context.ancestors().len()).push(TocNode {
+            title: chapter.name.clone(),
+            href: None,
+            children: Vec::new(),
+        });
+    }
+
+    fn visit_section_in(&mut self, section: &Section, context: &VisitContext) {
+        if section.file.as_os_str().is_empty() && section.name.is_empty() {
+            // The book does not have a preface.
+            return;
+        }
+
+        let href = if section.file.as_os_str().is_empty() {
+            None
+        } else {
+            let mut href = page_url(&section.file);
+            if let Some(anchor) = &section.anchor {
+                href.push('#');
+                href.push_str(anchor);
+            }
+            Some(href)
+        };
+        self.list_at(context.ancestors().len()).push(TocNode {
+            title: section.name.clone(),
+            href,
+            children: Vec::new(),
+        });
+    }
+}
+
+/// Render the specified entries as an HTML `<ul>` list.
+fn render_list(nodes: &[TocNode], output: &mut String) {
+    // The stack holds the remaining entries of every list under rendering, from the outermost
+    // one, so that deeply nested entries cannot overflow the call stack.
+    output.push_str("<ul>\n");
+    let mut stack = vec![nodes.iter()];
+    while let Some(list) = stack.last_mut() {
+        let node = match list.next() {
+            Some(node) => node,
+            None => {
+                stack.pop();
+                output.push_str("</ul>\n");
+                if !stack.is_empty() {
+                    output.push_str("</li>\n");
+                }
+                continue;
+            }
+        };
+
+        output.push_str("<li>");
+        match &node.href {
+            Some(href) => output.push_str(&format!(
+                "<a href=\"{}\">{}</a>",
+                escape_html(href),
+                escape_html(&node.title)
+            )),
+            None => output.push_str(&escape_html(&node.title)),
+        }
+        if node.children.is_empty() {
+            output.push_str("</li>\n");
+        } else {
+            output.push_str("\n<ul>\n");
+            stack.push(node.children.iter());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::builder::{BookBuilder, ChapterBuilder, SectionBuilder};
+    use crate::tree::visitor::tests::{chain_with, drop_book_iteratively, with_small_stack};
+    use crate::tree::visitor::visit;
+
+    fn node(title: &str, href: Option<&str>, children: Vec<TocNode>) -> TocNode {
+        TocNode {
+            title: title.to_owned(),
+            href: href.map(str::to_owned),
+            children,
+        }
+    }
+
+    fn guide() -> crate::tree::Book {
+        BookBuilder::new()
+            .set_preface(
+                SectionBuilder::new()
+                    .set_name("Preface")
+                    .set_file("README.md")
+                    .build(),
+            )
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("Basics")
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_name("Intro & Setup")
+                            .set_file("basics/intro setup.md")
+                            .add_subsection(
+                                SectionBuilder::new()
+                                    .set_name("Install")
+                                    .set_file("basics/intro setup.md")
+                                    .set_anchor("install")
+                                    .build(),
+                            )
+                            .build(),
+                    )
+                    .add_section(SectionBuilder::new().set_name("Coming soon").build())
+                    .build(),
+            )
+            .add_chapter(ChapterBuilder::new().set_name("Empty").build())
+            .build()
+    }
+
+    #[test]
+    fn mirrors_the_hierarchy_of_the_book() {
+        let mut toc = TocVisitor::new();
+        visit(&guide(), &mut toc);
+        assert_eq!(
+            toc.into_toc(),
+            vec![
+                node("Preface", Some("README.html"), Vec::new()),
+                node(
+                    "Basics",
+                    None,
+                    vec![
+                        node(
+                            "Intro & Setup",
+                            Some("basics/intro-setup.html"),
+                            vec![node(
+                                "Install",
+                                Some("basics/intro-setup.html#install"),
+                                Vec::new()
+                            )],
+                        ),
+                        node("Coming soon", None, Vec::new()),
+                    ],
+                ),
+                node("Empty", None, Vec::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_nested_lists() {
+        let mut toc = TocVisitor::new();
+        visit(&guide(), &mut toc);
+        assert_eq!(
+            toc.render_html(),
+            "<ul>\n\
+             <li><a href=\"README.html\">Preface</a></li>\n\
+             <li>Basics\n\
+             <ul>\n\
+             <li><a href=\"basics/intro-setup.html\">Intro &amp; Setup</a>\n\
+             <ul>\n\
+             <li><a href=\"basics/intro-setup.html#install\">Install</a></li>\n\
+             </ul>\n\
+             </li>\n\
+             <li>Coming soon</li>\n\
+             </ul>\n\
+             </li>\n\
+             <li>Empty</li>\n\
+             </ul>\n"
+        );
+    }
+
+    #[test]
+    fn renders_deeply_nested_lists() {
+        with_small_stack(render_deep_chain);
+    }
+
+    fn render_deep_chain() {
+        const DEPTH: usize = 10_000;
+        let chain = chain_with(DEPTH, |d| {
+            SectionBuilder::new()
+                .set_name(d.to_string())
+                .set_file(format!("{}.md", d))
+                .build()
+        });
+        let book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("Deep")
+                    .add_section(chain)
+                    .build(),
+            )
+            .build();
+        let mut toc = TocVisitor::new();
+        visit(&book, &mut toc);
+        drop_book_iteratively(book);
+
+        let html = toc.render_html();
+        assert!(html.starts_with("<ul>\n<li>Deep\n<ul>\n<li><a href=\"0.html\">0</a>\n<ul>\n"));
+        assert!(html.contains("<li><a href=\"10000.html\">10000</a></li>\n</ul>\n</li>\n"));
+        let closing = format!("{}</ul>\n", "</ul>\n</li>\n".repeat(DEPTH + 1));
+        assert!(html.ends_with(&closing));
+        assert_eq!(html.matches("<ul>").count(), DEPTH + 2);
+
+        // Dismantle the entries iteratively, since the drop glue of `TocNode` is recursive.
+        let mut pending = toc.into_toc();
+        while let Some(mut node) = pending.pop() {
+            pending.append(&mut node.children);
+        }
+    }
+}
@@ -0,0 +1,742 @@
+//! This module converts AsciiDoc content into markdown.
+//!
+//! Sections written in AsciiDoc are converted into markdown when they are loaded, like sections
+//! written in reStructuredText. AsciiDoc concepts that OpenBook has an equivalent for are mapped to
+//! it:
+//!
+//! * Attribute entries, like `:description: An open-source alternative to gitbook`, become section
+//!   metadata, with `keywords` becoming `tags`. References to attributes, like `{description}`,
+//!   are replaced by their values, and references to undefined attributes are kept as-is;
+//! * `include::snippet.adoc[lines=3..7]` directives become `{{#include snippet.adoc:3:7}}`
+//!   directives, which are resolved by the [`include`] module. Only the `lines` attribute is
+//!   supported; a `lines` attribute with multiple ranges becomes one directive per range. Since
+//!   the directives are resolved after the conversion, included files are inserted as-is and
+//!   should hold code or markdown rather than AsciiDoc.
+//!
+//! The following subset of AsciiDoc is supported besides:
+//!
+//! * Section titles from `=` to `======`, where `=` is the document title;
+//! * Paragraphs, unordered lists with `*` or `-` markers, and ordered lists with `.` markers,
+//!   nested by repeating the marker;
+//! * Listing blocks delimited by `----`, whose language is given by a `[source,rust]` line, and
+//!   literal blocks delimited by `....`, which become fenced code blocks;
+//! * Example, sidebar and quote blocks, and admonitions like `NOTE: text` or `[WARNING]` blocks,
+//!   which become quotes;
+//! * Passthrough blocks delimited by `++++`, which are kept as raw HTML;
+//! * Tables delimited by `|===`, whose first row is the header;
+//! * Block titles like `.Title`, block images, thematic breaks and hard line breaks;
+//! * Strong text, monospace text, passthroughs, highlights, links, cross references, inline
+//!   images and footnotes.
+//!
+//! Comments and page breaks do not produce any output, and all other constructs are kept as text.
+//!
+//! [`include`]: ../include/index.html
+//!
+
+use std::collections::BTreeMap;
+
+/// Names of the admonitions, which become quotes titled with their names.
+const ADMONITIONS: &[&str] = &["CAUTION", "IMPORTANT", "NOTE", "TIP", "WARNING"];
+
+/// Convert the specified AsciiDoc document into markdown.
+///
+/// Returns the metadata given by the attribute entries of the document and the markdown content.
+pub fn asciidoc_to_markdown(source: &str) -> (BTreeMap<String, String>, String) {
+    let lines: Vec<&str> = source.lines().map(str::trim_end).collect();
+    let mut converter = AsciiDocConverter::default();
+    converter.convert(&lines);
+
+    // Attribute entries of the header leave the blank line that ends the header behind.
+    let start = converter.output.iter().take_while(|l| l.is_empty()).count();
+    let mut output = converter.output[start..].join("\n");
+    if !converter.footnotes.is_empty() {
+        output.push('\n');
+        for (i, footnote) in converter.footnotes.iter().enumerate() {
+            output.push_str(&format!("\n[^{}]: {}", i + 1, footnote));
+        }
+    }
+    if !output.is_empty() {
+        output.push('\n');
+    }
+
+    let mut metadata = converter.attributes;
+    if let Some(keywords) = metadata.remove("keywords") {
+        metadata.insert("tags".to_owned(), keywords);
+    }
+    (metadata, output)
+}
+
+/// Converts the blocks of an AsciiDoc document into lines of markdown.
+#[derive(Default)]
+struct AsciiDocConverter {
+    /// Attributes defined by the attribute entries seen so far.
+    attributes: BTreeMap<String, String>,
+
+    /// Text of the footnotes seen so far, in order.
+    footnotes: Vec<String>,
+
+    output: Vec<String>,
+}
+
+impl AsciiDocConverter {
+    /// Convert the specified lines and append the result to the output.
+    fn convert(&mut self, lines: &[&str]) {
+        // The block attribute line, like `[source,rust]`, that applies to the next block.
+        let mut block_attributes: Option<&str> = None;
+        let mut i = 0;
+        while i < lines.len() {
+            let line = lines[i];
+            if line.is_empty() {
+                self.output.push(String::new());
+                block_attributes = None;
+                i += 1;
+                continue;
+            }
+
+            if let Some(delimiter) = block_delimiter(line) {
+                let end = (i + 1..lines.len())
+                    .find(|&j| lines[j] == line)
+                    .unwrap_or(lines.len());
+                let body = &lines[i + 1..end];
+                self.delimited_block(delimiter, block_attributes.take(), body);
+                i = (end + 1).min(lines.len());
+                continue;
+            }
+
+            if line.starts_with("//") {
+                i += 1;
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') && !line.starts_with("[[[") {
+                block_attributes = Some(&line[1..line.len() - 1]);
+                i += 1;
+                continue;
+            }
+            if let Some((name, value)) = attribute_entry(line) {
+                match name.strip_suffix('!') {
+                    Some(name) => {
+                        self.attributes.remove(name);
+                    }
+                    None => {
+                        let value = self.substitute(value);
+                        self.attributes.insert(name.to_owned(), value);
+                    }
+                }
+                i += 1;
+                continue;
+            }
+
+            if let Some(level) = heading_level(line) {
+                let title = self.inline(line[level..].trim());
+                self.output.push(format!("{} {}", "#".repeat(level), title));
+                i += 1;
+                continue;
+            }
+            if line == "'''" || line == "---" || line == "***" {
+                self.output.push(String::new());
+                self.output.push("---".to_owned());
+                i += 1;
+                continue;
+            }
+            if line == "<<<" {
+                i += 1;
+                continue;
+            }
+            if let Some(spec) = line.strip_prefix("include::") {
+                self.output.extend(include_directives(spec));
+                i += 1;
+                continue;
+            }
+            if let Some(spec) = line.strip_prefix("image::") {
+                let (target, alt) = macro_parts(spec);
+                self.output.push(format!("![{}]({})", alt, target));
+                i += 1;
+                continue;
+            }
+            if line.starts_with('.') && line.len() > 1 {
+                let title = &line[1..];
+                if !title.starts_with(|c: char| c == '.' || c.is_whitespace()) {
+                    let title = self.inline(title);
+                    self.output.push(format!("**{}**", title));
+                    self.output.push(String::new());
+                    i += 1;
+                    continue;
+                }
+            }
+
+            // A paragraph, possibly with an admonition label or a list marker.
+            let end = (i + 1..lines.len())
+                .find(|&j| {
+                    lines[j].is_empty()
+                        || block_delimiter(lines[j]).is_some()
+                        || list_marker(lines[j]).is_some()
+                })
+                .unwrap_or(lines.len());
+            let admonition = match block_attributes.take() {
+                Some(name) if ADMONITIONS.contains(&name) => Some(name.to_owned()),
+                _ => None,
+            };
+            self.paragraph(&lines[i..end], admonition);
+            i = end;
+        }
+    }
+
+    /// Convert the specified lines of a paragraph, which is an admonition with the specified name
+    /// if it is not `None`.
+    fn paragraph(&mut self, lines: &[&str], admonition: Option<String>) {
+        let mut text: Vec<String> = Vec::with_capacity(lines.len());
+        let mut admonition = admonition;
+        for (i, line) in lines.iter().enumerate() {
+            let mut line = *line;
+            if i == 0 && admonition.is_none() {
+                if let Some(end) = line.find(": ") {
+                    if ADMONITIONS.contains(&&line[..end]) {
+                        admonition = Some(line[..end].to_owned());
+                        line = &line[end + 2..];
+                    }
+                }
+            }
+
+            let mut converted = match list_marker(line) {
+                Some((marker, rest)) => format!("{}{}", marker, self.inline(rest)),
+                None => self.inline(line.trim_start()),
+            };
+            if let Some(stripped) = converted.strip_suffix(" +") {
+                converted = format!("{}\\", stripped);
+            }
+            text.push(converted);
+        }
+
+        match admonition {
+            Some(name) => {
+                let mut body = vec![format!("**{}**", title_case(&name)), String::new()];
+                body.extend(text);
+                self.push_quote(&body);
+            }
+            None => self.output.extend(text),
+        }
+    }
+
+    /// Convert the specified delimited block with the specified block attributes.
+    fn delimited_block(&mut self, delimiter: char, attributes: Option<&str>, body: &[&str]) {
+        match delimiter {
+            '-' | '.' => {
+                let language = match attributes {
+                    Some(attributes) if attributes.starts_with("source") => {
+                        attributes.split(',').nth(1).unwrap_or_default().trim()
+                    }
+                    _ => "",
+                };
+                let fence = if body.iter().any(|l| l.contains("```")) {
+                    "~~~~"
+                } else {
+                    "```"
+                };
+                self.output.push(format!("{}{}", fence, language));
+                for line in body {
+                    match line.strip_prefix("include::") {
+                        Some(spec) => self.output.extend(include_directives(spec)),
+                        None => self.output.push((*line).to_owned()),
+                    }
+                }
+                self.output.push(fence.to_owned());
+            }
+            '+' => self.output.extend(body.iter().map(|l| (*l).to_owned())),
+            '|' => self.table(body),
+            '/' => {}
+            _ => {
+                let mut nested = AsciiDocConverter {
+                    attributes: self.attributes.clone(),
+                    footnotes: std::mem::take(&mut self.footnotes),
+                    output: Vec::new(),
+                };
+                nested.convert(body);
+                self.footnotes = nested.footnotes;
+
+                let mut lines = Vec::new();
+                match attributes {
+                    Some(name) if ADMONITIONS.contains(&name) => {
+                        lines.push(format!("**{}**", title_case(name)));
+                        lines.push(String::new());
+                    }
+                    _ => {}
+                }
+                lines.extend(nested.output);
+                self.push_quote(&lines);
+            }
+        }
+    }
+
+    /// Convert the specified body of a table, whose first row is the header.
+    ///
+    /// The number of columns is the number of cells on the first line of the table. Lines that do
+    /// not start with `|` continue the last cell.
+    fn table(&mut self, body: &[&str]) {
+        let mut columns = 0;
+        let mut cells: Vec<String> = Vec::new();
+        for line in body.iter().filter(|l| !l.is_empty()) {
+            match line.strip_prefix('|') {
+                Some(line) => {
+                    let row: Vec<String> = line.split('|').map(|c| self.inline(c.trim())).collect();
+                    if columns == 0 {
+                        columns = row.len();
+                    }
+                    cells.extend(row);
+                }
+                None => {
+                    let text = self.inline(line.trim());
+                    if let Some(last) = cells.last_mut() {
+                        last.push(' ');
+                        last.push_str(&text);
+                    }
+                }
+            }
+        }
+        if columns == 0 {
+            return;
+        }
+
+        for (i, row) in cells.chunks(columns).enumerate() {
+            let mut line = String::from("|");
+            for column in 0..columns {
+                line.push(' ');
+                line.push_str(row.get(column).map(String::as_str).unwrap_or_default());
+                line.push_str(" |");
+            }
+            self.output.push(line);
+            if i == 0 {
+                self.output.push(format!("|{}", " --- |".repeat(columns)));
+            }
+        }
+    }
+
+    /// Append the specified markdown lines as a block quote.
+    fn push_quote(&mut self, lines: &[String]) {
+        let start = lines.iter().position(|l| !l.is_empty()).unwrap_or_default();
+        let end = lines
+            .iter()
+            .rposition(|l| !l.is_empty())
+            .map_or(0, |i| i + 1);
+        for line in lines.get(start..end).unwrap_or_default() {
+            if line.is_empty() {
+                self.output.push(">".to_owned());
+            } else {
+                self.output.push(format!("> {}", line));
+            }
+        }
+    }
+
+    /// Replace the references to defined attributes in the specified text by their values.
+    fn substitute(&self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('{') {
+            output.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = match rest.find('}') {
+                Some(end) => end,
+                None => break,
+            };
+            let name = &rest[1..end];
+            let value = match name {
+                "empty" => Some(""),
+                "sp" => Some(" "),
+                "nbsp" => Some("&#160;"),
+                "amp" => Some("&amp;"),
+                "lt" => Some("&lt;"),
+                "gt" => Some("&gt;"),
+                "plus" => Some("&#43;"),
+                "startsb" => Some("["),
+                "endsb" => Some("]"),
+                name => self.attributes.get(name).map(String::as_str),
+            };
+            match value {
+                Some(value) => output.push_str(value),
+                None => output.push_str(&rest[..=end]),
+            }
+            rest = &rest[end + 1..];
+        }
+        output.push_str(rest);
+        output
+    }
+
+    /// Convert the inline markup in the specified text.
+    fn inline(&mut self, text: &str) -> String {
+        let text = self.substitute(text);
+        let chars: Vec<char> = text.chars().collect();
+        let mut output = String::with_capacity(text.len());
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let boundary = i == 0 || !chars[i - 1].is_alphanumeric();
+            match c {
+                '\\' if i + 1 < chars.len() => {
+                    output.push(c);
+                    output.push(chars[i + 1]);
+                    i += 2;
+                    continue;
+                }
+                '`' => {
+                    if let Some(end) = find(&chars, i + 1, "`") {
+                        let literal: String = chars[i + 1..end].iter().collect();
+                        let literal = literal.trim_start_matches('+').trim_end_matches('+');
+                        output.push('`');
+                        output.push_str(literal);
+                        output.push('`');
+                        i = end + 1;
+                        continue;
+                    }
+                }
+                '*' if chars.get(i + 1) == Some(&'*') => {
+                    if let Some(end) = find(&chars, i + 2, "**") {
+                        let strong: String = chars[i + 2..end].iter().collect();
+                        output.push_str(&format!("**{}**", strong));
+                        i = end + 2;
+                        continue;
+                    }
+                }
+                '*' if boundary => {
+                    if let Some(end) = constrained_end(&chars, i, '*') {
+                        let strong: String = chars[i + 1..end].iter().collect();
+                        output.push_str(&format!("**{}**", strong));
+                        i = end + 1;
+                        continue;
+                    }
+                }
+                '#' if boundary => {
+                    if let Some(end) = constrained_end(&chars, i, '#') {
+                        let marked: String = chars[i + 1..end].iter().collect();
+                        output.push_str(&format!("<mark>{}</mark>", marked));
+                        i = end + 1;
+                        continue;
+                    }
+                }
+                '+' if boundary => {
+                    if let Some(end) = constrained_end(&chars, i, '+') {
+                        output.extend(&chars[i + 1..end]);
+                        i = end + 1;
+                        continue;
+                    }
+                }
+                '<' if chars.get(i + 1) == Some(&'<') => {
+                    if let Some(end) = find(&chars, i + 2, ">>") {
+                        let reference: String = chars[i + 2..end].iter().collect();
+                        let (target, text) = match reference.find(',') {
+                            Some(comma) => (
+                                reference[..comma].trim().to_owned(),
+                                reference[comma + 1..].trim().to_owned(),
+                            ),
+                            None => (reference.clone(), reference.clone()),
+                        };
+                        let target = if target.contains('#') || target.contains('.') {
+                            target
+                        } else {
+                            format!("#{}", target)
+                        };
+                        output.push_str(&format!("[{}]({})", text, target));
+                        i = end + 2;
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+
+            if boundary {
+                if let Some((converted, end)) = self.inline_macro(&chars, i) {
+                    output.push_str(&converted);
+                    i = end;
+                    continue;
+                }
+            }
+            output.push(c);
+            i += 1;
+        }
+        output
+    }
+
+    /// Convert the inline macro, like `link:target[text]`, `image:target[alt]`,
+    /// `footnote:[text]` or `https://url[text]`, that starts at the specified index.
+    ///
+    /// Returns the converted macro and the index after it.
+    fn inline_macro(&mut self, chars: &[char], start: usize) -> Option<(String, usize)> {
+        if !chars[start].is_ascii_alphabetic() {
+            return None;
+        }
+        let rest: String = chars[start..].iter().collect();
+        let colon = rest.find(':')?;
+        let name = &rest[..colon];
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphabetic()) {
+            return None;
+        }
+        let is_url = matches!(name, "http" | "https" | "ftp" | "irc" | "mailto");
+        let open = rest.find('[')?;
+        let close = open + rest[open..].find(']')?;
+        if rest[..open].contains(char::is_whitespace) {
+            return None;
+        }
+
+        let target = &rest[colon + 1..open];
+        let text = &rest[open + 1..close];
+        let end = start + rest[..=close].chars().count();
+        let converted = match name {
+            "footnote" => {
+                self.footnotes.push(text.to_owned());
+                format!("[^{}]", self.footnotes.len())
+            }
+            "image" => format!("![{}]({})", macro_text(text), target),
+            "link" | "xref" => {
+                let text = if text.is_empty() {
+                    target
+                } else {
+                    macro_text(text)
+                };
+                format!("[{}]({})", text, target)
+            }
+            _ if is_url => {
+                let url = &rest[..open];
+                let text = if text.is_empty() {
+                    url
+                } else {
+                    macro_text(text)
+                };
+                format!("[{}]({})", text, url)
+            }
+            _ => return None,
+        };
+        Some((converted, end))
+    }
+}
+
+/// Get the delimiter character if the specified line delimits a block, like `----`.
+fn block_delimiter(line: &str) -> Option<char> {
+    if line == "|===" {
+        return Some('|');
+    }
+    let c = line.chars().next()?;
+    if line.len() >= 4
+        && matches!(c, '-' | '.' | '=' | '*' | '_' | '+' | '/')
+        && line.chars().all(|x| x == c)
+    {
+        Some(c)
+    } else {
+        None
+    }
+}
+
+/// Parse the specified line as an attribute entry, like `:name: value`.
+fn attribute_entry(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix(':')?;
+    let end = rest.find(':')?;
+    let name = &rest[..end];
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '!'))
+    {
+        return None;
+    }
+    let value = &rest[end + 1..];
+    if !value.is_empty() && !value.starts_with(' ') {
+        return None;
+    }
+    Some((name, value.trim()))
+}
+
+/// Get the level of the section title on the specified line, like `== Title`.
+fn heading_level(line: &str) -> Option<usize> {
+    let level = line.len() - line.trim_start_matches(&['=', '#'][..]).len();
+    let marker = &line[..level];
+    let uniform = marker.chars().all(|c| c == '=') || marker.chars().all(|c| c == '#');
+    if (1..=6).contains(&level) && uniform && line[level..].starts_with(' ') {
+        Some(level)
+    } else {
+        None
+    }
+}
+
+/// Split the list marker off the specified line, converting it into a markdown list marker that
+/// is indented according to the depth of the item.
+fn list_marker(line: &str) -> Option<(String, &str)> {
+    let text = line.trim_start();
+    let c = text.chars().next()?;
+    if !matches!(c, '*' | '-' | '.') {
+        return None;
+    }
+    let depth = text.len() - text.trim_start_matches(c).len();
+    let rest = text[depth..].strip_prefix(' ')?;
+    if c == '-' && depth > 1 {
+        return None;
+    }
+
+    let indent = if c == '.' { "   " } else { "  " }.repeat(depth - 1);
+    let marker = if c == '.' { "1. " } else { "* " };
+    Some((format!("{}{}", indent, marker), rest.trim_start()))
+}
+
+/// Convert the specified `include::` directive, without its prefix, into `{{#include}}`
+/// directives.
+fn include_directives(spec: &str) -> Vec<String> {
+    let (target, attributes) = macro_parts(spec);
+    let ranges = attributes
+        .split(',')
+        .filter_map(|a| a.trim().strip_prefix("lines="))
+        .next()
+        .map(|lines| lines.trim_matches('"'));
+    let ranges: Vec<String> = match ranges {
+        Some(ranges) => ranges
+            .split(&[';', ','][..])
+            .filter(|r| !r.is_empty())
+            .map(|range| match range.find("..") {
+                Some(i) => {
+                    let (start, end) = (&range[..i], &range[i + 2..]);
+                    let end = if end == "-1" { "" } else { end };
+                    format!("{}:{}", start, end)
+                }
+                None => range.to_owned(),
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    if ranges.is_empty() {
+        vec![format!("{{{{#include {}}}}}", target)]
+    } else {
+        ranges
+            .iter()
+            .map(|range| format!("{{{{#include {}:{}}}}}", target, range))
+            .collect()
+    }
+}
+
+/// Split the specified macro, like `target[attributes]`, into its target and its attributes.
+fn macro_parts(spec: &str) -> (&str, &str) {
+    match spec.find('[') {
+        Some(open) => (
+            &spec[..open],
+            spec[open + 1..]
+                .strip_suffix(']')
+                .unwrap_or(&spec[open + 1..]),
+        ),
+        None => (spec, ""),
+    }
+}
+
+/// Get the text of a macro from its attributes, i.e. the first positional attribute.
+fn macro_text(attributes: &str) -> &str {
+    let text = attributes.split(',').next().unwrap_or_default().trim();
+    text.trim_matches('"')
+}
+
+/// Find the end of the constrained formatting, like `*strong*`, that opens with the specified mark
+/// at the specified index.
+fn constrained_end(chars: &[char], start: usize, mark: char) -> Option<usize> {
+    if matches!(chars.get(start + 1), None | Some(' ')) {
+        return None;
+    }
+    let end = (start + 1..chars.len()).find(|&i| chars[i] == mark)?;
+    let closes = chars[end - 1] != ' '
+        && matches!(
+            chars.get(end + 1),
+            None | Some(' ')
+                | Some(',')
+                | Some('.')
+                | Some(';')
+                | Some(':')
+                | Some('!')
+                | Some('?')
+                | Some(')')
+        );
+    if closes && end > start + 1 {
+        Some(end)
+    } else {
+        None
+    }
+}
+
+/// Find the specified pattern in the specified characters, starting at the specified index.
+fn find(chars: &[char], start: usize, pattern: &str) -> Option<usize> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    (start..chars.len()).find(|&i| chars[i..].starts_with(&pattern))
+}
+
+/// Convert the specified upper-case admonition name into title case, like `Note`.
+fn title_case(name: &str) -> String {
+    let mut title = name.to_ascii_lowercase();
+    title[..1].make_ascii_uppercase();
+    title
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    use crate::tree::Section;
+
+    #[test]
+    fn attribute_entries_become_metadata() {
+        let source =
+            "= Guide\n:description: A guide\n:keywords: rust, docs\n:product: OpenBook\n\n\
+                      Welcome to {product} and {missing}.\n";
+        let (metadata, markdown) = asciidoc_to_markdown(source);
+        assert_eq!(markdown, "# Guide\n\nWelcome to OpenBook and {missing}.\n");
+        assert_eq!(
+            metadata.get("description").map(String::as_str),
+            Some("A guide")
+        );
+        assert_eq!(metadata.get("tags").map(String::as_str), Some("rust, docs"));
+        assert!(!metadata.contains_key("keywords"));
+    }
+
+    #[test]
+    fn converts_titles_lists_and_listings() {
+        let source = "== Setup\n\n* one\n** nested\n\n. first\n. second\n\n\
+                      [source,rust]\n----\nfn main() {}\n----\n";
+        assert_eq!(
+            asciidoc_to_markdown(source).1,
+            "## Setup\n\n* one\n  * nested\n\n1. first\n1. second\n\n```rust\nfn main() {}\n```\n"
+        );
+    }
+
+    #[test]
+    fn converts_admonitions_and_tables() {
+        let source = "NOTE: Be careful.\n\n|===\n| A | B\n\n| 1 | 2\n|===\n";
+        assert_eq!(
+            asciidoc_to_markdown(source).1,
+            "> **Note**\n>\n> Be careful.\n\n| A | B |\n| --- | --- |\n| 1 | 2 |\n"
+        );
+    }
+
+    #[test]
+    fn converts_inline_markup_and_footnotes() {
+        let source = "See *bold*, `mono` and https://example.com[Example].footnote:[A note.]\n";
+        assert_eq!(
+            asciidoc_to_markdown(source).1,
+            "See **bold**, `mono` and [Example](https://example.com).[^1]\n\n[^1]: A note.\n"
+        );
+    }
+
+    #[test]
+    fn includes_become_include_directives() {
+        assert_eq!(
+            asciidoc_to_markdown("include::snippet.rs[lines=3..7]\n").1,
+            "{{#include snippet.rs:3:7}}\n"
+        );
+    }
+
+    #[test]
+    fn attributes_do_not_replace_existing_metadata() {
+        let mut section = Section {
+            file: PathBuf::from("guide.adoc"),
+            content: String::from(":description: From AsciiDoc\n:author: Someone\n\nText.\n"),
+            ..Section::default()
+        };
+        section
+            .metadata
+            .insert("description".to_owned(), "From frontmatter".to_owned());
+        section.convert_markup();
+        assert_eq!(section.content, "Text.\n");
+        assert_eq!(section.metadata["description"], "From frontmatter");
+        assert_eq!(section.metadata["author"], "Someone");
+    }
+}
@@ -0,0 +1,359 @@
+//! This module provides a visitor that reports sections whose content exceeds a length budget.
+//!
+//! Style guides often cap the length of a single page, e.g. "keep pages under 2000 words". The
+//! [`ContentBudgetVisitor`] measures the content of every visited section in words or bytes and
+//! reports the sections that exceed the configured [`ContentBudget`]. Sections that are
+//! intentionally long, such as appendices, can declare a budget of their own in their frontmatter:
+//!
+//! ```markdown
+//! ---
+//! word_budget: 10000
+//! ---
+//! ```
+//!
+//! The [`WORD_BUDGET_KEY`] entry sets a budget in words and the [`BYTE_BUDGET_KEY`] entry a budget
+//! in bytes.
+//!
+//! [`ContentBudgetVisitor`]: struct.ContentBudgetVisitor.html
+//! [`ContentBudget`]: enum.ContentBudget.html
+//! [`WORD_BUDGET_KEY`]: constant.WORD_BUDGET_KEY.html
+//! [`BYTE_BUDGET_KEY`]: constant.BYTE_BUDGET_KEY.html
+//!
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::error::{Error, Result};
+use crate::tree::visitor::Visitor;
+use crate::tree::Section;
+
+/// Key of the section metadata entry that overrides the budget of the section with a budget in
+/// words.
+pub const WORD_BUDGET_KEY: &str = "word_budget";
+
+/// Key of the section metadata entry that overrides the budget of the section with a budget in
+/// bytes.
+pub const BYTE_BUDGET_KEY: &str = "byte_budget";
+
+/// Maximum length of the content of a section.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ContentBudget {
+    /// At most the given number of words, as counted by [`count_words`].
+    ///
+    /// [`count_words`]: fn.count_words.html
+    Words(usize),
+
+    /// At most the given number of bytes of UTF-8 encoded content.
+    Bytes(usize),
+}
+
+impl ContentBudget {
+    /// Get the maximum length allowed by this budget, in the budget's own unit.
+    pub fn limit(self) -> usize {
+        match self {
+            Self::Words(limit) | Self::Bytes(limit) => limit,
+        }
+    }
+
+    /// Measure the specified content in the unit of this budget.
+    pub fn measure(self, content: &str) -> usize {
+        match self {
+            Self::Words(_) => count_words(content),
+            Self::Bytes(_) => content.len(),
+        }
+    }
+}
+
+/// A section whose content exceeds its budget.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BudgetOverage {
+    /// Name of the section.
+    pub section: String,
+
+    /// Path to the content file of the section.
+    pub file: PathBuf,
+
+    /// The budget that applies to the section.
+    pub budget: ContentBudget,
+
+    /// Length of the section's content, in the unit of `budget`.
+    pub size: usize,
+
+    /// Amount by which the content exceeds the budget, in the unit of `budget`.
+    pub overage: usize,
+}
+
+/// A visitor that reports sections whose content exceeds a length budget.
+///
+/// Every section is measured against the budget that the section declares in its metadata under
+/// [`WORD_BUDGET_KEY`] or [`BYTE_BUDGET_KEY`]. Sections that do not declare a budget are measured
+/// against the budget set for their content file via `set_section_budget`, and otherwise against
+/// the default budget given at construction. In strict mode, `finish` turns any overage into an
+/// error.
+///
+/// A section that declares an invalid budget, or both budgets at once, is measured against the
+/// budget it would get without the declaration, and `finish` reports the first such section as an
+/// error of kind `ErrorKind::Parse`.
+///
+/// [`WORD_BUDGET_KEY`]: constant.WORD_BUDGET_KEY.html
+/// [`BYTE_BUDGET_KEY`]: constant.BYTE_BUDGET_KEY.html
+#[derive(Debug)]
+pub struct ContentBudgetVisitor {
+    budget: ContentBudget,
+    section_budgets: HashMap<PathBuf, ContentBudget>,
+    strict: bool,
+    overages: Vec<BudgetOverage>,
+    error: Option<Error>,
+}
+
+impl ContentBudgetVisitor {
+    /// Create a new `ContentBudgetVisitor` instance that measures every section against the
+    /// specified budget.
+    pub fn new(budget: ContentBudget) -> Self {
+        Self {
+            budget,
+            section_budgets: HashMap::new(),
+            strict: false,
+            overages: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Set whether overages should be reported as an error by `finish`.
+    pub fn set_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Override the budget of the sections whose content file is at the specified path, unless
+    /// they declare a budget in their metadata.
+    ///
+    /// The path is compared against `Section::file` as-is.
+    pub fn set_section_budget<P: Into<PathBuf>>(mut self, file: P, budget: ContentBudget) -> Self {
+        self.section_budgets.insert(file.into(), budget);
+        self
+    }
+
+    /// Get the sections found so far whose content exceeds their budget, in visiting order.
+    pub fn overages(&self) -> &[BudgetOverage] {
+        &self.overages
+    }
+
+    /// Consume this visitor and produce the sections whose content exceeds their budget, in
+    /// visiting order.
+    ///
+    /// Returns the error for the first section that declares an invalid budget, if any. In strict
+    /// mode, an error that lists every overage is returned instead if there is any.
+    pub fn finish(self) -> Result<Vec<BudgetOverage>> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        if self.strict && !self.overages.is_empty() {
+            let details = self
+                .overages
+                .iter()
+                .map(|o| {
+                    format!(
+                        "\"{}\" exceeds its budget of {} by {}",
+                        o.section,
+                        o.budget.limit(),
+                        o.overage
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("; ");
+            return Err(Error::from_message(format!(
+                "{} section(s) exceed their content budget: {}",
+                self.overages.len(),
+                details
+            )));
+        }
+        Ok(self.overages)
+    }
+}
+
+impl Visitor for ContentBudgetVisitor {
+    fn visit_section(&mut self, section: &Section) {
+        let declared = match declared_budget(section) {
+            Ok(declared) => declared,
+            Err(e) => {
+                self.error.get_or_insert(e);
+                None
+            }
+        };
+        let budget = declared
+            .or_else(|| self.section_budgets.get(&section.file).copied())
+            .unwrap_or(self.budget);
+        let size = budget.measure(&section.content);
+        if size > budget.limit() {
+            self.overages.push(BudgetOverage {
+                section: section.name.clone(),
+                file: section.file.clone(),
+                budget,
+                size,
+                overage: size - budget.limit(),
+            });
+        }
+    }
+}
+
+/// Get the budget that the specified section declares in its metadata, if any.
+fn declared_budget(section: &Section) -> Result<Option<ContentBudget>> {
+    let parse = |key: &str| -> Result<Option<usize>> {
+        match section.metadata.get(key) {
+            Some(value) => value.trim().parse().map(Some).map_err(|_| {
+                Error::parse(format!(
+                    "section \"{}\": invalid \"{}\" value \"{}\"",
+                    section.name, key, value
+                ))
+            }),
+            None => Ok(None),
+        }
+    };
+    match (parse(WORD_BUDGET_KEY)?, parse(BYTE_BUDGET_KEY)?) {
+        (Some(_), Some(_)) => Err(Error::parse(format!(
+            "section \"{}\": \"{}\" and \"{}\" cannot be both set",
+            section.name, WORD_BUDGET_KEY, BYTE_BUDGET_KEY
+        ))),
+        (Some(words), None) => Ok(Some(ContentBudget::Words(words))),
+        (None, Some(bytes)) => Ok(Some(ContentBudget::Bytes(bytes))),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Count the words in the specified content.
+///
+/// Words are runs of non-whitespace characters. Since CJK text does not delimit words with
+/// whitespace, every CJK character is counted as a word of its own.
+pub fn count_words(content: &str) -> usize {
+    let mut count = 0;
+    let mut in_word = false;
+    for c in content.chars() {
+        if c.is_whitespace() {
+            in_word = false;
+        } else if is_cjk(c) {
+            count += 1;
+            in_word = false;
+        } else if !in_word {
+            count += 1;
+            in_word = true;
+        }
+    }
+    count
+}
+
+/// Determine whether the specified character is a CJK character.
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF // Hiragana and Katakana
+        | 0x3400..=0x4DBF // CJK Unified Ideographs Extension A
+        | 0x4E00..=0x9FFF // CJK Unified Ideographs
+        | 0xAC00..=0xD7AF // Hangul Syllables
+        | 0xF900..=0xFAFF // CJK Compatibility Ideographs
+        | 0x20000..=0x2FA1F // CJK Unified Ideographs Extension B and beyond
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::builder::{BookBuilder, ChapterBuilder, SectionBuilder};
+    use crate::tree::visitor::visit;
+    use crate::tree::Book;
+
+    fn book(sections: Vec<Section>) -> Book {
+        let chapter = sections
+            .into_iter()
+            .fold(ChapterBuilder::new(), |c, s| c.add_section(s))
+            .build();
+        BookBuilder::new().add_chapter(chapter).build()
+    }
+
+    fn section(name: &str, content: &str) -> Section {
+        let mut section = SectionBuilder::new()
+            .set_name(name)
+            .set_file(format!("{}.md", name))
+            .set_content(content)
+            .build();
+        section.extract_frontmatter().unwrap();
+        section
+    }
+
+    fn check(visitor: ContentBudgetVisitor, book: &Book) -> Result<Vec<BudgetOverage>> {
+        let mut visitor = visitor;
+        visit(book, &mut visitor);
+        visitor.finish()
+    }
+
+    #[test]
+    fn count_words_counts_cjk_characters() {
+        assert_eq!(count_words("one two  three\nfour"), 4);
+        assert_eq!(count_words("中文字 and"), 4);
+        assert_eq!(count_words(""), 0);
+    }
+
+    #[test]
+    fn reports_overages() {
+        let book = book(vec![
+            section("short", "one two"),
+            section("long", "one two three four"),
+        ]);
+        let overages = check(ContentBudgetVisitor::new(ContentBudget::Words(3)), &book).unwrap();
+        assert_eq!(
+            overages,
+            vec![BudgetOverage {
+                section: "long".to_owned(),
+                file: PathBuf::from("long.md"),
+                budget: ContentBudget::Words(3),
+                size: 4,
+                overage: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn strict_mode_turns_overages_into_errors() {
+        let book = book(vec![section("long", "one two three four")]);
+        let visitor = ContentBudgetVisitor::new(ContentBudget::Bytes(4)).set_strict(true);
+        let e = check(visitor, &book).unwrap_err();
+        assert!(e
+            .to_string()
+            .contains("\"long\" exceeds its budget of 4 by"));
+    }
+
+    #[test]
+    fn path_overrides_apply() {
+        let book = book(vec![section("appendix", "one two three four")]);
+        let visitor = ContentBudgetVisitor::new(ContentBudget::Words(3))
+            .set_section_budget("appendix.md", ContentBudget::Words(10));
+        assert!(check(visitor, &book).unwrap().is_empty());
+    }
+
+    #[test]
+    fn frontmatter_overrides_take_precedence() {
+        let book = book(vec![
+            section("appendix", "---\nword_budget: 10\n---\none two three four"),
+            section("bytes", "---\nbyte_budget = 5\n---\none two"),
+        ]);
+        let visitor = ContentBudgetVisitor::new(ContentBudget::Words(3))
+            .set_section_budget("appendix.md", ContentBudget::Words(1));
+        let overages = check(visitor, &book).unwrap();
+        assert_eq!(overages.len(), 1);
+        assert_eq!(overages[0].section, "bytes");
+        assert_eq!(overages[0].budget, ContentBudget::Bytes(5));
+    }
+
+    #[test]
+    fn invalid_frontmatter_budgets_are_errors() {
+        let invalid = book(vec![section("a", "---\nword_budget: many\n---\none")]);
+        let e = check(ContentBudgetVisitor::new(ContentBudget::Words(3)), &invalid).unwrap_err();
+        assert_eq!(e.kind(), crate::error::ErrorKind::Parse);
+
+        let both = book(vec![section(
+            "a",
+            "---\nword_budget: 1\nbyte_budget: 1\n---\none",
+        )]);
+        let e = check(ContentBudgetVisitor::new(ContentBudget::Words(3)), &both).unwrap_err();
+        assert!(e.to_string().contains("cannot be both set"));
+    }
+}
@@ -1,12 +1,20 @@
 //! This module defines builder types for building the nodes in an OpenBook document tree.
 //!
 
-use crate::tree::{Book, BookConfig, GlobalizedBooks};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::tree::frontmatter::FrontmatterFormat;
+use crate::tree::numbering::SectionNumber;
+use crate::tree::{
+    Book, BookConfig, BookStructureConfig, Chapter, GlobalizedBooks, Glossary, OutputConfig,
+    Section, TextDirection,
+};
 
 /// Build [`GlobalizedBooks`] nodes in a declarative way.
 ///
 /// [`GlobalizedBooks`]: ../struct.GlobalizedBooks.html
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct GlobalizedBooksBuilder {
     config: Option<BookConfig>,
     books: Vec<(String, Book)>,
@@ -57,3 +65,549 @@ impl GlobalizedBooksBuilder {
         }
     }
 }
+
+/// Build [`Book`] nodes in a declarative way.
+///
+/// [`Book`]: ../struct.Book.html
+#[derive(Clone, Debug, Default)]
+pub struct BookBuilder {
+    config: Option<BookConfig>,
+    preface: Option<Section>,
+    chapters: Vec<Chapter>,
+    glossary: Glossary,
+}
+
+impl BookBuilder {
+    /// Create a new `BookBuilder` instance.
+    pub fn new() -> Self {
+        Self {
+            config: None,
+            preface: None,
+            chapters: Vec::new(),
+            glossary: Glossary::default(),
+        }
+    }
+
+    /// Set the book's local configuration.
+    pub fn set_config(mut self, config: BookConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Set the preface section of the [`Book`] node under build.
+    ///
+    /// [`Book`]: ../struct.Book.html
+    pub fn set_preface(mut self, preface: Section) -> Self {
+        self.preface = Some(preface);
+        self
+    }
+
+    /// Add a [`Chapter`] node as the last chapter of the [`Book`] node under build.
+    ///
+    /// [`Chapter`]: ../struct.Chapter.html
+    /// [`Book`]: ../struct.Book.html
+    pub fn add_chapter(mut self, chapter: Chapter) -> Self {
+        self.chapters.push(chapter);
+        self
+    }
+
+    /// Set the glossary of the [`Book`] node under build.
+    ///
+    /// [`Book`]: ../struct.Book.html
+    pub fn set_glossary(mut self, glossary: Glossary) -> Self {
+        self.glossary = glossary;
+        self
+    }
+
+    /// Build the [`Book`] object, numbering its sections by `Book::number_sections`.
+    ///
+    /// [`Book`]: ../struct.Book.html
+    pub fn build(self) -> Book {
+        let mut book = Book {
+            config: self.config.unwrap_or_default(),
+            preface: self.preface.unwrap_or_default(),
+            chapters: self.chapters,
+            glossary: self.glossary,
+        };
+        book.number_sections();
+        book
+    }
+}
+
+/// Build [`Chapter`] nodes in a declarative way.
+///
+/// [`Chapter`]: ../struct.Chapter.html
+#[derive(Clone, Debug, Default)]
+pub struct ChapterBuilder {
+    name: String,
+    sections: Vec<Section>,
+    part: Option<String>,
+    separated: bool,
+    unnumbered: bool,
+}
+
+impl ChapterBuilder {
+    /// Create a new `ChapterBuilder` instance.
+    pub fn new() -> Self {
+        Self {
+            name: String::new(),
+            sections: Vec::new(),
+            part: None,
+            separated: false,
+            unnumbered: false,
+        }
+    }
+
+    /// Set the chapter's name.
+    pub fn set_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Add a [`Section`] node as the last section of the [`Chapter`] node under build.
+    ///
+    /// [`Section`]: ../struct.Section.html
+    /// [`Chapter`]: ../struct.Chapter.html
+    pub fn add_section(mut self, section: Section) -> Self {
+        self.sections.push(section);
+        self
+    }
+
+    /// Set the name of the part that the chapter belongs to.
+    pub fn set_part<S: Into<String>>(mut self, part: S) -> Self {
+        self.part = Some(part.into());
+        self
+    }
+
+    /// Set whether the chapter is separated from the previous one.
+    pub fn set_separated(mut self, separated: bool) -> Self {
+        self.separated = separated;
+        self
+    }
+
+    /// Set whether the sections of the chapter are not numbered.
+    pub fn set_unnumbered(mut self, unnumbered: bool) -> Self {
+        self.unnumbered = unnumbered;
+        self
+    }
+
+    /// Build the [`Chapter`] object.
+    ///
+    /// [`Chapter`]: ../struct.Chapter.html
+    pub fn build(self) -> Chapter {
+        Chapter {
+            name: self.name,
+            sections: self.sections,
+            part: self.part,
+            separated: self.separated,
+            unnumbered: self.unnumbered,
+        }
+    }
+}
+
+/// Build [`Section`] nodes in a declarative way.
+///
+/// [`Section`]: ../struct.Section.html
+#[derive(Clone, Debug, Default)]
+pub struct SectionBuilder {
+    file: PathBuf,
+    anchor: Option<String>,
+    name: String,
+    content: String,
+    metadata: BTreeMap<String, String>,
+    number: Option<SectionNumber>,
+    subsections: Vec<Section>,
+}
+
+impl SectionBuilder {
+    /// Create a new `SectionBuilder` instance.
+    pub fn new() -> Self {
+        Self {
+            file: PathBuf::new(),
+            anchor: None,
+            name: String::new(),
+            content: String::new(),
+            metadata: BTreeMap::new(),
+            number: None,
+            subsections: Vec::new(),
+        }
+    }
+
+    /// Set the path to the file that contains the section's content.
+    pub fn set_file<P: Into<PathBuf>>(mut self, file: P) -> Self {
+        self.file = file.into();
+        self
+    }
+
+    /// Set the anchor of the section's start point in its content file.
+    pub fn set_anchor<S: Into<String>>(mut self, anchor: S) -> Self {
+        self.anchor = Some(anchor.into());
+        self
+    }
+
+    /// Set the section's name.
+    pub fn set_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Set the section's content.
+    pub fn set_content<S: Into<String>>(mut self, content: S) -> Self {
+        self.content = content.into();
+        self
+    }
+
+    /// Set the metadata entry with the specified key of the section.
+    pub fn set_metadata<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set the section's number.
+    ///
+    /// The number is kept when the section becomes part of a book built by [`BookBuilder`], which
+    /// only numbers the sections that are not numbered yet.
+    ///
+    /// [`BookBuilder`]: struct.BookBuilder.html
+    pub fn set_number(mut self, number: SectionNumber) -> Self {
+        self.number = Some(number);
+        self
+    }
+
+    /// Add a [`Section`] node as the last subsection of the [`Section`] node under build.
+    ///
+    /// [`Section`]: ../struct.Section.html
+    pub fn add_subsection(mut self, subsection: Section) -> Self {
+        self.subsections.push(subsection);
+        self
+    }
+
+    /// Build the [`Section`] object.
+    ///
+    /// [`Section`]: ../struct.Section.html
+    pub fn build(self) -> Section {
+        Section {
+            file: self.file,
+            anchor: self.anchor,
+            name: self.name,
+            content: self.content,
+            metadata: self.metadata,
+            number: self.number,
+            subsections: self.subsections,
+        }
+    }
+}
+
+/// Build [`BookConfig`] objects in a declarative way.
+///
+/// Entries that are not set are left unset in the built configuration, so that they can be taken
+/// from another configuration by `BookConfig::merge`.
+///
+/// [`BookConfig`]: ../struct.BookConfig.html
+#[derive(Clone, Debug, Default)]
+pub struct BookConfigBuilder {
+    config: BookConfig,
+}
+
+impl BookConfigBuilder {
+    /// Create a new `BookConfigBuilder` instance.
+    pub fn new() -> Self {
+        Self {
+            config: BookConfig::default(),
+        }
+    }
+
+    /// Set the path to the root directory of the book.
+    pub fn set_root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.config.root = root.into();
+        self
+    }
+
+    /// Set the structural configuration of the book.
+    pub fn set_structure(mut self, structure: BookStructureConfig) -> Self {
+        self.config.structure = structure;
+        self
+    }
+
+    /// Set the path to the book's `README.md` file.
+    pub fn set_readme<P: Into<PathBuf>>(mut self, readme: P) -> Self {
+        self.config.structure.readme = Some(readme.into());
+        self
+    }
+
+    /// Set the path to the book's `SUMMARY.md` file.
+    pub fn set_summary<P: Into<PathBuf>>(mut self, summary: P) -> Self {
+        self.config.structure.summary = Some(summary.into());
+        self
+    }
+
+    /// Set the path to the book's `GLOSSARY.md` file.
+    pub fn set_glossary<P: Into<PathBuf>>(mut self, glossary: P) -> Self {
+        self.config.structure.glossary = Some(glossary.into());
+        self
+    }
+
+    /// Set the path to the book's `LANGS.md` file.
+    pub fn set_languages<P: Into<PathBuf>>(mut self, languages: P) -> Self {
+        self.config.structure.languages = Some(languages.into());
+        self
+    }
+
+    /// Set the book's title.
+    pub fn set_title<S: Into<String>>(mut self, title: S) -> Self {
+        self.config.title = Some(title.into());
+        self
+    }
+
+    /// Set the book's description.
+    pub fn set_description<S: Into<String>>(mut self, description: S) -> Self {
+        self.config.description = Some(description.into());
+        self
+    }
+
+    /// Set the book's author.
+    pub fn set_author<S: Into<String>>(mut self, author: S) -> Self {
+        self.config.author = Some(author.into());
+        self
+    }
+
+    /// Set the book's ISBN.
+    pub fn set_isbn<S: Into<String>>(mut self, isbn: S) -> Self {
+        self.config.isbn = Some(isbn.into());
+        self
+    }
+
+    /// Set the book's language, encoded in ISO 639-1 language code.
+    pub fn set_language<S: Into<String>>(mut self, language: S) -> Self {
+        self.config.language = Some(language.into());
+        self
+    }
+
+    /// Set the book's text direction.
+    pub fn set_direction(mut self, direction: TextDirection) -> Self {
+        self.config.direction = Some(direction);
+        self
+    }
+
+    /// Set the template of the URL at which the source of a section can be edited.
+    pub fn set_edit_url_template<S: Into<String>>(mut self, template: S) -> Self {
+        self.config.edit_url_template = Some(template.into());
+        self
+    }
+
+    /// Set whether section names are taken from the first level 1 heading of their content.
+    pub fn set_title_from_content(mut self, title_from_content: bool) -> Self {
+        self.config.title_from_content = Some(title_from_content);
+        self
+    }
+
+    /// Set whether section names taken from the content take precedence over `SUMMARY.md`.
+    pub fn set_force_content_title(mut self, force_content_title: bool) -> Self {
+        self.config.force_content_title = Some(force_content_title);
+        self
+    }
+
+    /// Set the format of the frontmatter of section content.
+    pub fn set_frontmatter_format(mut self, format: FrontmatterFormat) -> Self {
+        self.config.frontmatter_format = Some(format);
+        self
+    }
+
+    /// Add a build target, replacing the target with the same name if there is one.
+    pub fn add_output(mut self, output: OutputConfig) -> Self {
+        self.config.outputs.retain(|o| o.name != output.name);
+        self.config.outputs.push(output);
+        self.config.outputs.sort_by(|a, b| a.name.cmp(&b.name));
+        self
+    }
+
+    /// Set an unrecognized configuration entry at the specified dotted path.
+    pub fn set_extra<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.config.extra.insert(key.into(), value.into());
+        self
+    }
+
+    /// Build the [`BookConfig`] object.
+    ///
+    /// [`BookConfig`]: ../struct.BookConfig.html
+    pub fn build(self) -> BookConfig {
+        self.config
+    }
+}
+
+impl GlobalizedBooks {
+    /// Create a [`GlobalizedBooksBuilder`] that builds a copy of this node.
+    ///
+    /// This allows a loaded tree to be modified through the builder API and then rebuilt.
+    ///
+    /// [`GlobalizedBooksBuilder`]: struct.GlobalizedBooksBuilder.html
+    pub fn to_builder(&self) -> GlobalizedBooksBuilder {
+        GlobalizedBooksBuilder {
+            config: Some(self.config.clone()),
+            books: self.books.clone(),
+        }
+    }
+}
+
+impl Book {
+    /// Create a [`BookBuilder`] that builds a copy of this node.
+    ///
+    /// This allows a loaded book to be modified through the builder API and then rebuilt.
+    ///
+    /// [`BookBuilder`]: struct.BookBuilder.html
+    pub fn to_builder(&self) -> BookBuilder {
+        BookBuilder {
+            config: Some(self.config.clone()),
+            preface: Some(self.preface.clone()),
+            chapters: self.chapters.clone(),
+            glossary: self.glossary.clone(),
+        }
+    }
+}
+
+impl Chapter {
+    /// Create a [`ChapterBuilder`] that builds a copy of this node.
+    ///
+    /// [`ChapterBuilder`]: struct.ChapterBuilder.html
+    pub fn to_builder(&self) -> ChapterBuilder {
+        ChapterBuilder {
+            name: self.name.clone(),
+            sections: self.sections.clone(),
+            part: self.part.clone(),
+            separated: self.separated,
+            unnumbered: self.unnumbered,
+        }
+    }
+}
+
+impl Section {
+    /// Create a [`SectionBuilder`] that builds a copy of this node.
+    ///
+    /// [`SectionBuilder`]: struct.SectionBuilder.html
+    pub fn to_builder(&self) -> SectionBuilder {
+        SectionBuilder {
+            file: self.file.clone(),
+            anchor: self.anchor.clone(),
+            name: self.name.clone(),
+            content: self.content.clone(),
+            metadata: self.metadata.clone(),
+            number: self.number.clone(),
+            subsections: self.subsections.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_builders_build_default_nodes() {
+        assert_eq!(BookBuilder::new().build(), Book::default());
+        assert_eq!(ChapterBuilder::new().build(), Chapter::default());
+        assert_eq!(SectionBuilder::new().build(), Section::default());
+        assert_eq!(BookConfigBuilder::new().build(), BookConfig::default());
+    }
+
+    #[test]
+    fn builders_set_every_field() {
+        let section = SectionBuilder::new()
+            .set_name("Setup")
+            .set_file("guide/setup.md")
+            .set_content("Install it.")
+            .set_metadata("draft", "true")
+            .add_subsection(SectionBuilder::new().set_name("Linux").build())
+            .add_subsection(SectionBuilder::new().set_name("macOS").build())
+            .build();
+        assert_eq!(section.name, "Setup");
+        assert_eq!(section.file, PathBuf::from("guide/setup.md"));
+        assert_eq!(section.content, "Install it.");
+        assert_eq!(section.metadata["draft"], "true");
+        let subsections: Vec<&str> = section
+            .subsections
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(subsections, vec!["Linux", "macOS"]);
+
+        let chapter = ChapterBuilder::new()
+            .set_name("Guide")
+            .set_separated(true)
+            .add_section(section.clone())
+            .build();
+        assert_eq!(
+            chapter,
+            Chapter {
+                name: "Guide".to_owned(),
+                sections: vec![section],
+                separated: true,
+                ..Chapter::default()
+            }
+        );
+
+        let config = BookConfigBuilder::new().set_root("/book").build();
+        let book = BookBuilder::new()
+            .set_config(config.clone())
+            .add_chapter(chapter.clone())
+            .add_chapter(ChapterBuilder::new().set_name("Reference").build())
+            .build();
+        assert_eq!(book.config, config);
+        assert_eq!(book.chapters.len(), 2);
+        assert_eq!(book.chapters[0].name, chapter.name);
+
+        // Building a book numbers its sections.
+        let setup = &book.chapters[0].sections[0];
+        assert_eq!(setup.number, Some(SectionNumber::new(vec![1])));
+        assert_eq!(
+            setup.subsections[1].number,
+            Some(SectionNumber::new(vec![1, 2]))
+        );
+    }
+
+    #[test]
+    fn to_builder_round_trips_the_tree() {
+        let intro = SectionBuilder::new()
+            .set_name("Intro")
+            .set_file("intro.md")
+            .set_anchor("start")
+            .set_content("Hello")
+            .set_metadata("author", "Ana")
+            .set_number(SectionNumber::new(vec![1, 1]))
+            .add_subsection(SectionBuilder::new().set_name("Details").build())
+            .build();
+        let chapter = ChapterBuilder::new()
+            .set_name("Basics")
+            .set_part("Part I")
+            .set_unnumbered(true)
+            .add_section(intro.clone())
+            .build();
+        let book = BookBuilder::new()
+            .set_config(BookConfigBuilder::new().set_title("Guide").build())
+            .set_preface(SectionBuilder::new().set_name("Preface").build())
+            .add_chapter(chapter.clone())
+            .build();
+        let books = GlobalizedBooksBuilder::new()
+            .set_config(BookConfigBuilder::new().set_author("Ana").build())
+            .add_localized_book("en".to_owned(), book.clone())
+            .build();
+
+        assert_eq!(intro.to_builder().build(), intro);
+        assert_eq!(chapter.to_builder().build(), chapter);
+        assert_eq!(book.to_builder().build(), book);
+        assert_eq!(books.to_builder().build(), books);
+    }
+
+    #[test]
+    fn to_builder_allows_modifying_a_loaded_tree() {
+        let book = BookBuilder::new()
+            .add_chapter(ChapterBuilder::new().set_name("A").build())
+            .build();
+        let modified = book
+            .to_builder()
+            .add_chapter(ChapterBuilder::new().set_name("B").build())
+            .build();
+        let names: Vec<&str> = modified.chapters.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["A", "B"]);
+        assert_eq!(modified.preface, book.preface);
+    }
+}
@@ -0,0 +1,325 @@
+//! This module provides a visitor that checks books against a list of required sections.
+//!
+//! Teams that standardize the structure of their documents can use the
+//! [`StructureCoverageVisitor`] to find out, for each book and each of its chapters, which of the
+//! required sections are present and which are missing.
+//!
+//! [`StructureCoverageVisitor`]: struct.StructureCoverageVisitor.html
+//!
+
+use crate::tree::visitor::{VisitContext, Visitor};
+use crate::tree::{Book, Chapter, Section};
+
+/// Coverage of the required sections within a single book.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BookCoverage {
+    /// Required sections that are present in the book.
+    pub present: Vec<String>,
+
+    /// Required sections that are missing from the book.
+    pub missing: Vec<String>,
+
+    /// Coverage of the required sections within each chapter of the book, in chapter order.
+    pub chapters: Vec<ChapterCoverage>,
+}
+
+impl BookCoverage {
+    /// Get the percentage of required sections that are present in the book, in the range of
+    /// `0.0` to `100.0`.
+    ///
+    /// If no section is required, the coverage is `100.0`.
+    pub fn coverage(&self) -> f64 {
+        percentage(&self.present, &self.missing)
+    }
+}
+
+/// Coverage of the required sections within a single chapter.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ChapterCoverage {
+    /// Name of the chapter.
+    pub name: String,
+
+    /// Required sections that are present in the chapter.
+    pub present: Vec<String>,
+
+    /// Required sections that are missing from the chapter.
+    pub missing: Vec<String>,
+}
+
+impl ChapterCoverage {
+    /// Get the percentage of required sections that are present in the chapter, in the range of
+    /// `0.0` to `100.0`.
+    ///
+    /// If no section is required, the coverage is `100.0`.
+    pub fn coverage(&self) -> f64 {
+        percentage(&self.present, &self.missing)
+    }
+}
+
+/// Presence of each required section within a book and within each of its chapters.
+#[derive(Clone, Debug, Default)]
+struct BookFound {
+    found: Vec<bool>,
+    chapters: Vec<(String, Vec<bool>)>,
+}
+
+/// Compares a required section name against the name of a visited section.
+type SectionNameMatcher = Box<dyn Fn(&str, &str) -> bool>;
+
+/// A visitor that reports which required sections are present in each visited book.
+///
+/// Each visited section is compared against every required section name with a matcher. The
+/// default matcher compares names case-insensitively and ignores characters that are not
+/// alphanumeric, so that `"Usage"` matches `"usage:"`. A custom matcher can be supplied via
+/// `with_matcher`.
+pub struct StructureCoverageVisitor {
+    required: Vec<String>,
+    matcher: SectionNameMatcher,
+    found: Vec<BookFound>,
+}
+
+impl StructureCoverageVisitor {
+    /// Create a new `StructureCoverageVisitor` instance that checks for the specified required
+    /// section names with the default matcher.
+    pub fn new(required: Vec<String>) -> Self {
+        Self::with_matcher(required, default_matcher)
+    }
+
+    /// Create a new `StructureCoverageVisitor` instance that checks for the specified required
+    /// section names with the specified matcher.
+    ///
+    /// The first argument of the matcher is the required section name and the second argument is
+    /// the name of a visited section.
+    pub fn with_matcher<M>(required: Vec<String>, matcher: M) -> Self
+    where
+        M: Fn(&str, &str) -> bool + 'static,
+    {
+        Self {
+            required,
+            matcher: Box::new(matcher),
+            found: Vec::new(),
+        }
+    }
+
+    /// Consume this visitor and produce the coverage report of each visited book, in visiting
+    /// order.
+    pub fn into_report(self) -> Vec<BookCoverage> {
+        let required = self.required;
+        self.found
+            .into_iter()
+            .map(|book| {
+                let (present, missing) = split(&required, &book.found);
+                let chapters = book
+                    .chapters
+                    .into_iter()
+                    .map(|(name, found)| {
+                        let (present, missing) = split(&required, &found);
+                        ChapterCoverage {
+                            name,
+                            present,
+                            missing,
+                        }
+                    })
+                    .collect();
+                BookCoverage {
+                    present,
+                    missing,
+                    chapters,
+                }
+            })
+            .collect()
+    }
+
+    /// Get the presence of the required sections within the book that is being visited.
+    fn current_book(&mut self) -> &mut BookFound {
+        if self.found.is_empty() {
+            // Sections are visited without an enclosing book.
+            self.found.push(BookFound {
+                found: vec![false; self.required.len()],
+                chapters: Vec::new(),
+            });
+        }
+        self.found.last_mut().unwrap()
+    }
+}
+
+impl Visitor for StructureCoverageVisitor {
+    fn visit_book(&mut self, _book: &Book) {
+        self.found.push(BookFound {
+            found: vec![false; self.required.len()],
+            chapters: Vec::new(),
+        });
+    }
+
+    fn visit_chapter(&mut self, chapter: &Chapter) {
+        let required = self.required.len();
+        self.current_book()
+            .chapters
+            .push((chapter.name.clone(), vec![false; required]));
+    }
+
+    fn visit_section_in(&mut self, section: &Section, context: &VisitContext) {
+        let matches: Vec<bool> = self
+            .required
+            .iter()
+            .map(|name| (self.matcher)(name, &section.name))
+            .collect();
+        let in_chapter = context.chapter().is_some();
+
+        let book = self.current_book();
+        mark(&mut book.found, &matches);
+        if in_chapter {
+            if let Some((_, found)) = book.chapters.last_mut() {
+                mark(found, &matches);
+            }
+        }
+    }
+}
+
+/// Mark the required sections that match a visited section as present.
+fn mark(found: &mut [bool], matches: &[bool]) {
+    for (present, matched) in found.iter_mut().zip(matches) {
+        *present |= *matched;
+    }
+}
+
+/// Split the required section names into the present ones and the missing ones.
+fn split(required: &[String], found: &[bool]) -> (Vec<String>, Vec<String>) {
+    let mut present = Vec::new();
+    let mut missing = Vec::new();
+    for (name, found) in required.iter().zip(found) {
+        if *found {
+            present.push(name.clone());
+        } else {
+            missing.push(name.clone());
+        }
+    }
+    (present, missing)
+}
+
+/// Get the percentage of the required sections that are present, in the range of `0.0` to
+/// `100.0`.
+fn percentage(present: &[String], missing: &[String]) -> f64 {
+    let total = present.len() + missing.len();
+    if total == 0 {
+        return 100.0;
+    }
+    present.len() as f64 * 100.0 / total as f64
+}
+
+/// Compare the two section names case-insensitively, ignoring non-alphanumeric characters.
+fn default_matcher(required: &str, name: &str) -> bool {
+    fn normalize(s: &str) -> String {
+        s.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    }
+
+    normalize(required) == normalize(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::builder::{BookBuilder, ChapterBuilder, SectionBuilder};
+    use crate::tree::visitor::visit;
+
+    fn required() -> Vec<String> {
+        ["Overview", "Usage", "API", "Examples"]
+            .iter()
+            .map(|s| (*s).to_owned())
+            .collect()
+    }
+
+    fn section(name: &str) -> Section {
+        SectionBuilder::new().set_name(name).build()
+    }
+
+    fn report(book: &Book) -> Vec<BookCoverage> {
+        let mut visitor = StructureCoverageVisitor::new(required());
+        visit(book, &mut visitor);
+        visitor.into_report()
+    }
+
+    #[test]
+    fn reports_missing_sections_per_book_and_chapter() {
+        let book = BookBuilder::new()
+            .set_preface(section("Overview"))
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("core")
+                    .add_section(section("usage:"))
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_name("Reference")
+                            .add_subsection(section("api"))
+                            .build(),
+                    )
+                    .build(),
+            )
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("cli")
+                    .add_section(section("Usage"))
+                    .build(),
+            )
+            .build();
+
+        let report = report(&book);
+        assert_eq!(report.len(), 1);
+        let coverage = &report[0];
+        assert_eq!(coverage.present, vec!["Overview", "Usage", "API"]);
+        assert_eq!(coverage.missing, vec!["Examples"]);
+        assert!((coverage.coverage() - 75.0).abs() < f64::EPSILON);
+
+        assert_eq!(
+            coverage.chapters,
+            vec![
+                ChapterCoverage {
+                    name: "core".to_owned(),
+                    present: vec!["Usage".to_owned(), "API".to_owned()],
+                    missing: vec!["Overview".to_owned(), "Examples".to_owned()],
+                },
+                ChapterCoverage {
+                    name: "cli".to_owned(),
+                    present: vec!["Usage".to_owned()],
+                    missing: vec![
+                        "Overview".to_owned(),
+                        "API".to_owned(),
+                        "Examples".to_owned()
+                    ],
+                },
+            ]
+        );
+        assert!((coverage.chapters[1].coverage() - 25.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn custom_matcher_is_used() {
+        let book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("docs")
+                    .add_section(section("Usage guide"))
+                    .build(),
+            )
+            .build();
+        let mut visitor =
+            StructureCoverageVisitor::with_matcher(vec!["usage".to_owned()], |r, n| {
+                n.to_lowercase().starts_with(r)
+            });
+        visit(&book, &mut visitor);
+        let report = visitor.into_report();
+        assert_eq!(report[0].present, vec!["usage"]);
+        assert_eq!(report[0].chapters[0].present, vec!["usage"]);
+    }
+
+    #[test]
+    fn empty_requirements_are_fully_covered() {
+        let mut visitor = StructureCoverageVisitor::new(Vec::new());
+        visit(&BookBuilder::new().build(), &mut visitor);
+        let report = visitor.into_report();
+        assert!((report[0].coverage() - 100.0).abs() < f64::EPSILON);
+    }
+}
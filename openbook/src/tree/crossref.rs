@@ -0,0 +1,287 @@
+//! This module resolves the prerequisite and related sections that sections declare in their
+//! frontmatter.
+//!
+//! A section lists the sections that should be read before it under the `prerequisites` key of its
+//! frontmatter, and sections on similar topics under the `related` key, in the same formats as
+//! `tags`:
+//!
+//! ```markdown
+//! ---
+//! prerequisites = ["../basics/install.md"]
+//! related = ["config.md", "cli.md"]
+//! ---
+//! ```
+//!
+//! Every entry is the path to the content file of a section, relative to the directory of the
+//! content file of the declaring section, like the target of a markdown link. The
+//! [`CrossReferenceVisitor`] resolves the entries against a book into [`CrossLinks`] that renderers
+//! can surface, and reports the entries that do not refer to any section of the book.
+//!
+//! [`CrossReferenceVisitor`]: struct.CrossReferenceVisitor.html
+//! [`CrossLinks`]: struct.CrossLinks.html
+//!
+
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::fs::normalize;
+use crate::tree::visitor::Visitor;
+use crate::tree::{Book, Section};
+
+/// The frontmatter key that lists the prerequisite sections of a section.
+pub const PREREQUISITES_KEY: &str = "prerequisites";
+
+/// The frontmatter key that lists the related sections of a section.
+pub const RELATED_KEY: &str = "related";
+
+impl Section {
+    /// Get the content files of the prerequisite sections in the metadata of this section.
+    ///
+    /// The entries are resolved against the directory of the content file of this section and
+    /// normalized, so they can be compared with `Section::file`.
+    pub fn prerequisites(&self) -> Vec<PathBuf> {
+        self.referenced_files(PREREQUISITES_KEY)
+    }
+
+    /// Get the content files of the related sections in the metadata of this section.
+    ///
+    /// The entries are resolved as in `prerequisites`.
+    pub fn related(&self) -> Vec<PathBuf> {
+        self.referenced_files(RELATED_KEY)
+    }
+
+    /// Get the content files listed in the metadata of this section under the specified key.
+    fn referenced_files(&self, key: &str) -> Vec<PathBuf> {
+        let base = self.file.parent().unwrap_or_else(|| Path::new(""));
+        self.metadata_list(key)
+            .into_iter()
+            .map(|file| normalize(base.join(file)))
+            .collect()
+    }
+}
+
+/// A link to another section of the book.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CrossLink {
+    /// Title of the linked section.
+    pub title: String,
+
+    /// Content file of the linked section.
+    pub file: PathBuf,
+}
+
+/// The prerequisite and related sections of a section.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CrossLinks {
+    /// Name of the section that declares the links.
+    pub section: String,
+
+    /// Content file of the section that declares the links.
+    pub file: PathBuf,
+
+    /// Links to the prerequisite sections, in the order they are declared.
+    pub prerequisites: Vec<CrossLink>,
+
+    /// Links to the related sections, in the order they are declared.
+    pub related: Vec<CrossLink>,
+}
+
+/// A prerequisite or related entry that does not refer to any section of the book.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DanglingReference {
+    /// Name of the section that declares the entry.
+    pub section: String,
+
+    /// The frontmatter key of the entry, i.e. [`PREREQUISITES_KEY`] or [`RELATED_KEY`].
+    ///
+    /// [`PREREQUISITES_KEY`]: constant.PREREQUISITES_KEY.html
+    /// [`RELATED_KEY`]: constant.RELATED_KEY.html
+    pub key: &'static str,
+
+    /// The content file that the entry resolves to.
+    pub target: PathBuf,
+}
+
+/// A visitor that resolves the prerequisite and related sections of the visited sections against
+/// a book.
+///
+/// Sections that declare neither prerequisites nor related sections are skipped. All dangling
+/// references are collected rather than stopping at the first one.
+pub struct CrossReferenceVisitor<'a> {
+    book: &'a Book,
+    links: Vec<CrossLinks>,
+    dangling: Vec<DanglingReference>,
+}
+
+impl<'a> CrossReferenceVisitor<'a> {
+    /// Create a new `CrossReferenceVisitor` instance that resolves references against the
+    /// specified book.
+    pub fn new(book: &'a Book) -> Self {
+        Self {
+            book,
+            links: Vec::new(),
+            dangling: Vec::new(),
+        }
+    }
+
+    /// Get the dangling references found so far, in visiting order.
+    pub fn dangling_references(&self) -> &[DanglingReference] {
+        &self.dangling
+    }
+
+    /// Consume this visitor and get the links of every visited section that declares any, in
+    /// visiting order.
+    ///
+    /// Returns an error of kind `ErrorKind::NotFound` that names every dangling reference if there
+    /// is any.
+    pub fn finish(self) -> Result<Vec<CrossLinks>> {
+        if self.dangling.is_empty() {
+            return Ok(self.links);
+        }
+
+        let references: Vec<String> = self
+            .dangling
+            .iter()
+            .map(|r| {
+                format!(
+                    "section \"{}\": {} entry {} does not refer to any section",
+                    r.section,
+                    r.key,
+                    r.target.display()
+                )
+            })
+            .collect();
+        Err(Error::not_found(references.join("; ")))
+    }
+
+    /// Resolve the specified content files listed under the specified key of the specified section.
+    fn resolve(
+        &mut self,
+        section: &Section,
+        key: &'static str,
+        files: Vec<PathBuf>,
+    ) -> Vec<CrossLink> {
+        let mut links = Vec::with_capacity(files.len());
+        for file in files {
+            match self.book.find_section_by_file(&file) {
+                Some(target) => links.push(CrossLink {
+                    title: target.title().to_owned(),
+                    file,
+                }),
+                None => self.dangling.push(DanglingReference {
+                    section: section.name.clone(),
+                    key,
+                    target: file,
+                }),
+            }
+        }
+        links
+    }
+}
+
+impl<'a> Visitor for CrossReferenceVisitor<'a> {
+    fn visit_section(&mut self, section: &Section) {
+        let prerequisites = section.prerequisites();
+        let related = section.related();
+        if prerequisites.is_empty() && related.is_empty() {
+            return;
+        }
+
+        let prerequisites = self.resolve(section, PREREQUISITES_KEY, prerequisites);
+        let related = self.resolve(section, RELATED_KEY, related);
+        self.links.push(CrossLinks {
+            section: section.name.clone(),
+            file: section.file.clone(),
+            prerequisites,
+            related,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::tree::builder::{BookBuilder, ChapterBuilder, SectionBuilder};
+    use crate::tree::visitor::visit;
+
+    fn book(guide: Section) -> Book {
+        BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_name("Install")
+                            .set_file("basics/install.md")
+                            .build(),
+                    )
+                    .add_section(guide)
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_name("Config")
+                            .set_file("guide/config.md")
+                            .set_metadata("title", "Configuring")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+
+    fn guide(content: &str) -> Section {
+        let mut section = SectionBuilder::new()
+            .set_name("Usage")
+            .set_file("guide/usage.md")
+            .set_content(content)
+            .build();
+        section.extract_frontmatter().unwrap();
+        section
+    }
+
+    #[test]
+    fn resolves_declared_sections() {
+        let book = book(guide(
+            "---\nprerequisites = [\"../basics/install.md\"]\nrelated = \"config.md\"\n---\n",
+        ));
+        let mut visitor = CrossReferenceVisitor::new(&book);
+        visit(&book, &mut visitor);
+        assert!(visitor.dangling_references().is_empty());
+
+        assert_eq!(
+            visitor.finish().unwrap(),
+            vec![CrossLinks {
+                section: "Usage".to_owned(),
+                file: PathBuf::from("guide/usage.md"),
+                prerequisites: vec![CrossLink {
+                    title: "Install".to_owned(),
+                    file: PathBuf::from("basics/install.md"),
+                }],
+                related: vec![CrossLink {
+                    title: "Configuring".to_owned(),
+                    file: PathBuf::from("guide/config.md"),
+                }],
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_dangling_references() {
+        let book = book(guide(
+            "---\nprerequisites: [../basics/install.md, setup.md]\n---\n",
+        ));
+        let mut visitor = CrossReferenceVisitor::new(&book);
+        visit(&book, &mut visitor);
+        assert_eq!(
+            visitor.dangling_references(),
+            &[DanglingReference {
+                section: "Usage".to_owned(),
+                key: PREREQUISITES_KEY,
+                target: PathBuf::from("guide/setup.md"),
+            }][..]
+        );
+
+        let error = visitor.finish().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+        assert!(error.to_string().contains("guide/setup.md"));
+    }
+}
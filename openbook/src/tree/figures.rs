@@ -0,0 +1,511 @@
+//! This module implements automatic numbering of figures and tables in section content.
+//!
+//! The [`FigureNumberingProcessor`] recognizes the following markdown constructs:
+//!
+//! * A figure is a line that consists of a single image, optionally followed by a label attribute:
+//!   `![A cat](cat.png){#fig:cat}`;
+//! * A table is a markdown table, optionally preceded by a line that consists of a single label
+//!   attribute: `{#tbl:results}`.
+//!
+//! Every figure and table is assigned a sequential number, either across the whole book or within
+//! each chapter. An anchor and a caption carrying the number are injected next to it, and the
+//! label attributes are removed from the content. References of the form `{{#ref fig:cat}}` are
+//! then rewritten to links to the corresponding figure or table, such as `[Figure 2](#fig-cat)`.
+//!
+//! [`FigureNumberingProcessor`]: struct.FigureNumberingProcessor.html
+//!
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::fs::relative_link;
+use crate::tree::{Book, Section};
+
+/// Specifies the scope in which figures and tables are numbered sequentially.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum NumberingScope {
+    /// Figures and tables are numbered across the whole book, e.g. `Figure 7`.
+    Book,
+
+    /// Figures and tables are numbered within each chapter of the book, and the number is prefixed
+    /// with the chapter's position, e.g. `Figure 2.3`. The preface is not counted as a chapter and
+    /// its figures and tables are numbered without a prefix.
+    Chapter,
+}
+
+/// A `{{#ref ...}}` reference that does not refer to any labeled figure or table.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnresolvedReference {
+    /// Name of the section that contains the reference.
+    pub section: String,
+
+    /// The referenced label.
+    pub label: String,
+}
+
+/// Numbers figures and tables in the sections of a book and resolves references to them.
+#[derive(Clone, Debug)]
+pub struct FigureNumberingProcessor {
+    scope: NumberingScope,
+}
+
+/// The kind of a numbered element.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ElementKind {
+    Figure,
+    Table,
+}
+
+impl ElementKind {
+    fn caption_prefix(self) -> &'static str {
+        match self {
+            Self::Figure => "Figure",
+            Self::Table => "Table",
+        }
+    }
+
+    fn anchor_prefix(self) -> &'static str {
+        match self {
+            Self::Figure => "figure",
+            Self::Table => "table",
+        }
+    }
+}
+
+/// A numbered figure or table and where to find it.
+#[derive(Clone, Debug)]
+struct NumberedElement {
+    kind: ElementKind,
+    number: String,
+    anchor: String,
+    file: PathBuf,
+}
+
+/// Numbering state of the chapter under processing.
+struct Counters {
+    prefix: Option<usize>,
+    figures: usize,
+    tables: usize,
+}
+
+impl Counters {
+    fn next(&mut self, kind: ElementKind) -> String {
+        let counter = match kind {
+            ElementKind::Figure => &mut self.figures,
+            ElementKind::Table => &mut self.tables,
+        };
+        *counter += 1;
+
+        match self.prefix {
+            Some(prefix) => format!("{}.{}", prefix, counter),
+            None => counter.to_string(),
+        }
+    }
+}
+
+impl FigureNumberingProcessor {
+    /// Create a new `FigureNumberingProcessor` instance that numbers figures and tables within the
+    /// specified scope.
+    pub fn new(scope: NumberingScope) -> Self {
+        Self { scope }
+    }
+
+    /// Number all figures and tables in the specified book and resolve references to them.
+    ///
+    /// References that cannot be resolved are left untouched in the content and are returned.
+    pub fn process(&self, book: &mut Book) -> Vec<UnresolvedReference> {
+        let mut labels = HashMap::new();
+
+        let mut counters = Counters {
+            prefix: None,
+            figures: 0,
+            tables: 0,
+        };
+        number_section(&mut book.preface, &mut counters, &mut labels);
+        for (i, chapter) in book.chapters.iter_mut().enumerate() {
+            if self.scope == NumberingScope::Chapter {
+                counters = Counters {
+                    prefix: Some(i + 1),
+                    figures: 0,
+                    tables: 0,
+                };
+            }
+            for s in &mut chapter.sections {
+                number_section(s, &mut counters, &mut labels);
+            }
+        }
+
+        let mut unresolved = Vec::new();
+        resolve_references(&mut book.preface, &labels, &mut unresolved);
+        for s in book.chapters.iter_mut().flat_map(|c| &mut c.sections) {
+            resolve_references(s, &labels, &mut unresolved);
+        }
+        unresolved
+    }
+}
+
+/// Number the figures and tables in the given section and its subsections in depth-first
+/// pre-order, recording labeled elements into `labels`.
+fn number_section(
+    section: &mut Section,
+    counters: &mut Counters,
+    labels: &mut HashMap<String, NumberedElement>,
+) {
+    // An explicit stack keeps deeply nested sections from overflowing the call stack.
+    let mut stack = vec![section];
+    while let Some(s) = stack.pop() {
+        number_content(s, counters, labels);
+        stack.extend(s.subsections.iter_mut().rev());
+    }
+}
+
+/// Number the figures and tables in the content of the given section, recording labeled elements
+/// into `labels`.
+fn number_content(
+    section: &mut Section,
+    counters: &mut Counters,
+    labels: &mut HashMap<String, NumberedElement>,
+) {
+    let mut output = Vec::new();
+    let mut pending_label: Option<String> = None;
+    let mut in_code_block = false;
+    let mut in_table = false;
+
+    let lines: Vec<&str> = section.content.lines().collect();
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block || trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            output.push(line.to_string());
+            continue;
+        }
+
+        if in_table {
+            if trimmed.starts_with('|') {
+                output.push(line.to_string());
+                continue;
+            }
+            in_table = false;
+        }
+
+        if let Some(label) = parse_label_line(trimmed) {
+            if label.starts_with("tbl:") && i + 1 < lines.len() && is_table_start(&lines, i + 1) {
+                pending_label = Some(label.to_owned());
+                continue;
+            }
+        }
+
+        if is_table_start(&lines, i) {
+            let label = pending_label.take();
+            let number = counters.next(ElementKind::Table);
+            let anchor = element_anchor(ElementKind::Table, &number, label.as_deref());
+            output.push(format!("<a id=\"{}\"></a>", anchor));
+            output.push(String::new());
+            output.push(format!(
+                "*{} {}*",
+                ElementKind::Table.caption_prefix(),
+                number
+            ));
+            output.push(String::new());
+            output.push(line.to_string());
+            in_table = true;
+
+            if let Some(label) = label {
+                labels.insert(
+                    label,
+                    NumberedElement {
+                        kind: ElementKind::Table,
+                        number,
+                        anchor,
+                        file: section.file.clone(),
+                    },
+                );
+            }
+            continue;
+        }
+
+        if let Some((image, alt, label)) = parse_figure_line(trimmed) {
+            let number = counters.next(ElementKind::Figure);
+            let anchor = element_anchor(ElementKind::Figure, &number, label);
+            output.push(format!("<a id=\"{}\"></a>", anchor));
+            output.push(String::new());
+            output.push(image.to_owned());
+            output.push(String::new());
+            if alt.is_empty() {
+                output.push(format!(
+                    "*{} {}*",
+                    ElementKind::Figure.caption_prefix(),
+                    number
+                ));
+            } else {
+                output.push(format!(
+                    "*{} {}: {}*",
+                    ElementKind::Figure.caption_prefix(),
+                    number,
+                    alt
+                ));
+            }
+
+            if let Some(label) = label {
+                labels.insert(
+                    label.to_owned(),
+                    NumberedElement {
+                        kind: ElementKind::Figure,
+                        number,
+                        anchor,
+                        file: section.file.clone(),
+                    },
+                );
+            }
+            continue;
+        }
+
+        output.push(line.to_string());
+    }
+
+    if !output.is_empty() {
+        let mut content = output.join("\n");
+        if section.content.ends_with('\n') {
+            content.push('\n');
+        }
+        section.content = content;
+    }
+}
+
+/// Rewrite all `{{#ref label}}` references in the given section and its subsections.
+fn resolve_references(
+    section: &mut Section,
+    labels: &HashMap<String, NumberedElement>,
+    unresolved: &mut Vec<UnresolvedReference>,
+) {
+    let mut stack = vec![section];
+    while let Some(s) = stack.pop() {
+        resolve_content_references(s, labels, unresolved);
+        stack.extend(s.subsections.iter_mut().rev());
+    }
+}
+
+/// Rewrite all `{{#ref label}}` references in the content of the given section.
+fn resolve_content_references(
+    section: &mut Section,
+    labels: &HashMap<String, NumberedElement>,
+    unresolved: &mut Vec<UnresolvedReference>,
+) {
+    const REF_START: &str = "{{#ref ";
+    const REF_END: &str = "}}";
+
+    let mut output = String::with_capacity(section.content.len());
+    let mut rest = section.content.as_str();
+    while let Some(start) = rest.find(REF_START) {
+        let end = match rest[start..].find(REF_END) {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        output.push_str(&rest[..start]);
+        let directive = &rest[start..end + REF_END.len()];
+        let label = rest[start + REF_START.len()..end].trim();
+        match labels.get(label) {
+            Some(element) => {
+                let href = if element.file == section.file {
+                    format!("#{}", element.anchor)
+                } else {
+                    let dir = section.file.parent().unwrap_or_else(|| Path::new(""));
+                    format!("{}#{}", relative_link(dir, &element.file), element.anchor)
+                };
+                output.push_str(&format!(
+                    "[{} {}]({})",
+                    element.kind.caption_prefix(),
+                    element.number,
+                    href
+                ));
+            }
+            None => {
+                output.push_str(directive);
+                unresolved.push(UnresolvedReference {
+                    section: section.name.clone(),
+                    label: label.to_owned(),
+                });
+            }
+        }
+        rest = &rest[end + REF_END.len()..];
+    }
+    output.push_str(rest);
+    section.content = output;
+}
+
+/// Get the anchor of a numbered element.
+fn element_anchor(kind: ElementKind, number: &str, label: Option<&str>) -> String {
+    match label {
+        Some(label) => label.replace(':', "-"),
+        None => format!("{}-{}", kind.anchor_prefix(), number.replace('.', "-")),
+    }
+}
+
+/// Parse a line that consists of a single label attribute such as `{#tbl:results}`.
+fn parse_label_line(line: &str) -> Option<&str> {
+    let label = line.strip_prefix("{#")?.strip_suffix('}')?;
+    if label.is_empty() || label.contains(char::is_whitespace) {
+        return None;
+    }
+    Some(label)
+}
+
+/// Parse a line that consists of a single image optionally followed by a label attribute.
+///
+/// Returns the image markup, its alternative text and its label.
+fn parse_figure_line(line: &str) -> Option<(&str, &str, Option<&str>)> {
+    if !line.starts_with("![") {
+        return None;
+    }
+    let alt_end = line.find("](")?;
+    let image_end = alt_end + line[alt_end..].find(')')? + 1;
+    let image = &line[..image_end];
+    let alt = &line[2..alt_end];
+
+    let rest = line[image_end..].trim();
+    if rest.is_empty() {
+        return Some((image, alt, None));
+    }
+    let label = parse_label_line(rest)?;
+    if !label.starts_with("fig:") {
+        return None;
+    }
+    Some((image, alt, Some(label)))
+}
+
+/// Determine whether a markdown table starts at the given line.
+fn is_table_start(lines: &[&str], i: usize) -> bool {
+    let header = lines[i].trim();
+    let separator = match lines.get(i + 1) {
+        Some(line) => line.trim(),
+        None => return false,
+    };
+
+    header.starts_with('|')
+        && separator.starts_with('|')
+        && separator.contains('-')
+        && separator
+            .chars()
+            .all(|c| matches!(c, '|' | '-' | ':' | ' ' | '\t'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::builder::{BookBuilder, ChapterBuilder, SectionBuilder};
+    use crate::tree::visitor::tests::{chain_with, drop_book_iteratively, with_small_stack};
+
+    fn section(file: &str, content: &str) -> Section {
+        SectionBuilder::new()
+            .set_name(file)
+            .set_file(file)
+            .set_content(content)
+            .build()
+    }
+
+    fn book() -> Book {
+        BookBuilder::new()
+            .set_preface(section("README.md", "![Logo](logo.png)\n"))
+            .add_chapter(
+                ChapterBuilder::new()
+                    .add_section(section(
+                        "intro/cats.md",
+                        "![A cat](cat.png){#fig:cat}\n\n{#tbl:sizes}\n| a |\n|---|\n| 1 |\n",
+                    ))
+                    .build(),
+            )
+            .add_chapter(
+                ChapterBuilder::new()
+                    .add_section(section(
+                        "guide/dogs.md",
+                        "See {{#ref fig:cat}} and {{#ref tbl:sizes}}.\n\n![](dog.png)\n",
+                    ))
+                    .add_section(section("guide/more.md", "Missing {{#ref fig:none}}.\n"))
+                    .build(),
+            )
+            .build()
+    }
+
+    #[test]
+    fn numbers_elements_within_chapters() {
+        let mut book = book();
+        FigureNumberingProcessor::new(NumberingScope::Chapter).process(&mut book);
+
+        assert!(book.preface.content.contains("*Figure 1: Logo*"));
+        let cats = &book.chapters[0].sections[0].content;
+        assert!(cats.contains("<a id=\"fig-cat\"></a>"));
+        assert!(cats.contains("*Figure 1.1: A cat*"));
+        assert!(cats.contains("*Table 1.1*"));
+        assert!(!cats.contains("{#"));
+        assert!(book.chapters[1].sections[0]
+            .content
+            .contains("*Figure 2.1*"));
+    }
+
+    #[test]
+    fn numbers_deeply_nested_sections() {
+        with_small_stack(number_deep_chain);
+    }
+
+    fn number_deep_chain() {
+        const DEPTH: usize = 10_000;
+        let chain = chain_with(DEPTH, |d| match d {
+            0 => section("top.md", "![Top](top.png){#fig:top}\n"),
+            DEPTH => section("deep.md", "![](deep.png)\n\nSee {{#ref fig:top}}.\n"),
+            _ => SectionBuilder::new().set_name(d.to_string()).build(),
+        });
+        let mut book = BookBuilder::new()
+            .add_chapter(ChapterBuilder::new().add_section(chain).build())
+            .build();
+        let unresolved = FigureNumberingProcessor::new(NumberingScope::Book).process(&mut book);
+        assert!(unresolved.is_empty());
+
+        let mut deepest = &book.chapters[0].sections[0];
+        while let Some(s) = deepest.subsections.first() {
+            deepest = s;
+        }
+        assert!(
+            deepest.content.contains("*Figure 2*"),
+            "{}",
+            deepest.content
+        );
+        assert!(deepest.content.contains("[Figure 1](top.md#fig-top)"));
+        drop_book_iteratively(book);
+    }
+
+    #[test]
+    fn numbers_elements_across_the_book() {
+        let mut book = book();
+        FigureNumberingProcessor::new(NumberingScope::Book).process(&mut book);
+
+        assert!(book.chapters[0].sections[0]
+            .content
+            .contains("*Figure 2: A cat*"));
+        assert!(book.chapters[1].sections[0]
+            .content
+            .contains("<a id=\"figure-3\"></a>"));
+    }
+
+    #[test]
+    fn resolves_references_relative_to_the_referencing_file() {
+        let mut book = book();
+        let unresolved = FigureNumberingProcessor::new(NumberingScope::Chapter).process(&mut book);
+
+        assert!(book.chapters[1].sections[0].content.starts_with(
+            "See [Figure 1.1](../intro/cats.md#fig-cat) and [Table 1.1](../intro/cats.md#tbl-sizes)."
+        ));
+        assert_eq!(
+            unresolved,
+            vec![UnresolvedReference {
+                section: "guide/more.md".to_owned(),
+                label: "fig:none".to_owned(),
+            }]
+        );
+        assert!(book.chapters[1].sections[1]
+            .content
+            .contains("{{#ref fig:none}}"));
+    }
+}
@@ -0,0 +1,588 @@
+//! This module extracts the frontmatter of section content into section metadata.
+//!
+//! A frontmatter is a block at the very beginning of the content that holds the metadata of the
+//! section in YAML, TOML or JSON. YAML frontmatters are delimited by `---` lines, TOML
+//! frontmatters by `+++` lines, and a JSON frontmatter is an object that starts on the first line
+//! of the content with `{` followed by a line break or a `"`:
+//!
+//! ```markdown
+//! ---
+//! title: Getting started with OpenBook
+//! draft: true
+//! tags:
+//!   - guide
+//!   - beginner
+//! ---
+//! ```
+//!
+//! ```markdown
+//! +++
+//! draft = true
+//! slug = "getting-started"
+//! +++
+//!
+//! # Getting started
+//! ```
+//!
+//! ```markdown
+//! {
+//!   "title": "Getting started with OpenBook",
+//!   "tags": ["guide", "beginner"]
+//! }
+//! ```
+//!
+//! Which formats are recognized is selected by a [`FrontmatterFormat`], which is taken from the
+//! `frontmatter-format` entry of the configuration file and detects the format from the delimiter
+//! by default. String values are taken as-is and other values are kept in their TOML
+//! representation, and nested tables and mappings are flattened into keys joined with `.`, like
+//! `author.name`.
+//! JSON and YAML frontmatters are parsed with `serde_json` and `serde_yaml` and require the `serde`
+//! feature, except that a flat YAML frontmatter, where every non-empty line that does not start
+//! with `#` is a `key: value` pair, is parsed without it, with the quotes around the values
+//! removed. When the format is detected, a `---` block that is valid TOML is parsed as TOML, as
+//! earlier versions of OpenBook only recognized `---` delimiters.
+//!
+//! [`FrontmatterFormat`]: enum.FrontmatterFormat.html
+//!
+//! The following keys have a meaning to OpenBook:
+//!
+//! * `title` overrides the name of the section as the title of its page;
+//! * `description` describes the page;
+//! * `tags` lists the tags of the page, either as a TOML array, a YAML list or a comma-separated
+//!   list;
+//! * `draft = true` marks the section as a draft, which renderers may skip, just like sections
+//!   without a content file;
+//! * `edit = false` keeps renderers from linking the page to the URL at which its content file
+//!   can be edited, see `BookConfig::edit_url`;
+//! * `word_budget` and `byte_budget` override the content budget of the section, see the
+//!   `budget` module;
+//! * `prerequisites` and `related` list the content files of sections to read before this one
+//!   and of sections on similar topics, in the same formats as `tags`, see the `crossref` module.
+//!
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use toml::Value;
+
+use crate::config::parse_json_prefix;
+#[cfg(feature = "serde")]
+use crate::config::yaml_to_toml;
+use crate::error::{Error, Result};
+use crate::tree::Section;
+
+const YAML_DELIMITER: &str = "---";
+const TOML_DELIMITER: &str = "+++";
+
+/// Format of the frontmatter of section content.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum FrontmatterFormat {
+    /// YAML, delimited by `---` lines.
+    Yaml,
+
+    /// TOML, delimited by `+++` lines.
+    Toml,
+
+    /// A JSON object.
+    Json,
+
+    /// Any of the above, detected from the delimiter.
+    #[default]
+    Auto,
+}
+
+impl Section {
+    /// Move the frontmatter at the beginning of the content of this section into its metadata,
+    /// detecting its format from the delimiter.
+    ///
+    /// This is the same as `extract_frontmatter_with(FrontmatterFormat::Auto)`.
+    pub fn extract_frontmatter(&mut self) -> Result<()> {
+        self.extract_frontmatter_with(FrontmatterFormat::Auto)
+    }
+
+    /// Move the frontmatter in the specified format at the beginning of the content of this
+    /// section into its metadata.
+    ///
+    /// The frontmatter is removed from the content, and its entries are added to the metadata,
+    /// replacing existing entries with the same keys. The content is left untouched if it does not
+    /// start with a frontmatter in the specified format. Returns an error of kind
+    /// `ErrorKind::Parse` naming the offending line if the frontmatter is malformed.
+    pub fn extract_frontmatter_with(&mut self, format: FrontmatterFormat) -> Result<()> {
+        let (metadata, body) = split_frontmatter(&self.file, &self.content, format)?;
+        if let Some(body) = body {
+            self.content = body;
+        }
+        self.metadata.extend(metadata);
+        Ok(())
+    }
+
+    /// Determine whether this section is a draft, i.e. it does not have a content file or its
+    /// metadata contains `draft = true`.
+    pub fn is_draft(&self) -> bool {
+        !self.has_file() || self.metadata.get("draft").map(String::as_str) == Some("true")
+    }
+
+    /// Determine whether the page of this section links to the page at which its content file can
+    /// be edited, which it does unless its metadata contains `edit = false`.
+    pub fn is_editable(&self) -> bool {
+        self.metadata.get("edit").map(String::as_str) != Some("false")
+    }
+
+    /// Get the title of the page of this section, which is the `title` in its metadata if there
+    /// is one and the name of the section otherwise.
+    pub fn title(&self) -> &str {
+        match self.metadata.get("title") {
+            Some(title) if !title.is_empty() => title,
+            _ => &self.name,
+        }
+    }
+
+    /// Get the `description` in the metadata of this section.
+    pub fn description(&self) -> Option<&str> {
+        self.metadata.get("description").map(String::as_str)
+    }
+
+    /// Get the `tags` in the metadata of this section.
+    ///
+    /// The tags may be given as a TOML array of strings or as a comma-separated list, optionally
+    /// enclosed in `[]`. Quotes around each tag are removed, and empty tags are skipped.
+    pub fn tags(&self) -> Vec<String> {
+        self.metadata_list("tags")
+    }
+
+    /// Get the list in the metadata of this section under the specified key, in the same formats
+    /// as `tags`.
+    pub(crate) fn metadata_list(&self, key: &str) -> Vec<String> {
+        let list = match self.metadata.get(key) {
+            Some(list) => list.trim(),
+            None => return Vec::new(),
+        };
+        let list = if list.starts_with('[') && list.ends_with(']') {
+            &list[1..list.len() - 1]
+        } else {
+            list
+        };
+        list.split(',')
+            .map(|item| unquote(item.trim()))
+            .filter(|item| !item.is_empty())
+            .map(str::to_owned)
+            .collect()
+    }
+}
+
+/// Split the frontmatter in the specified format from the specified content of the specified
+/// file.
+///
+/// Returns the entries of the frontmatter and the content without the frontmatter, or an empty
+/// map and `None` if the content does not start with a frontmatter in that format.
+pub(crate) fn split_frontmatter(
+    file: &Path,
+    content: &str,
+    format: FrontmatterFormat,
+) -> Result<(BTreeMap<String, String>, Option<String>)> {
+    let first = content.split_inclusive('\n').next().unwrap_or_default();
+    let detected = match first.trim_end() {
+        YAML_DELIMITER => FrontmatterFormat::Yaml,
+        TOML_DELIMITER => FrontmatterFormat::Toml,
+        // `{{` starts a directive, like `{{#include}}`, rather than a JSON object.
+        line if line == "{" || line.starts_with("{\"") => FrontmatterFormat::Json,
+        _ => return Ok((BTreeMap::new(), None)),
+    };
+    if format != FrontmatterFormat::Auto && format != detected {
+        return Ok((BTreeMap::new(), None));
+    }
+
+    let error =
+        |line: usize, msg: String| Error::parse(format!("{}:{}: {}", file.display(), line, msg));
+    let (metadata, offset) = match detected {
+        FrontmatterFormat::Json => {
+            let (value, end) = parse_json_prefix(content).map_err(|(line, column, msg)| {
+                Error::parse(format!("{}:{}:{}: {}", file.display(), line, column, msg))
+            })?;
+            let table = match value {
+                Some(Value::Table(table)) => table,
+                _ => return Err(error(1, "expected a JSON object".to_owned())),
+            };
+            // The object must end its line.
+            let rest = &content[end..];
+            let line_end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+            if !rest[..line_end].trim().is_empty() {
+                let line = content[..end].matches('\n').count() + 1;
+                return Err(error(
+                    line,
+                    "unexpected characters after the frontmatter".to_owned(),
+                ));
+            }
+            (table_metadata(table), end + line_end)
+        }
+        _ => {
+            let delimiter = first.trim_end();
+            let (block, offset) = match split_block(content, first.len(), delimiter) {
+                Some(split) => split,
+                // A thematic break at the beginning of the content rather than a frontmatter.
+                None => return Ok((BTreeMap::new(), None)),
+            };
+            let metadata = match detected {
+                FrontmatterFormat::Toml => {
+                    parse_toml(&block).map_err(|(line, msg)| error(line, msg))?
+                }
+                _ if format == FrontmatterFormat::Auto => match parse_toml(&block) {
+                    Ok(metadata) => metadata,
+                    Err(_) => parse_yaml(&block).map_err(|(line, msg)| error(line, msg))?,
+                },
+                _ => parse_yaml(&block).map_err(|(line, msg)| error(line, msg))?,
+            };
+            (metadata, offset)
+        }
+    };
+
+    let body = content[offset..].trim_start_matches(&['\n', '\r'][..]);
+    Ok((metadata, Some(body.to_owned())))
+}
+
+/// Get the lines of the frontmatter block that starts at the specified position of the specified
+/// content, up to the specified closing delimiter, and the position after the delimiter.
+///
+/// Returns `None` if the block is not closed.
+fn split_block<'a>(
+    content: &'a str,
+    mut position: usize,
+    delimiter: &str,
+) -> Option<(Vec<&'a str>, usize)> {
+    let mut block = Vec::new();
+    for line in content[position..].split_inclusive('\n') {
+        position += line.len();
+        if line.trim_end() == delimiter {
+            return Some((block, position));
+        }
+        block.push(line.trim_end());
+    }
+    None
+}
+
+/// Parse the lines of a TOML frontmatter block.
+///
+/// On error, returns the line number within the content and the description of the problem.
+fn parse_toml(block: &[&str]) -> std::result::Result<BTreeMap<String, String>, (usize, String)> {
+    match block.join("\n").parse::<Value>() {
+        Ok(Value::Table(table)) => Ok(table_metadata(table)),
+        Ok(_) => Err((2, "expected a table".to_owned())),
+        Err(e) => {
+            // The block starts at the second line of the content.
+            let line = e.line_col().map_or(2, |(line, _)| line + 2);
+            Err((line, e.to_string()))
+        }
+    }
+}
+
+/// Get the metadata entries of the specified TOML table, where strings are taken as-is and other
+/// values are kept in their TOML representation.
+///
+/// Nested tables are flattened into entries whose keys join the keys of the tables with `.`, so
+/// `author = { name = "Ana" }` becomes the `author.name` entry.
+fn table_metadata(table: toml::value::Table) -> BTreeMap<String, String> {
+    let mut metadata = BTreeMap::new();
+    let mut tables = vec![(String::new(), table)];
+    while let Some((prefix, table)) = tables.pop() {
+        for (key, value) in table {
+            let key = if prefix.is_empty() {
+                key
+            } else {
+                format!("{}.{}", prefix, key)
+            };
+            let value = match value {
+                Value::Table(table) => {
+                    tables.push((key, table));
+                    continue;
+                }
+                Value::String(s) => s,
+                value => value.to_string(),
+            };
+            metadata.insert(key, value);
+        }
+    }
+    metadata
+}
+
+/// Parse the lines of a YAML frontmatter block.
+///
+/// Values are converted into TOML values like YAML configuration files are, and the metadata is
+/// taken from them like from a TOML frontmatter. On error, returns the line number within the
+/// content and the description of the problem.
+#[cfg(feature = "serde")]
+fn parse_yaml(block: &[&str]) -> std::result::Result<BTreeMap<String, String>, (usize, String)> {
+    let value: serde_yaml::Value = serde_yaml::from_str(&block.join("\n")).map_err(|e| {
+        // The block starts at the second line of the content.
+        let line = e.location().map_or(2, |location| location.line() + 1);
+        (line, e.to_string())
+    })?;
+    match yaml_to_toml(value).map_err(|msg| (2, msg))? {
+        Some(Value::Table(table)) => Ok(table_metadata(table)),
+        Some(_) => Err((2, "expected a mapping".to_owned())),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+/// Parse the lines of a flat YAML frontmatter block, where every non-empty line that does not
+/// start with `#` is a `key: value` pair.
+///
+/// Nested values need a YAML parser, so indented lines and list items are reported as errors.
+/// `key:` lines without a value are skipped, like `null`s are. On error, returns the line number
+/// within the content and the description of the problem.
+#[cfg(not(feature = "serde"))]
+fn parse_yaml(block: &[&str]) -> std::result::Result<BTreeMap<String, String>, (usize, String)> {
+    let mut metadata = BTreeMap::new();
+    for (index, line) in block.iter().enumerate() {
+        // The block starts at the second line of the content.
+        let line_number = index + 2;
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        if line.starts_with(char::is_whitespace) || line.starts_with('-') {
+            return Err((
+                line_number,
+                "nested YAML values require the \"serde\" feature of OpenBook".to_owned(),
+            ));
+        }
+
+        let (key, value) = match line.find(':') {
+            Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+            None => {
+                return Err((
+                    line_number,
+                    format!("expected a \"key: value\" pair, found \"{}\"", line),
+                ))
+            }
+        };
+        if key.is_empty() {
+            return Err((line_number, "missing key".to_owned()));
+        }
+        if !value.is_empty() {
+            metadata.insert(key.to_owned(), unquote(value).to_owned());
+        }
+    }
+
+    Ok(metadata)
+}
+
+/// Remove the double or single quotes around the specified value, if any.
+fn unquote(value: &str) -> &str {
+    if value.len() >= 2
+        && ((value.starts_with('"') && value.ends_with('"'))
+            || (value.starts_with('\'') && value.ends_with('\'')))
+    {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::tree::builder::SectionBuilder;
+
+    fn guide(content: &str) -> Section {
+        SectionBuilder::new()
+            .set_name("Guide")
+            .set_file("guide.md")
+            .set_content(content)
+            .build()
+    }
+
+    fn extract(content: &str, format: FrontmatterFormat) -> Section {
+        let mut section = guide(content);
+        section.extract_frontmatter_with(format).unwrap();
+        section
+    }
+
+    #[test]
+    fn parses_every_format_into_equivalent_metadata() {
+        let yaml =
+            "---\ntitle: Getting started\ndraft: true\ntags: [guide, beginner]\n---\n\n# Start\n";
+        let toml = "+++\ntitle = \"Getting started\"\ndraft = true\ntags = [\"guide\", \"beginner\"]\n+++\n\n# Start\n";
+        #[cfg(feature = "serde")]
+        let json = "{\n  \"title\": \"Getting started\",\n  \"draft\": true,\n  \"tags\": [\"guide\", \"beginner\"]\n}\n\n# Start\n";
+
+        let cases = [
+            (yaml, FrontmatterFormat::Yaml),
+            (toml, FrontmatterFormat::Toml),
+            #[cfg(feature = "serde")]
+            (json, FrontmatterFormat::Json),
+        ];
+        for (content, format) in &cases {
+            for format in &[*format, FrontmatterFormat::Auto] {
+                let section = extract(content, *format);
+                assert_eq!(section.content, "# Start\n", "{:?}", format);
+                assert_eq!(section.title(), "Getting started");
+                assert!(section.is_draft());
+                assert_eq!(section.tags(), vec!["guide", "beginner"]);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn flattens_nested_yaml_values() {
+        let content = "---\n\
+                       author:\n  name: Ana\n  links:\n    site: https://example.com\n\
+                       tags:\n  - guide\n  - beginner, advanced\n\
+                       motto: 'a: b'\n\
+                       draft: ~\n\
+                       ---\n# Start\n";
+        let section = extract(content, FrontmatterFormat::Yaml);
+        let keys: Vec<_> = section.metadata.keys().map(String::as_str).collect();
+        assert_eq!(
+            keys,
+            vec!["author.links.site", "author.name", "motto", "tags"]
+        );
+        assert_eq!(section.metadata["author.name"], "Ana");
+        assert_eq!(section.metadata["author.links.site"], "https://example.com");
+        assert_eq!(section.metadata["motto"], "a: b");
+        assert_eq!(
+            section.metadata["tags"],
+            "[\"guide\", \"beginner, advanced\"]"
+        );
+
+        let toml = "+++\n[author]\nname = \"Ana\"\n+++\n";
+        let section = extract(toml, FrontmatterFormat::Toml);
+        assert_eq!(section.metadata["author.name"], "Ana");
+    }
+
+    #[test]
+    #[cfg(not(feature = "serde"))]
+    fn nested_yaml_values_require_the_serde_feature() {
+        let section = extract("---\ntitle: 'Start'\nkey:\n---\n", FrontmatterFormat::Yaml);
+        assert_eq!(section.title(), "Start");
+        assert!(!section.metadata.contains_key("key"));
+
+        for content in &[
+            "---\nauthor:\n  name: Ana\n---\n",
+            "---\ntags:\n- guide\n---\n",
+        ] {
+            let mut section = guide(content);
+            let err = section
+                .extract_frontmatter_with(FrontmatterFormat::Yaml)
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Parse);
+            assert!(err.to_string().contains("guide.md:3:"), "{}", err);
+            assert!(err.to_string().contains("serde"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn accessors_fall_back_to_the_section() {
+        let section = extract("# Start\n", FrontmatterFormat::Auto);
+        assert_eq!(section.title(), "Guide");
+        assert_eq!(section.description(), None);
+        assert!(!section.is_draft());
+        assert!(section.tags().is_empty());
+
+        let draft = SectionBuilder::new().set_name("Soon").build();
+        assert!(draft.is_draft());
+    }
+
+    #[test]
+    fn frontmatter_entries_replace_existing_metadata() {
+        let mut section =
+            guide("---\nslug: start\nweight: 3\ndescription: First steps\n---\nBody\n");
+        section.metadata.insert("slug".to_owned(), "old".to_owned());
+        section
+            .metadata
+            .insert("author".to_owned(), "Ana".to_owned());
+        section.extract_frontmatter().unwrap();
+
+        assert_eq!(section.content, "Body\n");
+        assert_eq!(section.metadata["slug"], "start");
+        assert_eq!(section.metadata["weight"], "3");
+        assert_eq!(section.metadata["author"], "Ana");
+        assert_eq!(section.description(), Some("First steps"));
+    }
+
+    #[test]
+    fn tags_may_be_comma_separated() {
+        let mut section = guide("Body\n");
+        for tags in &[
+            "guide, beginner",
+            "[\"guide\", 'beginner', ]",
+            "guide,,beginner",
+        ] {
+            section
+                .metadata
+                .insert("tags".to_owned(), (*tags).to_owned());
+            assert_eq!(section.tags(), vec!["guide", "beginner"], "{}", tags);
+        }
+    }
+
+    #[test]
+    fn ignores_delimiters_of_other_formats() {
+        let content = "+++\ndraft = true\n+++\n# Start\n";
+        let section = extract(content, FrontmatterFormat::Yaml);
+        assert_eq!(section.content, content);
+        assert!(section.metadata.is_empty());
+
+        let content = "{{#include intro.md}}\n";
+        let section = extract(content, FrontmatterFormat::Auto);
+        assert_eq!(section.content, content);
+    }
+
+    #[test]
+    fn accepts_toml_between_yaml_delimiters_when_detecting() {
+        let content = "---\nslug = \"start\"\n---\n# Start\n";
+        let section = extract(content, FrontmatterFormat::Auto);
+        assert_eq!(
+            section.metadata.get("slug").map(String::as_str),
+            Some("start")
+        );
+
+        let mut section = guide(content);
+        let err = section
+            .extract_frontmatter_with(FrontmatterFormat::Yaml)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Parse);
+        assert!(err.to_string().contains("guide.md:2:"));
+    }
+
+    #[test]
+    fn reports_malformed_frontmatters() {
+        let mut section = guide("+++\ndraft = \n+++\n");
+        let err = section.extract_frontmatter().unwrap_err();
+        assert!(err.to_string().contains("guide.md:2:"), "{}", err);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn reports_malformed_json_frontmatters() {
+        let mut section = guide("{\n  \"draft\": tru,\n}\n");
+        let err = section.extract_frontmatter().unwrap_err();
+        assert!(err.to_string().contains("guide.md:2:15: "), "{}", err);
+
+        let mut section = guide("{\"draft\": true} # Start\n");
+        let err = section.extract_frontmatter().unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("unexpected characters after the frontmatter"));
+
+        let mut section = guide("{\"tags\": [\"guide\", null]}\n");
+        let err = section.extract_frontmatter().unwrap_err();
+        assert!(err.to_string().contains("null"), "{}", err);
+
+        let mut section = guide(&format!("{{\"a\": {}\n", "[".repeat(100_000)));
+        let err = section.extract_frontmatter().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Parse);
+    }
+
+    #[test]
+    #[cfg(not(feature = "serde"))]
+    fn json_frontmatters_require_the_serde_feature() {
+        let mut section = guide("{\"draft\": true}\n# Start\n");
+        let err = section.extract_frontmatter().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Parse);
+        assert!(err.to_string().contains("serde"), "{}", err);
+    }
+}
@@ -0,0 +1,180 @@
+//! This module provides a visitor that annotates glossary terms in section content.
+//!
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+use crate::render::escape_html;
+use crate::tree::visitor::VisitorMut;
+use crate::tree::{Glossary, Section};
+
+/// A visitor that annotates the first occurrence of each glossary term in every visited section.
+///
+/// Terms are matched case-insensitively at word boundaries, and the matched text is wrapped in
+/// `<span class="glossary-term" title="...">` markup whose title is the definition of the term, so
+/// that browsers show the definition as a tooltip. Text in code spans, code blocks, HTML and image
+/// descriptions is never annotated. When terms overlap, the longer term wins.
+pub struct GlossaryAnnotator<'a> {
+    glossary: &'a Glossary,
+}
+
+impl<'a> GlossaryAnnotator<'a> {
+    /// Create a new `GlossaryAnnotator` instance that annotates the terms in the specified
+    /// glossary.
+    pub fn new(glossary: &'a Glossary) -> Self {
+        Self { glossary }
+    }
+}
+
+impl<'a> VisitorMut for GlossaryAnnotator<'a> {
+    fn visit_section(&mut self, section: &mut Section) {
+        let ranges = text_ranges(&section.content);
+
+        let mut terms: Vec<(&String, &String)> = self
+            .glossary
+            .entries
+            .iter()
+            .filter(|(term, _)| !term.is_empty())
+            .collect();
+        terms.sort_by_key(|(term, _)| std::cmp::Reverse(term.chars().count()));
+
+        // Byte ranges of the matched occurrences, each with the definition of its term.
+        let mut matches: Vec<(usize, usize, &String)> = Vec::new();
+        for (term, definition) in terms {
+            let found = ranges
+                .iter()
+                .find_map(|&(start, end)| find_term(&section.content, start, end, term, &matches));
+            if let Some((start, end)) = found {
+                matches.push((start, end, definition));
+            }
+        }
+        if matches.is_empty() {
+            return;
+        }
+
+        matches.sort_by_key(|&(start, _, _)| start);
+        let content = &section.content;
+        let mut output = String::with_capacity(content.len());
+        let mut last = 0;
+        for (start, end, definition) in matches {
+            output.push_str(&content[last..start]);
+            output.push_str(&format!(
+                "<span class=\"glossary-term\" title=\"{}\">{}</span>",
+                escape_html(definition),
+                &content[start..end]
+            ));
+            last = end;
+        }
+        output.push_str(&content[last..]);
+        section.content = output;
+    }
+}
+
+/// Get the byte ranges of the plain text in the specified markdown content that may be annotated.
+fn text_ranges(content: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut excluded = 0usize;
+    for (event, range) in Parser::new(content).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Start(Tag::Image(..)) => excluded += 1,
+            Event::End(Tag::CodeBlock(_)) | Event::End(Tag::Image(..)) => excluded -= 1,
+            Event::Text(_) if excluded == 0 => ranges.push((range.start, range.end)),
+            _ => {}
+        }
+    }
+    ranges
+}
+
+/// Find the first occurrence of the specified term within `content[start..end]` that is at word
+/// boundaries and does not overlap any of the existing matches.
+fn find_term(
+    content: &str,
+    start: usize,
+    end: usize,
+    term: &str,
+    matches: &[(usize, usize, &String)],
+) -> Option<(usize, usize)> {
+    let text = &content[start..end];
+    for (offset, _) in text.char_indices() {
+        let len = match match_prefix(&text[offset..], term) {
+            Some(len) => len,
+            None => continue,
+        };
+        let (match_start, match_end) = (start + offset, start + offset + len);
+
+        let before = text[..offset].chars().next_back();
+        let after = text[offset + len..].chars().next();
+        let is_word_char = |c: Option<char>| matches!(c, Some(c) if c.is_alphanumeric());
+        if is_word_char(before) || is_word_char(after) {
+            continue;
+        }
+        if matches
+            .iter()
+            .any(|&(s, e, _)| match_start < e && s < match_end)
+        {
+            continue;
+        }
+        return Some((match_start, match_end));
+    }
+    None
+}
+
+/// Determine whether the specified text starts with the specified term, ignoring case, and get the
+/// length in bytes of the matched prefix.
+fn match_prefix(text: &str, term: &str) -> Option<usize> {
+    let mut chars = text.char_indices();
+    for expected in term.chars() {
+        let (_, c) = chars.next()?;
+        if !c.to_lowercase().eq(expected.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(chars.next().map_or(text.len(), |(i, _)| i))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::visitor::VisitorHostMut;
+
+    fn annotate(content: &str) -> String {
+        let mut glossary = Glossary::default();
+        for (term, definition) in &[
+            ("Book", "A \"book\""),
+            ("Open Book", "The project"),
+            ("Page", "A page"),
+        ] {
+            glossary
+                .entries
+                .insert(term.to_string(), definition.to_string());
+        }
+        let mut section = Section {
+            content: content.to_owned(),
+            ..Section::default()
+        };
+        section.visit_mut(&mut GlossaryAnnotator::new(&glossary));
+        section.content
+    }
+
+    #[test]
+    fn annotates_the_first_occurrence_of_each_term() {
+        assert_eq!(
+            annotate("A page of a book, and another book.\n"),
+            "A <span class=\"glossary-term\" title=\"A page\">page</span> of a \
+             <span class=\"glossary-term\" title=\"A &quot;book&quot;\">book</span>, and another book.\n"
+        );
+    }
+
+    #[test]
+    fn prefers_longer_terms_at_word_boundaries() {
+        assert_eq!(
+            annotate("The OPEN BOOK pages.\n"),
+            "The <span class=\"glossary-term\" title=\"The project\">OPEN BOOK</span> pages.\n"
+        );
+    }
+
+    #[test]
+    fn skips_code_and_images() {
+        let content = "`book` and ![page](page.png)\n\n```\nbook page\n```\n";
+        assert_eq!(annotate(content), content);
+    }
+}
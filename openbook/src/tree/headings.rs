@@ -0,0 +1,176 @@
+//! This module extracts the headings of section content and generates their anchor slugs.
+//!
+
+use std::collections::{HashMap, HashSet};
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+use crate::tree::Section;
+
+/// A heading in the content of a section.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Heading {
+    /// Level of the heading, from 1 to 6.
+    pub level: u32,
+
+    /// Text of the heading without the markdown syntax.
+    pub text: String,
+
+    /// Anchor slug of the heading, which is unique within the section.
+    pub slug: String,
+}
+
+impl Section {
+    /// Get the headings in the content of this section, in document order.
+    ///
+    /// Slugs are generated the way GitHub does: the text of the heading is lowercased, whitespace
+    /// is replaced by `-` and punctuation is removed, while non-ASCII letters are kept. If the same
+    /// slug appears more than once, the later ones are suffixed with `-1`, `-2` and so on.
+    pub fn headings(&self) -> Vec<Heading> {
+        let mut headings = Vec::new();
+        let mut slugs = Slugger::default();
+
+        let mut current: Option<(u32, String)> = None;
+        for event in Parser::new(&self.content) {
+            match event {
+                Event::Start(Tag::Heading(level)) => current = Some((level, String::new())),
+                Event::End(Tag::Heading(_)) => {
+                    if let Some((level, text)) = current.take() {
+                        let text = text.trim().to_owned();
+                        let slug = slugs.slug(&text);
+                        headings.push(Heading { level, text, slug });
+                    }
+                }
+                Event::Text(text) | Event::Code(text) => {
+                    if let Some((_, heading)) = &mut current {
+                        heading.push_str(&text);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        headings
+    }
+
+    /// Remove the first level 1 heading from the content of this section and get its text.
+    ///
+    /// The blank lines that follow the heading are removed with it. Returns `None` and leaves the
+    /// content unchanged if the content has no level 1 heading.
+    pub fn take_title_heading(&mut self) -> Option<String> {
+        let mut start = None;
+        let mut text = String::new();
+        let mut range = None;
+        for (event, span) in Parser::new(&self.content).into_offset_iter() {
+            match event {
+                Event::Start(Tag::Heading(1)) if start.is_none() => start = Some(span.start),
+                Event::End(Tag::Heading(1)) if start.is_some() => {
+                    range = Some(start.unwrap_or_default()..span.end);
+                    break;
+                }
+                Event::Text(t) | Event::Code(t) if start.is_some() => text.push_str(&t),
+                _ => {}
+            }
+        }
+
+        let range = range?;
+        let rest = self.content[range.end..].trim_start_matches(&['\r', '\n'][..]);
+        self.content = format!("{}{}", &self.content[..range.start], rest);
+        Some(text.trim().to_owned())
+    }
+}
+
+/// Generates unique slugs for the headings of a single document.
+#[derive(Default)]
+struct Slugger {
+    counts: HashMap<String, usize>,
+    used: HashSet<String>,
+}
+
+impl Slugger {
+    /// Get a slug of the specified heading text that has not been returned before.
+    fn slug(&mut self, text: &str) -> String {
+        let base = heading_slug(text);
+        let mut slug = base.clone();
+        while self.used.contains(&slug) {
+            let count = self.counts.entry(base.clone()).or_insert(0);
+            *count += 1;
+            slug = format!("{}-{}", base, count);
+        }
+        self.used.insert(slug.clone());
+        slug
+    }
+}
+
+/// Get the anchor slug of a heading with the specified text, without making it unique.
+///
+/// The text is lowercased, whitespace is replaced by `-`, and characters other than alphanumeric
+/// characters, `-` and `_` are removed.
+pub fn heading_slug(text: &str) -> String {
+    text.trim()
+        .chars()
+        .filter_map(|c| {
+            if c.is_whitespace() {
+                Some('-')
+            } else if c.is_alphanumeric() || c == '-' || c == '_' {
+                Some(c)
+            } else {
+                None
+            }
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headings_of(content: &str) -> Vec<Heading> {
+        Section {
+            content: content.to_owned(),
+            ..Section::default()
+        }
+        .headings()
+    }
+
+    fn slugs_of(content: &str) -> Vec<String> {
+        headings_of(content).into_iter().map(|h| h.slug).collect()
+    }
+
+    #[test]
+    fn extracts_headings_with_their_levels() {
+        assert_eq!(
+            headings_of("# Getting `started`\n\ntext\n\n### Next *steps*\n"),
+            vec![
+                Heading {
+                    level: 1,
+                    text: "Getting started".to_owned(),
+                    slug: "getting-started".to_owned(),
+                },
+                Heading {
+                    level: 3,
+                    text: "Next steps".to_owned(),
+                    slug: "next-steps".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn suffixes_duplicate_slugs() {
+        assert_eq!(
+            slugs_of("# Usage\n## Usage\n## Usage-1\n## Usage\n"),
+            vec!["usage", "usage-1", "usage-1-1", "usage-2"]
+        );
+    }
+
+    #[test]
+    fn strips_punctuation_and_keeps_non_ascii_letters() {
+        assert_eq!(
+            slugs_of("# What's new? (v2.0)\n## 快速 入门\n## Ünïcode_Names\n"),
+            vec!["whats-new-v20", "快速-入门", "ünïcode_names"]
+        );
+        assert_eq!(heading_slug("  C++ & Rust  "), "c--rust");
+    }
+}
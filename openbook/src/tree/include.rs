@@ -0,0 +1,536 @@
+//! This module implements `{{#include}}` directives in section content.
+//!
+//! An include directive is replaced by the content of the referenced file, or by a range of its
+//! lines:
+//!
+//! * `{{#include snippet.md}}` includes the whole file;
+//! * `{{#include snippet.md:3}}` includes line 3 only;
+//! * `{{#include snippet.md:3:}}` includes line 3 to the end of the file;
+//! * `{{#include snippet.md::7}}` includes the beginning of the file to line 7;
+//! * `{{#include snippet.md:3:7}}` includes line 3 to line 7.
+//!
+//! Line numbers start at 1 and ranges are inclusive. Paths are relative to the directory of the
+//! file that contains the directive, and included files may contain include directives of their
+//! own, up to a nesting depth of [`DEFAULT_MAX_INCLUDE_DEPTH`] unless another limit is set.
+//!
+//! The directives of a single section are resolved by [`resolve_includes`], and those of a whole
+//! tree by running an [`IncludeResolver`], e.g. as a stage of a `Pipeline`.
+//!
+//! [`DEFAULT_MAX_INCLUDE_DEPTH`]: constant.DEFAULT_MAX_INCLUDE_DEPTH.html
+//! [`resolve_includes`]: fn.resolve_includes.html
+//! [`IncludeResolver`]: struct.IncludeResolver.html
+//!
+
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::fs::{normalize, FileSystem};
+use crate::tree::visitor::VisitorMut;
+use crate::tree::{Book, Section};
+
+const INCLUDE_START: &str = "{{#include ";
+const INCLUDE_END: &str = "}}";
+
+/// The maximum nesting depth of include directives.
+///
+/// The directives in the content of a section are at depth 1, the directives in the files they
+/// include at depth 2, and so on.
+pub const DEFAULT_MAX_INCLUDE_DEPTH: usize = 16;
+
+/// An inclusive range of lines, where `None` stands for the beginning or the end of the file.
+type LineRange = (Option<usize>, Option<usize>);
+
+/// Resolve all include directives in the content of the specified section.
+///
+/// `root` is the root directory of the book, which `Section::file` and the included files are
+/// relative to. The subsections of the section are not processed.
+///
+/// Returns an error of kind `ErrorKind::NotFound` naming both the section and the file if an
+/// included file does not exist, and an error of kind `ErrorKind::Parse` if a directive is
+/// malformed, if files include each other in a cycle or if directives are nested deeper than
+/// [`DEFAULT_MAX_INCLUDE_DEPTH`].
+///
+/// [`DEFAULT_MAX_INCLUDE_DEPTH`]: constant.DEFAULT_MAX_INCLUDE_DEPTH.html
+pub fn resolve_includes<F: FileSystem>(fs: &F, root: &Path, section: &mut Section) -> Result<()> {
+    resolve_includes_with_max_depth(fs, root, section, DEFAULT_MAX_INCLUDE_DEPTH)
+}
+
+/// Resolve all include directives in the content of the specified section, as by
+/// [`resolve_includes`], but allow directives to be nested at most `max_depth` levels deep.
+///
+/// A `max_depth` of 0 forbids include directives altogether.
+///
+/// [`resolve_includes`]: fn.resolve_includes.html
+pub fn resolve_includes_with_max_depth<F: FileSystem>(
+    fs: &F,
+    root: &Path,
+    section: &mut Section,
+    max_depth: usize,
+) -> Result<()> {
+    let file = normalize(&section.file);
+    let mut expander = Expander {
+        fs,
+        root,
+        section: &section.name,
+        max_depth,
+        stack: vec![file.clone()],
+    };
+    section.content = expander.expand(&file, &section.content)?;
+    Ok(())
+}
+
+/// A visitor that resolves the include directives in every visited section, as by
+/// [`resolve_includes`].
+///
+/// Included files are resolved against the root directory of the book under visit, and sections
+/// without a content file are skipped. Directives may be nested [`DEFAULT_MAX_INCLUDE_DEPTH`]
+/// levels deep unless another limit is set by `set_max_depth`. After the first error, the
+/// remaining sections are left untouched and the error is reported by `take_error`.
+///
+/// [`resolve_includes`]: fn.resolve_includes.html
+/// [`DEFAULT_MAX_INCLUDE_DEPTH`]: constant.DEFAULT_MAX_INCLUDE_DEPTH.html
+pub struct IncludeResolver<'a, F: FileSystem> {
+    fs: &'a F,
+    root: PathBuf,
+    max_depth: usize,
+    error: Option<Error>,
+}
+
+impl<'a, F: FileSystem> IncludeResolver<'a, F> {
+    /// Create a new `IncludeResolver` instance that reads included files from the specified file
+    /// system.
+    pub fn new(fs: &'a F) -> Self {
+        Self {
+            fs,
+            root: PathBuf::new(),
+            max_depth: DEFAULT_MAX_INCLUDE_DEPTH,
+            error: None,
+        }
+    }
+
+    /// Set the maximum nesting depth of include directives, see
+    /// [`resolve_includes_with_max_depth`].
+    ///
+    /// [`resolve_includes_with_max_depth`]: fn.resolve_includes_with_max_depth.html
+    pub fn set_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+}
+
+impl<'a, F: FileSystem> VisitorMut for IncludeResolver<'a, F> {
+    fn visit_book(&mut self, book: &mut Book) {
+        self.root = book.config.root.clone();
+    }
+
+    fn visit_section(&mut self, section: &mut Section) {
+        if self.error.is_some() || !section.has_file() {
+            return;
+        }
+        if let Err(e) =
+            resolve_includes_with_max_depth(self.fs, &self.root, section, self.max_depth)
+        {
+            self.error = Some(e);
+        }
+    }
+
+    fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+}
+
+/// Get the files that the include directives in the content of the specified section refer to,
+/// relative to the root directory of the book.
+///
+/// Only the directives in the content of the section are considered, not those in the included
+/// files. Malformed directives are skipped.
+pub(crate) fn included_files(section: &Section) -> Vec<PathBuf> {
+    let file = normalize(&section.file);
+    let base = file.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut files = Vec::new();
+    let mut rest = section.content.as_str();
+    while let Some(start) = rest.find(INCLUDE_START) {
+        let end = match rest[start..].find(INCLUDE_END) {
+            Some(end) => start + end,
+            None => break,
+        };
+        if let Ok((path, _)) = parse_spec(rest[start + INCLUDE_START.len()..end].trim()) {
+            files.push(normalize(base.join(path)));
+        }
+        rest = &rest[end + INCLUDE_END.len()..];
+    }
+    files
+}
+
+/// The state of the expansion of the include directives of a section.
+struct Expander<'a, F: FileSystem> {
+    fs: &'a F,
+    root: &'a Path,
+
+    /// Name of the section, for error messages.
+    section: &'a str,
+
+    max_depth: usize,
+
+    /// The files whose directives are being expanded, from the section's own content file to the
+    /// innermost included file.
+    stack: Vec<PathBuf>,
+}
+
+impl<'a, F: FileSystem> Expander<'a, F> {
+    /// Expand the include directives in the content of the specified file, which is the last file
+    /// in `stack`.
+    fn expand(&mut self, file: &Path, content: &str) -> Result<String> {
+        let base = file.parent().unwrap_or_else(|| Path::new(""));
+
+        let mut output = String::with_capacity(content.len());
+        let mut rest = content;
+        while let Some(start) = rest.find(INCLUDE_START) {
+            let end = match rest[start..].find(INCLUDE_END) {
+                Some(end) => start + end,
+                None => break,
+            };
+            output.push_str(&rest[..start]);
+
+            let spec = rest[start + INCLUDE_START.len()..end].trim();
+            let (path, range) = parse_spec(spec).map_err(|msg| {
+                Error::parse(format!(
+                    "section \"{}\": malformed include directive \"{}\": {}",
+                    self.section, spec, msg
+                ))
+            })?;
+
+            let target = normalize(base.join(path));
+            if self.stack.contains(&target) {
+                return Err(Error::parse(format!(
+                    "section \"{}\": include cycle detected: {}",
+                    self.section,
+                    self.chain(&target)
+                )));
+            }
+            if self.stack.len() > self.max_depth {
+                return Err(Error::parse(format!(
+                    "section \"{}\": includes are nested deeper than {}: {}",
+                    self.section,
+                    self.max_depth,
+                    self.chain(&target)
+                )));
+            }
+
+            let target_file = self.root.join(&target);
+            if !self.fs.has_file(&target_file) {
+                return Err(Error::not_found(format!(
+                    "section \"{}\" includes {} which does not exist",
+                    self.section,
+                    target_file.display()
+                )));
+            }
+            let included = select_lines(&self.fs.read_file_as_string(&target_file)?, range);
+
+            self.stack.push(target.clone());
+            let expanded = self.expand(&target, &included)?;
+            self.stack.pop();
+            output.push_str(&expanded);
+
+            rest = &rest[end + INCLUDE_END.len()..];
+        }
+        output.push_str(rest);
+
+        Ok(output)
+    }
+
+    /// Describe the chain of includes from the section's own content file to the specified file.
+    fn chain(&self, target: &Path) -> String {
+        self.stack
+            .iter()
+            .map(PathBuf::as_path)
+            .chain(std::iter::once(target))
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>()
+            .join(" -> ")
+    }
+}
+
+/// Parse the path and the line range of an include directive.
+fn parse_spec(spec: &str) -> std::result::Result<(&str, Option<LineRange>), String> {
+    fn parse_line(s: &str) -> std::result::Result<Option<usize>, String> {
+        if s.is_empty() {
+            return Ok(None);
+        }
+        match s.parse::<usize>() {
+            Ok(0) | Err(_) => Err(format!("invalid line number \"{}\"", s)),
+            Ok(line) => Ok(Some(line)),
+        }
+    }
+
+    let mut parts = spec.splitn(3, ':');
+    let path = parts.next().unwrap_or_default();
+    if path.is_empty() {
+        return Err("missing path".to_owned());
+    }
+
+    let range = match (parts.next(), parts.next()) {
+        (None, _) => None,
+        (Some(line), None) => {
+            let line = parse_line(line)?;
+            if line.is_none() {
+                return Err("missing line number".to_owned());
+            }
+            Some((line, line))
+        }
+        (Some(start), Some(end)) => Some((parse_line(start)?, parse_line(end)?)),
+    };
+
+    Ok((path, range))
+}
+
+/// Select the specified range of lines from the content.
+fn select_lines(content: &str, range: Option<LineRange>) -> String {
+    let (start, end) = match range {
+        Some(range) => range,
+        None => return content.to_owned(),
+    };
+
+    let start = start.unwrap_or(1);
+    let lines = content.lines().enumerate().filter(|(i, _)| {
+        let line = i + 1;
+        line >= start && line <= end.unwrap_or(usize::MAX)
+    });
+    lines.map(|(_, line)| line).collect::<Vec<_>>().join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::fs::scratch::ScratchFileSystem;
+    use crate::tree::builder::{BookBuilder, BookConfigBuilder, ChapterBuilder, SectionBuilder};
+    use crate::tree::visitor::visit_mut;
+
+    const ROOT: &str = "/openbook-test/include";
+
+    fn fs(files: &[(&str, &str)]) -> ScratchFileSystem {
+        let fs = ScratchFileSystem::new();
+        for (file, content) in files {
+            fs.write_file(Path::new(ROOT).join(file), content.as_bytes())
+                .unwrap();
+        }
+        fs
+    }
+
+    fn section(file: &str, content: &str) -> Section {
+        SectionBuilder::new()
+            .set_name("page")
+            .set_file(file)
+            .set_content(content)
+            .build()
+    }
+
+    fn resolve(fs: &ScratchFileSystem, section: &mut Section) -> Result<()> {
+        resolve_includes(fs, Path::new(ROOT), section)
+    }
+
+    #[test]
+    fn includes_whole_files() {
+        let fs = fs(&[("snippet.md", "shared")]);
+        let mut s = section("page.md", "before {{#include snippet.md}} after");
+        resolve(&fs, &mut s).unwrap();
+        assert_eq!(s.content, "before shared after");
+    }
+
+    #[test]
+    fn includes_line_ranges() {
+        let fs = fs(&[("lines.txt", "1\n2\n3\n4\n5")]);
+        let cases = [
+            ("{{#include lines.txt:2}}", "2"),
+            ("{{#include lines.txt:2:4}}", "2\n3\n4"),
+            ("{{#include lines.txt:4:}}", "4\n5"),
+            ("{{#include lines.txt::2}}", "1\n2"),
+        ];
+        for (directive, expected) in cases.iter() {
+            let mut s = section("page.md", directive);
+            resolve(&fs, &mut s).unwrap();
+            assert_eq!(s.content, *expected, "{}", directive);
+        }
+    }
+
+    #[test]
+    fn malformed_directives_are_errors() {
+        let fs = fs(&[("lines.txt", "1")]);
+        for directive in &["{{#include lines.txt:0}}", "{{#include lines.txt:x:2}}"] {
+            let mut s = section("page.md", directive);
+            let e = resolve(&fs, &mut s).unwrap_err();
+            assert_eq!(e.kind(), ErrorKind::Parse, "{}", directive);
+        }
+    }
+
+    #[test]
+    fn missing_files_name_the_section_and_the_file() {
+        let fs = fs(&[]);
+        let mut s = section("page.md", "{{#include missing.md}}");
+        let e = resolve(&fs, &mut s).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::NotFound);
+        let msg = e.to_string();
+        assert!(
+            msg.contains("\"page\"") && msg.contains("missing.md"),
+            "{}",
+            msg
+        );
+    }
+
+    #[test]
+    fn cycles_are_errors() {
+        let fs = fs(&[("a.md", "{{#include b.md}}"), ("b.md", "{{#include a.md}}")]);
+        let mut s = section("page.md", "{{#include a.md}}");
+        let e = resolve(&fs, &mut s).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Parse);
+        assert!(e.to_string().contains("a.md -> b.md -> a.md"), "{}", e);
+    }
+
+    /// Create a file system where `1.md` includes `2.md` and so on, up to `<depth>.md`, and a
+    /// section that includes `1.md`, so that the includes are nested `depth` levels deep.
+    fn chain(depth: usize) -> (ScratchFileSystem, Section) {
+        let fs = fs(&[]);
+        for i in 1..depth {
+            let content = format!("{{{{#include {}.md}}}}", i + 1);
+            fs.write_file(
+                Path::new(ROOT).join(format!("{}.md", i)),
+                content.as_bytes(),
+            )
+            .unwrap();
+        }
+        fs.write_file(Path::new(ROOT).join(format!("{}.md", depth)), b"end")
+            .unwrap();
+        (fs, section("page.md", "{{#include 1.md}}"))
+    }
+
+    #[test]
+    fn deep_acyclic_chains_are_errors() {
+        let (fs, mut s) = chain(DEFAULT_MAX_INCLUDE_DEPTH);
+        resolve(&fs, &mut s).unwrap();
+        assert_eq!(s.content, "end");
+
+        let (fs, mut s) = chain(DEFAULT_MAX_INCLUDE_DEPTH + 1);
+        let e = resolve(&fs, &mut s).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Parse);
+        assert!(e.to_string().contains("nested deeper than 16"), "{}", e);
+    }
+
+    #[test]
+    fn max_depth_is_configurable() {
+        let (fs, mut s) = chain(3);
+        let e = resolve_includes_with_max_depth(&fs, Path::new(ROOT), &mut s, 2).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Parse);
+        assert!(
+            e.to_string().contains("page.md -> 1.md -> 2.md -> 3.md"),
+            "{}",
+            e
+        );
+
+        let (fs, mut s) = chain(3);
+        resolve_includes_with_max_depth(&fs, Path::new(ROOT), &mut s, 3).unwrap();
+        assert_eq!(s.content, "end");
+
+        let mut s = section("page.md", "{{#include 1.md}}");
+        assert!(resolve_includes_with_max_depth(&fs, Path::new(ROOT), &mut s, 0).is_err());
+    }
+
+    #[test]
+    fn resolver_honors_max_depth() {
+        let (fs, s) = chain(3);
+        let mut book = BookBuilder::new()
+            .set_config(BookConfigBuilder::new().set_root(ROOT).build())
+            .add_chapter(ChapterBuilder::new().add_section(s).build())
+            .build();
+
+        let mut resolver = IncludeResolver::new(&fs).set_max_depth(2);
+        visit_mut(&mut book, &mut resolver);
+        let e = resolver.take_error().unwrap();
+        assert!(e.to_string().contains("nested deeper than 2"), "{}", e);
+
+        let mut resolver = IncludeResolver::new(&fs);
+        visit_mut(&mut book, &mut resolver);
+        assert!(resolver.take_error().is_none());
+        assert_eq!(book.chapters[0].sections[0].content, "end");
+    }
+
+    #[test]
+    fn pipelines_resolve_includes_in_every_book() {
+        use crate::tree::builder::GlobalizedBooksBuilder;
+        use crate::tree::pipeline::Pipeline;
+
+        let fs = fs(&[
+            ("en/guide/steps.txt", "one\ntwo\nthree"),
+            ("en/shared.md", "English"),
+            ("fr/shared.md", "Français"),
+        ]);
+        let book = |language: &str, content: &str| {
+            BookBuilder::new()
+                .set_config(
+                    BookConfigBuilder::new()
+                        .set_root(Path::new(ROOT).join(language))
+                        .build(),
+                )
+                .add_chapter(
+                    ChapterBuilder::new()
+                        .add_section(section("guide/setup.md", content))
+                        .add_section(
+                            SectionBuilder::new()
+                                .set_content("{{#include x.md}}")
+                                .build(),
+                        )
+                        .build(),
+                )
+                .build()
+        };
+        let mut books = GlobalizedBooksBuilder::new()
+            .add_localized_book(
+                "en".to_owned(),
+                book(
+                    "en",
+                    "{{#include ../shared.md}} and {{#include steps.txt:2}}",
+                ),
+            )
+            .add_localized_book("fr".to_owned(), book("fr", "{{#include ../shared.md}}"))
+            .build();
+
+        Pipeline::new()
+            .add_stage(Box::new(IncludeResolver::new(&fs)))
+            .run(&mut books)
+            .unwrap();
+        let contents: Vec<&str> = books
+            .books
+            .iter()
+            .map(|(_, b)| b.chapters[0].sections[0].content.as_str())
+            .collect();
+        assert_eq!(contents, vec!["English and two", "Français"]);
+
+        // Drafts do not have a file to resolve their directives against.
+        let draft = &books.books[0].1.chapters[0].sections[1];
+        assert_eq!(draft.content, "{{#include x.md}}");
+    }
+
+    #[test]
+    fn paths_are_relative_to_the_including_file() {
+        let fs = fs(&[
+            ("guide/part.md", "{{#include ../shared/note.md}}"),
+            ("shared/note.md", "note"),
+        ]);
+        let mut s = section("guide/page.md", "{{#include part.md}}");
+        resolve(&fs, &mut s).unwrap();
+        assert_eq!(s.content, "note");
+    }
+
+    #[test]
+    fn included_files_lists_direct_includes() {
+        let s = section(
+            "guide/page.md",
+            "{{#include a.md}} {{#include ../b.md:1:2}} {{#include :bad}}",
+        );
+        assert_eq!(
+            included_files(&s),
+            vec![PathBuf::from("guide/a.md"), PathBuf::from("b.md")]
+        );
+    }
+}
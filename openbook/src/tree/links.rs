@@ -0,0 +1,246 @@
+//! This module provides a visitor that finds broken internal links in section content.
+//!
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+use crate::fs::normalize;
+use crate::tree::visitor::Visitor;
+use crate::tree::{Book, Section};
+
+/// The reason why a link is broken.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum BrokenLinkReason {
+    /// The link refers to a file that is not the content file of any section in the book.
+    MissingSection,
+
+    /// The link refers to an anchor that does not exist in the target section.
+    MissingAnchor,
+}
+
+/// A link that does not resolve to a section of the book.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BrokenLink {
+    /// Name of the section that contains the link.
+    pub section: String,
+
+    /// Target of the link as written in the content.
+    pub target: String,
+
+    /// The reason why the link is broken.
+    pub reason: BrokenLinkReason,
+}
+
+/// A visitor that checks the internal links in the visited sections against a book.
+///
+/// Every markdown link in the content of a visited section is classified as external if it has a
+/// URL scheme, such as `https://` or `mailto:`, and as internal otherwise. The path of an internal
+/// link is resolved against the directory of the section's content file and must be the content
+/// file of a section in the book. If the link has an `#anchor` fragment, the anchor must be the
+/// slug of a heading in the target section, or the `id` of an HTML element in it. A link that
+/// consists of a fragment only refers to the section that contains it.
+///
+/// All broken links are collected rather than stopping at the first one.
+pub struct LinkChecker<'a> {
+    book: &'a Book,
+    broken: Vec<BrokenLink>,
+}
+
+impl<'a> LinkChecker<'a> {
+    /// Create a new `LinkChecker` instance that resolves internal links against the specified
+    /// book.
+    pub fn new(book: &'a Book) -> Self {
+        Self {
+            book,
+            broken: Vec::new(),
+        }
+    }
+
+    /// Get the broken links found so far, in visiting order.
+    pub fn broken_links(&self) -> &[BrokenLink] {
+        &self.broken
+    }
+
+    /// Consume this checker and get the broken links, in visiting order.
+    pub fn into_broken_links(self) -> Vec<BrokenLink> {
+        self.broken
+    }
+
+    /// Check the specified internal link in the specified section, and get the reason why it is
+    /// broken.
+    fn check(&self, section: &Section, target: &str) -> Option<BrokenLinkReason> {
+        let (path, anchor) = match target.find('#') {
+            Some(i) => (&target[..i], Some(&target[i + 1..])),
+            None => (target, None),
+        };
+
+        let target_section = if path.is_empty() {
+            section
+        } else {
+            let base = section.file.parent().unwrap_or_else(|| Path::new(""));
+            let file = normalize(base.join(path));
+            match self.book.find_section_by_file(&file) {
+                Some(s) => s,
+                None => return Some(BrokenLinkReason::MissingSection),
+            }
+        };
+
+        match anchor {
+            Some(anchor) if !anchor.is_empty() => {
+                if collect_anchors(target_section).contains(anchor) {
+                    None
+                } else {
+                    Some(BrokenLinkReason::MissingAnchor)
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Visitor for LinkChecker<'a> {
+    fn visit_section(&mut self, section: &Section) {
+        for target in collect_links(&section.content) {
+            if is_external(&target) {
+                continue;
+            }
+
+            if let Some(reason) = self.check(section, &target) {
+                self.broken.push(BrokenLink {
+                    section: section.name.clone(),
+                    target,
+                    reason,
+                });
+            }
+        }
+    }
+}
+
+/// Determine whether the specified link target is external to the book.
+pub(crate) fn is_external(target: &str) -> bool {
+    if target.starts_with("//") {
+        return true;
+    }
+    // A URL scheme consists of a letter followed by letters, digits, `+`, `-` and `.`.
+    match target.find(':') {
+        Some(i) => {
+            let scheme = &target[..i];
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+/// Collect the targets of all links in the specified markdown content.
+fn collect_links(content: &str) -> Vec<String> {
+    Parser::new(content)
+        .filter_map(|event| match event {
+            Event::Start(Tag::Link(_, dest, _)) => Some(dest.into_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Collect the anchors defined in the content of the specified section, i.e. the slugs of its
+/// headings and the `id` attributes of its HTML elements.
+fn collect_anchors(section: &Section) -> HashSet<String> {
+    let mut anchors: HashSet<String> = section.headings().into_iter().map(|h| h.slug).collect();
+    for event in Parser::new(&section.content) {
+        if let Event::Html(html) = event {
+            collect_html_ids(&html, &mut anchors);
+        }
+    }
+    anchors
+}
+
+/// Collect the values of the `id` attributes in the specified HTML fragment.
+fn collect_html_ids(html: &str, anchors: &mut HashSet<String>) {
+    const ID_START: &str = "id=\"";
+
+    let mut rest = html;
+    while let Some(start) = rest.find(ID_START) {
+        rest = &rest[start + ID_START.len()..];
+        match rest.find('"') {
+            Some(end) => {
+                anchors.insert(rest[..end].to_owned());
+                rest = &rest[end + 1..];
+            }
+            None => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::builder::{BookBuilder, ChapterBuilder, SectionBuilder};
+    use crate::tree::visitor::visit;
+
+    fn check(content: &str) -> Vec<BrokenLink> {
+        let book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_name("Usage")
+                            .set_file("guide/usage.md")
+                            .set_content(content)
+                            .build(),
+                    )
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_name("Build")
+                            .set_file("guide/build.md")
+                            .set_content("# Build\n\n## Build Flags\n\n<a id=\"raw\"></a>\n")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let mut checker = LinkChecker::new(&book);
+        visit(&book, &mut checker);
+        checker.into_broken_links()
+    }
+
+    #[test]
+    fn accepts_valid_and_external_links() {
+        let content = "# Usage\n\n\
+                       [build](build.md) [flags](./build.md#build-flags) [raw](build.md#raw)\n\
+                       [self](#usage) [up](../guide/build.md)\n\
+                       [web](https://example.com/missing.md) [mail](mailto:a@example.com)\n";
+        assert_eq!(check(content), Vec::new());
+    }
+
+    #[test]
+    fn reports_missing_sections_and_anchors() {
+        let content = "# Usage\n\n[gone](gone.md) [flag](build.md#flag) [here](#nowhere)\n";
+        let broken = |target: &str, reason| BrokenLink {
+            section: "Usage".to_owned(),
+            target: target.to_owned(),
+            reason,
+        };
+        assert_eq!(
+            check(content),
+            vec![
+                broken("gone.md", BrokenLinkReason::MissingSection),
+                broken("build.md#flag", BrokenLinkReason::MissingAnchor),
+                broken("#nowhere", BrokenLinkReason::MissingAnchor),
+            ]
+        );
+    }
+
+    #[test]
+    fn classifies_external_links() {
+        assert!(is_external("https://example.com"));
+        assert!(is_external("//example.com/a.md"));
+        assert!(is_external("git+ssh://example.com"));
+        assert!(!is_external("intro.md"));
+        assert!(!is_external("#anchor"));
+        assert!(!is_external("1:2.md"));
+    }
+}
@@ -0,0 +1,584 @@
+//! This module describes a whole project in a machine-readable build manifest.
+//!
+//! A [`BuildManifest`] summarizes the configuration, the chapter and section hierarchy, the pages
+//! and the statistics of every book in a project, so that CI pipelines and external tools can
+//! inspect a built project without rendering it again. The manifest is serialized into JSON by
+//! `BuildManifest::to_json`, and its `schema_version` is bumped whenever the layout of the JSON
+//! changes in an incompatible way.
+//!
+//! [`BuildManifest`]: struct.BuildManifest.html
+//!
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::render::{json_string, path_to_url};
+use crate::tree::stats::{SectionStats, StatsVisitor};
+use crate::tree::visitor::VisitorHost;
+use crate::tree::{GlobalizedBooks, Section, TextDirection};
+
+/// Version of the schema of the JSON emitted by `BuildManifest::to_json`.
+pub const BUILD_MANIFEST_SCHEMA_VERSION: u32 = 1;
+
+/// A machine-readable description of a whole project.
+///
+/// All paths are relative to the root directory of the project, or to the root directory of the
+/// enclosing book for paths within a book, and use forward slash separators.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BuildManifest {
+    /// Version of the schema of this manifest, see [`BUILD_MANIFEST_SCHEMA_VERSION`].
+    ///
+    /// [`BUILD_MANIFEST_SCHEMA_VERSION`]: constant.BUILD_MANIFEST_SCHEMA_VERSION.html
+    pub schema_version: u32,
+
+    /// Title of the project, taken from the global configuration.
+    pub title: Option<String>,
+
+    /// Languages of all books in the project, in order. The language of the default book is an
+    /// empty string.
+    pub languages: Vec<String>,
+
+    /// All books in the project, in order.
+    pub books: Vec<BookManifest>,
+
+    /// All pages of the project in reading order, as produced by `GlobalizedBooks::flatten`.
+    pub pages: Vec<PageManifest>,
+
+    /// Number of words in all books.
+    pub words: usize,
+
+    /// Number of headings in all books.
+    pub headings: usize,
+
+    /// Number of sections in all books, including the prefaces.
+    pub section_count: usize,
+}
+
+/// Description of a book in a [`BuildManifest`].
+///
+/// [`BuildManifest`]: struct.BuildManifest.html
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BookManifest {
+    /// Language the book is associated with in the project.
+    pub language: String,
+
+    /// Root directory of the book, relative to the root directory of the project.
+    pub root: String,
+
+    /// Title of the book.
+    pub title: Option<String>,
+
+    /// Description of the book.
+    pub description: Option<String>,
+
+    /// Author of the book.
+    pub author: Option<String>,
+
+    /// ISBN of the book.
+    pub isbn: Option<String>,
+
+    /// Text direction of the book, as given by `BookConfig::effective_direction`.
+    pub direction: TextDirection,
+
+    /// Number of words in the book.
+    pub words: usize,
+
+    /// The preface section of the book.
+    pub preface: SectionManifest,
+
+    /// All chapters of the book, in order.
+    pub chapters: Vec<ChapterManifest>,
+}
+
+/// Description of a chapter in a [`BuildManifest`].
+///
+/// [`BuildManifest`]: struct.BuildManifest.html
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ChapterManifest {
+    /// Name of the chapter.
+    pub name: String,
+
+    /// Number of words in the chapter.
+    pub words: usize,
+
+    /// All top-level sections of the chapter, in order.
+    pub sections: Vec<SectionManifest>,
+}
+
+/// Description of a section in a [`BuildManifest`].
+///
+/// [`BuildManifest`]: struct.BuildManifest.html
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SectionManifest {
+    /// Name of the section.
+    pub name: String,
+
+    /// Path to the content file of the section, relative to the root directory of the book.
+    pub file: String,
+
+    /// Anchor within the content file at which the section starts.
+    pub anchor: Option<String>,
+
+    /// Number of words in the content of the section, not including its subsections.
+    pub words: usize,
+
+    /// Number of headings in the content of the section, not including its subsections.
+    pub headings: usize,
+
+    /// Metadata of the section.
+    pub metadata: BTreeMap<String, String>,
+
+    /// All subsections of the section, in order.
+    pub subsections: Vec<SectionManifest>,
+}
+
+/// Description of a page in a [`BuildManifest`].
+///
+/// [`BuildManifest`]: struct.BuildManifest.html
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PageManifest {
+    /// Language of the book that contains the page.
+    pub language: String,
+
+    /// Name of the chapter that contains the page, or `None` for the preface.
+    pub chapter: Option<String>,
+
+    /// Path to the content file of the page, relative to the root directory of the book.
+    pub file: String,
+
+    /// Name of the section that the page starts with.
+    pub name: String,
+}
+
+impl GlobalizedBooks {
+    /// Describe this project in a [`BuildManifest`].
+    ///
+    /// Statistics are collected as by [`StatsVisitor`].
+    ///
+    /// [`BuildManifest`]: manifest/struct.BuildManifest.html
+    /// [`StatsVisitor`]: stats/struct.StatsVisitor.html
+    pub fn to_build_manifest(&self) -> BuildManifest {
+        let mut visitor = StatsVisitor::new();
+        self.visit(&mut visitor);
+        let stats = visitor.into_stats();
+
+        let mut manifest = BuildManifest {
+            schema_version: BUILD_MANIFEST_SCHEMA_VERSION,
+            title: self.config.title.clone(),
+            ..BuildManifest::default()
+        };
+
+        for ((language, book), stats) in self.books.iter().zip(stats) {
+            let root = &book.config.root;
+            let mut sections = stats.sections.iter();
+            manifest.languages.push(language.clone());
+            manifest.words += stats.words;
+            manifest.headings += stats.headings;
+            manifest.section_count += stats.section_count();
+            manifest.books.push(BookManifest {
+                language: language.clone(),
+                root: relative_url(&self.config.root, root),
+                title: book.config.title.clone(),
+                description: book.config.description.clone(),
+                author: book.config.author.clone(),
+                isbn: book.config.isbn.clone(),
+                direction: book.config.effective_direction(),
+                words: stats.words,
+                preface: section_manifest(root, &book.preface, &mut sections),
+                chapters: book
+                    .chapters
+                    .iter()
+                    .zip(&stats.chapters)
+                    .map(|(chapter, chapter_stats)| ChapterManifest {
+                        name: chapter.name.clone(),
+                        words: chapter_stats.words,
+                        sections: chapter
+                            .sections
+                            .iter()
+                            .map(|s| section_manifest(root, s, &mut sections))
+                            .collect(),
+                    })
+                    .collect(),
+            });
+        }
+
+        for page in self.flatten() {
+            let root = self
+                .books
+                .iter()
+                .find(|(language, _)| *language == page.language)
+                .map(|(_, book)| book.config.root.as_path())
+                .unwrap_or_else(|| Path::new(""));
+            manifest.pages.push(PageManifest {
+                file: relative_url(root, &page.file),
+                language: page.language,
+                chapter: page.chapter,
+                name: page.name,
+            });
+        }
+
+        manifest
+    }
+}
+
+impl BuildManifest {
+    /// Serialize this manifest into a JSON object.
+    ///
+    /// The object has the same fields as this struct, with `snake_case` names. Optional values
+    /// that are absent are `null`, text directions are `"ltr"` or `"rtl"`, and section metadata
+    /// are objects mapping keys to string values.
+    pub fn to_json(&self) -> Result<String> {
+        let mut output = String::new();
+        output.push_str(&format!(
+            "{{\"schema_version\":{},\"title\":{},\"languages\":[",
+            self.schema_version,
+            json_option(&self.title)
+        ));
+        push_list(&mut output, &self.languages, |l, output| {
+            output.push_str(&json_string(l))
+        });
+        output.push_str("],\"books\":[");
+        push_list(&mut output, &self.books, push_book);
+        output.push_str("],\"pages\":[");
+        push_list(&mut output, &self.pages, |p, output| {
+            output.push_str(&format!(
+                "{{\"language\":{},\"chapter\":{},\"file\":{},\"name\":{}}}",
+                json_string(&p.language),
+                json_option(&p.chapter),
+                json_string(&p.file),
+                json_string(&p.name)
+            ))
+        });
+        output.push_str(&format!(
+            "],\"words\":{},\"headings\":{},\"section_count\":{}}}",
+            self.words, self.headings, self.section_count
+        ));
+        Ok(output)
+    }
+}
+
+/// Describe the specified section and its subsections, taking their statistics from the specified
+/// iterator over section statistics in depth-first pre-order.
+fn section_manifest<'a, I>(root: &Path, section: &Section, stats: &mut I) -> SectionManifest
+where
+    I: Iterator<Item = &'a SectionStats>,
+{
+    // The sections are described in depth-first pre-order along with the index of the description
+    // of their parent, and then moved into their parents from the last one, so that deeply nested
+    // sections cannot overflow the call stack.
+    let mut described: Vec<(Option<usize>, SectionManifest)> = Vec::new();
+    let mut stack = vec![(section, None)];
+    while let Some((s, parent)) = stack.pop() {
+        let (words, headings) = stats.next().map_or((0, 0), |s| (s.words, s.headings));
+        let index = described.len();
+        described.push((
+            parent,
+            SectionManifest {
+                name: s.name.clone(),
+                file: relative_url(root, &s.file),
+                anchor: s.anchor.clone(),
+                words,
+                headings,
+                metadata: s.metadata.clone(),
+                subsections: Vec::with_capacity(s.subsections.len()),
+            },
+        ));
+        stack.extend(s.subsections.iter().rev().map(|s| (s, Some(index))));
+    }
+
+    while let Some((parent, mut manifest)) = described.pop() {
+        // Subsections are moved in from the last one.
+        manifest.subsections.reverse();
+        match parent {
+            Some(parent) => described[parent].1.subsections.push(manifest),
+            None => return manifest,
+        }
+    }
+    unreachable!("the described sections start with the specified section")
+}
+
+/// Convert the specified path to a forward slash separated path relative to the specified root.
+///
+/// Paths that are not under the root are converted as-is.
+fn relative_url(root: &Path, path: &Path) -> String {
+    path_to_url(path.strip_prefix(root).unwrap_or(path))
+}
+
+/// Encode the specified optional text as a JSON string literal or `null`.
+fn json_option(text: &Option<String>) -> String {
+    match text {
+        Some(text) => json_string(text),
+        None => "null".to_owned(),
+    }
+}
+
+/// Append the specified items to a JSON array under construction, separated by commas.
+fn push_list<T, F: Fn(&T, &mut String)>(output: &mut String, items: &[T], push: F) {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            output.push(',');
+        }
+        push(item, output);
+    }
+}
+
+/// Append the JSON object of the specified book to the specified output.
+fn push_book(book: &BookManifest, output: &mut String) {
+    let direction = match book.direction {
+        TextDirection::Ltr => "ltr",
+        TextDirection::Rtl => "rtl",
+    };
+    output.push_str(&format!(
+        "{{\"language\":{},\"root\":{},\"title\":{},\"description\":{},\"author\":{},\
+         \"isbn\":{},\"direction\":\"{}\",\"words\":{},\"preface\":",
+        json_string(&book.language),
+        json_string(&book.root),
+        json_option(&book.title),
+        json_option(&book.description),
+        json_option(&book.author),
+        json_option(&book.isbn),
+        direction,
+        book.words
+    ));
+    push_section(&book.preface, output);
+    output.push_str(",\"chapters\":[");
+    push_list(output, &book.chapters, |c, output| {
+        output.push_str(&format!(
+            "{{\"name\":{},\"words\":{},\"sections\":[",
+            json_string(&c.name),
+            c.words
+        ));
+        push_list(output, &c.sections, push_section);
+        output.push_str("]}");
+    });
+    output.push_str("]}");
+}
+
+/// Append the JSON object of the specified section and its subsections to the specified output.
+fn push_section(section: &SectionManifest, output: &mut String) {
+    // An explicit stack keeps deeply nested sections from overflowing the call stack. `None`
+    // closes the subsections of a section, and every section is paired with whether it is the
+    // first of its siblings.
+    let mut stack = vec![Some((section, true))];
+    while let Some(item) = stack.pop() {
+        let (section, first) = match item {
+            Some(item) => item,
+            None => {
+                output.push_str("]}");
+                continue;
+            }
+        };
+        if !first {
+            output.push(',');
+        }
+        output.push_str(&format!(
+            "{{\"name\":{},\"file\":{},\"anchor\":{},\"words\":{},\"headings\":{},\"metadata\":{{",
+            json_string(&section.name),
+            json_string(&section.file),
+            json_option(&section.anchor),
+            section.words,
+            section.headings
+        ));
+        for (i, (key, value)) in section.metadata.iter().enumerate() {
+            if i > 0 {
+                output.push(',');
+            }
+            output.push_str(&format!("{}:{}", json_string(key), json_string(value)));
+        }
+        output.push_str("},\"subsections\":[");
+        stack.push(None);
+        stack.extend(
+            section
+                .subsections
+                .iter()
+                .enumerate()
+                .rev()
+                .map(|(i, s)| Some((s, i == 0))),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::builder::{
+        BookBuilder, BookConfigBuilder, ChapterBuilder, GlobalizedBooksBuilder, SectionBuilder,
+    };
+    use crate::tree::visitor::tests::{chain_with, drop_book_iteratively, with_small_stack};
+
+    fn project() -> GlobalizedBooks {
+        let book = BookBuilder::new()
+            .set_config(
+                BookConfigBuilder::new()
+                    .set_root("project/ar")
+                    .set_title("دليل")
+                    .set_language("ar")
+                    .build(),
+            )
+            .set_preface(
+                SectionBuilder::new()
+                    .set_name("Preface")
+                    .set_file("README.md")
+                    .set_content("Hello world")
+                    .build(),
+            )
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("Basics")
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_name("Intro")
+                            .set_file("guide/intro.md")
+                            .set_content("# Intro\n\nOne two three")
+                            .set_metadata("author", "Ana")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        GlobalizedBooksBuilder::new()
+            .set_config(
+                BookConfigBuilder::new()
+                    .set_root("project")
+                    .set_title("Guide")
+                    .build(),
+            )
+            .add_localized_book("ar".to_owned(), book)
+            .build()
+    }
+
+    #[test]
+    fn describes_books_and_pages() {
+        let manifest = project().to_build_manifest();
+        assert_eq!(manifest.schema_version, BUILD_MANIFEST_SCHEMA_VERSION);
+        assert_eq!(manifest.title.as_deref(), Some("Guide"));
+        assert_eq!(manifest.languages, vec!["ar"]);
+        assert_eq!(manifest.section_count, 2);
+
+        let book = &manifest.books[0];
+        assert_eq!(book.root, "ar");
+        assert_eq!(book.direction, TextDirection::Rtl);
+        assert_eq!(book.preface.file, "README.md");
+        let intro = &book.chapters[0].sections[0];
+        assert_eq!(intro.file, "guide/intro.md");
+        assert_eq!(intro.metadata["author"], "Ana");
+        assert_eq!(book.words, book.preface.words + book.chapters[0].words);
+
+        let pages: Vec<(&str, Option<&str>)> = manifest
+            .pages
+            .iter()
+            .map(|p| (p.file.as_str(), p.chapter.as_deref()))
+            .collect();
+        assert_eq!(
+            pages,
+            vec![("README.md", None), ("guide/intro.md", Some("Basics"))]
+        );
+    }
+
+    #[test]
+    fn serializes_a_versioned_json_object() {
+        let json = project().to_build_manifest().to_json().unwrap();
+        assert!(
+            json.starts_with("{\"schema_version\":1,\"title\":\"Guide\""),
+            "{}",
+            json
+        );
+        assert!(json.contains("\"languages\":[\"ar\"]"), "{}", json);
+        assert!(
+            json.contains(
+                "{\"language\":\"ar\",\"chapter\":\"Basics\",\"file\":\"guide/intro.md\",\"name\":\"Intro\"}"
+            ),
+            "{}",
+            json
+        );
+        assert!(json.contains("\"direction\":\"rtl\""), "{}", json);
+        assert!(json.contains("\"author\":\"Ana\""), "{}", json);
+        assert!(json.ends_with(",\"section_count\":2}"), "{}", json);
+    }
+
+    fn named(name: &str, subsections: Vec<Section>) -> Section {
+        let mut section = SectionBuilder::new()
+            .set_name(name)
+            .set_file(format!("{}.md", name))
+            .build();
+        section.subsections = subsections;
+        section
+    }
+
+    fn book_of(section: Section) -> GlobalizedBooks {
+        let book = BookBuilder::new()
+            .add_chapter(ChapterBuilder::new().add_section(section).build())
+            .build();
+        GlobalizedBooksBuilder::new().add_default_book(book).build()
+    }
+
+    #[test]
+    fn keeps_the_order_of_subsections() {
+        let project = book_of(named(
+            "a",
+            vec![
+                named("b", vec![named("c", Vec::new())]),
+                named("d", Vec::new()),
+            ],
+        ));
+        let manifest = project.to_build_manifest();
+        let a = &manifest.books[0].chapters[0].sections[0];
+        let names: Vec<_> = a.subsections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["b", "d"]);
+        assert_eq!(a.subsections[0].subsections[0].file, "c.md");
+
+        let json = manifest.to_json().unwrap();
+        let order: Vec<_> = ["\"a\"", "\"b\"", "\"c\"", "\"d\""]
+            .iter()
+            .map(|name| json.find(&format!("\"name\":{}", name)).unwrap())
+            .collect();
+        assert!(order.windows(2).all(|w| w[0] < w[1]), "{}", json);
+        assert!(
+            json.contains("\"subsections\":[]}]},{\"name\":\"d\""),
+            "{}",
+            json
+        );
+    }
+
+    #[test]
+    fn describes_deeply_nested_sections() {
+        with_small_stack(describe_deep_chain);
+    }
+
+    fn describe_deep_chain() {
+        const DEPTH: usize = 10_000;
+        let mut project = book_of(chain_with(DEPTH, |d| named(&d.to_string(), Vec::new())));
+        let mut manifest = project.to_build_manifest();
+        assert_eq!(manifest.section_count, DEPTH + 2);
+
+        let mut depth = 0;
+        let mut s = &manifest.books[0].chapters[0].sections[0];
+        while let Some(child) = s.subsections.first() {
+            depth += 1;
+            assert_eq!(child.name, depth.to_string());
+            s = child;
+        }
+        assert_eq!(depth, DEPTH);
+
+        let json = manifest.to_json().unwrap();
+        assert!(json.contains("{\"name\":\"10000\",\"file\":\"10000.md\""));
+        assert!(json.contains(&"]}".repeat(DEPTH + 1)));
+
+        // Dismantle the manifest iteratively, since the drop glue of `SectionManifest` is
+        // recursive.
+        let mut pending = std::mem::take(&mut manifest.books[0].chapters[0].sections);
+        while let Some(mut s) = pending.pop() {
+            pending.append(&mut s.subsections);
+        }
+        drop_book_iteratively(project.books.pop().unwrap().1);
+    }
+}
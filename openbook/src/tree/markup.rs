@@ -0,0 +1,232 @@
+//! This module determines the markup language in which the content of a section is written.
+//!
+//! Books may mix content files in several markup languages, which are told apart by the extension
+//! of the files:
+//!
+//! | Markup                         | Extensions                  |
+//! |--------------------------------|-----------------------------|
+//! | [`Markup::Markdown`]           | `.md`, `.markdown`, `.mdown` |
+//! | [`Markup::ReStructuredText`]   | `.rst`, `.rest`             |
+//! | [`Markup::AsciiDoc`]           | `.adoc`, `.asciidoc`, `.asc` |
+//! | [`Markup::PlainText`]          | `.txt`, `.text`             |
+//!
+//! The content of sections that are not written in markdown is converted into markdown by
+//! `Section::convert_markup` when the sections are loaded, so that visitors and renderers do not
+//! need to know about other markup languages. Metadata that the content defines in its own
+//! markup, like the attribute entries of AsciiDoc, is added to `Section::metadata` on the way.
+//!
+//! [`Markup::Markdown`]: enum.Markup.html#variant.Markdown
+//! [`Markup::ReStructuredText`]: enum.Markup.html#variant.ReStructuredText
+//! [`Markup::AsciiDoc`]: enum.Markup.html#variant.AsciiDoc
+//! [`Markup::PlainText`]: enum.Markup.html#variant.PlainText
+//!
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::tree::asciidoc::asciidoc_to_markdown;
+use crate::tree::rst::rst_to_markdown;
+use crate::tree::Section;
+
+/// Markup language of the content of a section.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Markup {
+    /// CommonMark markdown, with the extensions that OpenBook renders.
+    #[default]
+    Markdown,
+
+    /// reStructuredText, see the [`rst`] module for the supported constructs.
+    ///
+    /// [`rst`]: ../rst/index.html
+    ReStructuredText,
+
+    /// AsciiDoc, see the [`asciidoc`] module for the supported constructs.
+    ///
+    /// [`asciidoc`]: ../asciidoc/index.html
+    AsciiDoc,
+
+    /// Plain text, where every line is taken literally.
+    PlainText,
+}
+
+impl Markup {
+    /// Determine the markup of the file at the specified path by its extension.
+    ///
+    /// Returns `None` if the extension does not belong to any supported markup.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Markup> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "md" | "markdown" | "mdown" => Some(Markup::Markdown),
+            "rst" | "rest" => Some(Markup::ReStructuredText),
+            "adoc" | "asciidoc" | "asc" => Some(Markup::AsciiDoc),
+            "txt" | "text" => Some(Markup::PlainText),
+            _ => None,
+        }
+    }
+
+    /// Convert the specified content written in this markup into markdown.
+    pub fn to_markdown(self, content: &str) -> Cow<'_, str> {
+        self.convert(content).1
+    }
+
+    /// Convert the specified content written in this markup into markdown, and get the metadata
+    /// that the content defines in this markup.
+    pub fn convert(self, content: &str) -> (BTreeMap<String, String>, Cow<'_, str>) {
+        match self {
+            Markup::Markdown => (BTreeMap::new(), Cow::Borrowed(content)),
+            Markup::ReStructuredText => (BTreeMap::new(), Cow::Owned(rst_to_markdown(content))),
+            Markup::AsciiDoc => {
+                let (metadata, content) = asciidoc_to_markdown(content);
+                (metadata, Cow::Owned(content))
+            }
+            Markup::PlainText => (BTreeMap::new(), Cow::Owned(plain_text_to_markdown(content))),
+        }
+    }
+}
+
+impl Section {
+    /// Get the markup of the content file of this section.
+    ///
+    /// Sections without a content file and sections whose content file has an unknown extension
+    /// are written in markdown.
+    pub fn markup(&self) -> Markup {
+        Markup::from_path(&self.file).unwrap_or_default()
+    }
+
+    /// Convert the content of this section from the markup of its content file into markdown.
+    ///
+    /// The content of sections written in markdown is left untouched. Metadata defined by the
+    /// content is added to the metadata of this section, without replacing existing entries such
+    /// as those from the frontmatter. This should be called once, after the content is loaded and
+    /// its frontmatter is extracted.
+    pub fn convert_markup(&mut self) {
+        if let Some(content) = convert_content(&self.file, &self.content, &mut self.metadata) {
+            self.content = content;
+        }
+    }
+
+    /// Get the path of the content file of this section as a markdown file, i.e. `file` with its
+    /// extension replaced by `.md` if the section is not written in markdown.
+    pub fn markdown_file(&self) -> PathBuf {
+        match self.markup() {
+            Markup::Markdown => self.file.clone(),
+            _ => self.file.with_extension("md"),
+        }
+    }
+}
+
+/// Convert the specified content of the specified file into markdown as by
+/// `Section::convert_markup`, adding the metadata it defines to `metadata`.
+///
+/// Returns `None` if the content is already markdown.
+pub(crate) fn convert_content(
+    file: &Path,
+    content: &str,
+    metadata: &mut BTreeMap<String, String>,
+) -> Option<String> {
+    let (defined, content) = Markup::from_path(file).unwrap_or_default().convert(content);
+    for (key, value) in defined {
+        metadata.entry(key).or_insert(value);
+    }
+    match content {
+        Cow::Borrowed(_) => None,
+        Cow::Owned(content) => Some(content),
+    }
+}
+
+/// Convert the specified plain text into markdown that renders every line as-is.
+///
+/// Paragraphs are separated by blank lines and every other line break is kept as a hard line
+/// break. Characters that have a meaning in markdown are escaped.
+fn plain_text_to_markdown(content: &str) -> String {
+    let lines: Vec<&str> = content.lines().map(str::trim_end).collect();
+    let mut output = String::with_capacity(content.len());
+    for (i, line) in lines.iter().enumerate() {
+        let text = line.trim_start();
+        for (j, c) in text.char_indices() {
+            let marker = j == 0 && matches!(c, '-' | '+' | '=' | '>')
+                || matches!(c, '.' | ')') && text[..j].chars().all(|d| d.is_ascii_digit());
+            if marker
+                || matches!(
+                    c,
+                    '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '#' | '!' | '|' | '~' | '&'
+                )
+            {
+                output.push('\\');
+            }
+            output.push(c);
+        }
+
+        let next_is_text = matches!(lines.get(i + 1), Some(next) if !next.trim().is_empty());
+        if !text.is_empty() && next_is_text {
+            output.push('\\');
+        }
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markup_is_determined_by_the_extension() {
+        assert_eq!(Markup::from_path("intro.md"), Some(Markup::Markdown));
+        assert_eq!(Markup::from_path("a/b.MARKDOWN"), Some(Markup::Markdown));
+        assert_eq!(
+            Markup::from_path("guide.rst"),
+            Some(Markup::ReStructuredText)
+        );
+        assert_eq!(
+            Markup::from_path("guide.rest"),
+            Some(Markup::ReStructuredText)
+        );
+        assert_eq!(Markup::from_path("guide.adoc"), Some(Markup::AsciiDoc));
+        assert_eq!(Markup::from_path("notes.txt"), Some(Markup::PlainText));
+        assert_eq!(Markup::from_path("notes.html"), None);
+        assert_eq!(Markup::from_path("LICENSE"), None);
+    }
+
+    #[test]
+    fn markdown_is_borrowed_as_is() {
+        assert!(matches!(
+            Markup::Markdown.to_markdown("# Title\n"),
+            Cow::Borrowed("# Title\n")
+        ));
+    }
+
+    #[test]
+    fn plain_text_is_escaped_and_keeps_line_breaks() {
+        assert_eq!(
+            Markup::PlainText.to_markdown("# not a heading\n1. not a list\nline *two*\n\nnext\n"),
+            "\\# not a heading\\\n1\\. not a list\\\nline \\*two\\*\n\nnext\n"
+        );
+    }
+
+    #[test]
+    fn sections_are_converted_into_markdown() {
+        let mut section = Section {
+            file: PathBuf::from("guide/setup.rst"),
+            content: String::from("Setup\n=====\n\nRun it.\n"),
+            ..Section::default()
+        };
+        assert_eq!(section.markup(), Markup::ReStructuredText);
+        assert_eq!(section.markdown_file(), PathBuf::from("guide/setup.md"));
+
+        section.convert_markup();
+        assert_eq!(section.content, "# Setup\n\nRun it.\n");
+
+        let markdown = Section {
+            file: PathBuf::from("intro.md"),
+            content: String::from("Setup\n=====\n"),
+            ..Section::default()
+        };
+        let mut converted = markdown.clone();
+        converted.convert_markup();
+        assert_eq!(converted, markdown);
+        assert_eq!(markdown.markdown_file(), PathBuf::from("intro.md"));
+        assert_eq!(Section::default().markup(), Markup::Markdown);
+    }
+}
@@ -8,7 +8,7 @@
 //! |- Book [lang="en"]
 //! |  |- BookConfig { ... }
 //! |  |- Section [preface, name="Preface"]
-//! |  |- Section [name="User Manual"]
+//! |  |- Chapter [name="User Manual"]
 //! |  |  |- Section [name="What is OpenBook"]
 //! |  |  |- Section [name="How to use OpenBook"]
 //! |  |     |- Section [name="How to build"]
@@ -17,34 +17,71 @@
 //! |- Book [lang="zh"]
 //!    |- BookConfig { ... }
 //!    |- Section [preface, name="前言"]
-//!    |- Section [name="用户手册"]
+//!    |- Chapter [name="用户手册"]
 //!    |  |- Section [name="什么是 OpenBook"]
 //!    |  |- Section [name="如何使用 OpenBook"]
 //!    |     |- Section [name="如何构建"]
-//!    |- Section [name="开发者手册"]
+//!    |- Chapter [name="开发者手册"]
 //!       |- Section [name="如何贡献"]
 //! ```
 //!
 //! The root of the project tree is represented by [`GlobalizedBooks`] struct. It is a container for
 //! all books written in different languages. A book written in a specific language is represented
-//! by the [`Book`] struct. Each book has its own logical structure represented by [`Section`]s. In
-//! sections are the book's actual content.
+//! by the [`Book`] struct. Each book starts with a preface section and has its own logical
+//! structure represented by [`Chapter`]s, each of which consists of [`Section`]s. In sections are
+//! the book's actual content.
 //!
 //! [`GlobalizedBooks`]: struct.GlobalizedBooks.html
 //! [`Book`]: struct.Book.html
+//! [`Chapter`]: struct.Chapter.html
 //! [`Section`]: struct.Section.html
 //!
 
+pub mod asciidoc;
+pub mod budget;
 pub mod builder;
+pub mod coverage;
+pub mod crossref;
+pub mod figures;
+pub mod frontmatter;
+pub mod glossary;
+pub mod headings;
+pub mod include;
+pub mod links;
+pub mod manifest;
+pub mod markup;
+pub mod numbering;
+pub mod orphans;
+pub mod pages;
+pub mod patch;
+pub mod pipeline;
+pub mod query;
+pub mod rename;
+pub mod rst;
+pub mod stats;
+pub mod variables;
 pub mod visitor;
 
-use std::path::PathBuf;
+#[cfg(feature = "serde")]
+mod serde_path;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::config::{merge_extra, DEFAULT_OUTPUT_DIR};
+use crate::error::{Error, Result};
+use crate::tree::frontmatter::FrontmatterFormat;
+use crate::tree::numbering::SectionNumber;
 
 /// The root of the OpenBook project tree.
 ///
 /// This is the container for all books contained in the project that are written in different
 /// natural languages.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct GlobalizedBooks {
     /// The global configuration.
     pub config: BookConfig,
@@ -56,29 +93,115 @@ pub struct GlobalizedBooks {
     pub books: Vec<(String, Book)>,
 }
 
+impl GlobalizedBooks {
+    /// Get the effective configuration of every book, which is its local configuration layered
+    /// over the global configuration by `BookConfig::merge_over`, paired with the language of the
+    /// book.
+    ///
+    /// The configurations are returned in the order of `books`. Merging is idempotent, so the
+    /// configurations of books loaded by `parse::load_project`, which are merged already, are
+    /// returned unchanged.
+    pub fn effective_configs(&self) -> Vec<(String, BookConfig)> {
+        self.books
+            .iter()
+            .map(|(language, book)| (language.clone(), book.config.merge_over(&self.config)))
+            .collect()
+    }
+}
+
 /// A book written in some language.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Book {
     /// The local configuration.
     ///
     /// Entries in local configuration overwrite the corresponding entries given in the global
-    /// configuration.
+    /// configuration, see `BookConfig::merge_over`.
     pub config: BookConfig,
 
     /// The preface section of this book.
     pub preface: Section,
 
-    /// All sections contained in this book.
-    pub sections: Vec<Section>,
+    /// All chapters contained in this book.
+    pub chapters: Vec<Chapter>,
+
+    /// The glossary of this book, which is empty if the book does not have a glossary file.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub glossary: Glossary,
+}
+
+impl Book {
+    /// Consume this book and get all of its sections as owned values, starting with the preface
+    /// and followed by the sections of each chapter.
+    ///
+    /// The returned sections are detached from the book and can be moved into other threads. For
+    /// differences on different values of `mode`, please refer to [`SectionListMode`].
+    ///
+    /// [`SectionListMode`]: enum.SectionListMode.html
+    pub fn into_section_list(self, mode: SectionListMode) -> Vec<Section> {
+        let mut sections = vec![self.preface];
+        for chapter in self.chapters {
+            sections.extend(chapter.sections);
+        }
+
+        match mode {
+            SectionListMode::Nested => sections,
+            SectionListMode::Flatten => {
+                let mut flattened = Vec::new();
+                let mut stack: Vec<Section> = sections.into_iter().rev().collect();
+                while let Some(mut s) = stack.pop() {
+                    let subsections = std::mem::take(&mut s.subsections);
+                    flattened.push(s);
+                    stack.extend(subsections.into_iter().rev());
+                }
+                flattened
+            }
+        }
+    }
+}
+
+impl Book {
+    /// Group the chapters of this book by their parts.
+    ///
+    /// Every element is the name of a part, or `None` for chapters outside any part, paired with
+    /// the consecutive chapters that belong to it, in order.
+    pub fn parts(&self) -> Vec<(Option<&str>, &[Chapter])> {
+        let mut parts = Vec::new();
+        let mut start = 0;
+        for i in 1..=self.chapters.len() {
+            if i == self.chapters.len() || self.chapters[i].part != self.chapters[start].part {
+                parts.push((
+                    self.chapters[start].part.as_deref(),
+                    &self.chapters[start..i],
+                ));
+                start = i;
+            }
+        }
+        parts
+    }
+}
+
+/// Specifies how `Book::into_section_list` treats subsections.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SectionListMode {
+    /// Only the top-level sections are returned and each of them retains its subsections.
+    Nested,
+
+    /// All sections and subsections are returned in depth-first pre-order, and the `subsections`
+    /// field of every returned section is empty.
+    Flatten,
 }
 
 /// Book configuration.
 ///
 /// The configuration is separated into global configuration and local configuration. Entries in the
-/// local configuration overwrite the corresponding entries in the local configuration.
-#[derive(Clone, Debug, Default)]
+/// local configuration overwrite the corresponding entries in the global configuration, see
+/// `BookConfig::merge_over`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BookConfig {
     /// Path to the root directory of the book.
+    #[cfg_attr(feature = "serde", serde(with = "serde_path"))]
     pub root: PathBuf,
 
     /// Structural configuration of the book.
@@ -107,6 +230,205 @@ pub struct BookConfig {
 
     /// Text direction of the book.
     pub direction: Option<TextDirection>,
+
+    /// Template of the URL at which the source of a section can be edited.
+    ///
+    /// The `{path}` placeholder in the template is replaced with the path to the section's source
+    /// file, relative to the root directory of the book. For example,
+    /// `"https://github.com/org/repo/edit/main/{path}"`. The HTML renderer links every page to
+    /// the URL, see `BookConfig::edit_url`.
+    pub edit_url_template: Option<String>,
+
+    /// Whether the name of a section is taken from the first level 1 heading of its content, which
+    /// is then removed from the content. Names given in `SUMMARY.md` take precedence unless
+    /// `force_content_title` is set.
+    ///
+    /// Use `effective_title_from_content` to get the value with the default applied.
+    pub title_from_content: Option<bool>,
+
+    /// Whether the first level 1 heading of the content of a section takes precedence over the
+    /// name given in `SUMMARY.md` when `title_from_content` is set.
+    ///
+    /// Use `effective_force_content_title` to get the value with the default applied.
+    pub force_content_title: Option<bool>,
+
+    /// Format of the frontmatter of section content.
+    ///
+    /// Use `effective_frontmatter_format` to get the value with the default applied.
+    pub frontmatter_format: Option<FrontmatterFormat>,
+
+    /// Build targets of the book, from the `outputs` table of the configuration file, in the order
+    /// of their names.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub outputs: Vec<OutputConfig>,
+
+    /// Entries of the configuration file that OpenBook does not recognize.
+    ///
+    /// Unrecognized top-level entries are keyed by their key, e.g. `output`, and unrecognized
+    /// entries of the `structure` table by `structure.` followed by their key. String values are
+    /// kept as-is, and other values, including tables, are kept in their TOML representation.
+    /// Renderers and plugins read their own entries, like `[output.html]`, with
+    /// `BookConfig::get_extra` or `BookConfig::get_deserialized`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extra: BTreeMap<String, String>,
+}
+
+impl BookConfig {
+    /// Merge the specified local configuration into the specified global configuration.
+    ///
+    /// This is the same as `local.merge_over(global)`.
+    pub fn merge(global: &BookConfig, local: &BookConfig) -> BookConfig {
+        local.merge_over(global)
+    }
+
+    /// Layer this configuration, which is the local configuration of a book, over the specified
+    /// global configuration.
+    ///
+    /// The entries of the returned configuration are determined as follows:
+    ///
+    /// * `root`: The root directory of this configuration, or that of `global` if it is empty;
+    /// * Every optional entry, including each path in `structure`: The entry of this
+    ///   configuration if it is set, and the entry of `global` otherwise;
+    /// * `outputs`: The targets of both configurations. A target of this configuration with the
+    ///   same name as a target of `global` is merged into it: its `format` and `directory` are
+    ///   taken if they are set, and its `options` replace those of `global` with the same key;
+    /// * `extra`: The entries of both configurations, where entries of this configuration replace
+    ///   the entries of `global` with the same key, unless both are tables, which are merged
+    ///   recursively in the same way.
+    ///
+    /// Paths in `structure` are always relative to the root directory of the book they belong to,
+    /// whichever configuration they come from, so that a `summary` given in the global
+    /// configuration refers to the summary file under the root directory of each book, like in
+    /// GitBook. Use `resolve_structure_path` to join them with the root directory.
+    pub fn merge_over(&self, global: &BookConfig) -> BookConfig {
+        fn pick<T: Clone>(global: &Option<T>, local: &Option<T>) -> Option<T> {
+            local.as_ref().or(global.as_ref()).cloned()
+        }
+
+        let local = self;
+        BookConfig {
+            root: if local.root.as_os_str().is_empty() {
+                global.root.clone()
+            } else {
+                local.root.clone()
+            },
+            structure: BookStructureConfig {
+                readme: pick(&global.structure.readme, &local.structure.readme),
+                summary: pick(&global.structure.summary, &local.structure.summary),
+                glossary: pick(&global.structure.glossary, &local.structure.glossary),
+                languages: pick(&global.structure.languages, &local.structure.languages),
+            },
+            title: pick(&global.title, &local.title),
+            description: pick(&global.description, &local.description),
+            author: pick(&global.author, &local.author),
+            isbn: pick(&global.isbn, &local.isbn),
+            language: pick(&global.language, &local.language),
+            direction: pick(&global.direction, &local.direction),
+            edit_url_template: pick(&global.edit_url_template, &local.edit_url_template),
+            title_from_content: pick(&global.title_from_content, &local.title_from_content),
+            force_content_title: pick(&global.force_content_title, &local.force_content_title),
+            frontmatter_format: pick(&global.frontmatter_format, &local.frontmatter_format),
+            outputs: {
+                let mut outputs = global.outputs.clone();
+                for output in &local.outputs {
+                    match outputs.iter_mut().find(|o| o.name == output.name) {
+                        Some(merged) => {
+                            merged.format = pick(&merged.format, &output.format);
+                            merged.directory = pick(&merged.directory, &output.directory);
+                            merged.options.extend(output.options.clone());
+                        }
+                        None => outputs.push(output.clone()),
+                    }
+                }
+                outputs.sort_by(|a, b| a.name.cmp(&b.name));
+                outputs
+            },
+            extra: {
+                let mut extra = global.extra.clone();
+                for (key, value) in &local.extra {
+                    let merged = match extra.get(key) {
+                        Some(global) => merge_extra(global, value),
+                        None => value.clone(),
+                    };
+                    extra.insert(key.clone(), merged);
+                }
+                extra
+            },
+        }
+    }
+
+    /// Get whether section names are taken from the content of the sections, which defaults to
+    /// `false`.
+    pub fn effective_title_from_content(&self) -> bool {
+        self.title_from_content.unwrap_or(false)
+    }
+
+    /// Get whether section names taken from the content of the sections take precedence over the
+    /// names given in `SUMMARY.md`, which defaults to `false`.
+    pub fn effective_force_content_title(&self) -> bool {
+        self.force_content_title.unwrap_or(false)
+    }
+
+    /// Get the format of the frontmatter of section content, which defaults to
+    /// `FrontmatterFormat::Auto`.
+    pub fn effective_frontmatter_format(&self) -> FrontmatterFormat {
+        self.frontmatter_format.unwrap_or_default()
+    }
+
+    /// Resolve the specified path in `structure` against the root directory of the book.
+    ///
+    /// `path` is one of the paths in `structure`, and `default` is used in its place if it is not
+    /// set. Relative paths are joined with `root`, while absolute paths are returned as-is.
+    pub fn resolve_structure_path(&self, path: &Option<PathBuf>, default: &str) -> PathBuf {
+        match path {
+            Some(path) => self.root.join(path),
+            None => self.root.join(default),
+        }
+    }
+
+    /// Get the text direction of the book.
+    ///
+    /// The explicit `direction` is returned if it is set. Otherwise, the direction is inferred from
+    /// the ISO 639-1 code at the beginning of `language`: Arabic, Dhivehi, Hebrew, Pashto, Persian,
+    /// Sindhi, Uyghur, Urdu and Yiddish are written from right to left, and everything else,
+    /// including books without a language, from left to right.
+    pub fn effective_direction(&self) -> TextDirection {
+        if let Some(direction) = self.direction {
+            return direction;
+        }
+
+        let code = match &self.language {
+            Some(language) => language
+                .split(&['-', '_'][..])
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase(),
+            None => return TextDirection::Ltr,
+        };
+        match code.as_str() {
+            "ar" | "dv" | "fa" | "he" | "ps" | "sd" | "ug" | "ur" | "yi" => TextDirection::Rtl,
+            _ => TextDirection::Ltr,
+        }
+    }
+
+    /// Compose the URL at which the specified section source file can be edited.
+    ///
+    /// If `file` is under the root directory of the book, the root directory is stripped from it
+    /// before expanding the template. Path separators are always expanded as forward slashes.
+    /// Returns `None` if `edit_url_template` is not set.
+    pub fn edit_url<P: AsRef<Path>>(&self, file: P) -> Option<String> {
+        let template = self.edit_url_template.as_ref()?;
+
+        let file = file.as_ref();
+        let relative = file.strip_prefix(&self.root).unwrap_or(file);
+        let path = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        Some(template.replace("{path}", &path))
+    }
 }
 
 /// Book structural configurations.
@@ -117,42 +439,197 @@ pub struct BookConfig {
 /// * `SUMMARY.md`, which can be used for providing an alternate table of contents to the book;
 /// * `GLOSSARY.md`, which provides a list of terms to annotate;
 /// * `LANGS.md`, which provides a description of the mapping from language names to books that are
-/// written in that language.
-#[derive(Clone, Debug, Default)]
+///   written in that language.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BookStructureConfig {
     /// Path to the `README.md` file.
+    #[cfg_attr(feature = "serde", serde(with = "serde_path::option"))]
     pub readme: Option<PathBuf>,
 
     /// Path to the `SUMMARY.md` file.
+    #[cfg_attr(feature = "serde", serde(with = "serde_path::option"))]
     pub summary: Option<PathBuf>,
 
     /// Path to the `GLOSSARY.md` file.
+    #[cfg_attr(feature = "serde", serde(with = "serde_path::option"))]
     pub glossary: Option<PathBuf>,
 
     /// Path to the `LANGS.md` file.
+    #[cfg_attr(feature = "serde", serde(with = "serde_path::option"))]
     pub languages: Option<PathBuf>,
 }
 
+/// Configuration of a build target of a book, from an `[outputs.<name>]` table of the
+/// configuration file.
+///
+/// For how the targets are built, please refer to `render::build_outputs`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OutputConfig {
+    /// Name of the target, which is its key in the `outputs` table.
+    pub name: String,
+
+    /// Format of the output, like `html`, `epub` or `pdf`.
+    ///
+    /// Use `effective_format` to get the format with the default applied.
+    pub format: Option<String>,
+
+    /// Path to the directory that the target is built into, relative to the root directory of the
+    /// project.
+    ///
+    /// Use `effective_directory` to get the directory with the default applied.
+    #[cfg_attr(feature = "serde", serde(with = "serde_path::option"))]
+    pub directory: Option<PathBuf>,
+
+    /// Other entries of the target, which are options for the renderer of its format.
+    ///
+    /// String values are kept as-is, and other values are kept in their TOML representation, like
+    /// in `BookConfig::extra`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub options: BTreeMap<String, String>,
+}
+
+impl OutputConfig {
+    /// Create a new `OutputConfig` instance for the target with the specified name, whose entries
+    /// are not set.
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Get the format of the output, which defaults to the name of the target.
+    pub fn effective_format(&self) -> &str {
+        self.format.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Get the directory that the target is built into, which defaults to the directory named
+    /// after the target under [`DEFAULT_OUTPUT_DIR`].
+    ///
+    /// [`DEFAULT_OUTPUT_DIR`]: ../config/constant.DEFAULT_OUTPUT_DIR.html
+    pub fn effective_directory(&self) -> PathBuf {
+        match &self.directory {
+            Some(directory) => directory.clone(),
+            None => Path::new(DEFAULT_OUTPUT_DIR).join(&self.name),
+        }
+    }
+}
+
 /// Text direction of a book.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum TextDirection {
     /// Left to right.
+    #[default]
     Ltr,
 
     /// Right to left.
     Rtl,
 }
 
-impl Default for TextDirection {
-    fn default() -> Self {
-        Self::Ltr
+/// A chapter within a book.
+///
+/// A chapter groups a sequence of sections under a name. It does not have content on its own.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Chapter {
+    /// Name of the chapter.
+    pub name: String,
+
+    /// All sections contained in this chapter.
+    pub sections: Vec<Section>,
+
+    /// Name of the part that this chapter belongs to, if any.
+    ///
+    /// Consecutive chapters with the same part form the part, see `Book::parts`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub part: Option<String>,
+
+    /// Whether this chapter is separated from the previous one, e.g. by a `---` line in
+    /// `SUMMARY.md`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub separated: bool,
+
+    /// Whether the sections of this chapter are not numbered, like the prefix and suffix chapters
+    /// of mdBook.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unnumbered: bool,
+}
+
+impl Chapter {
+    /// Merge the sections at the specified indices into a single new section with the
+    /// specified name.
+    ///
+    /// The content of the merged sections is concatenated in the order given by `indices`, each
+    /// preceded by a heading that carries the original section's name. The subsections of the
+    /// merged sections become subsections of the new section, in the same order. The new section
+    /// takes the content file of the first merged section and is placed at the position of the
+    /// first merged section in the chapter; the original sections are removed.
+    ///
+    /// Returns an error if `indices` is empty, contains an index that is out of range, or contains
+    /// the same index more than once.
+    pub fn merge_sections(&mut self, indices: &[usize], new_name: String) -> Result<()> {
+        if indices.is_empty() {
+            return Err(Error::from_message("no sections to merge"));
+        }
+        for (i, &index) in indices.iter().enumerate() {
+            if index >= self.sections.len() {
+                return Err(Error::from_message(format!(
+                    "section index {} is out of range, the chapter has {} sections",
+                    index,
+                    self.sections.len()
+                )));
+            }
+            if indices[..i].contains(&index) {
+                return Err(Error::from_message(format!(
+                    "section index {} is given more than once",
+                    index
+                )));
+            }
+        }
+
+        let mut merged = Section {
+            file: self.sections[indices[0]].file.clone(),
+            anchor: None,
+            name: new_name,
+            content: String::new(),
+            metadata: BTreeMap::new(),
+            number: self.sections[indices[0]].number.clone(),
+            subsections: Vec::new(),
+        };
+        let mut bodies = Vec::with_capacity(indices.len());
+        for &index in indices {
+            let s = &mut self.sections[index];
+            bodies.push(format!("# {}\n\n{}", s.name, s.content));
+            merged.subsections.append(&mut s.subsections);
+        }
+        merged.content = bodies.join("\n\n");
+
+        let position = *indices.iter().min().unwrap();
+        let mut index = 0;
+        self.sections.retain(|_| {
+            let keep = !indices.contains(&index);
+            index += 1;
+            keep
+        });
+        self.sections.insert(position, merged);
+
+        Ok(())
     }
 }
 
 /// A section within a chapter.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Section {
     /// Path to the file that contains the content of this section.
+    ///
+    /// The path is empty if the section does not have a content file, e.g. a draft section listed
+    /// as `- [Coming soon]()` in `SUMMARY.md`, or the preface of a book without `README.md`.
+    #[cfg_attr(feature = "serde", serde(with = "serde_path"))]
     pub file: PathBuf,
 
     /// Anchor of the start point of this section in the content file.
@@ -164,6 +641,351 @@ pub struct Section {
     /// Content in the section.
     pub content: String,
 
+    /// Metadata of the section, taken from the frontmatter of its content.
+    ///
+    /// For the format of the frontmatter, please refer to the `frontmatter` module.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub metadata: BTreeMap<String, String>,
+
+    /// Number of the section, or `None` if the section is not numbered.
+    ///
+    /// For how sections are numbered, please refer to the `numbering` module.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub number: Option<SectionNumber>,
+
     /// All subsections of this section.
     pub subsections: Vec<Section>,
 }
+
+impl Section {
+    /// Determine whether this section has a content file.
+    pub fn has_file(&self) -> bool {
+        !self.file.as_os_str().is_empty()
+    }
+}
+
+/// The glossary of a book, which maps terms to their definitions.
+///
+/// The glossary is usually parsed from the `GLOSSARY.md` file of a book.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Glossary {
+    /// Definitions of the terms, keyed by the terms.
+    pub entries: BTreeMap<String, String>,
+
+    /// Locations of the terms and their definitions in the glossary file, keyed by the terms.
+    ///
+    /// Terms that are not parsed from a file do not have a span.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub spans: BTreeMap<String, GlossarySpan>,
+}
+
+/// The lines of a glossary file that hold a term and its definition.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GlossarySpan {
+    /// Number of the line that holds the term, starting from 1.
+    pub start_line: usize,
+
+    /// Number of the last line of the definition, starting from 1.
+    pub end_line: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::builder::{
+        BookBuilder, BookConfigBuilder, ChapterBuilder, GlobalizedBooksBuilder, SectionBuilder,
+    };
+
+    #[test]
+    fn effective_configs_merge_global_and_local_entries() {
+        let global = BookConfigBuilder::new()
+            .set_root("project")
+            .set_title("Global title")
+            .set_author("Global author")
+            .set_summary("TOC.md")
+            .build();
+        let local = BookConfigBuilder::new()
+            .set_root("project/zh")
+            .set_title("Local title")
+            .set_language("zh")
+            .build();
+        let books = GlobalizedBooksBuilder::new()
+            .set_config(global)
+            .add_localized_book(
+                "Chinese".to_owned(),
+                BookBuilder::new().set_config(local).build(),
+            )
+            .add_default_book(BookBuilder::new().build())
+            .build();
+
+        let configs = books.effective_configs();
+        assert_eq!(configs.len(), 2);
+
+        let (language, config) = &configs[0];
+        assert_eq!(language, "Chinese");
+        assert_eq!(config.root, PathBuf::from("project/zh"));
+        assert_eq!(config.title.as_deref(), Some("Local title"));
+        assert_eq!(config.author.as_deref(), Some("Global author"));
+        assert_eq!(config.language.as_deref(), Some("zh"));
+        assert_eq!(config.structure.summary, Some(PathBuf::from("TOC.md")));
+
+        let (language, config) = &configs[1];
+        assert_eq!(language, "");
+        assert_eq!(config.root, PathBuf::from("project"));
+        assert_eq!(config.title.as_deref(), Some("Global title"));
+    }
+
+    #[test]
+    fn effective_configs_are_idempotent() {
+        let global = BookConfigBuilder::new()
+            .set_title("Global")
+            .set_extra("output", "[html]\ntheme = \"dark\"\n")
+            .build();
+        let local = BookConfigBuilder::new()
+            .set_root("en")
+            .set_extra("output", "[html]\nfold = true\n")
+            .build()
+            .merge_over(&global);
+        let books = GlobalizedBooksBuilder::new()
+            .set_config(global)
+            .add_localized_book(
+                "English".to_owned(),
+                BookBuilder::new().set_config(local.clone()).build(),
+            )
+            .build();
+
+        assert_eq!(
+            books.effective_configs(),
+            vec![("English".to_owned(), local)]
+        );
+    }
+
+    #[test]
+    fn edit_url_expands_the_path_relative_to_the_root() {
+        let config = BookConfigBuilder::new()
+            .set_root("project")
+            .set_edit_url_template("https://example.com/edit/main/{path}")
+            .build();
+        assert_eq!(
+            config.edit_url("project/guide/usage.md").as_deref(),
+            Some("https://example.com/edit/main/guide/usage.md")
+        );
+        assert_eq!(
+            config.edit_url("other/a.md").as_deref(),
+            Some("https://example.com/edit/main/other/a.md")
+        );
+        assert_eq!(BookConfig::default().edit_url("a.md"), None);
+    }
+
+    fn nested_book() -> Book {
+        let section = |name: &str| SectionBuilder::new().set_name(name).build();
+        BookBuilder::new()
+            .set_preface(section("Preface"))
+            .add_chapter(
+                ChapterBuilder::new()
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_name("A")
+                            .add_subsection(
+                                SectionBuilder::new()
+                                    .set_name("A.1")
+                                    .add_subsection(section("A.1.1"))
+                                    .build(),
+                            )
+                            .build(),
+                    )
+                    .add_section(section("B"))
+                    .build(),
+            )
+            .add_chapter(ChapterBuilder::new().add_section(section("C")).build())
+            .build()
+    }
+
+    #[test]
+    fn section_lists_are_nested_or_flattened() {
+        let names = |sections: &[Section]| -> Vec<String> {
+            sections.iter().map(|s| s.name.clone()).collect()
+        };
+
+        let nested = nested_book().into_section_list(SectionListMode::Nested);
+        assert_eq!(names(&nested), vec!["Preface", "A", "B", "C"]);
+        assert_eq!(nested[1].subsections[0].subsections[0].name, "A.1.1");
+
+        let flattened = nested_book().into_section_list(SectionListMode::Flatten);
+        assert_eq!(
+            names(&flattened),
+            vec!["Preface", "A", "A.1", "A.1.1", "B", "C"]
+        );
+        assert!(flattened.iter().all(|s| s.subsections.is_empty()));
+    }
+
+    #[test]
+    fn merge_sections_combines_content_and_subsections() {
+        let section = |name: &str, file: &str, content: &str| {
+            SectionBuilder::new()
+                .set_name(name)
+                .set_file(file)
+                .set_content(content)
+                .add_subsection(
+                    SectionBuilder::new()
+                        .set_name(format!("{}.1", name))
+                        .build(),
+                )
+                .build()
+        };
+        let mut chapter = ChapterBuilder::new()
+            .add_section(section("A", "a.md", "a"))
+            .add_section(section("B", "b.md", "b"))
+            .add_section(section("C", "c.md", "c"))
+            .build();
+
+        chapter
+            .merge_sections(&[2, 1], "B and C".to_owned())
+            .unwrap();
+        let names: Vec<&str> = chapter.sections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["A", "B and C"]);
+
+        let merged = &chapter.sections[1];
+        assert_eq!(merged.file, PathBuf::from("c.md"));
+        assert_eq!(merged.content, "# C\n\nc\n\n# B\n\nb");
+        let subsections: Vec<&str> = merged.subsections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(subsections, vec!["C.1", "B.1"]);
+
+        assert!(chapter.merge_sections(&[], String::new()).is_err());
+        assert!(chapter.merge_sections(&[0, 2], String::new()).is_err());
+        assert!(chapter.merge_sections(&[0, 0], String::new()).is_err());
+        assert_eq!(chapter.sections.len(), 2);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn trees_round_trip_through_serde() {
+        let config = BookConfigBuilder::new()
+            .set_root("books")
+            .set_title("Guide")
+            .set_direction(TextDirection::Rtl)
+            .build();
+        let book = BookBuilder::new()
+            .set_preface(SectionBuilder::new().set_name("Preface").build())
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("Basics")
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_name("Intro")
+                            .set_file(Path::new("basics").join("intro.md"))
+                            .set_content("# Intro")
+                            .set_metadata("author", "Ana")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let books = GlobalizedBooksBuilder::new()
+            .set_config(config)
+            .add_localized_book("ar".to_owned(), book)
+            .build();
+
+        let serialized = serde_yaml::to_string(&books).unwrap();
+        assert!(serialized.contains("direction: rtl"), "{}", serialized);
+        assert!(
+            serialized.contains("file: basics/intro.md"),
+            "{}",
+            serialized
+        );
+        let deserialized: GlobalizedBooks = serde_yaml::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, books);
+    }
+
+    #[test]
+    fn local_configs_override_global_configs_entry_by_entry() {
+        let global = BookConfigBuilder::new()
+            .set_root("project")
+            .set_title("Global title")
+            .set_author("Global author")
+            .set_summary("TOC.md")
+            .set_glossary("TERMS.md")
+            .build();
+        let local = BookConfigBuilder::new()
+            .set_title("Local title")
+            .set_glossary("GLOSSARY.md")
+            .set_direction(TextDirection::Rtl)
+            .build();
+
+        let merged = BookConfig::merge(&global, &local);
+        assert_eq!(merged, local.merge_over(&global));
+        assert_eq!(merged.root, PathBuf::from("project"));
+        assert_eq!(merged.title.as_deref(), Some("Local title"));
+        assert_eq!(merged.author.as_deref(), Some("Global author"));
+        assert_eq!(merged.direction, Some(TextDirection::Rtl));
+        assert_eq!(merged.structure.summary, Some(PathBuf::from("TOC.md")));
+        assert_eq!(
+            merged.structure.glossary,
+            Some(PathBuf::from("GLOSSARY.md"))
+        );
+        assert_eq!(merged.structure.readme, None);
+
+        let local = BookConfigBuilder::new().set_root("project/zh").build();
+        assert_eq!(
+            BookConfig::merge(&global, &local).root,
+            PathBuf::from("project/zh")
+        );
+    }
+
+    #[test]
+    fn merged_structure_paths_stay_relative_to_the_local_root() {
+        let global = BookConfigBuilder::new()
+            .set_root("/project")
+            .set_summary("TOC.md")
+            .set_extra("theme", "light")
+            .set_extra("search", "true")
+            .build();
+        let local = BookConfigBuilder::new()
+            .set_root("/project/zh")
+            .set_extra("theme", "dark")
+            .build();
+
+        let merged = local.merge_over(&global);
+        assert_eq!(
+            merged.resolve_structure_path(&merged.structure.summary, "SUMMARY.md"),
+            PathBuf::from("/project/zh/TOC.md")
+        );
+        assert_eq!(
+            merged.resolve_structure_path(&merged.structure.glossary, "GLOSSARY.md"),
+            PathBuf::from("/project/zh/GLOSSARY.md")
+        );
+        assert_eq!(merged.extra["theme"], "dark");
+        assert_eq!(merged.extra["search"], "true");
+        assert_eq!(BookConfig::default().merge_over(&global), global);
+    }
+
+    #[test]
+    fn direction_is_inferred_from_the_language() {
+        let direction = |language: Option<&str>, direction: Option<TextDirection>| {
+            let mut builder = BookConfigBuilder::new();
+            if let Some(language) = language {
+                builder = builder.set_language(language);
+            }
+            if let Some(direction) = direction {
+                builder = builder.set_direction(direction);
+            }
+            builder.build().effective_direction()
+        };
+
+        assert_eq!(direction(Some("ar"), None), TextDirection::Rtl);
+        assert_eq!(direction(Some("he-IL"), None), TextDirection::Rtl);
+        assert_eq!(direction(Some("FA_ir"), None), TextDirection::Rtl);
+        assert_eq!(direction(Some("en"), None), TextDirection::Ltr);
+        assert_eq!(direction(None, None), TextDirection::Ltr);
+        assert_eq!(
+            direction(Some("ar"), Some(TextDirection::Ltr)),
+            TextDirection::Ltr
+        );
+        assert_eq!(
+            direction(Some("en"), Some(TextDirection::Rtl)),
+            TextDirection::Rtl
+        );
+    }
+}
@@ -0,0 +1,365 @@
+//! This module implements the numbering of sections.
+//!
+//! Sections are numbered by their position in the book, like `1.2.3` for the third subsection of
+//! the second subsection of the first section. The top-level sections of all numbered chapters are
+//! numbered consecutively from 1 across the whole book, regardless of the parts the chapters belong
+//! to, and subsections are numbered from 1 within their parent section. The preface and the
+//! sections of chapters whose `Chapter::unnumbered` is set, like the prefix and suffix chapters of
+//! mdBook, are not numbered.
+//!
+//! Numbers are assigned to `Section::number` by [`Book::number_sections`] when the tree is built
+//! by the `parse` module or by `BookBuilder`. A number that is already set, e.g. by
+//! `SectionBuilder::set_number`, is kept, and the following siblings of the section continue from
+//! it. Code that restructures the tree afterwards should reset the numbers it wants reassigned and
+//! call [`Book::number_sections`] again, or call [`Book::renumber_sections`].
+//!
+//! [`Book::number_sections`]: ../struct.Book.html#method.number_sections
+//! [`Book::renumber_sections`]: ../struct.Book.html#method.renumber_sections
+//!
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::tree::{Book, Section};
+
+/// The number of a section, like `1.2.3`.
+///
+/// Every component of the number is the position, starting from 1, of the section or one of its
+/// ancestors among its siblings, from the top-level ancestor down to the section itself. Numbers
+/// are ordered in the order of the sections they belong to.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SectionNumber(Vec<usize>);
+
+impl SectionNumber {
+    /// Create a new `SectionNumber` instance from the specified components.
+    pub fn new(components: Vec<usize>) -> Self {
+        Self(components)
+    }
+
+    /// Get the components of this number.
+    pub fn components(&self) -> &[usize] {
+        &self.0
+    }
+
+    /// Get the nesting depth of the numbered section, which is 1 for top-level sections.
+    pub fn depth(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Get the number of the parent section, or `None` if this is the number of a top-level
+    /// section.
+    pub fn parent(&self) -> Option<SectionNumber> {
+        match self.0.len() {
+            0 | 1 => None,
+            len => Some(Self(self.0[..len - 1].to_vec())),
+        }
+    }
+
+    /// Get the number of the child section at the specified position, starting from 1.
+    pub fn child(&self, position: usize) -> SectionNumber {
+        let mut components = self.0.clone();
+        components.push(position);
+        Self(components)
+    }
+}
+
+impl Display for SectionNumber {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, component) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(".")?;
+            }
+            write!(f, "{}", component)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SectionNumber {
+    type Err = Error;
+
+    /// Parse a section number like `1.2.3`. A trailing `.`, as in `1.2.`, is accepted.
+    ///
+    /// Returns an error of kind `ErrorKind::Parse` if a component is not a positive integer.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s.strip_suffix('.').unwrap_or(s);
+        s.split('.')
+            .map(|component| match component.parse::<usize>() {
+                Ok(n) if n > 0 => Ok(n),
+                _ => Err(Error::parse(format!("invalid section number \"{}\"", s))),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self)
+    }
+}
+
+impl Book {
+    /// Assign `Section::number` of every section in this book that is not numbered yet, as
+    /// described in the [`numbering`] module.
+    ///
+    /// Sections that already have a number keep it, and the positions of their following siblings
+    /// continue from the last component of that number. Subsections are numbered under the number
+    /// of their parent, and sections whose parent is not numbered are not numbered either. The
+    /// tree is traversed iteratively, so arbitrarily deep books are supported.
+    ///
+    /// [`numbering`]: numbering/index.html
+    pub fn number_sections(&mut self) {
+        let mut next = 1;
+        for chapter in &mut self.chapters {
+            if chapter.unnumbered {
+                assign_numbers(&mut chapter.sections, None, 1);
+            } else {
+                next = assign_numbers(&mut chapter.sections, Some(SectionNumber::default()), next);
+            }
+        }
+    }
+
+    /// Reset `Section::number` of every section in this book, including the preface, and number
+    /// the sections again by [`number_sections`].
+    ///
+    /// [`number_sections`]: #method.number_sections
+    pub fn renumber_sections(&mut self) {
+        let mut stack = vec![&mut self.preface];
+        stack.extend(self.chapters.iter_mut().flat_map(|c| &mut c.sections));
+        while let Some(section) = stack.pop() {
+            section.number = None;
+            stack.extend(&mut section.subsections);
+        }
+        self.number_sections();
+    }
+}
+
+/// Number the specified sibling sections and their subsections, and get the position of the
+/// sibling that would follow the last one.
+///
+/// `parent` is the number of the parent of the sections, or `None` if they are not numbered, and
+/// `first` is the position of the first of them among their siblings.
+fn assign_numbers(sections: &mut [Section], parent: Option<SectionNumber>, first: usize) -> usize {
+    // Every frame holds the remaining siblings at a level, the number of their parent and the
+    // position of the next of them. The number of the parent is borrowed from the parent, so
+    // every number is only built once.
+    let parent = parent.as_ref();
+    let mut stack = vec![(sections.iter_mut(), parent, first)];
+    let mut top_next = first;
+    while let Some((siblings, parent, next)) = stack.last_mut() {
+        let section = match siblings.next() {
+            Some(section) => section,
+            None => {
+                stack.pop();
+                continue;
+            }
+        };
+
+        let Section {
+            number,
+            subsections,
+            ..
+        } = section;
+        if number.is_none() {
+            *number = parent.map(|p| p.child(*next));
+        }
+        *next = match number.as_ref().and_then(|n| n.0.last()) {
+            Some(&position) => position + 1,
+            None => *next + 1,
+        };
+        if stack.len() == 1 {
+            top_next = stack[0].2;
+        }
+
+        stack.push((subsections.iter_mut(), number.as_ref(), 1));
+    }
+    top_next
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::builder::{BookBuilder, ChapterBuilder, SectionBuilder};
+
+    fn section(name: &str) -> SectionBuilder {
+        SectionBuilder::new().set_name(name)
+    }
+
+    fn numbers(book: &Book) -> Vec<(String, Option<String>)> {
+        let mut numbers = Vec::new();
+        let mut stack: Vec<&Section> = book
+            .chapters
+            .iter()
+            .flat_map(|c| &c.sections)
+            .rev()
+            .collect();
+        while let Some(s) = stack.pop() {
+            numbers.push((s.name.clone(), s.number.as_ref().map(ToString::to_string)));
+            stack.extend(s.subsections.iter().rev());
+        }
+        numbers
+    }
+
+    fn expected(entries: &[(&str, Option<&str>)]) -> Vec<(String, Option<String>)> {
+        entries
+            .iter()
+            .map(|(name, number)| ((*name).to_owned(), number.map(ToOwned::to_owned)))
+            .collect()
+    }
+
+    #[test]
+    fn display_and_parse() {
+        let number = SectionNumber::new(vec![1, 2, 3]);
+        assert_eq!(number.to_string(), "1.2.3");
+        assert_eq!("1.2.3".parse::<SectionNumber>().unwrap(), number);
+        assert_eq!("1.2.3.".parse::<SectionNumber>().unwrap(), number);
+        assert!("1.0".parse::<SectionNumber>().is_err());
+        assert!("a.b".parse::<SectionNumber>().is_err());
+        assert!("".parse::<SectionNumber>().is_err());
+    }
+
+    #[test]
+    fn parent_and_child() {
+        let number = SectionNumber::new(vec![2, 4]);
+        assert_eq!(number.depth(), 2);
+        assert_eq!(number.parent(), Some(SectionNumber::new(vec![2])));
+        assert_eq!(SectionNumber::new(vec![2]).parent(), None);
+        assert_eq!(number.child(1).components(), &[2, 4, 1]);
+        assert!(SectionNumber::new(vec![1, 9]) < SectionNumber::new(vec![2]));
+    }
+
+    #[test]
+    fn numbers_continue_across_chapters() {
+        let book = BookBuilder::new()
+            .set_preface(section("preface").build())
+            .add_chapter(
+                ChapterBuilder::new()
+                    .add_section(
+                        section("a")
+                            .add_subsection(section("a1").build())
+                            .add_subsection(section("a2").build())
+                            .build(),
+                    )
+                    .build(),
+            )
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_unnumbered(true)
+                    .add_section(section("u").add_subsection(section("u1").build()).build())
+                    .build(),
+            )
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_part("Part")
+                    .add_section(section("b").build())
+                    .build(),
+            )
+            .build();
+
+        assert_eq!(book.preface.number, None);
+        assert_eq!(
+            numbers(&book),
+            expected(&[
+                ("a", Some("1")),
+                ("a1", Some("1.1")),
+                ("a2", Some("1.2")),
+                ("u", None),
+                ("u1", None),
+                ("b", Some("2")),
+            ])
+        );
+    }
+
+    #[test]
+    fn explicit_numbers_are_kept() {
+        let book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .add_section(section("a").build())
+                    .add_section(
+                        section("b")
+                            .set_number(SectionNumber::new(vec![5]))
+                            .add_subsection(section("b1").build())
+                            .build(),
+                    )
+                    .add_section(section("c").build())
+                    .build(),
+            )
+            .build();
+
+        assert_eq!(
+            numbers(&book),
+            expected(&[
+                ("a", Some("1")),
+                ("b", Some("5")),
+                ("b1", Some("5.1")),
+                ("c", Some("6")),
+            ])
+        );
+    }
+
+    #[test]
+    fn renumber_resets_numbers() {
+        let mut book = BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .add_section(section("a").set_number(SectionNumber::new(vec![7])).build())
+                    .add_section(section("b").build())
+                    .build(),
+            )
+            .build();
+        book.chapters[0].sections.swap(0, 1);
+        book.renumber_sections();
+
+        assert_eq!(
+            numbers(&book),
+            expected(&[("b", Some("1")), ("a", Some("2"))])
+        );
+    }
+
+    #[test]
+    fn deep_trees_do_not_overflow_the_stack() {
+        // The stack is small enough for a recursive traversal to overflow it at this depth.
+        std::thread::Builder::new()
+            .stack_size(128 * 1024)
+            .spawn(number_deep_tree)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    fn number_deep_tree() {
+        const DEPTH: usize = 5_000;
+
+        let mut root = section("0").build();
+        {
+            let mut s = &mut root;
+            for i in 1..DEPTH {
+                s.subsections.push(section(&i.to_string()).build());
+                s = &mut s.subsections[0];
+            }
+        }
+        let mut book = BookBuilder::new()
+            .add_chapter(ChapterBuilder::new().add_section(root).build())
+            .build();
+
+        let mut depth = 0;
+        let mut s = &book.chapters[0].sections[0];
+        loop {
+            depth += 1;
+            assert_eq!(s.number.as_ref().map(SectionNumber::depth), Some(depth));
+            match s.subsections.first() {
+                Some(child) => s = child,
+                None => break,
+            }
+        }
+        assert_eq!(depth, DEPTH);
+
+        // Dismantle the tree iteratively, since the drop glue of `Section` is recursive.
+        let mut pending = std::mem::take(&mut book.chapters[0].sections);
+        while let Some(mut s) = pending.pop() {
+            pending.append(&mut s.subsections);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! This module provides an analysis that finds the orphan content files of a book.
+//!
+//! A content file is an orphan if it exists under the root directory of a book but cannot be
+//! reached from the tree of the book, usually because `SUMMARY.md` does not link to it. Orphan
+//! files are never rendered, so they often indicate a forgotten summary entry or a leftover file.
+//!
+
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::Result;
+use crate::fs::{normalize, FileSystem};
+use crate::parse::{DEFAULT_GLOSSARY_PATH, DEFAULT_LANGUAGES_PATH, DEFAULT_SUMMARY_PATH};
+use crate::tree::include::included_files;
+use crate::tree::markup::Markup;
+use crate::tree::visitor::{visit, Visitor};
+use crate::tree::{Book, Section};
+
+/// A content file that cannot be reached from the tree of a book.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OrphanFile {
+    /// Path to the file, relative to the root directory of the book.
+    pub file: PathBuf,
+
+    /// Markup of the file.
+    pub markup: Markup,
+}
+
+/// Find the orphan content files of the specified book.
+///
+/// The root directory of the book is walked through `FileSystem::walk`. Every file in a markup
+/// that `Markup::from_path` recognizes is a content file, except for the summary, glossary and
+/// languages files of the book and the files under entries whose names start with `.` or `_`,
+/// which `parse_directory` skips as well. A content file can be reached if it is the content file
+/// of a section of the book, including the preface, or if it is included into the content of such
+/// a section by an include directive. The orphans are returned in the order of their paths.
+///
+/// Returns an error if a directory under the root directory cannot be read.
+pub fn find_orphan_files<F: FileSystem>(fs: &F, book: &Book) -> Result<Vec<OrphanFile>> {
+    let config = &book.config;
+    let root = normalize(&config.root);
+    let structure = &config.structure;
+    let excluded = [
+        config.resolve_structure_path(&structure.summary, DEFAULT_SUMMARY_PATH),
+        config.resolve_structure_path(&structure.glossary, DEFAULT_GLOSSARY_PATH),
+        config.resolve_structure_path(&structure.languages, DEFAULT_LANGUAGES_PATH),
+    ];
+    let excluded: Vec<PathBuf> = excluded.iter().map(normalize).collect();
+
+    let mut collector = ReferenceCollector::default();
+    visit(book, &mut collector);
+
+    let mut orphans = Vec::new();
+    for path in fs.walk(&root) {
+        let path = normalize(path?);
+        let file = match path.strip_prefix(&root) {
+            Ok(file) => file.to_path_buf(),
+            Err(_) => continue,
+        };
+        let markup = match Markup::from_path(&file) {
+            Some(markup) => markup,
+            None => continue,
+        };
+        if is_skipped(&file) || excluded.contains(&path) || collector.files.contains(&file) {
+            continue;
+        }
+        orphans.push(OrphanFile { file, markup });
+    }
+
+    orphans.sort_by(|a, b| a.file.cmp(&b.file));
+    Ok(orphans)
+}
+
+/// Determine whether the specified path, relative to the root directory of a book, is under an
+/// entry whose name starts with `.` or `_`.
+fn is_skipped(file: &Path) -> bool {
+    file.components().any(|c| match c {
+        Component::Normal(name) => {
+            let name = name.to_string_lossy();
+            name.starts_with('.') || name.starts_with('_')
+        }
+        _ => false,
+    })
+}
+
+/// A visitor that collects the files referenced by the visited sections, relative to the root
+/// directory of the book.
+#[derive(Default)]
+struct ReferenceCollector {
+    files: HashSet<PathBuf>,
+}
+
+impl Visitor for ReferenceCollector {
+    fn visit_section(&mut self, section: &Section) {
+        if !section.has_file() {
+            return;
+        }
+        self.files.insert(normalize(&section.file));
+        self.files.extend(included_files(section));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::scratch::ScratchFileSystem;
+    use crate::parse::parse_summary;
+    use crate::tree::builder::BookConfigBuilder;
+
+    const ROOT: &str = "/openbook-test/orphans";
+
+    fn project(files: &[(&str, &str)]) -> ScratchFileSystem {
+        let fs = ScratchFileSystem::new();
+        for (file, content) in files {
+            fs.write_file(Path::new(ROOT).join(file), content.as_bytes())
+                .unwrap();
+        }
+        fs
+    }
+
+    fn orphans(fs: &ScratchFileSystem, book: &Book) -> Vec<(String, Markup)> {
+        find_orphan_files(fs, book)
+            .unwrap()
+            .into_iter()
+            .map(|orphan| (orphan.file.display().to_string(), orphan.markup))
+            .collect()
+    }
+
+    #[test]
+    fn finds_content_files_that_the_book_does_not_reach() {
+        let fs = project(&[
+            (
+                "SUMMARY.md",
+                "* [Intro](intro.md)\n  * [Setup](guide/setup.rst)\n",
+            ),
+            ("README.md", "# Welcome\n"),
+            ("GLOSSARY.md", "Term\n: Definition\n"),
+            ("LANGS.md", ""),
+            ("intro.md", "{{#include shared/snippet.md}}\n"),
+            ("shared/snippet.md", "Shared.\n"),
+            ("guide/setup.rst", "Setup\n=====\n"),
+            ("guide/old.rst", "Old\n===\n"),
+            ("notes.txt", "Notes.\n"),
+            ("extra.md", "Extra.\n"),
+            ("image.png", ""),
+            ("_drafts/next.md", "Next.\n"),
+            (".github/ISSUE.md", "Issue.\n"),
+        ]);
+        let config = BookConfigBuilder::new().set_root(ROOT).build();
+        let book = parse_summary(&fs, &config).unwrap();
+
+        assert_eq!(
+            orphans(&fs, &book),
+            vec![
+                ("extra.md".to_owned(), Markup::Markdown),
+                ("guide/old.rst".to_owned(), Markup::ReStructuredText),
+                ("notes.txt".to_owned(), Markup::PlainText),
+            ]
+        );
+    }
+
+    #[test]
+    fn configured_structure_files_are_not_orphans() {
+        let fs = project(&[
+            ("TOC.md", "* [Intro](intro.md)\n"),
+            ("intro.md", "Intro.\n"),
+            ("SUMMARY.md", "Not the summary of this book.\n"),
+        ]);
+        let config = BookConfigBuilder::new()
+            .set_root(ROOT)
+            .set_summary("TOC.md")
+            .build();
+        let book = parse_summary(&fs, &config).unwrap();
+
+        assert_eq!(
+            orphans(&fs, &book),
+            vec![("SUMMARY.md".to_owned(), Markup::Markdown)]
+        );
+    }
+}
@@ -0,0 +1,231 @@
+//! This module flattens the document tree into a list of pages in reading order.
+//!
+//! A page is a content file of a book. The [`FlattenVisitor`] collects the pages of every book in
+//! depth-first reading order, which renderers can use to generate "next page" and "previous page"
+//! navigation without recursing into the tree themselves.
+//!
+//! [`FlattenVisitor`]: struct.FlattenVisitor.html
+//!
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::fs::normalize;
+
+use crate::tree::visitor::{VisitContext, Visitor, VisitorHost};
+use crate::tree::{GlobalizedBooks, Section};
+
+/// Reference to a page in the flattened document tree.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct PageRef {
+    /// Language of the book that contains the page.
+    pub language: String,
+
+    /// Name of the chapter that contains the page, or `None` for the preface.
+    pub chapter: Option<String>,
+
+    /// Path to the content file of the page, relative to the root directory of the book.
+    pub file: PathBuf,
+
+    /// Name of the section that the page starts with.
+    pub name: String,
+}
+
+/// A visitor that collects the pages of the visited books in depth-first reading order.
+///
+/// Every leaf section with a content file becomes a page, including the preface when it has no
+/// subsections. Sections that have subsections are skipped, since their subsections are the pages.
+/// Sections that start at an anchor within a content file that is already a page of the same book
+/// are merged into that page.
+#[derive(Clone, Debug, Default)]
+pub struct FlattenVisitor {
+    pages: Vec<PageRef>,
+    seen: HashSet<(String, PathBuf)>,
+}
+
+impl FlattenVisitor {
+    /// Create a new `FlattenVisitor` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consume this visitor and get the collected pages.
+    pub fn into_pages(self) -> Vec<PageRef> {
+        self.pages
+    }
+}
+
+impl Visitor for FlattenVisitor {
+    fn visit_section_in(&mut self, section: &Section, context: &VisitContext) {
+        if section.file.as_os_str().is_empty() || !section.subsections.is_empty() {
+            return;
+        }
+
+        let language = context.language().unwrap_or_default().to_owned();
+        if !self.seen.insert((language.clone(), section.file.clone())) {
+            return;
+        }
+        self.pages.push(PageRef {
+            language,
+            chapter: context.chapter().map(str::to_owned),
+            file: section.file.clone(),
+            name: section.name.clone(),
+        });
+    }
+}
+
+impl GlobalizedBooks {
+    /// Get the pages of all books in depth-first reading order, book by book.
+    ///
+    /// For the pages collected, please refer to [`FlattenVisitor`].
+    ///
+    /// [`FlattenVisitor`]: pages/struct.FlattenVisitor.html
+    pub fn flatten(&self) -> Vec<PageRef> {
+        let mut visitor = FlattenVisitor::new();
+        self.visit(&mut visitor);
+        visitor.into_pages()
+    }
+
+    /// Get the page that follows the page with the specified content file in reading order.
+    ///
+    /// `file` is either relative to the root directory of a book or joined with it; relative paths
+    /// that are pages of several books are looked up in the first of those books. Returns `None`
+    /// if the file is not a page or if it is the last page of its book, since the next page never
+    /// belongs to a book in another language.
+    pub fn next_page(&self, file: &Path) -> Option<PageRef> {
+        let pages = self.flatten();
+        let index = self.find_page(&pages, file)?;
+        pages
+            .get(index + 1)
+            .filter(|p| p.language == pages[index].language)
+            .cloned()
+    }
+
+    /// Get the page that precedes the page with the specified content file in reading order.
+    ///
+    /// `file` is looked up as in `next_page`. Returns `None` if the file is not a page or if it is
+    /// the first page of its book.
+    pub fn prev_page(&self, file: &Path) -> Option<PageRef> {
+        let pages = self.flatten();
+        let index = self.find_page(&pages, file)?;
+        index
+            .checked_sub(1)
+            .map(|i| &pages[i])
+            .filter(|p| p.language == pages[index].language)
+            .cloned()
+    }
+
+    /// Find the index of the page with the specified content file in the specified pages.
+    ///
+    /// Paths are normalized before they are compared, so `./a/../b.md` finds the page `b.md`.
+    fn find_page(&self, pages: &[PageRef], file: &Path) -> Option<usize> {
+        let file = normalize(file);
+        pages.iter().position(|p| {
+            normalize(&p.file) == file
+                || self.books.iter().any(|(l, b)| {
+                    *l == p.language && normalize(b.config.root.join(&p.file)) == file
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::builder::{
+        BookBuilder, BookConfigBuilder, ChapterBuilder, GlobalizedBooksBuilder, SectionBuilder,
+    };
+
+    fn section(name: &str, file: &str) -> SectionBuilder {
+        SectionBuilder::new().set_name(name).set_file(file)
+    }
+
+    fn localized_book(root: &str) -> crate::tree::Book {
+        BookBuilder::new()
+            .set_config(BookConfigBuilder::new().set_root(root).build())
+            .set_preface(section("Preface", "README.md").build())
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("First")
+                    .add_section(
+                        section("Parent", "parent.md")
+                            .add_subsection(section("Child", "child.md").build())
+                            .add_subsection(section("Anchor", "child.md").set_anchor("a").build())
+                            .build(),
+                    )
+                    .add_section(section("Draft", "").build())
+                    .build(),
+            )
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("Second")
+                    .add_section(section("Last", "last.md").build())
+                    .build(),
+            )
+            .build()
+    }
+
+    fn books() -> GlobalizedBooks {
+        GlobalizedBooksBuilder::new()
+            .add_localized_book("en".to_owned(), localized_book("book/en"))
+            .add_localized_book("zh".to_owned(), localized_book("book/zh"))
+            .build()
+    }
+
+    #[test]
+    fn flatten_collects_leaf_pages_in_reading_order() {
+        let pages = books().flatten();
+        let summary: Vec<_> = pages
+            .iter()
+            .map(|p| (p.language.as_str(), p.chapter.as_deref(), p.name.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("en", None, "Preface"),
+                ("en", Some("First"), "Child"),
+                ("en", Some("Second"), "Last"),
+                ("zh", None, "Preface"),
+                ("zh", Some("First"), "Child"),
+                ("zh", Some("Second"), "Last"),
+            ]
+        );
+        assert_eq!(pages[1].file, PathBuf::from("child.md"));
+    }
+
+    #[test]
+    fn first_and_last_pages_have_no_neighbors() {
+        let books = books();
+        assert_eq!(books.prev_page(Path::new("README.md")), None);
+        assert_eq!(books.next_page(Path::new("book/en/last.md")), None);
+        assert_eq!(books.prev_page(Path::new("book/zh/README.md")), None);
+        assert_eq!(books.next_page(Path::new("book/zh/last.md")), None);
+    }
+
+    #[test]
+    fn neighbors_stay_within_a_language() {
+        let books = books();
+        let next = books.next_page(Path::new("book/zh/child.md")).unwrap();
+        assert_eq!((next.language.as_str(), next.name.as_str()), ("zh", "Last"));
+        let prev = books.prev_page(Path::new("child.md")).unwrap();
+        assert_eq!(
+            (prev.language.as_str(), prev.name.as_str()),
+            ("en", "Preface")
+        );
+        assert_eq!(books.next_page(Path::new("parent.md")), None);
+    }
+
+    #[test]
+    fn lookup_normalizes_paths() {
+        let books = books();
+        let next = books
+            .next_page(Path::new("./book/zh/../zh/README.md"))
+            .unwrap();
+        assert_eq!(
+            (next.language.as_str(), next.name.as_str()),
+            ("zh", "Child")
+        );
+        let prev = books.prev_page(Path::new("sub/../last.md")).unwrap();
+        assert_eq!(prev.name, "Child");
+    }
+}
@@ -0,0 +1,607 @@
+//! This module implements incremental patching of an in-memory OpenBook document tree in response
+//! to file system events.
+//!
+
+use std::path::{Path, PathBuf};
+
+use crate::config::ConfigFormat;
+use crate::error::Result;
+use crate::fs::{normalize, FileSystem, FileSystemEvent};
+use crate::parse::{DEFAULT_GLOSSARY_PATH, DEFAULT_LANGUAGES_PATH, DEFAULT_SUMMARY_PATH};
+use crate::tree::frontmatter::split_frontmatter;
+use crate::tree::markup::convert_content;
+use crate::tree::{Book, BookConfig, GlobalizedBooks, Section};
+
+/// Result of patching a [`Book`] with a file system event.
+///
+/// [`Book`]: ../struct.Book.html
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PatchResult {
+    /// The content of the sections with the given names is reloaded in place.
+    Patched(Vec<String>),
+
+    /// The event may change the structure of the book and the whole book should be reloaded.
+    NeedsReload,
+
+    /// The event does not affect the book.
+    Unaffected,
+}
+
+/// Outcome of applying a file system event to a [`GlobalizedBooks`].
+///
+/// [`GlobalizedBooks`]: ../struct.GlobalizedBooks.html
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum UpdateOutcome {
+    /// The content of the sections is reloaded in place. Every element is the language of a book
+    /// paired with the names of the updated sections in that book.
+    Updated(Vec<(String, Vec<String>)>),
+
+    /// The event may change the structure of the project and the whole project should be
+    /// reloaded.
+    NeedsFullReload,
+
+    /// The event does not affect any book.
+    Unchanged,
+}
+
+/// Classification of a `Rename` event relative to the root directory of a book.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RenameClass {
+    /// Both the old and the new path are under the root directory.
+    WithinProject,
+
+    /// The old path is under the root directory while the new path is not.
+    MovedOut,
+
+    /// The new path is under the root directory while the old path is not.
+    MovedIn,
+
+    /// Neither path is under the root directory.
+    Unrelated,
+}
+
+/// Classify a rename from `from` to `to` by testing both paths against the root directory of the
+/// book with the specified configuration.
+///
+/// Paths are compared after lexically removing `.` components and resolving `..` components. An
+/// empty root directory contains every relative path.
+pub fn classify_rename(config: &BookConfig, from: &Path, to: &Path) -> RenameClass {
+    let root = normalize(&config.root);
+    match (
+        normalize(from).starts_with(&root),
+        normalize(to).starts_with(&root),
+    ) {
+        (true, true) => RenameClass::WithinProject,
+        (true, false) => RenameClass::MovedOut,
+        (false, true) => RenameClass::MovedIn,
+        (false, false) => RenameClass::Unrelated,
+    }
+}
+
+impl GlobalizedBooks {
+    /// Apply the specified file system event to the books in place.
+    ///
+    /// A `Write` to the content file of known sections reloads the content of just those sections,
+    /// as `Book::patch` does. `Create`, `Delete` and `Rename` events of paths under the root
+    /// directory of any book may change the structure of that book, and `Rescan` and `Error`
+    /// events may stand for missed changes, so [`UpdateOutcome::NeedsFullReload`] is returned for
+    /// them and the books are left untouched. The same applies to any event that touches a
+    /// structure file of the project or of one of its books, i.e. a summary, glossary or languages
+    /// file or a configuration file in any of the formats of [`ConfigFormat::ALL`].
+    ///
+    /// [`UpdateOutcome::NeedsFullReload`]: enum.UpdateOutcome.html#variant.NeedsFullReload
+    /// [`ConfigFormat::ALL`]: ../../config/enum.ConfigFormat.html#associatedconstant.ALL
+    pub fn apply_event<F: FileSystem>(
+        &mut self,
+        fs: &F,
+        event: &FileSystemEvent,
+    ) -> Result<UpdateOutcome> {
+        let configs =
+            std::iter::once(&self.config).chain(self.books.iter().map(|(_, b)| &b.config));
+        if configs
+            .flat_map(structure_files)
+            .any(|file| event_touches(event, &file))
+        {
+            return Ok(UpdateOutcome::NeedsFullReload);
+        }
+
+        let affected = match event {
+            FileSystemEvent::Write(_) => None,
+            FileSystemEvent::Create(path) | FileSystemEvent::Delete(path) => {
+                let path = normalize(path);
+                Some(
+                    self.books
+                        .iter()
+                        .any(|(_, book)| path.starts_with(normalize(&book.config.root))),
+                )
+            }
+            FileSystemEvent::Rename { from, to } => Some(self.books.iter().any(|(_, book)| {
+                classify_rename(&book.config, from, to) != RenameClass::Unrelated
+            })),
+            FileSystemEvent::Rescan | FileSystemEvent::Error(..) => {
+                return Ok(UpdateOutcome::NeedsFullReload)
+            }
+        };
+        if let Some(affected) = affected {
+            return Ok(if affected {
+                UpdateOutcome::NeedsFullReload
+            } else {
+                UpdateOutcome::Unchanged
+            });
+        }
+
+        let mut updated = Vec::new();
+        for (language, book) in &mut self.books {
+            match book.patch(fs, event)? {
+                PatchResult::Patched(names) => updated.push((language.clone(), names)),
+                PatchResult::NeedsReload => return Ok(UpdateOutcome::NeedsFullReload),
+                PatchResult::Unaffected => {}
+            }
+        }
+
+        if updated.is_empty() {
+            Ok(UpdateOutcome::Unchanged)
+        } else {
+            Ok(UpdateOutcome::Updated(updated))
+        }
+    }
+}
+
+impl Book {
+    /// Patch this book in place according to the specified file system event.
+    ///
+    /// A `Write` to the content file of known sections reloads the content and the frontmatter
+    /// metadata of just those sections.
+    /// `Create`, `Delete` and `Rename` events may change the structure of the book, so
+    /// [`PatchResult::NeedsReload`] is returned for them and the book is left untouched. The same
+    /// applies to sections that start at an anchor within their content file, since only a full
+    /// reload knows how to split the file between sections. A `Write` to a structure file of the
+    /// book, i.e. its summary, glossary or languages file or a configuration file in any of the
+    /// formats of [`ConfigFormat::ALL`], also needs a reload.
+    ///
+    /// Paths are compared after lexically removing `.` components and resolving `..` components.
+    ///
+    /// [`PatchResult::NeedsReload`]: enum.PatchResult.html#variant.NeedsReload
+    /// [`ConfigFormat::ALL`]: ../../config/enum.ConfigFormat.html#associatedconstant.ALL
+    pub fn patch<F: FileSystem>(&mut self, fs: &F, event: &FileSystemEvent) -> Result<PatchResult> {
+        let path = match event {
+            FileSystemEvent::Write(path) => path,
+            _ => return Ok(PatchResult::NeedsReload),
+        };
+        let path = &normalize(path);
+
+        if structure_files(&self.config).contains(path) {
+            return Ok(PatchResult::NeedsReload);
+        }
+
+        let root = &self.config.root;
+
+        let mut affected = Vec::new();
+        for s in self.all_sections() {
+            if is_section_file(root, s, path) {
+                affected.push(s);
+            }
+        }
+
+        if affected.is_empty() {
+            return Ok(PatchResult::Unaffected);
+        }
+        if affected.iter().any(|s| s.anchor.is_some()) {
+            return Ok(PatchResult::NeedsReload);
+        }
+
+        let raw = fs.read_file_as_string(path)?;
+        let (mut metadata, body) =
+            split_frontmatter(path, &raw, self.config.effective_frontmatter_format())?;
+        let body = body.unwrap_or(raw);
+        let content = convert_content(path, &body, &mut metadata).unwrap_or(body);
+        let root = self.config.root.clone();
+        let mut names = Vec::new();
+        let mut update = |s: &mut Section| {
+            if is_section_file(&root, s, path) {
+                s.content = content.clone();
+                s.metadata = metadata.clone();
+                names.push(s.name.clone());
+            }
+        };
+        update_sections(&mut self.preface, &mut update);
+        for s in self.chapters.iter_mut().flat_map(|c| &mut c.sections) {
+            update_sections(s, &mut update);
+        }
+
+        Ok(PatchResult::Patched(names))
+    }
+
+    /// Get all sections in this book, including the preface, in depth-first pre-order.
+    fn all_sections(&self) -> Vec<&Section> {
+        let mut sections = Vec::new();
+        let mut stack = vec![&self.preface];
+        stack.extend(self.chapters.iter().flat_map(|c| &c.sections).rev());
+        while let Some(s) = stack.pop() {
+            sections.push(s);
+            stack.extend(s.subsections.iter().rev());
+        }
+        sections
+    }
+}
+
+/// Call the specified function on the given section and all of its subsections, in depth-first
+/// pre-order.
+fn update_sections<F: FnMut(&mut Section)>(section: &mut Section, f: &mut F) {
+    // An explicit stack keeps deeply nested sections from overflowing the call stack.
+    let mut stack = vec![section];
+    while let Some(s) = stack.pop() {
+        f(s);
+        stack.extend(s.subsections.iter_mut().rev());
+    }
+}
+
+/// Get the normalized paths of the files that determine the structure of the book with the
+/// specified configuration, i.e. its summary, glossary and languages files and its configuration
+/// files in all formats.
+fn structure_files(config: &BookConfig) -> Vec<PathBuf> {
+    let structure = &config.structure;
+    let mut files = vec![
+        config.resolve_structure_path(&structure.summary, DEFAULT_SUMMARY_PATH),
+        config.resolve_structure_path(&structure.glossary, DEFAULT_GLOSSARY_PATH),
+        config.resolve_structure_path(&structure.languages, DEFAULT_LANGUAGES_PATH),
+    ];
+    files.extend(
+        ConfigFormat::ALL
+            .iter()
+            .map(|format| config.root.join(format.file_name())),
+    );
+    files.iter().map(normalize).collect()
+}
+
+/// Determine whether the specified event touches the file at the specified normalized path.
+fn event_touches(event: &FileSystemEvent, file: &Path) -> bool {
+    match event {
+        FileSystemEvent::Create(path)
+        | FileSystemEvent::Delete(path)
+        | FileSystemEvent::Write(path) => normalize(path) == file,
+        FileSystemEvent::Rename { from, to } => normalize(from) == file || normalize(to) == file,
+        FileSystemEvent::Rescan | FileSystemEvent::Error(..) => false,
+    }
+}
+
+/// Determine whether the given section takes its content from the file at the specified path.
+///
+/// Paths are compared after lexically removing `.` components and resolving `..` components.
+fn is_section_file(root: &Path, section: &Section, path: &Path) -> bool {
+    if !section.has_file() {
+        return false;
+    }
+    let path = normalize(path);
+    normalize(&section.file) == path || normalize(root.join(&section.file)) == path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::scratch::ScratchFileSystem;
+    use crate::tree::builder::GlobalizedBooksBuilder;
+    use crate::tree::builder::{BookBuilder, BookConfigBuilder, ChapterBuilder, SectionBuilder};
+    use crate::tree::visitor::tests::{chain_with, drop_book_iteratively, with_small_stack};
+
+    const ROOT: &str = "/openbook-test/patch";
+
+    fn book() -> Book {
+        let config = BookConfigBuilder::new().set_root(ROOT).build();
+        let chapter = ChapterBuilder::new()
+            .add_section(
+                SectionBuilder::new()
+                    .set_name("a")
+                    .set_file("a.md")
+                    .set_content("old")
+                    .build(),
+            )
+            .build();
+        BookBuilder::new()
+            .set_config(config)
+            .add_chapter(chapter)
+            .build()
+    }
+
+    fn write(path: &str) -> FileSystemEvent {
+        FileSystemEvent::Write(PathBuf::from(path))
+    }
+
+    #[test]
+    fn patch_reloads_section_content() {
+        let fs = ScratchFileSystem::new();
+        fs.write_file(Path::new(ROOT).join("a.md"), b"new").unwrap();
+
+        let mut book = book();
+        let result = book
+            .patch(&fs, &write("/openbook-test/patch/a.md"))
+            .unwrap();
+        assert_eq!(result, PatchResult::Patched(vec!["a".to_owned()]));
+        assert_eq!(book.chapters[0].sections[0].content, "new");
+    }
+
+    #[test]
+    fn patch_reloads_deeply_nested_sections() {
+        with_small_stack(patch_deep_chain);
+    }
+
+    fn patch_deep_chain() {
+        const DEPTH: usize = 10_000;
+        let fs = ScratchFileSystem::new();
+        fs.write_file(Path::new(ROOT).join("deep.md"), b"new")
+            .unwrap();
+
+        let chain = chain_with(DEPTH, |d| {
+            let section = SectionBuilder::new().set_name(d.to_string());
+            match d {
+                DEPTH => section.set_file("deep.md").set_content("old").build(),
+                _ => section.build(),
+            }
+        });
+        let mut book = BookBuilder::new()
+            .set_config(BookConfigBuilder::new().set_root(ROOT).build())
+            .add_chapter(ChapterBuilder::new().add_section(chain).build())
+            .build();
+        let result = book
+            .patch(&fs, &write("/openbook-test/patch/deep.md"))
+            .unwrap();
+        assert_eq!(result, PatchResult::Patched(vec![DEPTH.to_string()]));
+
+        let mut deepest = &book.chapters[0].sections[0];
+        while let Some(s) = deepest.subsections.first() {
+            deepest = s;
+        }
+        assert_eq!(deepest.content, "new");
+        drop_book_iteratively(book);
+    }
+
+    #[test]
+    fn patch_normalizes_paths() {
+        let fs = ScratchFileSystem::new();
+        fs.write_file(Path::new(ROOT).join("a.md"), b"new").unwrap();
+
+        for path in &[
+            "/openbook-test/patch/./a.md",
+            "/openbook-test/patch/../patch/a.md",
+        ] {
+            let mut book = book();
+            let result = book.patch(&fs, &write(path)).unwrap();
+            assert_eq!(
+                result,
+                PatchResult::Patched(vec!["a".to_owned()]),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn patch_ignores_unrelated_files() {
+        let fs = ScratchFileSystem::new();
+        let mut book = book();
+        let result = book
+            .patch(&fs, &write("/openbook-test/patch/b.md"))
+            .unwrap();
+        assert_eq!(result, PatchResult::Unaffected);
+    }
+
+    #[test]
+    fn patch_reloads_on_events_that_change_the_structure() {
+        let fs = ScratchFileSystem::new();
+        let a = PathBuf::from("/openbook-test/patch/a.md");
+        let events = vec![
+            FileSystemEvent::Create(a.clone()),
+            FileSystemEvent::Delete(a.clone()),
+            FileSystemEvent::Rename {
+                from: a.clone(),
+                to: PathBuf::from("/openbook-test/patch/b.md"),
+            },
+        ];
+        for event in &events {
+            let mut book = book();
+            let result = book.patch(&fs, event).unwrap();
+            assert_eq!(result, PatchResult::NeedsReload, "{:?}", event);
+            assert_eq!(book.chapters[0].sections[0].content, "old");
+        }
+    }
+
+    #[test]
+    fn patch_updates_every_section_of_the_file() {
+        let fs = ScratchFileSystem::new();
+        fs.write_file(
+            Path::new(ROOT).join("shared.md"),
+            b"---\ndraft: true\n---\nnew",
+        )
+        .unwrap();
+
+        let shared = |name: &str| {
+            SectionBuilder::new()
+                .set_name(name)
+                .set_file("shared.md")
+                .set_metadata("draft", "false")
+                .set_content("old")
+        };
+        let mut book = BookBuilder::new()
+            .set_config(BookConfigBuilder::new().set_root(ROOT).build())
+            .set_preface(shared("preface").build())
+            .add_chapter(
+                ChapterBuilder::new()
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_name("a")
+                            .set_file("a.md")
+                            .set_content("old")
+                            .add_subsection(shared("nested").build())
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+
+        let result = book
+            .patch(&fs, &write("/openbook-test/patch/shared.md"))
+            .unwrap();
+        assert_eq!(
+            result,
+            PatchResult::Patched(vec!["preface".to_owned(), "nested".to_owned()])
+        );
+        let nested = &book.chapters[0].sections[0].subsections[0];
+        assert_eq!(nested.content, "new");
+        assert_eq!(nested.metadata["draft"], "true");
+        assert_eq!(book.preface.content, "new");
+        assert_eq!(book.chapters[0].sections[0].content, "old");
+    }
+
+    #[test]
+    fn patch_reloads_sections_that_start_at_an_anchor() {
+        let fs = ScratchFileSystem::new();
+        let mut book = book();
+        book.chapters[0].sections[0].anchor = Some("usage".to_owned());
+        let result = book
+            .patch(&fs, &write("/openbook-test/patch/a.md"))
+            .unwrap();
+        assert_eq!(result, PatchResult::NeedsReload);
+    }
+
+    #[test]
+    fn patch_reloads_on_structure_files() {
+        let fs = ScratchFileSystem::new();
+        for file in &[
+            "SUMMARY.md",
+            "./GLOSSARY.md",
+            "LANGS.md",
+            "book.toml",
+            "book.yaml",
+            "book.json",
+        ] {
+            let mut book = book();
+            let path = Path::new(ROOT).join(file);
+            let result = book.patch(&fs, &FileSystemEvent::Write(path)).unwrap();
+            assert_eq!(result, PatchResult::NeedsReload, "{}", file);
+        }
+    }
+
+    #[test]
+    fn patch_reloads_on_custom_summary() {
+        let fs = ScratchFileSystem::new();
+        let mut book = book();
+        book.config.structure.summary = Some(PathBuf::from("toc/INDEX.md"));
+        let result = book
+            .patch(&fs, &write("/openbook-test/patch/toc/INDEX.md"))
+            .unwrap();
+        assert_eq!(result, PatchResult::NeedsReload);
+        let result = book
+            .patch(&fs, &write("/openbook-test/patch/SUMMARY.md"))
+            .unwrap();
+        assert_eq!(result, PatchResult::Unaffected);
+    }
+
+    #[test]
+    fn apply_event_reloads_on_project_structure_files() {
+        let fs = ScratchFileSystem::new();
+        let mut book = book();
+        book.config.root = Path::new(ROOT).join("en");
+        let mut books = GlobalizedBooksBuilder::new()
+            .set_config(BookConfigBuilder::new().set_root(ROOT).build())
+            .add_localized_book("en".to_owned(), book)
+            .build();
+
+        let events = vec![
+            write("/openbook-test/patch/LANGS.md"),
+            FileSystemEvent::Create(PathBuf::from("/openbook-test/patch/book.yaml")),
+            FileSystemEvent::Delete(PathBuf::from("/openbook-test/patch/book.toml")),
+            FileSystemEvent::Rename {
+                from: PathBuf::from("/openbook-test/patch/book.json"),
+                to: PathBuf::from("/tmp/book.json"),
+            },
+            write("/openbook-test/patch/en/SUMMARY.md"),
+        ];
+        for event in &events {
+            let outcome = books.apply_event(&fs, event).unwrap();
+            assert_eq!(outcome, UpdateOutcome::NeedsFullReload, "{:?}", event);
+        }
+
+        let outcome = books
+            .apply_event(&fs, &write("/openbook-test/patch/README.txt"))
+            .unwrap();
+        assert_eq!(outcome, UpdateOutcome::Unchanged);
+    }
+
+    #[test]
+    fn classify_rename_normalizes_paths() {
+        let config = BookConfigBuilder::new().set_root(ROOT).build();
+        let class = classify_rename(
+            &config,
+            Path::new("/openbook-test/patch/./a.md"),
+            Path::new("/openbook-test/other/../patch/b.md"),
+        );
+        assert_eq!(class, RenameClass::WithinProject);
+        let class = classify_rename(&config, Path::new("/tmp/a.md"), Path::new("/tmp/b.md"));
+        assert_eq!(class, RenameClass::Unrelated);
+    }
+
+    #[test]
+    fn classify_rename_tests_both_paths_against_the_root() {
+        let config = BookConfigBuilder::new().set_root(ROOT).build();
+        let cases = [
+            (
+                "/openbook-test/patch/a.md",
+                "/openbook-test/patch/b/a.md",
+                RenameClass::WithinProject,
+            ),
+            (
+                "/openbook-test/patch/a.md",
+                "/tmp/a.md",
+                RenameClass::MovedOut,
+            ),
+            (
+                "/tmp/a.md",
+                "/openbook-test/patch/a.md",
+                RenameClass::MovedIn,
+            ),
+            (
+                "/openbook-test/patch-old/a.md",
+                "/openbook-test/patches/a.md",
+                RenameClass::Unrelated,
+            ),
+        ];
+        for (from, to, class) in cases.iter() {
+            assert_eq!(
+                classify_rename(&config, Path::new(from), Path::new(to)),
+                *class,
+                "{} -> {}",
+                from,
+                to
+            );
+        }
+
+        let config = BookConfigBuilder::new().build();
+        let class = classify_rename(&config, Path::new("a.md"), Path::new("b/a.md"));
+        assert_eq!(class, RenameClass::WithinProject);
+    }
+
+    #[test]
+    fn apply_event_reloads_on_renames_that_touch_a_book() {
+        let fs = ScratchFileSystem::new();
+        let mut books = GlobalizedBooksBuilder::new()
+            .set_config(BookConfigBuilder::new().set_root("/openbook-test").build())
+            .add_default_book(book())
+            .build();
+        let rename = |from: &str, to: &str| FileSystemEvent::Rename {
+            from: PathBuf::from(from),
+            to: PathBuf::from(to),
+        };
+
+        for event in &[
+            rename("/openbook-test/patch/a.md", "/tmp/a.md"),
+            rename("/tmp/a.md", "/openbook-test/patch/a.md"),
+        ] {
+            let outcome = books.apply_event(&fs, event).unwrap();
+            assert_eq!(outcome, UpdateOutcome::NeedsFullReload, "{:?}", event);
+        }
+        let outcome = books
+            .apply_event(&fs, &rename("/tmp/a.md", "/tmp/b.md"))
+            .unwrap();
+        assert_eq!(outcome, UpdateOutcome::Unchanged);
+    }
+}
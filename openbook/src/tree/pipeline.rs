@@ -0,0 +1,148 @@
+//! This module provides a pipeline that applies a sequence of mutable visitors to the document
+//! tree.
+//!
+//! Preprocessing steps such as include resolution and glossary annotation are implemented as
+//! [`VisitorMut`]s. A [`Pipeline`] runs them over the whole tree one after another in the order in
+//! which they are added:
+//!
+//! ```ignore
+//! let mut pipeline = Pipeline::new()
+//!     .add_stage(Box::new(IncludeResolver::new(&fs)))
+//!     .add_stage(Box::new(GlossaryAnnotator::new(&glossary)));
+//! pipeline.run(&mut books)?;
+//! ```
+//!
+//! [`VisitorMut`]: ../visitor/trait.VisitorMut.html
+//! [`Pipeline`]: struct.Pipeline.html
+//!
+
+use crate::error::Result;
+use crate::tree::visitor::{VisitorHostMut, VisitorMut};
+use crate::tree::GlobalizedBooks;
+
+/// An ordered sequence of [`VisitorMut`]s that are applied to the document tree in turn.
+///
+/// [`VisitorMut`]: ../visitor/trait.VisitorMut.html
+#[derive(Default)]
+pub struct Pipeline<'a> {
+    stages: Vec<Box<dyn VisitorMut + 'a>>,
+}
+
+impl<'a> Pipeline<'a> {
+    /// Create a new `Pipeline` instance without any stage.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append the specified visitor as the last stage of this pipeline.
+    pub fn add_stage(mut self, stage: Box<dyn VisitorMut + 'a>) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    /// Get the number of stages in this pipeline.
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    /// Determine whether this pipeline has no stage.
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Apply every stage of this pipeline to the whole specified tree, in order.
+    ///
+    /// Each stage traverses the tree completely before the next one starts. After each traversal,
+    /// the error reported by `VisitorMut::take_error` of the stage, if any, is returned and the
+    /// remaining stages are not run. The changes made by the stages that have already run are kept.
+    pub fn run(&mut self, tree: &mut GlobalizedBooks) -> Result<()> {
+        for stage in &mut self.stages {
+            tree.visit_mut(stage);
+            if let Some(e) = stage.take_error() {
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::tree::builder::{
+        BookBuilder, ChapterBuilder, GlobalizedBooksBuilder, SectionBuilder,
+    };
+    use crate::tree::Section;
+
+    /// Appends a suffix to the content of every section.
+    struct Append(&'static str);
+
+    impl VisitorMut for Append {
+        fn visit_section(&mut self, section: &mut Section) {
+            section.content.push_str(self.0);
+        }
+    }
+
+    /// Fails on the first section it visits.
+    #[derive(Default)]
+    struct Fail(Option<Error>);
+
+    impl VisitorMut for Fail {
+        fn visit_section(&mut self, _section: &mut Section) {
+            if self.0.is_none() {
+                self.0 = Some(Error::from_message("stage failed"));
+            }
+        }
+
+        fn take_error(&mut self) -> Option<Error> {
+            self.0.take()
+        }
+    }
+
+    fn books() -> GlobalizedBooks {
+        GlobalizedBooksBuilder::new()
+            .add_default_book(
+                BookBuilder::new()
+                    .add_chapter(
+                        ChapterBuilder::new()
+                            .add_section(SectionBuilder::new().set_name("A").build())
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+
+    fn content(books: &GlobalizedBooks) -> &str {
+        &books.books[0].1.chapters[0].sections[0].content
+    }
+
+    #[test]
+    fn runs_stages_in_order() {
+        let mut pipeline = Pipeline::new()
+            .add_stage(Box::new(Append("1")))
+            .add_stage(Box::new(Append("2")));
+        assert_eq!(pipeline.len(), 2);
+        assert!(!pipeline.is_empty());
+
+        let mut books = books();
+        pipeline.run(&mut books).unwrap();
+        assert_eq!(content(&books), "12");
+        assert_eq!(books.books[0].1.preface.content, "12");
+    }
+
+    #[test]
+    fn stops_at_the_first_failing_stage() {
+        let mut pipeline = Pipeline::new()
+            .add_stage(Box::new(Append("1")))
+            .add_stage(Box::new(Fail::default()))
+            .add_stage(Box::new(Append("2")));
+
+        let mut books = books();
+        let err = pipeline.run(&mut books).unwrap_err();
+        assert!(err.to_string().contains("stage failed"), "{}", err);
+        assert_eq!(content(&books), "1");
+        assert!(Pipeline::new().run(&mut books).is_ok());
+    }
+}
@@ -0,0 +1,323 @@
+//! This module implements lookup of books and sections in the OpenBook document tree.
+//!
+
+use std::path::Path;
+
+use crate::fs::normalize;
+
+use crate::tree::{Book, GlobalizedBooks, Section};
+
+impl GlobalizedBooks {
+    /// Find the first section, in any book, whose content file is at the specified path.
+    ///
+    /// For how paths are compared, please refer to `Book::find_section_by_file`.
+    pub fn find_section_by_file<P: AsRef<Path>>(&self, path: P) -> Option<&Section> {
+        let path = path.as_ref();
+        self.books
+            .iter()
+            .find_map(|(_, book)| book.find_section_by_file(path))
+    }
+
+    /// Find the first section, in any book, whose content file is at the specified path, and get a
+    /// mutable reference to it.
+    ///
+    /// For how paths are compared, please refer to `Book::find_section_by_file`.
+    pub fn find_section_by_file_mut<P: AsRef<Path>>(&mut self, path: P) -> Option<&mut Section> {
+        let path = path.as_ref();
+        self.books
+            .iter_mut()
+            .find_map(|(_, book)| book.find_section_by_file_mut(path))
+    }
+
+    /// Find the book that best serves the specified language.
+    ///
+    /// The language of a book is either the language it is associated with in `books` or its
+    /// `BookConfig::language`, since books loaded from `LANGS.md` are associated with the display
+    /// name of their language rather than the language code.
+    ///
+    /// The book whose language is exactly the specified language is preferred. Otherwise, the
+    /// trailing subtags of the language are removed one at a time and the book of the resulting
+    /// language is taken, so `en-US` falls back to `en` and `zh-Hant-TW` to `zh-Hant` and then
+    /// `zh`. Otherwise, the default book, whose language is an empty string, is taken. Languages
+    /// are compared case-insensitively, and `_` is accepted as a subtag separator in place of `-`.
+    ///
+    /// Returns `None` if there is no such book.
+    pub fn book_for_language(&self, lang: &str) -> Option<&Book> {
+        let is_lang = |l: &str, lang: &str| l.replace('_', "-").eq_ignore_ascii_case(lang);
+        let find = |lang: &str| {
+            self.books
+                .iter()
+                .find(|(l, book)| {
+                    is_lang(l, lang) || matches!(&book.config.language, Some(l) if is_lang(l, lang))
+                })
+                .map(|(_, book)| book)
+        };
+
+        let mut lang = lang.replace('_', "-");
+        loop {
+            if let Some(book) = find(&lang) {
+                return Some(book);
+            }
+            match lang.rfind('-') {
+                Some(i) => lang.truncate(i),
+                None => break,
+            }
+        }
+        find("")
+    }
+}
+
+impl Book {
+    /// Find the first section in depth-first pre-order whose content file is at the specified path.
+    ///
+    /// The path may either be relative to the root directory of the book, like `Section::file`, or
+    /// be the root directory joined with such a path. Paths are compared after lexically removing
+    /// `.` components and resolving `..` components, so `./guide/../intro.md` matches `intro.md`.
+    pub fn find_section_by_file<P: AsRef<Path>>(&self, path: P) -> Option<&Section> {
+        let path = normalize(path.as_ref());
+        let root = &self.config.root;
+
+        let mut stack = self.top_level_sections();
+        stack.reverse();
+        while let Some(s) = stack.pop() {
+            if is_file_of(root, s, &path) {
+                return Some(s);
+            }
+            stack.extend(s.subsections.iter().rev());
+        }
+        None
+    }
+
+    /// Find the first section in depth-first pre-order whose content file is at the specified
+    /// path, and get a mutable reference to it.
+    ///
+    /// For how paths are compared, please refer to `find_section_by_file`.
+    pub fn find_section_by_file_mut<P: AsRef<Path>>(&mut self, path: P) -> Option<&mut Section> {
+        let path = normalize(path.as_ref());
+        let root = &self.config.root;
+
+        let mut stack = vec![&mut self.preface];
+        stack.extend(self.chapters.iter_mut().flat_map(|c| &mut c.sections));
+        stack.reverse();
+        while let Some(s) = stack.pop() {
+            if is_file_of(root, s, &path) {
+                return Some(s);
+            }
+            stack.extend(s.subsections.iter_mut().rev());
+        }
+        None
+    }
+
+    /// Find the section at the specified path of names.
+    ///
+    /// The first name is the name of a chapter and the following names are the names of a top-level
+    /// section in that chapter and of its nested subsections, e.g.
+    /// `["User Manual", "How to use OpenBook", "How to build"]`. The preface and its subsections
+    /// are found by starting the path with the name of the preface instead of a chapter. If
+    /// multiple nodes at the same level have the same name, the first one is taken.
+    pub fn find_section_by_path(&self, names: &[&str]) -> Option<&Section> {
+        let (first, rest) = names.split_first()?;
+
+        let (mut section, rest) = if self.preface.name == *first {
+            (&self.preface, rest)
+        } else {
+            let chapter = self.chapters.iter().find(|c| c.name == *first)?;
+            let (name, rest) = rest.split_first()?;
+            (chapter.sections.iter().find(|s| s.name == *name)?, rest)
+        };
+        for name in rest {
+            section = section.subsections.iter().find(|s| s.name == *name)?;
+        }
+        Some(section)
+    }
+
+    /// Find the section at the specified path of names, and get a mutable reference to it.
+    ///
+    /// For the format of the path, please refer to `find_section_by_path`.
+    pub fn find_section_by_path_mut(&mut self, names: &[&str]) -> Option<&mut Section> {
+        let (first, rest) = names.split_first()?;
+
+        let (mut section, rest) = if self.preface.name == *first {
+            (&mut self.preface, rest)
+        } else {
+            let chapter = self.chapters.iter_mut().find(|c| c.name == *first)?;
+            let (name, rest) = rest.split_first()?;
+            (chapter.sections.iter_mut().find(|s| s.name == *name)?, rest)
+        };
+        for name in rest {
+            section = section.subsections.iter_mut().find(|s| s.name == *name)?;
+        }
+        Some(section)
+    }
+
+    /// Get the preface followed by the top-level sections of every chapter.
+    fn top_level_sections(&self) -> Vec<&Section> {
+        let mut sections = vec![&self.preface];
+        sections.extend(self.chapters.iter().flat_map(|c| &c.sections));
+        sections
+    }
+}
+
+/// Determine whether the content file of the given section is at the specified normalized path.
+fn is_file_of(root: &Path, section: &Section, path: &Path) -> bool {
+    if section.file.as_os_str().is_empty() {
+        return false;
+    }
+    let file = normalize(&section.file);
+    file == path || normalize(root.join(&file)) == path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::builder::{
+        BookBuilder, BookConfigBuilder, ChapterBuilder, GlobalizedBooksBuilder, SectionBuilder,
+    };
+
+    fn guide() -> Book {
+        let section = |name: &str, file: &str| SectionBuilder::new().set_name(name).set_file(file);
+        BookBuilder::new()
+            .set_config(BookConfigBuilder::new().set_root("/books/guide").build())
+            .set_preface(section("Preface", "README.md").build())
+            .add_chapter(
+                ChapterBuilder::new()
+                    .set_name("User Manual")
+                    .add_section(
+                        section("Usage", "user/usage.md")
+                            .add_subsection(section("Build", "user/build.md").build())
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+
+    #[test]
+    fn finds_sections_by_normalized_files() {
+        let mut book = guide();
+        assert_eq!(
+            book.find_section_by_file("README.md").unwrap().name,
+            "Preface"
+        );
+        assert_eq!(
+            book.find_section_by_file("./user/../user/build.md")
+                .unwrap()
+                .name,
+            "Build"
+        );
+        assert_eq!(
+            book.find_section_by_file("/books/guide/user/usage.md")
+                .unwrap()
+                .name,
+            "Usage"
+        );
+        assert!(book.find_section_by_file("user/missing.md").is_none());
+        assert!(book.find_section_by_file("").is_none());
+
+        book.find_section_by_file_mut("./user/build.md")
+            .unwrap()
+            .content = "updated".to_owned();
+        assert_eq!(
+            book.chapters[0].sections[0].subsections[0].content,
+            "updated"
+        );
+
+        let mut books = GlobalizedBooksBuilder::new()
+            .add_localized_book("en".to_owned(), BookBuilder::new().build())
+            .add_localized_book("zh".to_owned(), guide())
+            .build();
+        assert_eq!(
+            books.find_section_by_file("user/usage.md").unwrap().name,
+            "Usage"
+        );
+        assert!(books.find_section_by_file_mut("user/build.md").is_some());
+        assert!(books.find_section_by_file("user/missing.md").is_none());
+    }
+
+    #[test]
+    fn finds_sections_by_paths_of_names() {
+        let mut book = guide();
+        assert_eq!(
+            book.find_section_by_path(&["User Manual", "Usage", "Build"])
+                .unwrap()
+                .file,
+            Path::new("user/build.md")
+        );
+        assert_eq!(
+            book.find_section_by_path(&["Preface"]).unwrap().file,
+            Path::new("README.md")
+        );
+        assert!(book.find_section_by_path(&["User Manual"]).is_none());
+        assert!(book
+            .find_section_by_path(&["User Manual", "Missing"])
+            .is_none());
+        assert!(book.find_section_by_path(&[]).is_none());
+
+        book.find_section_by_path_mut(&["User Manual", "Usage"])
+            .unwrap()
+            .name = "Use".to_owned();
+        assert_eq!(book.chapters[0].sections[0].name, "Use");
+    }
+
+    fn titled(title: &str) -> Book {
+        BookBuilder::new()
+            .set_config(BookConfigBuilder::new().set_title(title).build())
+            .build()
+    }
+
+    fn title(book: Option<&Book>) -> Option<&str> {
+        book.and_then(|b| b.config.title.as_deref())
+    }
+
+    #[test]
+    fn book_for_language_falls_back_to_base_languages_and_the_default() {
+        let books = GlobalizedBooksBuilder::new()
+            .add_default_book(titled("default"))
+            .add_localized_book("en".to_owned(), titled("en"))
+            .add_localized_book("zh-Hant".to_owned(), titled("zh-Hant"))
+            .build();
+
+        assert_eq!(title(books.book_for_language("en")), Some("en"));
+        assert_eq!(title(books.book_for_language("EN")), Some("en"));
+        assert_eq!(title(books.book_for_language("en-US")), Some("en"));
+        assert_eq!(
+            title(books.book_for_language("zh_Hant_TW")),
+            Some("zh-Hant")
+        );
+        assert_eq!(title(books.book_for_language("zh")), Some("default"));
+        assert_eq!(title(books.book_for_language("fr")), Some("default"));
+
+        let without_default = GlobalizedBooksBuilder::new()
+            .add_localized_book("en".to_owned(), titled("en"))
+            .build();
+        assert_eq!(
+            title(without_default.book_for_language("en-GB")),
+            Some("en")
+        );
+        assert!(without_default.book_for_language("fr").is_none());
+    }
+
+    #[test]
+    fn book_for_language_matches_language_codes_of_named_books() {
+        let coded = |title: &str, language: &str| {
+            BookBuilder::new()
+                .set_config(
+                    BookConfigBuilder::new()
+                        .set_title(title)
+                        .set_language(language)
+                        .build(),
+                )
+                .build()
+        };
+        let books = GlobalizedBooksBuilder::new()
+            .add_localized_book("English".to_owned(), coded("en", "en"))
+            .add_localized_book("中文".to_owned(), coded("zh", "zh-CN"))
+            .build();
+
+        assert_eq!(title(books.book_for_language("English")), Some("en"));
+        assert_eq!(title(books.book_for_language("en-AU")), Some("en"));
+        assert_eq!(title(books.book_for_language("zh_cn")), Some("zh"));
+        assert_eq!(title(books.book_for_language("中文")), Some("zh"));
+        assert!(books.book_for_language("zh").is_none());
+    }
+}
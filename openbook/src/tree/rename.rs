@@ -0,0 +1,401 @@
+//! This module renames sections in place and fixes the links that refer to them.
+//!
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use pulldown_cmark::{Event, LinkType, Parser, Tag};
+
+use crate::error::{Error, Result};
+use crate::fs::normalize;
+use crate::tree::headings::heading_slug;
+use crate::tree::links::is_external;
+use crate::tree::{Book, Section};
+
+/// Identifies a section of a book by where it starts: its content file and, for sections that
+/// start at a heading within the file, the anchor of that heading.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct SectionId {
+    /// Path to the content file of the section, relative to the root directory of the book.
+    pub file: PathBuf,
+
+    /// Anchor at which the section starts, see `Section::anchor`.
+    pub anchor: Option<String>,
+}
+
+impl SectionId {
+    /// Create a new `SectionId` instance that identifies the section of the whole specified
+    /// content file.
+    pub fn new<P: Into<PathBuf>>(file: P) -> Self {
+        Self {
+            file: file.into(),
+            anchor: None,
+        }
+    }
+
+    /// Set the anchor at which the section starts.
+    pub fn set_anchor<S: Into<String>>(mut self, anchor: S) -> Self {
+        self.anchor = Some(anchor.into());
+        self
+    }
+
+    /// Get the identifier of the specified section.
+    pub fn of(section: &Section) -> Self {
+        Self {
+            file: section.file.clone(),
+            anchor: section.anchor.clone(),
+        }
+    }
+
+    /// Determine whether the specified section is identified by this identifier.
+    fn matches(&self, section: &Section) -> bool {
+        section.has_file()
+            && normalize(&section.file) == normalize(&self.file)
+            && section.anchor == self.anchor
+    }
+}
+
+/// Report of a `Book::rename_section` call.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RenameReport {
+    /// Name of the section before the rename.
+    pub old_name: String,
+
+    /// Identifier of the section after the rename, whose anchor changes with its name if the
+    /// section starts at a heading.
+    pub id: SectionId,
+
+    /// Number of links that were rewritten to the new anchor.
+    pub links_updated: usize,
+}
+
+impl Book {
+    /// Rename the section with the specified identifier.
+    ///
+    /// If the section covers its whole content file, only `Section::name` changes, since the page
+    /// of the section is named after the file. If the section starts at a heading within the file,
+    /// the text of the heading in the content of every section of that file is replaced with the
+    /// new name as well, and the anchor of the section becomes the slug of the new name, see
+    /// `headings::heading_slug`. Every link in the book that points at the old anchor of the file,
+    /// either with a path or as a fragment within the file, is then rewritten to point at the new
+    /// anchor; links to other anchors and to other files are left untouched. Both inline links and
+    /// reference definitions are rewritten.
+    ///
+    /// Returns an error of kind `ErrorKind::NotFound` if the book has no such section.
+    pub fn rename_section(&mut self, id: &SectionId, new_name: String) -> Result<RenameReport> {
+        let section = match self.find_section_mut(id) {
+            Some(section) => section,
+            None => {
+                return Err(Error::not_found(format!(
+                    "section {}{} does not exist",
+                    id.file.display(),
+                    id.anchor
+                        .as_ref()
+                        .map(|a| format!("#{}", a))
+                        .unwrap_or_default()
+                )))
+            }
+        };
+
+        let old_name = std::mem::replace(&mut section.name, new_name.clone());
+        let old_anchor = match &section.anchor {
+            Some(anchor) => anchor.clone(),
+            None => {
+                return Ok(RenameReport {
+                    old_name,
+                    id: id.clone(),
+                    links_updated: 0,
+                })
+            }
+        };
+        let new_anchor = heading_slug(&new_name);
+        section.anchor = Some(new_anchor.clone());
+
+        let file = normalize(&id.file);
+        let mut links_updated = 0;
+        self.for_each_section_mut(|section| {
+            let same_file = normalize(&section.file) == file;
+            if same_file {
+                rename_heading(section, &old_anchor, &new_name);
+            }
+            let base = section
+                .file
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .to_owned();
+            let rewrite = |target: &str| -> Option<String> {
+                if is_external(target) {
+                    return None;
+                }
+                let (path, anchor) = target.split_at(target.find('#')?);
+                let points_at_file = if path.is_empty() {
+                    same_file
+                } else {
+                    normalize(base.join(path)) == file
+                };
+                if points_at_file && anchor[1..] == old_anchor {
+                    Some(format!("{}#{}", path, new_anchor))
+                } else {
+                    None
+                }
+            };
+            let (content, count) = rewrite_links(&section.content, rewrite);
+            if count > 0 {
+                section.content = content;
+                links_updated += count;
+            }
+        });
+
+        Ok(RenameReport {
+            old_name,
+            id: SectionId {
+                file: id.file.clone(),
+                anchor: Some(new_anchor),
+            },
+            links_updated,
+        })
+    }
+
+    /// Find the section with the specified identifier and get a mutable reference to it.
+    fn find_section_mut(&mut self, id: &SectionId) -> Option<&mut Section> {
+        let mut stack = vec![&mut self.preface];
+        stack.extend(self.chapters.iter_mut().flat_map(|c| &mut c.sections));
+        stack.reverse();
+        while let Some(s) = stack.pop() {
+            if id.matches(s) {
+                return Some(s);
+            }
+            stack.extend(s.subsections.iter_mut().rev());
+        }
+        None
+    }
+
+    /// Call the specified function on every section of this book in depth-first pre-order.
+    fn for_each_section_mut<F: FnMut(&mut Section)>(&mut self, mut f: F) {
+        let mut stack = vec![&mut self.preface];
+        stack.extend(self.chapters.iter_mut().flat_map(|c| &mut c.sections));
+        stack.reverse();
+        while let Some(s) = stack.pop() {
+            f(s);
+            stack.extend(s.subsections.iter_mut().rev());
+        }
+    }
+}
+
+/// Replace the text of the first heading in the content of the specified section whose slug is
+/// the specified anchor with the specified text, keeping the level of the heading.
+fn rename_heading(section: &mut Section, anchor: &str, text: &str) {
+    let mut current: Option<(u32, usize, String)> = None;
+    let mut found = None;
+    for (event, span) in Parser::new(&section.content).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading(level)) => current = Some((level, span.start, String::new())),
+            Event::End(Tag::Heading(_)) => {
+                if let Some((level, start, heading)) = current.take() {
+                    if heading_slug(&heading) == anchor {
+                        found = Some((level, start..span.end));
+                        break;
+                    }
+                }
+            }
+            Event::Text(t) | Event::Code(t) => {
+                if let Some((_, _, heading)) = &mut current {
+                    heading.push_str(&t);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((level, range)) = found {
+        // The span of a heading ends after its line break, if any, which is kept.
+        let heading = &section.content[range.clone()];
+        let end = range.start + heading.trim_end_matches(&['\r', '\n'][..]).len();
+        let replacement = format!("{} {}", "#".repeat(level as usize), text);
+        section
+            .content
+            .replace_range(range.start..end, &replacement);
+    }
+}
+
+/// Rewrite the targets of the links in the specified markdown content with the specified
+/// function, which returns the new target of a link or `None` to leave it unchanged.
+///
+/// Both the destinations of inline links and those of link reference definitions are rewritten.
+/// Returns the new content and the number of rewritten targets.
+fn rewrite_links<F: Fn(&str) -> Option<String>>(content: &str, rewrite: F) -> (String, usize) {
+    let mut edits = Vec::new();
+    let mut code_blocks = Vec::new();
+    for (event, span) in Parser::new(content).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Link(LinkType::Inline, dest, _)) => {
+                // The destination follows the `](` that closes the link text.
+                let source = &content[span.clone()];
+                let start = match source.rfind("](") {
+                    Some(i) => span.start + i + 2,
+                    None => continue,
+                };
+                if let Some(edit) = find_destination(content, start, &dest, &rewrite) {
+                    edits.push(edit);
+                }
+            }
+            Event::Start(Tag::CodeBlock(_)) => code_blocks.push(span),
+            _ => {}
+        }
+    }
+
+    // Reference definitions do not produce events, so they are found line by line instead.
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        if code_blocks.iter().any(|r| r.contains(&start)) {
+            continue;
+        }
+        // A definition is indented by at most three spaces and starts with a `[label]:`.
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        if indent > 3 || !line[indent..].starts_with('[') {
+            continue;
+        }
+        let after = match line.find("]:") {
+            Some(i) => start + i + 2,
+            None => continue,
+        };
+        let rest = &content[after..offset];
+        let mut start = after + rest.len() - rest.trim_start().len();
+        if content[start..].starts_with('<') {
+            start += 1;
+        }
+        let dest: String = content[start..offset]
+            .chars()
+            .take_while(|c| !c.is_whitespace() && *c != '>')
+            .collect();
+        if let Some(edit) = find_destination(content, start, &dest, &rewrite) {
+            edits.push(edit);
+        }
+    }
+
+    let count = edits.len();
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    let mut content = content.to_owned();
+    for (range, target) in edits {
+        content.replace_range(range, &target);
+    }
+    (content, count)
+}
+
+/// Get the range of the specified link destination, written at or soon after the specified
+/// offset in the specified content, and its new target if it is to be rewritten.
+fn find_destination<F: Fn(&str) -> Option<String>>(
+    content: &str,
+    offset: usize,
+    dest: &str,
+    rewrite: &F,
+) -> Option<(Range<usize>, String)> {
+    let target = rewrite(dest)?;
+    let start = offset + content[offset..].find(dest)?;
+    Some((start..start + dest.len(), target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::tree::builder::{BookBuilder, ChapterBuilder, SectionBuilder};
+
+    fn book(sibling: &str) -> Book {
+        BookBuilder::new()
+            .add_chapter(
+                ChapterBuilder::new()
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_name("Usage")
+                            .set_file("guide/usage.md")
+                            .set_content("# Usage\n\nSee [building](#how-to-build).\n")
+                            .add_subsection(
+                                SectionBuilder::new()
+                                    .set_name("How to build")
+                                    .set_file("guide/usage.md")
+                                    .set_anchor("how-to-build")
+                                    .set_content("## How to build\n\nRun it.\n\n## Notes\n")
+                                    .build(),
+                            )
+                            .build(),
+                    )
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_name("Install")
+                            .set_file("basics/install.md")
+                            .set_content(sibling)
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+
+    fn install(book: &Book) -> &str {
+        &book.chapters[0].sections[1].content
+    }
+
+    #[test]
+    fn updates_links_in_sibling_sections() {
+        let mut book = book(
+            "Read [this](../guide/usage.md#how-to-build) and [notes](../guide/usage.md#notes).\n\n\
+             Also [that][build].\n\n[build]: <../guide/usage.md#how-to-build> \"Build\"\n",
+        );
+        let id = SectionId::new("guide/usage.md").set_anchor("how-to-build");
+        let report = book
+            .rename_section(&id, "Building from source".to_owned())
+            .unwrap();
+
+        assert_eq!(
+            report,
+            RenameReport {
+                old_name: "How to build".to_owned(),
+                id: SectionId::new("guide/usage.md").set_anchor("building-from-source"),
+                links_updated: 3,
+            }
+        );
+        assert_eq!(
+            install(&book),
+            "Read [this](../guide/usage.md#building-from-source) and \
+             [notes](../guide/usage.md#notes).\n\nAlso [that][build].\n\n\
+             [build]: <../guide/usage.md#building-from-source> \"Build\"\n"
+        );
+
+        let usage = &book.chapters[0].sections[0];
+        assert_eq!(
+            usage.content,
+            "# Usage\n\nSee [building](#building-from-source).\n"
+        );
+        let section = &usage.subsections[0];
+        assert_eq!(section.name, "Building from source");
+        assert_eq!(section.anchor.as_deref(), Some("building-from-source"));
+        assert_eq!(
+            section.content,
+            "## Building from source\n\nRun it.\n\n## Notes\n"
+        );
+    }
+
+    #[test]
+    fn leaves_links_unchanged_for_whole_file_sections() {
+        let content = "See [usage](../guide/usage.md) and [top](../guide/usage.md#usage).\n";
+        let mut book = book(content);
+        let report = book
+            .rename_section(&SectionId::new("guide/usage.md"), "Using".to_owned())
+            .unwrap();
+        assert_eq!(report.old_name, "Usage");
+        assert_eq!(report.links_updated, 0);
+        assert_eq!(book.chapters[0].sections[0].name, "Using");
+        assert_eq!(install(&book), content);
+    }
+
+    #[test]
+    fn fails_on_missing_sections() {
+        let mut book = book("");
+        let id = SectionId::new("guide/usage.md").set_anchor("missing");
+        let error = book.rename_section(&id, "Missing".to_owned()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+    }
+}
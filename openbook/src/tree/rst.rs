@@ -0,0 +1,668 @@
+//! This module converts reStructuredText content into markdown.
+//!
+//! Sections written in reStructuredText are converted into markdown when they are loaded, so that
+//! the rest of OpenBook only ever deals with markdown. The following subset of reStructuredText is
+//! supported:
+//!
+//! * Section titles with an underline or an overline and an underline. Heading levels are assigned
+//!   to adornment styles in the order in which the styles first appear, as in docutils;
+//! * Paragraphs, bullet lists and enumerated lists, including auto-numbered `#.` items;
+//! * Literal blocks introduced by `::`, and `code-block`, `code`, `sourcecode` and `math`
+//!   directives, which become fenced code blocks;
+//! * Block quotes, and admonitions such as `note` and `warning`, which become quotes that start
+//!   with the title of the admonition in bold;
+//! * `image` and `figure` directives, and `raw` directives for HTML;
+//! * Footnotes, and transitions, which become thematic breaks;
+//! * Inline literals, emphasis, strong emphasis, interpreted text with roles such as `:code:` and
+//!   `:ref:`, embedded URIs like `` `OpenBook <https://github.com/Lancern/OpenBook>`_ `` and
+//!   references to hyperlink targets like `` `OpenBook`_ ``.
+//!
+//! Comments, hyperlink targets, substitution definitions and other directives, such as `toctree`
+//! and `include`, do not produce any output, and all other constructs are kept as text.
+//!
+
+use std::collections::HashMap;
+
+/// Names of the admonition directives, which are rendered as quotes titled with their names.
+const ADMONITIONS: &[&str] = &[
+    "attention",
+    "caution",
+    "danger",
+    "error",
+    "hint",
+    "important",
+    "note",
+    "tip",
+    "warning",
+];
+
+/// Convert the specified reStructuredText document into markdown.
+pub fn rst_to_markdown(source: &str) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let targets = collect_targets(&lines);
+    let mut converter = RstConverter::new(&targets);
+    converter.convert(&lines);
+
+    let mut output = converter.output.join("\n");
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    output
+}
+
+/// Converts the blocks of a reStructuredText document into lines of markdown.
+struct RstConverter<'a> {
+    /// Named hyperlink targets of the document, keyed by their normalized names.
+    targets: &'a HashMap<String, String>,
+
+    /// Adornment styles of section titles in the order they first appear, given by the adornment
+    /// character and whether the title has an overline. The heading level of a title is the index
+    /// of its style plus one.
+    styles: Vec<(char, bool)>,
+
+    output: Vec<String>,
+}
+
+impl<'a> RstConverter<'a> {
+    fn new(targets: &'a HashMap<String, String>) -> Self {
+        Self {
+            targets,
+            styles: Vec::new(),
+            output: Vec::new(),
+        }
+    }
+
+    /// Convert the specified lines and append the result to the output.
+    fn convert(&mut self, lines: &[&str]) {
+        let mut i = 0;
+        let mut literal_next = false;
+        let mut in_list = false;
+        while i < lines.len() {
+            let line = lines[i].trim_end();
+            if line.is_empty() {
+                self.output.push(String::new());
+                i += 1;
+                continue;
+            }
+
+            let indent = indentation(line);
+            if literal_next && indent > 0 {
+                let end = block_end(lines, i);
+                self.push_fence("", &dedent(&lines[i..end]));
+                literal_next = false;
+                i = end;
+                continue;
+            }
+            literal_next = false;
+
+            if indent == 0 {
+                if let Some(c) = adornment(line) {
+                    if i + 2 < lines.len()
+                        && !lines[i + 1].trim().is_empty()
+                        && lines[i + 2].trim_end() == line
+                    {
+                        self.push_heading(lines[i + 1].trim(), (c, true));
+                        i += 3;
+                        continue;
+                    }
+                    if line.len() >= 4 {
+                        // A break right after a paragraph would make it a setext heading.
+                        if self.output.last().is_some_and(|l| !l.is_empty()) {
+                            self.output.push(String::new());
+                        }
+                        self.output.push("---".to_owned());
+                        i += 1;
+                        continue;
+                    }
+                } else if let Some(c) = lines.get(i + 1).and_then(|l| underline(line, l)) {
+                    self.push_heading(line, (c, false));
+                    i += 2;
+                    continue;
+                }
+
+                if line == ".." || line.starts_with(".. ") {
+                    let end = block_end(lines, i + 1);
+                    self.directive(line[2..].trim(), &lines[i + 1..end]);
+                    in_list = false;
+                    i = end;
+                    continue;
+                }
+                if line.starts_with("__ ") {
+                    i = block_end(lines, i + 1);
+                    continue;
+                }
+            } else if !in_list {
+                let end = block_end(lines, i);
+                let body = self.convert_nested(&dedent(&lines[i..end]));
+                self.push_quote(&body);
+                i = end;
+                continue;
+            }
+
+            let (marker, text) = list_marker(line);
+            if marker.is_some() {
+                in_list = true;
+            } else if indent == 0 {
+                in_list = false;
+            }
+
+            let mut text = text.trim_end();
+            if text.ends_with("::") {
+                literal_next = true;
+                text = if text == "::" {
+                    ""
+                } else if let Some(stripped) = text.strip_suffix(" ::") {
+                    stripped
+                } else {
+                    &text[..text.len() - 1]
+                };
+            }
+            if marker.is_none() && text.is_empty() {
+                i += 1;
+                continue;
+            }
+
+            let mut converted = " ".repeat(indent);
+            if let Some(marker) = marker {
+                converted.push_str(&marker);
+            }
+            converted.push_str(&self.inline(text));
+            self.output.push(converted);
+            i += 1;
+        }
+    }
+
+    /// Convert the specified lines as a nested document that shares the hyperlink targets of this
+    /// document.
+    fn convert_nested<S: AsRef<str>>(&self, lines: &[S]) -> Vec<String> {
+        let lines: Vec<&str> = lines.iter().map(AsRef::as_ref).collect();
+        let mut nested = RstConverter::new(self.targets);
+        nested.convert(&lines);
+        nested.output
+    }
+
+    /// Convert the explicit markup block that starts with `..` followed by the specified text,
+    /// whose indented body is given by `body`.
+    fn directive(&mut self, text: &str, body: &[&str]) {
+        if text.starts_with('[') {
+            if let Some(end) = text.find(']') {
+                let mut footnote = format!(
+                    "[^{}]: {}",
+                    &text[1..end],
+                    self.inline(text[end + 1..].trim())
+                );
+                for line in dedent(body) {
+                    footnote.push_str("\n    ");
+                    footnote.push_str(&self.inline(&line));
+                }
+                self.output.push(footnote);
+            }
+            return;
+        }
+
+        let (name, argument) = match text.find("::") {
+            Some(i) if !text.starts_with('_') && !text.starts_with('|') => {
+                (text[..i].trim(), text[i + 2..].trim())
+            }
+            // Comments, hyperlink targets and substitution definitions.
+            _ => return,
+        };
+        let body = dedent(body);
+        let mut options = HashMap::new();
+        let mut content = &body[..];
+        while let Some(line) = content.first() {
+            match parse_option(line) {
+                Some((key, value)) => {
+                    options.insert(key, value);
+                    content = &content[1..];
+                }
+                None => break,
+            }
+        }
+        while matches!(content.first(), Some(l) if l.trim().is_empty()) {
+            content = &content[1..];
+        }
+
+        match name {
+            "code-block" | "code" | "sourcecode" => self.push_fence(argument, content),
+            "math" => {
+                let mut lines = content.to_vec();
+                if !argument.is_empty() {
+                    lines.insert(0, argument.to_owned());
+                }
+                self.push_fence("math", &lines);
+            }
+            "image" | "figure" => {
+                let alt = options.get("alt").map(String::as_str).unwrap_or_default();
+                self.output.push(format!("![{}]({})", alt, argument));
+                if name == "figure" && !content.is_empty() {
+                    self.output.push(String::new());
+                    let caption = self.convert_nested(content);
+                    self.output.extend(caption);
+                }
+            }
+            "raw" if argument == "html" => self.output.extend(content.iter().cloned()),
+            "admonition" => self.push_admonition(argument, "", content),
+            name if ADMONITIONS.contains(&name) => {
+                let mut title = name.to_owned();
+                title[..1].make_ascii_uppercase();
+                self.push_admonition(&title, argument, content);
+            }
+            _ => {}
+        }
+    }
+
+    /// Append the specified admonition, whose content starts with the text on the line of the
+    /// directive itself, as a quote.
+    fn push_admonition(&mut self, title: &str, first: &str, content: &[String]) {
+        let mut lines = Vec::with_capacity(content.len() + 1);
+        if !first.is_empty() {
+            lines.push(first.to_owned());
+        }
+        lines.extend(content.iter().cloned());
+
+        let mut body = vec![format!("**{}**", self.inline(title)), String::new()];
+        body.extend(self.convert_nested(&lines));
+        self.push_quote(&body);
+    }
+
+    /// Append the specified title as a heading at the level of the specified adornment style.
+    fn push_heading(&mut self, title: &str, style: (char, bool)) {
+        let level = match self.styles.iter().position(|s| *s == style) {
+            Some(i) => i + 1,
+            None => {
+                self.styles.push(style);
+                self.styles.len()
+            }
+        };
+        self.output.push(format!(
+            "{} {}",
+            "#".repeat(level.min(6)),
+            self.inline(title)
+        ));
+    }
+
+    /// Append the specified lines as a fenced code block with the specified info string.
+    fn push_fence(&mut self, info: &str, lines: &[String]) {
+        let end = lines
+            .iter()
+            .rposition(|l| !l.trim().is_empty())
+            .map_or(0, |i| i + 1);
+        let fence = if lines.iter().any(|l| l.contains("```")) {
+            "~~~~"
+        } else {
+            "```"
+        };
+        self.output.push(format!("{}{}", fence, info));
+        self.output.extend(lines[..end].iter().cloned());
+        self.output.push(fence.to_owned());
+    }
+
+    /// Append the specified markdown lines as a block quote.
+    fn push_quote(&mut self, lines: &[String]) {
+        let end = lines
+            .iter()
+            .rposition(|l| !l.trim().is_empty())
+            .map_or(0, |i| i + 1);
+        for line in &lines[..end] {
+            if line.is_empty() {
+                self.output.push(">".to_owned());
+            } else {
+                self.output.push(format!("> {}", line));
+            }
+        }
+    }
+
+    /// Convert the inline markup in the specified text.
+    fn inline(&self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut output = String::with_capacity(text.len());
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            match c {
+                '\\' if i + 1 < chars.len() => {
+                    output.push(c);
+                    output.push(chars[i + 1]);
+                    i += 2;
+                }
+                '`' if chars.get(i + 1) == Some(&'`') => match find(&chars, i + 2, "``") {
+                    Some(end) => {
+                        let literal: String = chars[i + 2..end].iter().collect();
+                        output.push_str(&code_span(&literal));
+                        i = end + 2;
+                    }
+                    None => {
+                        output.push_str("``");
+                        i += 2;
+                    }
+                },
+                '`' => match find(&chars, i + 1, "`") {
+                    Some(end) => {
+                        let body: String = chars[i + 1..end].iter().collect();
+                        i = end + 1;
+                        if chars.get(i) == Some(&'_') {
+                            i += 1;
+                            if chars.get(i) == Some(&'_') {
+                                i += 1;
+                            }
+                            output.push_str(&self.reference(&body));
+                        } else {
+                            output.push('*');
+                            output.push_str(&body);
+                            output.push('*');
+                        }
+                    }
+                    None => {
+                        output.push(c);
+                        i += 1;
+                    }
+                },
+                ':' => match parse_role(&chars, i) {
+                    Some((role, start)) => match find(&chars, start + 1, "`") {
+                        Some(end) => {
+                            let body: String = chars[start + 1..end].iter().collect();
+                            output.push_str(&role_text(&role, &body));
+                            i = end + 1;
+                        }
+                        None => {
+                            output.push(c);
+                            i += 1;
+                        }
+                    },
+                    None => {
+                        output.push(c);
+                        i += 1;
+                    }
+                },
+                '[' => match footnote_reference(&chars, i) {
+                    Some((label, end)) => {
+                        output.push_str(&format!("[^{}]", label));
+                        i = end;
+                    }
+                    None => {
+                        output.push(c);
+                        i += 1;
+                    }
+                },
+                _ => {
+                    output.push(c);
+                    i += 1;
+                }
+            }
+        }
+        output
+    }
+
+    /// Convert the hyperlink reference with the specified text between its backquotes.
+    fn reference(&self, body: &str) -> String {
+        if body.ends_with('>') {
+            if let Some(start) = body.rfind('<') {
+                let text = body[..start].trim();
+                let mut uri = body[start + 1..body.len() - 1].trim();
+                if uri.ends_with('_') {
+                    if let Some(target) = self.targets.get(&normalize_name(&uri[..uri.len() - 1])) {
+                        uri = target.as_str();
+                    }
+                }
+                let text = if text.is_empty() { uri } else { text };
+                return format!("[{}]({})", text, uri);
+            }
+        }
+
+        match self.targets.get(&normalize_name(body)) {
+            Some(uri) => format!("[{}]({})", body, uri),
+            None => body.to_owned(),
+        }
+    }
+}
+
+/// Collect the named hyperlink targets, like `.. _OpenBook: https://github.com/Lancern/OpenBook`,
+/// of the specified document, keyed by their normalized names.
+fn collect_targets(lines: &[&str]) -> HashMap<String, String> {
+    let mut targets = HashMap::new();
+    for line in lines {
+        let rest = match line.trim().strip_prefix(".. _") {
+            Some(rest) => rest,
+            None => continue,
+        };
+        let (name, uri) = if let Some(quoted) = rest.strip_prefix('`') {
+            match quoted.find("`:") {
+                Some(end) => (&quoted[..end], &quoted[end + 2..]),
+                None => continue,
+            }
+        } else {
+            match rest.find(": ") {
+                Some(end) => (&rest[..end], &rest[end + 2..]),
+                None => continue,
+            }
+        };
+        let uri = uri.trim();
+        if !uri.is_empty() {
+            targets.insert(normalize_name(name), uri.to_owned());
+        }
+    }
+    targets
+}
+
+/// Normalize the specified reference name, which is case-insensitive and whitespace-neutral.
+fn normalize_name(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Get the number of leading whitespace characters of the specified line.
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Get the index of the line after the indented block that starts at the specified line, not
+/// including trailing blank lines.
+fn block_end(lines: &[&str], start: usize) -> usize {
+    let mut end = start;
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if indentation(line) == 0 {
+            break;
+        }
+        end = i + 1;
+    }
+    end
+}
+
+/// Remove the common indentation of the specified lines.
+fn dedent(lines: &[&str]) -> Vec<String> {
+    let indent = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| indentation(l))
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|l| l.get(indent..).unwrap_or_default().trim_end().to_owned())
+        .collect()
+}
+
+/// Get the adornment character if the specified line is a section title adornment, i.e. a line
+/// that repeats a single punctuation character.
+fn adornment(line: &str) -> Option<char> {
+    let c = line.chars().next()?;
+    if c.is_ascii_punctuation() && line.chars().all(|x| x == c) {
+        Some(c)
+    } else {
+        None
+    }
+}
+
+/// Get the adornment character if the specified text line is a section title underlined by the
+/// specified next line.
+fn underline(text: &str, next: &str) -> Option<char> {
+    let next = next.trim_end();
+    let c = adornment(next)?;
+    if next.chars().count() >= text.chars().count() && (next.len() >= 2 || text.len() == 1) {
+        Some(c)
+    } else {
+        None
+    }
+}
+
+/// Split the list marker off the specified line, converting it into a markdown list marker.
+///
+/// Returns the markdown marker, if the line is a list item, and the rest of the line.
+fn list_marker(line: &str) -> (Option<String>, &str) {
+    let text = line.trim_start();
+    for bullet in &["* ", "- ", "+ ", "• "] {
+        if let Some(rest) = text.strip_prefix(bullet) {
+            return (Some(bullet.to_string()), rest);
+        }
+    }
+    if let Some(rest) = text.strip_prefix("#. ") {
+        return (Some("1. ".to_owned()), rest);
+    }
+
+    let digits = text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 {
+        let rest = &text[digits..];
+        if rest.starts_with(". ") || rest.starts_with(") ") {
+            return (Some(format!("{}. ", &text[..digits])), &rest[2..]);
+        }
+    }
+    (None, text)
+}
+
+/// Parse a `:key: value` directive option.
+fn parse_option(line: &str) -> Option<(String, String)> {
+    let rest = line.trim().strip_prefix(':')?;
+    let end = rest.find(':')?;
+    if end == 0 || rest[..end].contains(' ') {
+        return None;
+    }
+    Some((rest[..end].to_owned(), rest[end + 1..].trim().to_owned()))
+}
+
+/// Parse the role, like `:code:`, of the interpreted text that starts at the specified index.
+///
+/// Returns the name of the role and the index of the opening backquote of the text.
+fn parse_role(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let mut end = start + 1;
+    while end < chars.len()
+        && (chars[end].is_ascii_alphanumeric() || matches!(chars[end], '-' | '_' | '.' | '+'))
+    {
+        end += 1;
+    }
+    if end == start + 1 || chars.get(end) != Some(&':') || chars.get(end + 1) != Some(&'`') {
+        return None;
+    }
+    Some((chars[start + 1..end].iter().collect(), end + 1))
+}
+
+/// Convert the interpreted text with the specified role.
+fn role_text(role: &str, body: &str) -> String {
+    match role {
+        "code" | "literal" | "samp" | "file" | "command" | "kbd" | "math" => code_span(body),
+        "emphasis" => format!("*{}*", body),
+        "strong" => format!("**{}**", body),
+        "sub" | "subscript" => format!("<sub>{}</sub>", body),
+        "sup" | "superscript" => format!("<sup>{}</sup>", body),
+        // Cross references, like :ref:`Title <label>`, are replaced by their titles.
+        _ => match body.rfind(" <") {
+            Some(end) if body.ends_with('>') => body[..end].trim().to_owned(),
+            _ => body.trim_start_matches('~').to_owned(),
+        },
+    }
+}
+
+/// Parse the footnote reference, like `[1]_`, that starts at the specified index.
+///
+/// Returns the label of the footnote and the index after the reference.
+fn footnote_reference(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let end = find(chars, start + 1, "]")?;
+    if end == start + 1 || chars.get(end + 1) != Some(&'_') {
+        return None;
+    }
+    let label: String = chars[start + 1..end].iter().collect();
+    if label.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((label, end + 2))
+}
+
+/// Find the specified pattern in the specified characters, starting at the specified index.
+fn find(chars: &[char], start: usize, pattern: &str) -> Option<usize> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    (start..chars.len()).find(|&i| chars[i..].starts_with(&pattern))
+}
+
+/// Format the specified text as a markdown code span.
+fn code_span(text: &str) -> String {
+    if text.contains('`') {
+        format!("`` {} ``", text)
+    } else {
+        format!("`{}`", text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn title_levels_follow_the_order_of_adornment_styles() {
+        let source = "=====\nTitle\n=====\n\nPart\n----\n\nSub\n~~~\n\nOther part\n----------\n";
+        assert_eq!(
+            rst_to_markdown(source),
+            "# Title\n\n## Part\n\n### Sub\n\n## Other part\n"
+        );
+    }
+
+    #[test]
+    fn converts_lists_and_inline_markup() {
+        let source =
+            "Intro with ``code``, *em* and **strong**.\n\n* one\n* two\n\n#. first\n#. second\n";
+        assert_eq!(
+            rst_to_markdown(source),
+            "Intro with `code`, *em* and **strong**.\n\n* one\n* two\n\n1. first\n1. second\n"
+        );
+    }
+
+    #[test]
+    fn literal_blocks_and_code_directives_become_fenced_code_blocks() {
+        let source = "Example::\n\n    let x = 1;\n\n.. code-block:: rust\n\n    fn main() {}\n";
+        assert_eq!(
+            rst_to_markdown(source),
+            "Example:\n\n```\nlet x = 1;\n```\n\n```rust\nfn main() {}\n```\n"
+        );
+    }
+
+    #[test]
+    fn admonitions_become_titled_quotes() {
+        assert_eq!(
+            rst_to_markdown(".. note::\n\n   Be careful.\n"),
+            "> **Note**\n>\n> Be careful.\n"
+        );
+    }
+
+    #[test]
+    fn resolves_embedded_uris_and_hyperlink_targets() {
+        let source = "See `OpenBook <https://github.com/Lancern/OpenBook>`_ and `Docs`_.\n\n\
+                      .. _Docs: https://docs.rs\n";
+        let markdown = rst_to_markdown(source);
+        assert!(markdown.starts_with(
+            "See [OpenBook](https://github.com/Lancern/OpenBook) and [Docs](https://docs.rs).\n"
+        ));
+        assert!(!markdown.contains(".. _Docs"), "{}", markdown);
+    }
+
+    #[test]
+    fn transitions_become_thematic_breaks() {
+        assert_eq!(
+            rst_to_markdown("Before.\n\n----\n\nAfter.\n"),
+            "Before.\n\n---\n\nAfter.\n"
+        );
+    }
+}
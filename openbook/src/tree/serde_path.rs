@@ -0,0 +1,48 @@
+//! This module serializes paths in the document tree as strings with forward slash separators, so
+//! that serialized trees are the same across platforms.
+//!
+
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Convert the specified path to a string with forward slash separators.
+fn to_slash_string(path: &Path) -> String {
+    let s = path.to_string_lossy();
+    if MAIN_SEPARATOR == '/' {
+        s.into_owned()
+    } else {
+        s.replace(MAIN_SEPARATOR, "/")
+    }
+}
+
+pub(super) fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&to_slash_string(path))
+}
+
+pub(super) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+    String::deserialize(deserializer).map(PathBuf::from)
+}
+
+/// Serialize optional paths with forward slash separators.
+pub(super) mod option {
+    use std::path::PathBuf;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(in crate::tree) fn serialize<S: Serializer>(
+        path: &Option<PathBuf>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match path {
+            Some(path) => serializer.serialize_some(&super::to_slash_string(path)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(in crate::tree) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<PathBuf>, D::Error> {
+        Option::<String>::deserialize(deserializer).map(|path| path.map(PathBuf::from))
+    }
+}
@@ -0,0 +1,304 @@
+//! This module provides a visitor that collects statistics about the content of books.
+//!
+
+use std::time::Duration;
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+use crate::tree::budget::count_words;
+use crate::tree::visitor::{VisitContext, Visitor};
+use crate::tree::{Book, Chapter, Section};
+
+/// Statistics of a single section, not including its subsections.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SectionStats {
+    /// Name of the section.
+    pub name: String,
+
+    /// Number of words in the content of the section.
+    pub words: usize,
+
+    /// Number of headings in the content of the section.
+    pub headings: usize,
+}
+
+/// Statistics of a chapter, including all of its sections and their subsections.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ChapterStats {
+    /// Name of the chapter.
+    pub name: String,
+
+    /// Number of words in the chapter.
+    pub words: usize,
+
+    /// Number of headings in the chapter.
+    pub headings: usize,
+
+    /// Number of sections in the chapter.
+    pub section_count: usize,
+}
+
+/// Statistics of a book.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BookStats {
+    /// Language of the book.
+    pub language: String,
+
+    /// Number of words in the book.
+    pub words: usize,
+
+    /// Number of headings in the book.
+    pub headings: usize,
+
+    /// Statistics of each chapter of the book, in order.
+    pub chapters: Vec<ChapterStats>,
+
+    /// Statistics of each section of the book, including the preface, in depth-first pre-order.
+    pub sections: Vec<SectionStats>,
+}
+
+impl BookStats {
+    /// Get the number of sections in the book, including the preface.
+    pub fn section_count(&self) -> usize {
+        self.sections.len()
+    }
+
+    /// Estimate the time it takes to read the whole book at the specified number of words per
+    /// minute.
+    ///
+    /// If `words_per_minute` is zero, it is treated as one.
+    pub fn reading_time(&self, words_per_minute: usize) -> Duration {
+        let words_per_minute = words_per_minute.max(1);
+        Duration::from_secs_f64(self.words as f64 * 60.0 / words_per_minute as f64)
+    }
+}
+
+/// A visitor that counts the words, headings and sections of the visited books.
+///
+/// Words are counted in the text of the content after stripping the markdown syntax, by splitting
+/// it on whitespace. In books whose language is Chinese, Japanese or Korean, every CJK character
+/// is counted as a word instead, since such text does not delimit words with whitespace. The
+/// language of a book is taken from `BookConfig::language` and falls back to the language the book
+/// is associated with in [`GlobalizedBooks`].
+///
+/// [`GlobalizedBooks`]: ../struct.GlobalizedBooks.html
+#[derive(Clone, Debug, Default)]
+pub struct StatsVisitor {
+    books: Vec<BookStats>,
+    cjk: bool,
+}
+
+impl StatsVisitor {
+    /// Create a new `StatsVisitor` instance.
+    pub fn new() -> Self {
+        Self {
+            books: Vec::new(),
+            cjk: false,
+        }
+    }
+
+    /// Consume this visitor and get the statistics of each visited book, in visiting order.
+    pub fn into_stats(self) -> Vec<BookStats> {
+        self.books
+    }
+}
+
+impl Visitor for StatsVisitor {
+    fn visit_book_in(&mut self, book: &Book, context: &VisitContext) {
+        let language = book
+            .config
+            .language
+            .clone()
+            .or_else(|| context.language().map(str::to_owned))
+            .unwrap_or_default();
+        self.cjk = is_cjk_language(&language);
+        self.books.push(BookStats {
+            language,
+            ..BookStats::default()
+        });
+    }
+
+    fn visit_chapter(&mut self, chapter: &Chapter) {
+        if self.books.is_empty() {
+            // Chapters are visited without an enclosing book.
+            self.books.push(BookStats::default());
+        }
+        self.books.last_mut().unwrap().chapters.push(ChapterStats {
+            name: chapter.name.clone(),
+            ..ChapterStats::default()
+        });
+    }
+
+    fn visit_section_in(&mut self, section: &Section, context: &VisitContext) {
+        if self.books.is_empty() {
+            // Sections are visited without an enclosing book.
+            self.books.push(BookStats::default());
+        }
+
+        let text = strip_markdown(&section.content);
+        let words = if self.cjk {
+            count_words(&text)
+        } else {
+            text.split_whitespace().count()
+        };
+        let headings = count_headings(&section.content);
+
+        let book = self.books.last_mut().unwrap();
+        book.words += words;
+        book.headings += headings;
+        if context.chapter().is_some() {
+            if let Some(chapter) = book.chapters.last_mut() {
+                chapter.words += words;
+                chapter.headings += headings;
+                chapter.section_count += 1;
+            }
+        }
+        book.sections.push(SectionStats {
+            name: section.name.clone(),
+            words,
+            headings,
+        });
+    }
+}
+
+/// Determine whether the specified ISO 639-1 language code, optionally followed by a region,
+/// denotes Chinese, Japanese or Korean.
+fn is_cjk_language(language: &str) -> bool {
+    let code = language
+        .split(&['-', '_'][..])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    matches!(code.as_str(), "zh" | "ja" | "ko")
+}
+
+/// Get the text of the specified markdown content without the markdown syntax.
+///
+/// Text that is split into several events by inline markup or entities, like `foo**bar**baz` or
+/// `don&apos;t`, is joined back together, and whitespace is only inserted at the boundaries of
+/// blocks and at line breaks, so that words are counted as they are written.
+fn strip_markdown(content: &str) -> String {
+    let mut text = String::with_capacity(content.len());
+    for event in Parser::new(content) {
+        match event {
+            Event::Text(s) | Event::Code(s) => text.push_str(&s),
+            Event::Start(tag) | Event::End(tag) if !is_inline(&tag) => text.push(' '),
+            Event::SoftBreak | Event::HardBreak | Event::Rule => text.push(' '),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Determine whether the specified tag is an inline element, which does not separate words.
+fn is_inline(tag: &Tag) -> bool {
+    matches!(
+        tag,
+        Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..) | Tag::Image(..)
+    )
+}
+
+/// Count the headings in the specified markdown content.
+fn count_headings(content: &str) -> usize {
+    Parser::new(content)
+        .filter(|event| matches!(event, Event::Start(Tag::Heading(_))))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::builder::{BookBuilder, BookConfigBuilder, ChapterBuilder, SectionBuilder};
+    use crate::tree::visitor::visit;
+
+    fn stats(language: Option<&str>, contents: &[&str]) -> BookStats {
+        let mut config = BookConfigBuilder::new();
+        if let Some(language) = language {
+            config = config.set_language(language);
+        }
+        let chapter = contents
+            .iter()
+            .enumerate()
+            .fold(
+                ChapterBuilder::new().set_name("chapter"),
+                |c, (i, content)| {
+                    c.add_section(
+                        SectionBuilder::new()
+                            .set_name(format!("s{}", i))
+                            .set_content(*content)
+                            .build(),
+                    )
+                },
+            )
+            .build();
+        let book = BookBuilder::new()
+            .set_config(config.build())
+            .set_preface(SectionBuilder::new().set_content("preface words").build())
+            .add_chapter(chapter)
+            .build();
+
+        let mut visitor = StatsVisitor::new();
+        visit(&book, &mut visitor);
+        visitor.into_stats().pop().unwrap()
+    }
+
+    #[test]
+    fn strip_markdown_keeps_inline_words_together() {
+        assert_eq!(
+            strip_markdown("foo**bar**baz").split_whitespace().count(),
+            1
+        );
+        assert_eq!(
+            strip_markdown("don&apos;t stop").split_whitespace().count(),
+            2
+        );
+        assert_eq!(
+            strip_markdown("a [link](x.md)s `code`")
+                .split_whitespace()
+                .count(),
+            3
+        );
+    }
+
+    #[test]
+    fn strip_markdown_separates_blocks_and_lines() {
+        let text = strip_markdown("# Title\nfirst\nsecond\n\n- one\n- two\n\n> quote");
+        assert_eq!(
+            text.split_whitespace().collect::<Vec<_>>(),
+            vec!["Title", "first", "second", "one", "two", "quote"]
+        );
+    }
+
+    #[test]
+    fn counts_words_headings_and_sections() {
+        let stats = stats(None, &["# Intro\n\nHello *big* world.", "## More\n\ntext"]);
+        assert_eq!(stats.words, 2 + 4 + 2);
+        assert_eq!(stats.headings, 2);
+        assert_eq!(stats.section_count(), 3);
+        assert_eq!(
+            stats.chapters,
+            vec![ChapterStats {
+                name: "chapter".to_owned(),
+                words: 6,
+                headings: 2,
+                section_count: 2,
+            }]
+        );
+        assert_eq!(stats.sections[1].words, 4);
+    }
+
+    #[test]
+    fn counts_cjk_characters_as_words() {
+        let stats = stats(Some("zh-CN"), &["你好世界"]);
+        assert_eq!(stats.language, "zh-CN");
+        assert_eq!(stats.sections[1].words, 4);
+    }
+
+    #[test]
+    fn reading_time_scales_with_words() {
+        let stats = stats(None, &["one two three four"]);
+        assert_eq!(stats.words, 6);
+        assert_eq!(stats.reading_time(6), Duration::from_secs(60));
+        assert_eq!(stats.reading_time(0), Duration::from_secs(360));
+    }
+}
@@ -0,0 +1,354 @@
+//! This module implements variable substitution in section content.
+//!
+//! A variable reference like `{{ book.title }}` in the content of a section is replaced by the
+//! value of the named variable. The whitespace around the name is optional. The following
+//! variables are defined:
+//!
+//! * `book.title`, `book.description`, `book.author`, `book.isbn` and `book.language`: The
+//!   corresponding entries of the configuration of the book, if they are set;
+//! * `page.name`: The name of the section;
+//! * `page.title`: The title of the section, as by `Section::title`;
+//! * `page.file`: The path of the content file of the section;
+//! * `page.<key>`: The `<key>` entry of the metadata of the section, e.g. from its frontmatter;
+//! * `<name>`: The `<name>` entry of the `variables` table of the configuration, where the keys of
+//!   nested tables are joined with `.`. Strings are substituted as they are and other scalars in
+//!   their TOML representation. These variables cannot shadow the `book` and `page` variables.
+//!
+//! Substitution follows these escaping rules:
+//!
+//! * Markdown syntax characters in the values are escaped, so that the values render literally;
+//! * A reference preceded by a backslash, like `\{{ page.name }}`, is not substituted and renders
+//!   as `{{ page.name }}`;
+//! * References in code spans and code blocks are not substituted;
+//! * Text between `{{` and `}}` that is not a variable name is left untouched, so `{{#include}}`
+//!   directives and the placeholders of HTML templates are not affected.
+//!
+//! References to undefined variables are errors.
+//!
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Parser, Tag};
+use toml::Value;
+
+use crate::error::Error;
+use crate::tree::visitor::VisitorMut;
+use crate::tree::{Book, BookConfig, Section};
+
+const REFERENCE_START: &str = "{{";
+const REFERENCE_END: &str = "}}";
+
+/// Key of the table of user-defined variables in the configuration.
+const VARIABLES_KEY: &str = "variables";
+
+/// A visitor that substitutes the variable references in every visited section with the values of
+/// the variables, as described in the [module documentation].
+///
+/// The `book` and user-defined variables are taken from the configuration of the book under visit.
+/// After the first error, the remaining sections are left untouched and the error is reported by
+/// `take_error`.
+///
+/// [module documentation]: index.html
+#[derive(Debug, Default)]
+pub struct VariableSubstitutor {
+    variables: BTreeMap<String, String>,
+    error: Option<Error>,
+}
+
+impl VariableSubstitutor {
+    /// Create a new `VariableSubstitutor` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl VisitorMut for VariableSubstitutor {
+    fn visit_book(&mut self, book: &mut Book) {
+        self.variables = book_variables(&book.config);
+    }
+
+    fn visit_section(&mut self, section: &mut Section) {
+        if self.error.is_some() {
+            return;
+        }
+        let substituted = substitute(&section.content, |name| self.lookup(section, name));
+        match substituted {
+            Ok(Some(content)) => section.content = content,
+            Ok(None) => {}
+            Err(name) => {
+                self.error = Some(Error::not_found(format!(
+                    "section \"{}\": undefined variable \"{}\"",
+                    section.name, name
+                )))
+            }
+        }
+    }
+
+    fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+}
+
+impl VariableSubstitutor {
+    /// Get the value of the variable with the specified name in the specified section.
+    fn lookup(&self, section: &Section, name: &str) -> Option<String> {
+        if let Some(key) = name.strip_prefix("page.") {
+            return match key {
+                "name" => Some(section.name.clone()),
+                "title" => Some(section.title().to_owned()),
+                "file" => Some(section.file.display().to_string()),
+                _ => section.metadata.get(key).cloned(),
+            };
+        }
+        self.variables.get(name).cloned()
+    }
+}
+
+/// Substitute the variables in the specified section content.
+///
+/// `lookup` gets the value of a variable by its name. Returns `None` if the content does not
+/// contain any variable reference, and the name of the first undefined variable as an error.
+fn substitute<L>(content: &str, lookup: L) -> std::result::Result<Option<String>, String>
+where
+    L: Fn(&str) -> Option<String>,
+{
+    let excluded = code_ranges(content);
+    let mut output = String::with_capacity(content.len());
+    let mut last = 0;
+    let mut offset = 0;
+    while let Some(found) = content[offset..].find(REFERENCE_START) {
+        let start = offset + found;
+        offset = start + REFERENCE_START.len();
+
+        let end = match content[offset..].find(REFERENCE_END) {
+            Some(end) => offset + end,
+            None => break,
+        };
+        let name = content[offset..end].trim();
+        if !is_variable_name(name)
+            || content[..start].ends_with('\\')
+            || excluded.iter().any(|range| range.contains(&start))
+        {
+            continue;
+        }
+
+        let value = lookup(name).ok_or_else(|| name.to_owned())?;
+        output.push_str(&content[last..start]);
+        output.push_str(&escape_markdown(&value));
+        last = end + REFERENCE_END.len();
+        offset = last;
+    }
+
+    if last == 0 {
+        return Ok(None);
+    }
+    output.push_str(&content[last..]);
+    Ok(Some(output))
+}
+
+/// Collect the `book` and user-defined variables from the specified configuration.
+fn book_variables(config: &BookConfig) -> BTreeMap<String, String> {
+    let mut user = BTreeMap::new();
+    if let Some(value @ Value::Table(_)) = config.get_extra(VARIABLES_KEY) {
+        flatten_variables("", &value, &mut user);
+    }
+    let mut variables: BTreeMap<String, String> = user
+        .into_iter()
+        .filter(|(name, _)| !name.starts_with("book.") && !name.starts_with("page."))
+        .collect();
+
+    let entries = [
+        ("book.title", &config.title),
+        ("book.description", &config.description),
+        ("book.author", &config.author),
+        ("book.isbn", &config.isbn),
+        ("book.language", &config.language),
+    ];
+    for (name, value) in entries.iter() {
+        if let Some(value) = value {
+            variables.insert((*name).to_owned(), value.clone());
+        }
+    }
+    variables
+}
+
+/// Add the scalars in the specified TOML value to `variables`, naming each after its key path
+/// under the specified prefix. Arrays are skipped.
+fn flatten_variables(prefix: &str, value: &Value, variables: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table {
+                let name = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten_variables(&name, value, variables);
+            }
+        }
+        Value::Array(_) => {}
+        Value::String(s) => {
+            variables.insert(prefix.to_owned(), s.clone());
+        }
+        value => {
+            variables.insert(prefix.to_owned(), value.to_string());
+        }
+    }
+}
+
+/// Determine whether the specified text is a variable name, i.e. a sequence of keys made of ASCII
+/// letters, digits, `_` and `-` that are separated by `.`.
+fn is_variable_name(name: &str) -> bool {
+    name.split('.').all(|key| {
+        !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    })
+}
+
+/// Get the byte ranges of the code spans and code blocks in the specified markdown content.
+fn code_ranges(content: &str) -> Vec<Range<usize>> {
+    Parser::new(content)
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Code(_) => Some(range),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Escape the characters in the specified text that have a meaning in markdown.
+fn escape_markdown(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`'
+                | '*'
+                | '_'
+                | '['
+                | ']'
+                | '<'
+                | '>'
+                | '#'
+                | '!'
+                | '|'
+                | '~'
+                | '&'
+                | '{'
+                | '}'
+        ) {
+            output.push('\\');
+        }
+        output.push(c);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    use crate::config::{load_config, CONFIG_FILE_NAME};
+    use crate::error::ErrorKind;
+    use crate::fs::scratch::ScratchFileSystem;
+    use crate::fs::FileSystem;
+    use crate::tree::builder::{BookBuilder, BookConfigBuilder, ChapterBuilder, SectionBuilder};
+    use crate::tree::visitor::visit_mut;
+
+    const ROOT: &str = "/openbook-test/variables";
+
+    fn book(config: BookConfig, content: &str) -> Book {
+        BookBuilder::new()
+            .set_config(config)
+            .add_chapter(
+                ChapterBuilder::new()
+                    .add_section(
+                        SectionBuilder::new()
+                            .set_name("Setup")
+                            .set_file("guide/setup.md")
+                            .set_metadata("owner", "docs-team")
+                            .set_content(content)
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+
+    fn run(book: &mut Book) -> Option<Error> {
+        let mut substitutor = VariableSubstitutor::new();
+        visit_mut(book, &mut substitutor);
+        substitutor.take_error()
+    }
+
+    fn content(book: &Book) -> &str {
+        &book.chapters[0].sections[0].content
+    }
+
+    #[test]
+    fn substitutes_book_and_page_variables() {
+        let config = BookConfigBuilder::new()
+            .set_title("OpenBook")
+            .set_author("Sirui Mu")
+            .build();
+        let mut book = book(
+            config,
+            "{{book.title}} by {{ book.author }}: {{ page.name }} in {{page.file}}, \
+             owned by {{ page.owner }}",
+        );
+        assert!(run(&mut book).is_none());
+        assert_eq!(
+            content(&book),
+            "OpenBook by Sirui Mu: Setup in guide/setup.md, owned by docs-team"
+        );
+    }
+
+    #[test]
+    fn substitutes_user_defined_variables() {
+        let fs = ScratchFileSystem::new();
+        let toml = "[variables]\nversion = \"1.2\"\nbook = \"shadowed\"\n\n\
+                    [variables.limits]\nmax = 10\n";
+        fs.write_file(Path::new(ROOT).join(CONFIG_FILE_NAME), toml.as_bytes())
+            .unwrap();
+        let config = load_config(&fs, Path::new(ROOT)).unwrap();
+
+        let mut book = book(config, "v{{ version }}, at most {{ limits.max }}");
+        assert!(run(&mut book).is_none());
+        assert_eq!(content(&book), "v1.2, at most 10");
+    }
+
+    #[test]
+    fn escaped_references_code_and_other_tags_are_left_as_is() {
+        let source = "\\{{ page.name }} `{{ page.name }}` {{#include a.md}} {{> nav}}\n\n\
+                      ```\n{{ page.name }}\n```\n";
+        let mut book = book(BookConfig::default(), source);
+        assert!(run(&mut book).is_none());
+        assert_eq!(content(&book), source);
+    }
+
+    #[test]
+    fn values_are_escaped() {
+        let config = BookConfigBuilder::new().set_title("*Open* {Book}").build();
+        let mut book = book(config, "# {{ book.title }}");
+        assert!(run(&mut book).is_none());
+        assert_eq!(content(&book), "# \\*Open\\* \\{Book\\}");
+    }
+
+    #[test]
+    fn undefined_variables_are_errors() {
+        let mut book = book(BookConfig::default(), "{{ book.title }}");
+        let err = run(&mut book).unwrap();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(
+            err.to_string()
+                .contains("section \"Setup\": undefined variable \"book.title\""),
+            "{}",
+            err
+        );
+        assert_eq!(content(&book), "{{ book.title }}");
+    }
+}
@@ -1,13 +1,20 @@
 //! This module defines a visitor type for traversing the OpenBook document tree.
 //!
-//! OpenBook document tree visitors should implement the [`Visitor`] trait and implement all of its
-//! three associative functions:
+//! OpenBook document tree visitors should implement the [`Visitor`] trait and override the
+//! associative functions for the nodes they are interested in. All of them do nothing by default:
 //! * `visit_globalized_books`: This function will be called when traversing a [`GlobalizedBooks`]
-//! node;
+//!   node;
 //! * `visit_book`: This function will be called when traversing a [`Book`] node;
+//! * `visit_chapter`: This function will be called when traversing a [`Chapter`] node, before its
+//!   sections;
 //! * `visit_section`: This function will be called when traversing a [`Section`] node.
 //!
 //! The OpenBook document tree will be traversed in a **depth-first pre-order traverse** manner.
+//! Within a book, the preface section is visited first, followed by each chapter and its sections.
+//! Sections are traversed iteratively, so arbitrarily deep section trees can be visited without
+//! overflowing the stack. Visitors that aggregate the results of children into their parents can
+//! use the `visit_with_order` function to traverse the tree in depth-first post-order instead, in
+//! which every node is visited after its children.
 //!
 //! To start traversing from a node, you can call the `visit` function:
 //!
@@ -17,70 +24,722 @@
 //! visit(&node, &mut visitor);
 //! ```
 //!
+//! Visitors that need to know where a node sits in the tree, e.g. to generate breadcrumbs or
+//! relative URLs, can override the `visit_*_in` functions of [`Visitor`], which additionally
+//! receive a [`VisitContext`] with the current language, chapter and ancestor sections.
+//!
+//! Visitors that modify the tree in place should implement the [`VisitorMut`] trait instead, which
+//! receives mutable references to the nodes. Such visitors are started with the `visit_mut`
+//! function and traverse the tree in the same order.
+//!
 //! [`GlobalizedBooks`]: ../struct.GlobalizedBooks.html
 //! [`Book`]: ../struct.Book.html
+//! [`Chapter`]: ../struct.Chapter.html
 //! [`Section`]: ../struct.Section.html
 //! [`Visitor`]: trait.Visitor.html
+//! [`VisitorMut`]: trait.VisitorMut.html
+//! [`VisitContext`]: struct.VisitContext.html
 //!
 
-use crate::tree::{Book, GlobalizedBooks, Section};
+use crate::error::Error;
+use crate::tree::{Book, Chapter, GlobalizedBooks, Section};
 
 /// OpenBook document tree visitors. User-defined visitors should implement this trait.
+///
+/// Visitors that need to know where a node sits in the tree can override the `visit_*_in`
+/// functions, which receive a [`VisitContext`] in addition to the node. By default they call the
+/// corresponding function without the context.
+///
+/// [`VisitContext`]: struct.VisitContext.html
 pub trait Visitor {
     /// Visit the given [`GlobalizedBooks`] node.
     ///
     /// [`GlobalizedBooks`]: ../struct.GlobalizedBooks.html
-    fn visit_globalized_books(&mut self, globalized_books: &GlobalizedBooks);
+    fn visit_globalized_books(&mut self, _globalized_books: &GlobalizedBooks) {}
 
     /// Visit the given [`Book`] node.
     ///
     /// [`Book`]: ../struct.Book.html
-    fn visit_book(&mut self, book: &Book);
+    fn visit_book(&mut self, _book: &Book) {}
+
+    /// Visit the given [`Chapter`] node.
+    ///
+    /// [`Chapter`]: ../struct.Chapter.html
+    fn visit_chapter(&mut self, _chapter: &Chapter) {}
 
     /// Visit the given [`Section`] node.
     ///
     /// [`Section`]: ../struct.Section.html
-    fn visit_section(&mut self, section: &Section);
+    fn visit_section(&mut self, _section: &Section) {}
+
+    /// Visit the given [`Book`] node within the specified context.
+    ///
+    /// [`Book`]: ../struct.Book.html
+    fn visit_book_in(&mut self, book: &Book, _context: &VisitContext) {
+        self.visit_book(book);
+    }
+
+    /// Visit the given [`Chapter`] node within the specified context.
+    ///
+    /// [`Chapter`]: ../struct.Chapter.html
+    fn visit_chapter_in(&mut self, chapter: &Chapter, _context: &VisitContext) {
+        self.visit_chapter(chapter);
+    }
+
+    /// Visit the given [`Section`] node within the specified context.
+    ///
+    /// [`Section`]: ../struct.Section.html
+    fn visit_section_in(&mut self, section: &Section, _context: &VisitContext) {
+        self.visit_section(section);
+    }
+}
+
+/// The position of the visited node in the document tree.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VisitContext {
+    language: Option<String>,
+    chapter: Option<String>,
+    ancestors: Vec<String>,
+}
+
+impl VisitContext {
+    /// Get the language of the book that contains the visited node.
+    ///
+    /// Returns `None` if the traversal did not start from a [`GlobalizedBooks`] node. The
+    /// language of a book that is not associated with any language is an empty string.
+    ///
+    /// [`GlobalizedBooks`]: ../struct.GlobalizedBooks.html
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    /// Get the name of the chapter that contains the visited node.
+    ///
+    /// Returns `None` for the preface section and its subsections, and for nodes that are not
+    /// within a chapter.
+    pub fn chapter(&self) -> Option<&str> {
+        self.chapter.as_deref()
+    }
+
+    /// Get the names of the chapter and the sections that enclose the visited node, from the
+    /// outermost to the innermost.
+    ///
+    /// The visited node itself is not included.
+    pub fn ancestors(&self) -> &[String] {
+        &self.ancestors
+    }
+}
+
+/// Specifies the order in which the nodes of the document tree are visited.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TraversalOrder {
+    /// Every node is visited before its children.
+    PreOrder,
+
+    /// Every node is visited after its children.
+    PostOrder,
 }
 
 /// Extension trait for types that supports `visit` operation. In normal cases, users should not use
 /// this trait directly.
 pub trait VisitorHost {
-    /// Visit this object with the specified visitor.
-    fn visit<V: Visitor>(&self, visitor: &mut V);
+    /// Visit this object with the specified visitor in depth-first pre-order.
+    fn visit<V: Visitor>(&self, visitor: &mut V) {
+        self.visit_with_order(visitor, TraversalOrder::PreOrder);
+    }
+
+    /// Visit this object with the specified visitor in the specified order.
+    fn visit_with_order<V: Visitor>(&self, visitor: &mut V, order: TraversalOrder) {
+        self.visit_in(visitor, order, &mut VisitContext::default());
+    }
+
+    /// Visit this object with the specified visitor in the specified order, where this object is
+    /// at the position described by `context`.
+    ///
+    /// Implementations must restore `context` before returning.
+    fn visit_in<V: Visitor>(
+        &self,
+        visitor: &mut V,
+        order: TraversalOrder,
+        context: &mut VisitContext,
+    );
 }
 
 impl VisitorHost for GlobalizedBooks {
-    fn visit<V: Visitor>(&self, visitor: &mut V) {
-        visitor.visit_globalized_books(self);
-        for (_, book) in &self.books {
-            book.visit(visitor);
+    fn visit_in<V: Visitor>(
+        &self,
+        visitor: &mut V,
+        order: TraversalOrder,
+        context: &mut VisitContext,
+    ) {
+        if order == TraversalOrder::PreOrder {
+            visitor.visit_globalized_books(self);
+        }
+        let language = context.language.take();
+        for (name, book) in &self.books {
+            context.language = Some(name.clone());
+            book.visit_in(visitor, order, context);
+        }
+        context.language = language;
+        if order == TraversalOrder::PostOrder {
+            visitor.visit_globalized_books(self);
         }
     }
 }
 
 impl VisitorHost for Book {
-    fn visit<V: Visitor>(&self, visitor: &mut V) {
-        visitor.visit_book(self);
-        self.preface.visit(visitor);
+    fn visit_in<V: Visitor>(
+        &self,
+        visitor: &mut V,
+        order: TraversalOrder,
+        context: &mut VisitContext,
+    ) {
+        if order == TraversalOrder::PreOrder {
+            visitor.visit_book_in(self, context);
+        }
+        self.preface.visit_in(visitor, order, context);
+        for chapter in &self.chapters {
+            chapter.visit_in(visitor, order, context);
+        }
+        if order == TraversalOrder::PostOrder {
+            visitor.visit_book_in(self, context);
+        }
+    }
+}
+
+impl VisitorHost for Chapter {
+    fn visit_in<V: Visitor>(
+        &self,
+        visitor: &mut V,
+        order: TraversalOrder,
+        context: &mut VisitContext,
+    ) {
+        if order == TraversalOrder::PreOrder {
+            visitor.visit_chapter_in(self, context);
+        }
+        let chapter = context.chapter.replace(self.name.clone());
+        context.ancestors.push(self.name.clone());
         for s in &self.sections {
-            s.visit(visitor);
+            s.visit_in(visitor, order, context);
+        }
+        context.ancestors.pop();
+        context.chapter = chapter;
+        if order == TraversalOrder::PostOrder {
+            visitor.visit_chapter_in(self, context);
         }
     }
 }
 
 impl VisitorHost for Section {
-    fn visit<V: Visitor>(&self, visitor: &mut V) {
-        visitor.visit_section(self);
-        for s in &self.subsections {
-            s.visit(visitor);
+    fn visit_in<V: Visitor>(
+        &self,
+        visitor: &mut V,
+        order: TraversalOrder,
+        context: &mut VisitContext,
+    ) {
+        // Walk the section tree with an explicit stack rather than recursion so that deeply nested
+        // sections cannot overflow the call stack. Each element carries the depth of the section
+        // below `self`, which determines how many of its ancestors are in the context.
+        let base = context.ancestors.len();
+        match order {
+            TraversalOrder::PreOrder => {
+                let mut stack = vec![(self, 0)];
+                while let Some((s, depth)) = stack.pop() {
+                    context.ancestors.truncate(base + depth);
+                    visitor.visit_section_in(s, context);
+                    context.ancestors.push(s.name.clone());
+                    stack.extend(s.subsections.iter().rev().map(|c| (c, depth + 1)));
+                }
+            }
+            TraversalOrder::PostOrder => {
+                // The flag tells whether the children of the section have been visited.
+                let mut stack = vec![(self, 0, false)];
+                while let Some((s, depth, children_visited)) = stack.pop() {
+                    context.ancestors.truncate(base + depth);
+                    if children_visited {
+                        visitor.visit_section_in(s, context);
+                    } else {
+                        context.ancestors.push(s.name.clone());
+                        stack.push((s, depth, true));
+                        stack.extend(s.subsections.iter().rev().map(|c| (c, depth + 1, false)));
+                    }
+                }
+            }
         }
+        context.ancestors.truncate(base);
     }
 }
 
-/// Visit the specified [`VisitorHost`] object with the specified visitor.
+/// Visit the specified [`VisitorHost`] object with the specified visitor in depth-first
+/// pre-order.
 ///
 /// [`VisitorHost`]: trait.VisitorHost.html
 pub fn visit<H: VisitorHost, V: Visitor>(host: &H, visitor: &mut V) {
-    host.visit(visitor);
+    visit_with_order(host, visitor, TraversalOrder::PreOrder);
+}
+
+/// Visit the specified [`VisitorHost`] object with the specified visitor in the specified order.
+///
+/// [`VisitorHost`]: trait.VisitorHost.html
+pub fn visit_with_order<H: VisitorHost, V: Visitor>(
+    host: &H,
+    visitor: &mut V,
+    order: TraversalOrder,
+) {
+    host.visit_with_order(visitor, order);
+}
+
+/// OpenBook document tree visitors that modify the visited nodes in place.
+///
+/// As with [`Visitor`], all functions do nothing by default.
+///
+/// [`Visitor`]: trait.Visitor.html
+pub trait VisitorMut {
+    /// Visit the given [`GlobalizedBooks`] node.
+    ///
+    /// [`GlobalizedBooks`]: ../struct.GlobalizedBooks.html
+    fn visit_globalized_books(&mut self, _globalized_books: &mut GlobalizedBooks) {}
+
+    /// Visit the given [`Book`] node.
+    ///
+    /// [`Book`]: ../struct.Book.html
+    fn visit_book(&mut self, _book: &mut Book) {}
+
+    /// Visit the given [`Chapter`] node.
+    ///
+    /// [`Chapter`]: ../struct.Chapter.html
+    fn visit_chapter(&mut self, _chapter: &mut Chapter) {}
+
+    /// Visit the given [`Section`] node.
+    ///
+    /// [`Section`]: ../struct.Section.html
+    fn visit_section(&mut self, _section: &mut Section) {}
+
+    /// Take the error that occurred while visiting, if any.
+    ///
+    /// Visitors that may fail should record the first error instead of panicking, skip the
+    /// remaining nodes and report the error here. A [`Pipeline`] calls this function after every
+    /// traversal to stop at the first failing visitor. Returns `None` by default.
+    ///
+    /// [`Pipeline`]: ../pipeline/struct.Pipeline.html
+    fn take_error(&mut self) -> Option<Error> {
+        None
+    }
+}
+
+impl<V: VisitorMut + ?Sized> VisitorMut for Box<V> {
+    fn visit_globalized_books(&mut self, globalized_books: &mut GlobalizedBooks) {
+        (**self).visit_globalized_books(globalized_books);
+    }
+
+    fn visit_book(&mut self, book: &mut Book) {
+        (**self).visit_book(book);
+    }
+
+    fn visit_chapter(&mut self, chapter: &mut Chapter) {
+        (**self).visit_chapter(chapter);
+    }
+
+    fn visit_section(&mut self, section: &mut Section) {
+        (**self).visit_section(section);
+    }
+
+    fn take_error(&mut self) -> Option<Error> {
+        (**self).take_error()
+    }
+}
+
+/// Extension trait for types that supports `visit_mut` operation. In normal cases, users should not
+/// use this trait directly.
+pub trait VisitorHostMut {
+    /// Visit this object with the specified mutable visitor.
+    fn visit_mut<V: VisitorMut>(&mut self, visitor: &mut V);
+}
+
+impl VisitorHostMut for GlobalizedBooks {
+    fn visit_mut<V: VisitorMut>(&mut self, visitor: &mut V) {
+        visitor.visit_globalized_books(self);
+        for (_, book) in &mut self.books {
+            book.visit_mut(visitor);
+        }
+    }
+}
+
+impl VisitorHostMut for Book {
+    fn visit_mut<V: VisitorMut>(&mut self, visitor: &mut V) {
+        visitor.visit_book(self);
+        self.preface.visit_mut(visitor);
+        for chapter in &mut self.chapters {
+            chapter.visit_mut(visitor);
+        }
+    }
+}
+
+impl VisitorHostMut for Chapter {
+    fn visit_mut<V: VisitorMut>(&mut self, visitor: &mut V) {
+        visitor.visit_chapter(self);
+        for s in &mut self.sections {
+            s.visit_mut(visitor);
+        }
+    }
+}
+
+impl VisitorHostMut for Section {
+    fn visit_mut<V: VisitorMut>(&mut self, visitor: &mut V) {
+        let mut stack = vec![self];
+        while let Some(s) = stack.pop() {
+            visitor.visit_section(s);
+            stack.extend(s.subsections.iter_mut().rev());
+        }
+    }
+}
+
+/// Visit the specified [`VisitorHostMut`] object with the specified mutable visitor.
+///
+/// [`VisitorHostMut`]: trait.VisitorHostMut.html
+pub fn visit_mut<H: VisitorHostMut, V: VisitorMut>(host: &mut H, visitor: &mut V) {
+    host.visit_mut(visitor);
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Create a section with the specified name and subsections.
+    fn named(name: &str, subsections: Vec<Section>) -> Section {
+        Section {
+            name: name.to_owned(),
+            subsections,
+            ..Section::default()
+        }
+    }
+
+    /// A chain of sections nested `depth` levels deep, named by their depth.
+    fn chain(depth: usize) -> Section {
+        chain_with(depth, |d| named(&d.to_string(), Vec::new()))
+    }
+
+    /// A chain of sections nested `depth` levels deep, where the section at every depth is created
+    /// by the specified function from the depth.
+    pub(crate) fn chain_with<F: FnMut(usize) -> Section>(depth: usize, mut section: F) -> Section {
+        let mut chain = section(depth);
+        for d in (0..depth).rev() {
+            let mut parent = section(d);
+            parent.subsections.push(chain);
+            chain = parent;
+        }
+        chain
+    }
+
+    /// Drop the specified section without recursing into its subsections.
+    pub(crate) fn drop_iteratively(section: Section) {
+        let mut stack = vec![section];
+        while let Some(mut s) = stack.pop() {
+            stack.append(&mut s.subsections);
+        }
+    }
+
+    /// Run the specified function on a thread whose stack is small enough for a recursive
+    /// traversal of a chain of thousands of sections to overflow it.
+    pub(crate) fn with_small_stack<F: FnOnce() + Send + 'static>(f: F) {
+        std::thread::Builder::new()
+            .stack_size(128 * 1024)
+            .spawn(f)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    /// Drop the specified book without recursing into its sections.
+    pub(crate) fn drop_book_iteratively(mut book: Book) {
+        drop_iteratively(std::mem::take(&mut book.preface));
+        for chapter in &mut book.chapters {
+            for section in std::mem::take(&mut chapter.sections) {
+                drop_iteratively(section);
+            }
+        }
+    }
+
+    /// Records the names of the visited sections.
+    #[derive(Default)]
+    struct Names(Vec<String>);
+
+    impl Visitor for Names {
+        fn visit_section(&mut self, section: &Section) {
+            self.0.push(section.name.clone());
+        }
+    }
+
+    #[test]
+    fn visits_deep_section_chains_without_overflowing() {
+        const DEPTH: usize = 10_000;
+        let section = chain(DEPTH);
+
+        let mut names = Names::default();
+        section.visit(&mut names);
+        let expected: Vec<String> = (0..=DEPTH).map(|d| d.to_string()).collect();
+        assert_eq!(names.0, expected);
+
+        drop_iteratively(section);
+    }
+
+    #[test]
+    fn visits_sections_in_pre_order() {
+        let section = named(
+            "a",
+            vec![
+                named("b", vec![named("c", Vec::new())]),
+                named("d", Vec::new()),
+            ],
+        );
+        let mut names = Names::default();
+        visit(&section, &mut names);
+        assert_eq!(names.0, vec!["a", "b", "c", "d"]);
+    }
+
+    /// Uppercases the names of all sections.
+    struct Uppercase;
+
+    impl VisitorMut for Uppercase {
+        fn visit_section(&mut self, section: &mut Section) {
+            section.name = section.name.to_uppercase();
+        }
+    }
+
+    #[test]
+    fn mutable_visitors_modify_every_section() {
+        let mut books = GlobalizedBooks {
+            books: vec![(
+                String::new(),
+                Book {
+                    preface: named("preface", Vec::new()),
+                    chapters: vec![Chapter {
+                        sections: vec![named("a", vec![named("b", Vec::new())])],
+                        ..Chapter::default()
+                    }],
+                    ..Book::default()
+                },
+            )],
+            ..GlobalizedBooks::default()
+        };
+        visit_mut(&mut books, &mut Uppercase);
+
+        let book = &books.books[0].1;
+        assert_eq!(book.preface.name, "PREFACE");
+        let a = &book.chapters[0].sections[0];
+        assert_eq!(a.name, "A");
+        assert_eq!(a.subsections[0].name, "B");
+    }
+
+    /// Records the kinds and names of all visited nodes.
+    #[derive(Default)]
+    struct Trace(Vec<String>);
+
+    impl Visitor for Trace {
+        fn visit_book(&mut self, _book: &Book) {
+            self.0.push("book".to_owned());
+        }
+
+        fn visit_chapter(&mut self, chapter: &Chapter) {
+            self.0.push(format!("chapter {}", chapter.name));
+        }
+
+        fn visit_section(&mut self, section: &Section) {
+            self.0.push(section.name.clone());
+        }
+    }
+
+    #[test]
+    fn post_order_visits_children_before_their_parents() {
+        let book = Book {
+            preface: named("preface", Vec::new()),
+            chapters: vec![Chapter {
+                name: "c".to_owned(),
+                sections: vec![
+                    named("a", vec![named("a.1", vec![named("a.1.1", Vec::new())])]),
+                    named("b", Vec::new()),
+                ],
+                ..Chapter::default()
+            }],
+            ..Book::default()
+        };
+
+        let mut pre = Trace::default();
+        visit_with_order(&book, &mut pre, TraversalOrder::PreOrder);
+        assert_eq!(
+            pre.0,
+            vec!["book", "preface", "chapter c", "a", "a.1", "a.1.1", "b"]
+        );
+
+        let mut post = Trace::default();
+        visit_with_order(&book, &mut post, TraversalOrder::PostOrder);
+        assert_eq!(
+            post.0,
+            vec!["preface", "a.1.1", "a.1", "a", "b", "chapter c", "book"]
+        );
+
+        let mut default = Trace::default();
+        visit(&book, &mut default);
+        assert_eq!(default.0, pre.0);
+    }
+
+    /// Counts the visited chapters and their sections, with default bodies for the other hooks.
+    #[derive(Default)]
+    struct Chapters(Vec<(String, usize)>);
+
+    impl Visitor for Chapters {
+        fn visit_chapter(&mut self, chapter: &Chapter) {
+            self.0.push((chapter.name.clone(), chapter.sections.len()));
+        }
+    }
+
+    /// Renames every chapter, with default bodies for the other hooks.
+    struct RenameChapters;
+
+    impl VisitorMut for RenameChapters {
+        fn visit_chapter(&mut self, chapter: &mut Chapter) {
+            chapter.name = format!("Chapter {}", chapter.name);
+        }
+    }
+
+    #[test]
+    fn visitors_can_implement_only_the_chapter_hook() {
+        let mut book = Book {
+            preface: named("preface", Vec::new()),
+            chapters: vec![
+                Chapter {
+                    name: "one".to_owned(),
+                    sections: vec![named("a", vec![named("a.1", Vec::new())])],
+                    ..Chapter::default()
+                },
+                Chapter {
+                    name: "two".to_owned(),
+                    ..Chapter::default()
+                },
+            ],
+            ..Book::default()
+        };
+
+        let mut chapters = Chapters::default();
+        visit(&book, &mut chapters);
+        assert_eq!(
+            chapters.0,
+            vec![("one".to_owned(), 1), ("two".to_owned(), 0)]
+        );
+
+        visit_mut(&mut book, &mut RenameChapters);
+        assert_eq!(book.chapters[0].name, "Chapter one");
+        assert_eq!(book.chapters[1].name, "Chapter two");
+        assert_eq!(book.chapters[0].sections[0].name, "a");
+    }
+
+    #[test]
+    fn books_visit_their_preface_and_then_every_chapter() {
+        let book = |preface: &str| Book {
+            preface: named(preface, Vec::new()),
+            chapters: vec![
+                Chapter {
+                    name: "one".to_owned(),
+                    sections: vec![named("a", Vec::new())],
+                    ..Chapter::default()
+                },
+                Chapter {
+                    name: "two".to_owned(),
+                    sections: vec![named("b", Vec::new()), named("c", Vec::new())],
+                    ..Chapter::default()
+                },
+            ],
+            ..Book::default()
+        };
+        let books = GlobalizedBooks {
+            books: vec![
+                ("en".to_owned(), book("preface")),
+                ("zh".to_owned(), book("前言")),
+            ],
+            ..GlobalizedBooks::default()
+        };
+
+        let mut trace = Trace::default();
+        visit(&books, &mut trace);
+        let expected: Vec<&str> = vec![
+            "book",
+            "preface",
+            "chapter one",
+            "a",
+            "chapter two",
+            "b",
+            "c",
+            "book",
+            "前言",
+            "chapter one",
+            "a",
+            "chapter two",
+            "b",
+            "c",
+        ];
+        assert_eq!(trace.0, expected);
+    }
+
+    /// Records the context of every visited section.
+    #[derive(Default)]
+    struct Contexts(Vec<(String, VisitContext)>);
+
+    impl Visitor for Contexts {
+        fn visit_section_in(&mut self, section: &Section, context: &VisitContext) {
+            self.0.push((section.name.clone(), context.clone()));
+        }
+    }
+
+    #[test]
+    fn contexts_track_the_position_of_sections() {
+        let chapter = |name: &str, sections: Vec<Section>| Chapter {
+            name: name.to_owned(),
+            sections,
+            ..Chapter::default()
+        };
+        let books = GlobalizedBooks {
+            books: vec![(
+                "en".to_owned(),
+                Book {
+                    preface: named("Preface", Vec::new()),
+                    chapters: vec![
+                        chapter(
+                            "ChapterA",
+                            vec![named("Intro", vec![named("Details", Vec::new())])],
+                        ),
+                        chapter("ChapterB", vec![named("Outro", Vec::new())]),
+                    ],
+                    ..Book::default()
+                },
+            )],
+            ..GlobalizedBooks::default()
+        };
+
+        for order in &[TraversalOrder::PreOrder, TraversalOrder::PostOrder] {
+            let mut contexts = Contexts::default();
+            visit_with_order(&books, &mut contexts, *order);
+            let context = |name: &str| {
+                contexts
+                    .0
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .map(|(_, c)| c.clone())
+                    .unwrap()
+            };
+
+            let details = context("Details");
+            assert_eq!(details.language(), Some("en"));
+            assert_eq!(details.chapter(), Some("ChapterA"));
+            assert_eq!(details.ancestors(), ["ChapterA", "Intro"]);
+            assert_eq!(context("Intro").ancestors(), ["ChapterA"]);
+            assert_eq!(context("Outro").ancestors(), ["ChapterB"]);
+            assert_eq!(context("Outro").chapter(), Some("ChapterB"));
+            assert_eq!(context("Preface").chapter(), None);
+            assert!(context("Preface").ancestors().is_empty());
+        }
+
+        let mut contexts = Contexts::default();
+        visit(&books.books[0].1, &mut contexts);
+        assert_eq!(contexts.0[0].1.language(), None);
+    }
 }
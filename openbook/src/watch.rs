@@ -0,0 +1,233 @@
+//! This module provides a watcher that watches all books in an OpenBook project and attributes file
+//! system changes to the books they belong to.
+//!
+//! The [`ProjectWatcher`] watches the root directory of the project and the root directories of
+//! all books contained in the project. Every [`FileSystemEvent`] emitted by the underlying file
+//! system watcher is attributed to the book whose root directory contains the event's path, and a
+//! corresponding [`ProjectEvent`] is emitted into the user-supplied [`ProjectEventSink`].
+//!
+//! [`ProjectWatcher`]: struct.ProjectWatcher.html
+//! [`FileSystemEvent`]: ../fs/enum.FileSystemEvent.html
+//! [`ProjectEvent`]: enum.ProjectEvent.html
+//! [`ProjectEventSink`]: trait.ProjectEventSink.html
+//!
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+use crate::error::{Error, Result};
+use crate::fs::{
+    FileSystem, FileSystemEvent, FileSystemEventSink, FileSystemWatchMode, FileSystemWatcher,
+};
+use crate::tree::GlobalizedBooks;
+
+/// Events emitted by a [`ProjectWatcher`].
+///
+/// [`ProjectWatcher`]: struct.ProjectWatcher.html
+#[derive(Debug)]
+pub enum ProjectEvent {
+    /// A file under the root directory of a book is changed.
+    BookChanged {
+        /// Language of the book that owns the changed file.
+        language: String,
+
+        /// The underlying file system event.
+        event: FileSystemEvent,
+    },
+
+    /// A file that does not belong to any book is changed, or the change cannot be attributed to a
+    /// single book.
+    ProjectConfigChanged(FileSystemEvent),
+}
+
+/// Project watchers emit project events into this sink.
+pub trait ProjectEventSink: Send {
+    /// Send the specified event into this sink.
+    fn send(&self, event: ProjectEvent) -> Result<()>;
+}
+
+impl ProjectEventSink for Sender<ProjectEvent> {
+    fn send(&self, event: ProjectEvent) -> Result<()> {
+        self.send(event).map_err(Error::from_inner)
+    }
+}
+
+/// A [`FileSystemEventSink`] that attributes file system events to the books they belong to and
+/// forwards the attributed events into a [`ProjectEventSink`].
+///
+/// [`FileSystemEventSink`]: ../fs/trait.FileSystemEventSink.html
+/// [`ProjectEventSink`]: trait.ProjectEventSink.html
+pub struct ProjectEventRouter {
+    roots: Vec<(String, PathBuf)>,
+    sink: Box<dyn ProjectEventSink>,
+}
+
+impl ProjectEventRouter {
+    /// Create a new `ProjectEventRouter` instance that routes file system events to the books
+    /// contained in the specified project and emits the routed events into the specified sink.
+    ///
+    /// Books whose root directory is empty are not considered during routing.
+    pub fn new(books: &GlobalizedBooks, sink: Box<dyn ProjectEventSink>) -> Self {
+        let roots = books
+            .books
+            .iter()
+            .filter(|(_, book)| !book.config.root.as_os_str().is_empty())
+            .map(|(language, book)| (language.clone(), book.config.root.clone()))
+            .collect();
+        Self { roots, sink }
+    }
+
+    /// Attribute the specified file system event to the book it belongs to.
+    ///
+    /// If the event's path is contained in the root directories of multiple books, the book with
+    /// the innermost root directory wins. For `Rename` events, the destination path is considered
+    /// before the source path.
+    pub fn route(&self, event: FileSystemEvent) -> ProjectEvent {
+        let language = match &event {
+            FileSystemEvent::Create(path)
+            | FileSystemEvent::Delete(path)
+            | FileSystemEvent::Write(path) => self.find_owner(path),
+            FileSystemEvent::Rename { from, to } => {
+                self.find_owner(to).or_else(|| self.find_owner(from))
+            }
+            FileSystemEvent::Rescan => None,
+            FileSystemEvent::Error(_, path) => path.as_ref().and_then(|p| self.find_owner(p)),
+        };
+
+        match language {
+            Some(language) => ProjectEvent::BookChanged {
+                language: language.to_owned(),
+                event,
+            },
+            None => ProjectEvent::ProjectConfigChanged(event),
+        }
+    }
+
+    /// Find the language of the book whose root directory contains the specified path.
+    fn find_owner(&self, path: &Path) -> Option<&str> {
+        self.roots
+            .iter()
+            .filter(|(_, root)| path.starts_with(root))
+            .max_by_key(|(_, root)| root.components().count())
+            .map(|(language, _)| language.as_str())
+    }
+}
+
+impl FileSystemEventSink for ProjectEventRouter {
+    fn send(&self, event: FileSystemEvent) -> Result<()> {
+        self.sink.send(self.route(event))
+    }
+}
+
+/// Watches all books contained in an OpenBook project and emits per-book change events.
+pub struct ProjectWatcher<W: FileSystemWatcher> {
+    watcher: W,
+}
+
+impl<W: FileSystemWatcher> ProjectWatcher<W> {
+    /// Create a new `ProjectWatcher` instance that watches the specified project on the specified
+    /// file system and emits project events into the specified sink.
+    ///
+    /// The root directory of the project and the root directories of all books are watched
+    /// recursively.
+    pub fn new<F>(fs: &F, books: &GlobalizedBooks, sink: Box<dyn ProjectEventSink>) -> Result<Self>
+    where
+        F: FileSystem<Watcher = W>,
+    {
+        let router = ProjectEventRouter::new(books, sink);
+        let watcher = fs.create_watcher(Box::new(router))?;
+
+        let global_root = &books.config.root;
+        if !global_root.as_os_str().is_empty() {
+            watcher.watch(global_root, FileSystemWatchMode::Recursive)?;
+        }
+
+        for (_, book) in &books.books {
+            let root = &book.config.root;
+            if root.as_os_str().is_empty() {
+                continue;
+            }
+            if !global_root.as_os_str().is_empty() && root.starts_with(global_root) {
+                // The book's root directory is already covered by the recursive watch on the
+                // project's root directory.
+                continue;
+            }
+            watcher.watch(root, FileSystemWatchMode::Recursive)?;
+        }
+
+        Ok(Self { watcher })
+    }
+
+    /// Get the underlying file system watcher.
+    pub fn watcher(&self) -> &W {
+        &self.watcher
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use super::*;
+    use crate::tree::builder::{BookBuilder, BookConfigBuilder, GlobalizedBooksBuilder};
+
+    fn router() -> (ProjectEventRouter, std::sync::mpsc::Receiver<ProjectEvent>) {
+        let book = |root: &str| {
+            BookBuilder::new()
+                .set_config(BookConfigBuilder::new().set_root(root).build())
+                .build()
+        };
+        let books = GlobalizedBooksBuilder::new()
+            .add_localized_book(String::from("en"), book("/project/en"))
+            .add_localized_book(String::from("en-GB"), book("/project/en/gb"))
+            .add_localized_book(String::from("draft"), book(""))
+            .build();
+        let (sender, receiver) = channel();
+        (ProjectEventRouter::new(&books, Box::new(sender)), receiver)
+    }
+
+    fn owner(event: &ProjectEvent) -> Option<&str> {
+        match event {
+            ProjectEvent::BookChanged { language, .. } => Some(language),
+            ProjectEvent::ProjectConfigChanged(_) => None,
+        }
+    }
+
+    #[test]
+    fn attributes_events_to_the_innermost_book() {
+        let (router, _) = router();
+        let write = |path: &str| FileSystemEvent::Write(PathBuf::from(path));
+        assert_eq!(owner(&router.route(write("/project/en/a.md"))), Some("en"));
+        assert_eq!(
+            owner(&router.route(write("/project/en/gb/a.md"))),
+            Some("en-GB")
+        );
+        assert_eq!(owner(&router.route(write("/project/book.toml"))), None);
+        assert_eq!(owner(&router.route(FileSystemEvent::Rescan)), None);
+    }
+
+    #[test]
+    fn attributes_renames_to_their_destination_first() {
+        let (router, receiver) = router();
+        let rename = |from: &str, to: &str| FileSystemEvent::Rename {
+            from: PathBuf::from(from),
+            to: PathBuf::from(to),
+        };
+        router
+            .send(rename("/project/en/a.md", "/project/en/gb/a.md"))
+            .unwrap();
+        router
+            .send(rename("/project/en/a.md", "/tmp/a.md"))
+            .unwrap();
+        router.send(rename("/tmp/a.md", "/tmp/b.md")).unwrap();
+
+        let owners: Vec<_> = receiver
+            .try_iter()
+            .map(|e| owner(&e).map(str::to_owned))
+            .collect();
+        assert_eq!(
+            owners,
+            vec![Some(String::from("en-GB")), Some(String::from("en")), None]
+        );
+    }
+}